@@ -0,0 +1,77 @@
+//! `cargo bench --bench imu_json`: serde_json against `ImuJsonWriter` on
+//! IMU measurements, after checking both produce the same text.
+
+use carla_data_serde::json_writer::ImuJsonWriter;
+use carla_data_serde::{ImuFrameSerDe, ImuMeasurementSerDe, SensorDataSerDe, Vector3DSerDe};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 1_000_000;
+
+fn measure(name: &str, mut f: impl FnMut(u32) -> usize) -> Duration {
+    // warm up caches and the writer's buffer
+    for i in 0..ITERATIONS / 10 {
+        black_box(f(i));
+    }
+    let start = Instant::now();
+    let mut bytes = 0;
+    for i in 0..ITERATIONS {
+        bytes += black_box(f(i));
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{name:<24} {:>8.1} ns/frame  ({} bytes/frame)",
+        elapsed.as_nanos() as f64 / ITERATIONS as f64,
+        bytes / ITERATIONS as usize
+    );
+    elapsed
+}
+
+fn imu(i: u32) -> ImuMeasurementSerDe {
+    let t = i as f32 * 1e-3;
+    ImuMeasurementSerDe {
+        accelerometer: Vector3DSerDe {
+            x: 0.12 + t.sin() * 0.01,
+            y: -0.03,
+            z: 9.81,
+        },
+        gyroscope: Vector3DSerDe {
+            x: 0.0,
+            y: 0.0012,
+            z: t.cos() * 0.05,
+        },
+        compass: 1.57,
+        noise_model: None,
+    }
+}
+
+fn main() {
+    let frames: Vec<_> = (0..1024).map(imu).collect();
+    let frame = |i: u32| &frames[i as usize % frames.len()];
+
+    let mut writer = ImuJsonWriter::new();
+    for f in &frames {
+        let frame = ImuFrameSerDe::from(*f);
+        let json = serde_json::to_string(&SensorDataSerDe::Imu(frame.clone())).unwrap();
+        assert_eq!(writer.write_frame(&frame), json);
+    }
+
+    let serde = measure("serde_json::to_string", |i| {
+        serde_json::to_string(frame(i)).unwrap().len()
+    });
+    let mut buf = Vec::new();
+    let reused = measure("serde_json::to_writer", |i| {
+        buf.clear();
+        serde_json::to_writer(&mut buf, frame(i)).unwrap();
+        buf.len()
+    });
+    let fast = measure("ImuJsonWriter", |i| {
+        writer.write_measurement(frame(i)).len()
+    });
+
+    println!(
+        "speedup: {:.1}x over to_string, {:.1}x over to_writer",
+        serde.as_secs_f64() / fast.as_secs_f64(),
+        reused.as_secs_f64() / fast.as_secs_f64()
+    );
+}
@@ -0,0 +1,204 @@
+//! Access labels on frames, for sharing recordings with partners.
+//!
+//! A label such as `internal` or `shareable` is stored in the frame's
+//! annotations under [`ACCESS_LABEL_KEY`], so it survives every format the
+//! frame is written in. [`AccessTagger`] assigns labels per sensor kind as
+//! frames pass through a pipeline; [`AccessFilter`] and [`FilteredSink`]
+//! drop the frames an export must not contain.
+
+use crate::pipeline::{FrameTransform, SharedFrame, Sink};
+use crate::{
+    AnnotationValueSerDe, GapReason, GapRecordSerDe, ProcessingStepSerDe, SensorDataSerDe,
+    SensorKind,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub const ACCESS_LABEL_KEY: &str = "access_label";
+
+pub fn access_label(frame: &SensorDataSerDe) -> Option<&str> {
+    match frame.annotations().get(ACCESS_LABEL_KEY)? {
+        AnnotationValueSerDe::Text(label) => Some(label),
+        _ => None,
+    }
+}
+
+pub fn set_access_label(frame: &mut SensorDataSerDe, label: impl Into<String>) {
+    frame
+        .annotations_mut()
+        .insert(ACCESS_LABEL_KEY, AnnotationValueSerDe::Text(label.into()));
+}
+
+/// Labels frames by sensor kind. Frames that already carry a label keep
+/// it, so individual frames can be relabeled upstream.
+#[derive(Clone, Debug, Default)]
+pub struct AccessTagger {
+    default: Option<String>,
+    by_kind: BTreeMap<SensorKind, String>,
+}
+
+impl AccessTagger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Label for sensor kinds without their own label.
+    pub fn with_default(mut self, label: impl Into<String>) -> Self {
+        self.default = Some(label.into());
+        self
+    }
+
+    pub fn with_kind(mut self, kind: SensorKind, label: impl Into<String>) -> Self {
+        self.by_kind.insert(kind, label.into());
+        self
+    }
+
+    fn label(&self, kind: SensorKind) -> Option<&String> {
+        self.by_kind.get(&kind).or(self.default.as_ref())
+    }
+}
+
+impl FrameTransform for AccessTagger {
+    fn accepts(&self, frame: &SensorDataSerDe) -> bool {
+        access_label(frame).is_none() && self.label(frame.kind()).is_some()
+    }
+
+    fn apply(&mut self, frame: &mut SensorDataSerDe) {
+        if access_label(frame).is_none()
+            && let Some(label) = self.label(frame.kind())
+        {
+            set_access_label(frame, label.clone());
+        }
+    }
+
+    fn describe(&self) -> ProcessingStepSerDe {
+        let mut step = ProcessingStepSerDe::new("access_tag");
+        if let Some(label) = &self.default {
+            step = step.with_param("default", label.as_str());
+        }
+        for (kind, label) in &self.by_kind {
+            step = step.with_param(format!("{kind:?}"), label.as_str());
+        }
+        step
+    }
+}
+
+/// The set of labels an export may contain. Unlabeled frames are dropped
+/// unless [`keep_unlabeled`](Self::keep_unlabeled) is set.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccessFilter {
+    allowed: BTreeSet<String>,
+    keep_unlabeled: bool,
+}
+
+impl AccessFilter {
+    pub fn new<S: Into<String>>(allowed: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            allowed: allowed.into_iter().map(Into::into).collect(),
+            keep_unlabeled: false,
+        }
+    }
+
+    pub fn keep_unlabeled(mut self, keep: bool) -> Self {
+        self.keep_unlabeled = keep;
+        self
+    }
+
+    pub fn allows(&self, frame: &SensorDataSerDe) -> bool {
+        match access_label(frame) {
+            Some(label) => self.allowed.contains(label),
+            None => self.keep_unlabeled,
+        }
+    }
+
+    /// Drop the frames the filter does not allow; returns how many.
+    pub fn retain(&self, frames: &mut Vec<SensorDataSerDe>) -> usize {
+        let before = frames.len();
+        frames.retain(|f| self.allows(f));
+        before - frames.len()
+    }
+}
+
+/// Forwards only the frames an [`AccessFilter`] allows, e.g. in front of
+/// the writer of a partner export. The withheld frames are counted per
+/// sensor kind, for [`GapReason::Filtered`] records, see
+/// [`FilteredSink::take_gaps`].
+#[derive(Debug)]
+pub struct FilteredSink<S> {
+    filter: AccessFilter,
+    inner: S,
+    dropped: u64,
+    // withheld since the last take_gaps
+    pending: BTreeMap<SensorKind, u64>,
+}
+
+impl<S: Sink> FilteredSink<S> {
+    pub fn new(filter: AccessFilter, inner: S) -> Self {
+        Self {
+            filter,
+            inner,
+            dropped: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Frames withheld from the inner sink so far.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// One [`GapReason::Filtered`] record per sensor kind withheld since
+    /// the last call, for a writer to put into its stream.
+    pub fn take_gaps(&mut self) -> Vec<GapRecordSerDe> {
+        std::mem::take(&mut self.pending)
+            .into_iter()
+            .map(|(kind, n)| GapRecordSerDe::new(kind, n, GapReason::Filtered))
+            .collect()
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Sink> Sink for FilteredSink<S> {
+    fn consume(&mut self, frame: SharedFrame) {
+        if self.filter.allows(&frame) {
+            self.inner.consume(frame);
+        } else {
+            self.dropped += 1;
+            *self.pending.entry(frame.kind()).or_default() += 1;
+        }
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn filtered_frames_become_gaps() {
+        let mut forwarded = 0;
+        let mut sink = FilteredSink::new(AccessFilter::new(["public"]), |_| forwarded += 1);
+        let mut frames = crate::fixtures::all();
+        set_access_label(&mut frames[0], "public");
+        let kinds: Vec<_> = frames[1..3].iter().map(SensorDataSerDe::kind).collect();
+        for frame in frames.into_iter().take(3) {
+            sink.consume(Arc::new(frame));
+        }
+        assert_eq!(sink.dropped(), 2);
+        let gaps = sink.take_gaps();
+        let expected: Vec<_> = kinds
+            .into_iter()
+            .map(|kind| GapRecordSerDe::new(kind, 1, GapReason::Filtered))
+            .collect();
+        assert_eq!(gaps, expected);
+        assert!(sink.take_gaps().is_empty());
+        drop(sink);
+        assert_eq!(forwarded, 1);
+    }
+}
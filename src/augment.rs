@@ -0,0 +1,14 @@
+//! Simulation-side augmentation of serialized sensor data.
+//!
+//! Each stage applies a configurable corruption to a frame and records its
+//! parameters in the frame, so the augmented data stays reproducible.
+
+mod image;
+mod imu;
+mod normalize;
+mod rng;
+
+pub use image::*;
+pub use imu::*;
+pub use normalize::*;
+pub use rng::*;
@@ -0,0 +1,229 @@
+use super::NoiseRng;
+use crate::{ImageAugmentationSerDe, ImageAugmentationStageSerDe, ImageEventSerDe};
+use carla::sensor::data::Color;
+use ndarray::{Array2, s};
+
+/// Applies a fixed chain of [`ImageAugmentationStageSerDe`]s to a stream of
+/// images.
+///
+/// Every frame gets its own seed drawn from the augmenter's generator; the
+/// seed and the stages are appended to `ImageEventSerDe::augmentations`.
+#[derive(Clone, Debug)]
+pub struct ImageAugmenter {
+    stages: Vec<ImageAugmentationStageSerDe>,
+    seed: u64,
+    rng: NoiseRng,
+}
+
+impl ImageAugmenter {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            stages: Vec::new(),
+            seed,
+            rng: NoiseRng::new(seed),
+        }
+    }
+
+    /// Append a stage to the chain.
+    pub fn then(mut self, stage: ImageAugmentationStageSerDe) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub fn stages(&self) -> &[ImageAugmentationStageSerDe] {
+        &self.stages
+    }
+
+    /// The seed the augmenter was created with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn apply(&mut self, image: &mut ImageEventSerDe) {
+        let record = ImageAugmentationSerDe {
+            seed: self.rng.next_u64(),
+            stages: self.stages.clone(),
+        };
+        replay_image_augmentation(&record, &mut image.array);
+        image.augmentations.push(record);
+    }
+}
+
+/// Re-apply a recorded augmentation to an image array.
+pub fn replay_image_augmentation(record: &ImageAugmentationSerDe, array: &mut Array2<Color>) {
+    let mut rng = NoiseRng::new(record.seed);
+    for stage in &record.stages {
+        match *stage {
+            ImageAugmentationStageSerDe::GaussianNoise { stddev } => {
+                gaussian_noise(array, stddev, &mut rng)
+            }
+            ImageAugmentationStageSerDe::MotionBlur { length, angle } => {
+                motion_blur(array, length, angle)
+            }
+            ImageAugmentationStageSerDe::Vignette { strength } => vignette(array, strength),
+            ImageAugmentationStageSerDe::Raindrops { count, max_radius } => {
+                raindrops(array, count, max_radius, &mut rng)
+            }
+            tone @ (ImageAugmentationStageSerDe::Exposure { .. }
+            | ImageAugmentationStageSerDe::WhiteBalance { .. }
+            | ImageAugmentationStageSerDe::Gamma { .. }
+            | ImageAugmentationStageSerDe::ToneCurve { .. }) => {
+                map_channels(array, |c, v| tone_map(&tone, c, v))
+            }
+        }
+    }
+}
+
+/// Undo a recorded augmentation of an image array, the stages in reverse.
+///
+/// Returns `false` and leaves the array alone if a stage is not
+/// [invertible](ImageAugmentationStageSerDe::is_invertible). Values a stage
+/// clipped to 0 or 255 stay clipped.
+pub fn invert_image_augmentation(
+    record: &ImageAugmentationSerDe,
+    array: &mut Array2<Color>,
+) -> bool {
+    if !record.stages.iter().all(|stage| stage.is_invertible()) {
+        return false;
+    }
+    for stage in record.stages.iter().rev() {
+        map_channels(array, |c, v| tone_unmap(stage, c, v));
+    }
+    true
+}
+
+// Channel `c` (0 red, 1 green, 2 blue) value `v` after a per-pixel stage.
+fn tone_map(stage: &ImageAugmentationStageSerDe, c: usize, v: f32) -> f32 {
+    match *stage {
+        ImageAugmentationStageSerDe::Exposure { gain } => v * gain,
+        ImageAugmentationStageSerDe::WhiteBalance { r, g, b } => v * [r, g, b][c],
+        ImageAugmentationStageSerDe::Gamma { gamma } => 255.0 * (v / 255.0).powf(1.0 / gamma),
+        ImageAugmentationStageSerDe::ToneCurve { knots } => {
+            let i = ((v / 16.0) as usize).min(15);
+            let t = v / 16.0 - i as f32;
+            knots[i] + (knots[i + 1] - knots[i]) * t
+        }
+        _ => v,
+    }
+}
+
+fn tone_unmap(stage: &ImageAugmentationStageSerDe, c: usize, v: f32) -> f32 {
+    match *stage {
+        ImageAugmentationStageSerDe::Exposure { gain } => v / gain,
+        ImageAugmentationStageSerDe::WhiteBalance { r, g, b } => v / [r, g, b][c],
+        ImageAugmentationStageSerDe::Gamma { gamma } => 255.0 * (v / 255.0).powf(gamma),
+        ImageAugmentationStageSerDe::ToneCurve { knots } => {
+            let i = knots[1..16].iter().take_while(|&&k| k <= v).count();
+            let t = (v - knots[i]) / (knots[i + 1] - knots[i]);
+            16.0 * (i as f32 + t)
+        }
+        _ => v,
+    }
+}
+
+// Apply a per-channel value map through lookup tables.
+fn map_channels(array: &mut Array2<Color>, f: impl Fn(usize, f32) -> f32) {
+    let lut: [[u8; 256]; 3] =
+        std::array::from_fn(|c| std::array::from_fn(|v| to_u8(f(c, v as f32))));
+    for px in array.iter_mut() {
+        px.r = lut[0][px.r as usize];
+        px.g = lut[1][px.g as usize];
+        px.b = lut[2][px.b as usize];
+    }
+}
+
+#[inline]
+fn to_u8(v: f32) -> u8 {
+    v.round().clamp(0.0, 255.0) as u8
+}
+
+fn gaussian_noise(array: &mut Array2<Color>, stddev: f32, rng: &mut NoiseRng) {
+    for px in array.iter_mut() {
+        px.r = to_u8(px.r as f32 + rng.gaussian() * stddev);
+        px.g = to_u8(px.g as f32 + rng.gaussian() * stddev);
+        px.b = to_u8(px.b as f32 + rng.gaussian() * stddev);
+    }
+}
+
+fn motion_blur(array: &mut Array2<Color>, length: usize, angle: f32) {
+    if length < 2 {
+        return;
+    }
+    let (h, w) = array.dim();
+    let (dy, dx) = angle.to_radians().sin_cos();
+    let half = (length - 1) as f32 / 2.0;
+    let src = array.clone();
+
+    *array = Array2::from_shape_fn((h, w), |(y, x)| {
+        let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+        for i in 0..length {
+            let t = i as f32 - half;
+            // image rows grow downwards, hence the minus on dy
+            let sx = (x as f32 + t * dx).round().clamp(0.0, (w - 1) as f32) as usize;
+            let sy = (y as f32 - t * dy).round().clamp(0.0, (h - 1) as f32) as usize;
+            let s = &src[(sy, sx)];
+            r += s.r as f32;
+            g += s.g as f32;
+            b += s.b as f32;
+        }
+        let n = length as f32;
+        Color {
+            b: to_u8(b / n),
+            g: to_u8(g / n),
+            r: to_u8(r / n),
+            a: src[(y, x)].a,
+        }
+    });
+}
+
+fn vignette(array: &mut Array2<Color>, strength: f32) {
+    let (h, w) = array.dim();
+    let (cy, cx) = ((h as f32 - 1.0) / 2.0, (w as f32 - 1.0) / 2.0);
+    let max_sq = cx * cx + cy * cy;
+    if max_sq == 0.0 {
+        return;
+    }
+    for ((y, x), px) in array.indexed_iter_mut() {
+        let (ry, rx) = (y as f32 - cy, x as f32 - cx);
+        let k = 1.0 - strength * (rx * rx + ry * ry) / max_sq;
+        px.r = to_u8(px.r as f32 * k);
+        px.g = to_u8(px.g as f32 * k);
+        px.b = to_u8(px.b as f32 * k);
+    }
+}
+
+// Each drop acts as a small lens: it shows the scene behind it shrunk and
+// flipped, slightly brightened.
+fn raindrops(array: &mut Array2<Color>, count: usize, max_radius: f32, rng: &mut NoiseRng) {
+    let (h, w) = array.dim();
+    if h == 0 || w == 0 || max_radius <= 0.0 {
+        return;
+    }
+    for _ in 0..count {
+        let cx = rng.uniform() * w as f32;
+        let cy = rng.uniform() * h as f32;
+        let radius = max_radius * (0.3 + 0.7 * rng.uniform());
+
+        let y0 = (cy - radius).max(0.0) as usize;
+        let y1 = ((cy + radius).ceil() as usize).min(h);
+        let x0 = (cx - radius).max(0.0) as usize;
+        let x1 = ((cx + radius).ceil() as usize).min(w);
+        // a drop only shows the scene behind its own bounding box
+        let src = array.slice(s![y0..y1, x0..x1]).to_owned();
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (ry, rx) = (y as f32 - cy, x as f32 - cx);
+                if rx * rx + ry * ry > radius * radius {
+                    continue;
+                }
+                let sx = (cx - rx * 0.5).clamp(x0 as f32, (x1 - 1) as f32) as usize;
+                let sy = (cy - ry * 0.5).clamp(y0 as f32, (y1 - 1) as f32) as usize;
+                let s = &src[(sy - y0, sx - x0)];
+                let px = &mut array[(y, x)];
+                px.r = to_u8(s.r as f32 * 1.1 + 10.0);
+                px.g = to_u8(s.g as f32 * 1.1 + 10.0);
+                px.b = to_u8(s.b as f32 * 1.1 + 10.0);
+            }
+        }
+    }
+}
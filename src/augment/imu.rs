@@ -0,0 +1,51 @@
+use super::NoiseRng;
+use crate::{ImuMeasurementSerDe, ImuNoiseModelSerDe, Vector3DSerDe};
+use std::f32::consts::TAU;
+
+/// Adds constant bias and gaussian white noise to a stream of IMU samples.
+///
+/// The generator is seeded from [`ImuNoiseModelSerDe::seed`] and advances
+/// with every sample, so feeding the same stream twice gives the same output.
+#[derive(Clone, Debug)]
+pub struct ImuAugmenter {
+    model: ImuNoiseModelSerDe,
+    rng: NoiseRng,
+}
+
+impl ImuAugmenter {
+    pub fn new(model: ImuNoiseModelSerDe) -> Self {
+        Self {
+            rng: NoiseRng::new(model.seed),
+            model,
+        }
+    }
+
+    pub fn model(&self) -> &ImuNoiseModelSerDe {
+        &self.model
+    }
+
+    /// Corrupt `m` in place and record the model in `m.noise_model`.
+    pub fn apply(&mut self, m: &mut ImuMeasurementSerDe) {
+        let model = self.model;
+        self.perturb(
+            &mut m.accelerometer,
+            &model.accelerometer_bias,
+            &model.accelerometer_stddev,
+        );
+        self.perturb(
+            &mut m.gyroscope,
+            &model.gyroscope_bias,
+            &model.gyroscope_stddev,
+        );
+        if model.compass_stddev != 0.0 {
+            m.compass = (m.compass + self.rng.gaussian() * model.compass_stddev).rem_euclid(TAU);
+        }
+        m.noise_model = Some(model);
+    }
+
+    fn perturb(&mut self, v: &mut Vector3DSerDe, bias: &Vector3DSerDe, stddev: &Vector3DSerDe) {
+        v.x += bias.x + self.rng.gaussian() * stddev.x;
+        v.y += bias.y + self.rng.gaussian() * stddev.y;
+        v.z += bias.z + self.rng.gaussian() * stddev.z;
+    }
+}
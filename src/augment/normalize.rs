@@ -0,0 +1,181 @@
+use super::replay_image_augmentation;
+use crate::pipeline::FrameTransform;
+use crate::{
+    ImageAugmentationSerDe, ImageAugmentationStageSerDe, ImageEventSerDe, ImageHashSerDe,
+    ProcessingStepSerDe, SensorDataSerDe,
+};
+use carla::sensor::data::Color;
+use ndarray::Array2;
+
+/// Evens out the white balance, exposure and contrast of images, so frames
+/// of different weather and times of day look alike.
+///
+/// The parameters are measured on every frame and appended to
+/// `ImageEventSerDe::augmentations` as a record of
+/// [`ImageAugmentationStageSerDe`]s, white balance, exposure, tone curve
+/// and gamma in that order, so the dataset documents what was done and
+/// [`invert_image_augmentation`](super::invert_image_augmentation) takes a
+/// frame back. Pixels with an alpha of 0 are left out of the measurements.
+/// A hash the image had is recomputed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageNormalizer {
+    white_balance: bool,
+    target_mean: Option<f32>,
+    max_gain: f32,
+    equalization: f32,
+    gamma: f32,
+}
+
+impl Default for ImageNormalizer {
+    fn default() -> Self {
+        Self {
+            white_balance: true,
+            target_mean: Some(118.0),
+            max_gain: 4.0,
+            equalization: 0.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl ImageNormalizer {
+    /// Gray-world white balance and an exposure to a mean luma of 118, a
+    /// mid gray, with gains up to 4.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_white_balance(mut self, enabled: bool) -> Self {
+        self.white_balance = enabled;
+        self
+    }
+
+    /// Mean luma, 0 to 255, the exposure brings frames to; `None` to keep
+    /// the exposure.
+    pub fn with_exposure(mut self, target_mean: Option<f32>) -> Self {
+        self.target_mean = target_mean;
+        self
+    }
+
+    /// Largest gain, and smallest as its inverse, of the white balance and
+    /// the exposure.
+    pub fn with_max_gain(mut self, gain: f32) -> Self {
+        self.max_gain = gain.max(1.0);
+        self
+    }
+
+    /// Blend of the identity, 0, and the histogram-equalizing tone curve,
+    /// 1. Below 1 the curve stays invertible.
+    pub fn with_equalization(mut self, strength: f32) -> Self {
+        self.equalization = strength.clamp(0.0, 1.0);
+        self
+    }
+
+    /// A fixed gamma applied last; 1 leaves it out.
+    pub fn with_gamma(mut self, gamma: f32) -> Self {
+        self.gamma = gamma;
+        self
+    }
+
+    /// The stages that normalize `array`.
+    pub fn stages(&self, array: &Array2<Color>) -> Vec<ImageAugmentationStageSerDe> {
+        let mut stages = Vec::new();
+        let seen = || array.iter().filter(|px| px.a > 0);
+        let n = seen().count();
+        if n == 0 {
+            return stages;
+        }
+        let clamp = |gain: f32| {
+            if gain.is_finite() {
+                gain.clamp(1.0 / self.max_gain, self.max_gain)
+            } else {
+                self.max_gain
+            }
+        };
+
+        let mut gains = [1.0f32; 3];
+        if self.white_balance {
+            let mut sums = [0.0f64; 3];
+            for px in seen() {
+                sums[0] += px.r as f64;
+                sums[1] += px.g as f64;
+                sums[2] += px.b as f64;
+            }
+            let gray = sums.iter().sum::<f64>() / 3.0;
+            gains = sums.map(|sum| clamp((gray / sum) as f32));
+            stages.push(ImageAugmentationStageSerDe::WhiteBalance {
+                r: gains[0],
+                g: gains[1],
+                b: gains[2],
+            });
+        }
+        let luma = |px: &Color, gain: f32| {
+            let c = |v: u8, g: f32| (v as f32 * g * gain).min(255.0);
+            0.299 * c(px.r, gains[0]) + 0.587 * c(px.g, gains[1]) + 0.114 * c(px.b, gains[2])
+        };
+
+        let mut exposure = 1.0;
+        if let Some(target) = self.target_mean {
+            let mean = seen().map(|px| luma(px, 1.0) as f64).sum::<f64>() / n as f64;
+            exposure = clamp(target / mean as f32);
+            stages.push(ImageAugmentationStageSerDe::Exposure { gain: exposure });
+        }
+
+        if self.equalization > 0.0 {
+            let mut histogram = [0usize; 256];
+            for px in seen() {
+                histogram[luma(px, exposure).round() as usize] += 1;
+            }
+            let mut below = 0;
+            let knots = std::array::from_fn(|i| {
+                let v = 16 * i;
+                below += histogram[v.saturating_sub(16)..v].iter().sum::<usize>();
+                let identity = v.min(255) as f32;
+                let equalized = 255.0 * below as f32 / n as f32;
+                identity + self.equalization * (equalized - identity)
+            });
+            stages.push(ImageAugmentationStageSerDe::ToneCurve { knots });
+        }
+
+        if self.gamma != 1.0 {
+            stages.push(ImageAugmentationStageSerDe::Gamma { gamma: self.gamma });
+        }
+        stages
+    }
+
+    pub fn apply(&self, image: &mut ImageEventSerDe) {
+        let record = ImageAugmentationSerDe {
+            seed: 0,
+            stages: self.stages(&image.array),
+        };
+        replay_image_augmentation(&record, &mut image.array);
+        image.augmentations.push(record);
+        if image.hash.is_some() {
+            image.hash = Some(ImageHashSerDe::from_array(image.array.view()));
+        }
+    }
+}
+
+impl FrameTransform for ImageNormalizer {
+    fn accepts(&self, frame: &SensorDataSerDe) -> bool {
+        matches!(frame, SensorDataSerDe::Image(_))
+    }
+
+    fn apply(&mut self, frame: &mut SensorDataSerDe) {
+        if let SensorDataSerDe::Image(image) = frame {
+            ImageNormalizer::apply(self, image);
+        }
+    }
+
+    fn describe(&self) -> ProcessingStepSerDe {
+        let step = ProcessingStepSerDe::new("image_normalize")
+            .with_param("white_balance", self.white_balance)
+            .with_param("max_gain", self.max_gain as f64)
+            .with_param("equalization", self.equalization as f64)
+            .with_param("gamma", self.gamma as f64);
+        match self.target_mean {
+            Some(mean) => step.with_param("target_mean", mean as f64),
+            None => step,
+        }
+    }
+}
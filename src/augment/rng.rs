@@ -0,0 +1,46 @@
+/// Small seedable generator (SplitMix64) so augmentations are reproducible
+/// without pulling in `rand`. Not suitable for anything cryptographic.
+#[derive(Clone, Debug)]
+pub struct NoiseRng {
+    state: u64,
+    spare: Option<f64>,
+}
+
+impl NoiseRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            spare: None,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform sample in `[0, 1)`.
+    pub fn uniform(&mut self) -> f32 {
+        self.uniform_f64() as f32
+    }
+
+    /// Standard normal sample (Box-Muller).
+    pub fn gaussian(&mut self) -> f32 {
+        if let Some(z) = self.spare.take() {
+            return z as f32;
+        }
+        let u1 = 1.0 - self.uniform_f64(); // (0, 1], keeps ln finite
+        let u2 = self.uniform_f64();
+        let r = (-2.0 * u1.ln()).sqrt();
+        let theta = std::f64::consts::TAU * u2;
+        self.spare = Some(r * theta.sin());
+        (r * theta.cos()) as f32
+    }
+
+    fn uniform_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,365 @@
+//! Serialization with a deadline.
+//!
+//! A large camera or lidar frame can take long enough to encode that a
+//! live pipeline falls behind. [`SerializationBudget::write_json`] stops
+//! encoding a frame once its deadline has passed and writes a reduced
+//! version instead: a preview-resolution image, or a depth, segmentation
+//! or optical flow image or point cloud without its pixels or points.
+//! Reduced frames are still valid [`SensorDataSerDe`] and carry
+//! `degraded: true` in their annotations, see [`DEGRADED_KEY`].
+//!
+//! The deadline bounds the full encoding attempt; the reduced frame is
+//! written after it, so a late frame costs the deadline plus the encoding
+//! of its fallback. IMU and event frames are small and always written in
+//! full.
+
+use crate::report::downscale;
+use crate::{
+    AnnotationValueSerDe, AnnotationsSerDe, DepthImageSerDe, ImageEventSerDe, ImageHashSerDe,
+    InstanceSegmentationImageSerDe, LidarMeasurementSerDe, OpticalFlowImageSerDe,
+    RadarMeasurementSerDe, RawSensorDataSerDe, SemanticLidarMeasurementSerDe,
+    SemanticSegmentationImageSerDe, SensorDataSerDe,
+};
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Annotation set on frames written at reduced [`Fidelity`].
+pub const DEGRADED_KEY: &str = "degraded";
+
+/// Annotation holding the pixel or point count a reduced frame had before.
+pub const ORIGINAL_LEN_KEY: &str = "original_len";
+
+// bytes written between two clock reads
+const CHECK_INTERVAL: usize = 64 * 1024;
+
+/// How much of a frame [`SerializationBudget::write_json`] wrote.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Fidelity {
+    Full,
+    /// Images only: downscaled to the preview width.
+    Preview,
+    /// Everything except the pixels or points.
+    Metadata,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SerializationBudget {
+    deadline: Duration,
+    preview_width: usize,
+}
+
+impl SerializationBudget {
+    /// Give each frame `deadline` for its full encoding. Previews are 160
+    /// pixels wide.
+    pub fn new(deadline: Duration) -> Self {
+        Self {
+            deadline,
+            preview_width: 160,
+        }
+    }
+
+    pub fn with_preview_width(mut self, width: usize) -> Self {
+        self.preview_width = width.max(1);
+        self
+    }
+
+    pub fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    /// Append `frame` to `out` as JSON, reduced if the full encoding misses
+    /// the deadline. On success nothing from the aborted attempt is left in
+    /// `out`.
+    pub fn write_json(
+        &self,
+        frame: &SensorDataSerDe,
+        out: &mut Vec<u8>,
+    ) -> serde_json::Result<Fidelity> {
+        if !matches!(
+            frame,
+            SensorDataSerDe::Image(_)
+                | SensorDataSerDe::OpticalFlow(_)
+                | SensorDataSerDe::SemanticSegmentation(_)
+                | SensorDataSerDe::InstanceSegmentation(_)
+                | SensorDataSerDe::Depth(_)
+                | SensorDataSerDe::Lidar(_)
+                | SensorDataSerDe::SemanticLidar(_)
+                | SensorDataSerDe::Radar(_)
+                | SensorDataSerDe::Raw(_)
+        ) {
+            serde_json::to_writer(&mut *out, frame)?;
+            return Ok(Fidelity::Full);
+        }
+
+        let start = out.len();
+        let mut writer = DeadlineWriter {
+            out: &mut *out,
+            deadline: Instant::now() + self.deadline,
+            unchecked: 0,
+        };
+        match serde_json::to_writer(&mut writer, frame) {
+            Ok(()) => return Ok(Fidelity::Full),
+            Err(e) if e.io_error_kind() == Some(io::ErrorKind::TimedOut) => {}
+            Err(e) => return Err(e),
+        }
+        out.truncate(start);
+
+        let (reduced, fidelity) = self.reduce(frame);
+        serde_json::to_writer(&mut *out, &reduced)?;
+        Ok(fidelity)
+    }
+
+    /// The frame [`write_json`](Self::write_json) falls back to.
+    pub fn reduce(&self, frame: &SensorDataSerDe) -> (SensorDataSerDe, Fidelity) {
+        match frame {
+            SensorDataSerDe::Image(image) => {
+                let array = downscale(&image.array, self.preview_width);
+                let mut preview = ImageEventSerDe {
+                    height: 0,
+                    width: 0,
+                    len: 0,
+                    is_empty: true,
+                    fov_angle: image.fov_angle,
+                    // the hash of the full image would not match the pixels
+                    hash: image.hash.map(|_| ImageHashSerDe::from_array(array.view())),
+                    array,
+                    augmentations: image.augmentations.clone(),
+                    annotations: degraded(&image.annotations, image.len()),
+                };
+                preview.reconcile_shape();
+                (preview.into(), Fidelity::Preview)
+            }
+            SensorDataSerDe::OpticalFlow(flow) => {
+                let mut reduced = OpticalFlowImageSerDe {
+                    array: Array2::default((0, 0)),
+                    annotations: degraded(&flow.annotations, flow.len()),
+                    ..flow.clone()
+                };
+                reduced.reconcile_shape();
+                (reduced.into(), Fidelity::Metadata)
+            }
+            SensorDataSerDe::Depth(depth) => {
+                let mut reduced = DepthImageSerDe {
+                    depth: Array2::default((0, 0)),
+                    raw: None,
+                    annotations: degraded(&depth.annotations, depth.len()),
+                    ..depth.clone()
+                };
+                reduced.reconcile_shape();
+                (reduced.into(), Fidelity::Metadata)
+            }
+            SensorDataSerDe::Raw(raw) => {
+                let reduced = RawSensorDataSerDe {
+                    data: Vec::new(),
+                    annotations: degraded(&raw.annotations, raw.len()),
+                    ..raw.clone()
+                };
+                (reduced.into(), Fidelity::Metadata)
+            }
+            SensorDataSerDe::InstanceSegmentation(seg) => {
+                let mut reduced = InstanceSegmentationImageSerDe {
+                    array: Array2::default((0, 0)),
+                    annotations: degraded(&seg.annotations, seg.len()),
+                    ..seg.clone()
+                };
+                reduced.reconcile_shape();
+                (reduced.into(), Fidelity::Metadata)
+            }
+            SensorDataSerDe::SemanticSegmentation(seg) => {
+                let mut reduced = SemanticSegmentationImageSerDe {
+                    labels: Array2::default((0, 0)),
+                    raw: None,
+                    annotations: degraded(&seg.annotations, seg.len()),
+                    ..seg.clone()
+                };
+                reduced.reconcile_shape();
+                (reduced.into(), Fidelity::Metadata)
+            }
+            SensorDataSerDe::Lidar(lidar) => {
+                let reduced = LidarMeasurementSerDe {
+                    horizontal_angle: lidar.horizontal_angle,
+                    channel_count: lidar.channel_count,
+                    len: 0,
+                    is_empty: true,
+                    detections: Vec::new(),
+                    noise_model: lidar.noise_model,
+                    annotations: degraded(&lidar.annotations, lidar.len()),
+                };
+                (reduced.into(), Fidelity::Metadata)
+            }
+            SensorDataSerDe::SemanticLidar(lidar) => {
+                let reduced = SemanticLidarMeasurementSerDe {
+                    horizontal_angle: lidar.horizontal_angle,
+                    channel_count: lidar.channel_count,
+                    len: 0,
+                    is_empty: true,
+                    detections: Vec::new(),
+                    annotations: degraded(&lidar.annotations, lidar.len()),
+                };
+                (reduced.into(), Fidelity::Metadata)
+            }
+            SensorDataSerDe::Radar(radar) => {
+                let reduced = RadarMeasurementSerDe {
+                    detection_amount: 0,
+                    detections: Vec::new(),
+                    len: 0,
+                    is_empty: true,
+                    annotations: degraded(&radar.annotations, radar.len()),
+                };
+                (reduced.into(), Fidelity::Metadata)
+            }
+            other => (other.clone(), Fidelity::Full),
+        }
+    }
+}
+
+fn degraded(annotations: &AnnotationsSerDe, original_len: usize) -> AnnotationsSerDe {
+    let mut annotations = annotations.clone();
+    annotations.insert(DEGRADED_KEY, AnnotationValueSerDe::Bool(true));
+    annotations.insert(ORIGINAL_LEN_KEY, original_len as i64);
+    annotations
+}
+
+// Fails with `TimedOut` once the deadline has passed. The clock is read
+// every CHECK_INTERVAL bytes, serde_json writes in much smaller pieces.
+struct DeadlineWriter<'a> {
+    out: &'a mut Vec<u8>,
+    deadline: Instant,
+    unchecked: usize,
+}
+
+impl Write for DeadlineWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.unchecked += buf.len();
+        if self.unchecked >= CHECK_INTERVAL {
+            self.unchecked = 0;
+            if Instant::now() >= self.deadline {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+        }
+        self.out.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use carla::sensor::data::Color;
+
+    // large enough that encoding it reads the clock
+    fn image(height: usize, width: usize) -> ImageEventSerDe {
+        let array = Array2::from_shape_fn((height, width), |(y, x)| Color {
+            b: x as u8,
+            g: y as u8,
+            r: 0,
+            a: 255,
+        });
+        ImageEventSerDe {
+            height,
+            width,
+            len: height * width,
+            is_empty: false,
+            fov_angle: 90.0,
+            hash: Some(ImageHashSerDe::from_array(array.view())),
+            array,
+            augmentations: Vec::new(),
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+
+    fn written(out: &[u8]) -> SensorDataSerDe {
+        serde_json::from_slice(out).unwrap()
+    }
+
+    #[test]
+    fn late_images_fall_back_to_a_preview() {
+        let frame = SensorDataSerDe::Image(image(120, 640));
+        let mut out = b"kept".to_vec();
+        let budget = SerializationBudget::new(Duration::ZERO).with_preview_width(64);
+        assert_eq!(
+            budget.write_json(&frame, &mut out).unwrap(),
+            Fidelity::Preview
+        );
+        assert!(out.starts_with(b"kept"));
+
+        let SensorDataSerDe::Image(preview) = written(&out[4..]) else {
+            panic!("not an image")
+        };
+        assert_eq!((preview.height, preview.width), (12, 64));
+        assert_eq!(preview.len, 12 * 64);
+        let annotations = &preview.annotations;
+        assert_eq!(
+            annotations.get(DEGRADED_KEY),
+            Some(&AnnotationValueSerDe::Bool(true))
+        );
+        assert_eq!(
+            annotations.get(ORIGINAL_LEN_KEY),
+            Some(&AnnotationValueSerDe::Int(120 * 640))
+        );
+        assert_eq!(
+            preview.hash,
+            Some(ImageHashSerDe::from_array(preview.array.view()))
+        );
+    }
+
+    #[test]
+    fn late_point_clouds_keep_only_their_metadata() {
+        // the detections are not `Clone`, so the fixture is repeated as JSON
+        let mut json: serde_json::Value =
+            serde_json::from_slice(crate::fixtures::LIDAR_JSON).unwrap();
+        let detections = json["Lidar"]["detections"].as_array().unwrap().clone();
+        json["Lidar"]["detections"] = detections
+            .iter()
+            .cycle()
+            .take(detections.len() * 5_000)
+            .cloned()
+            .collect();
+        json["Lidar"]["len"] = (detections.len() * 5_000).into();
+        let frame: SensorDataSerDe = serde_json::from_value(json).unwrap();
+        let SensorDataSerDe::Lidar(lidar) = &frame else {
+            unreachable!()
+        };
+        let mut out = Vec::new();
+        let budget = SerializationBudget::new(Duration::ZERO);
+        assert_eq!(
+            budget.write_json(&frame, &mut out).unwrap(),
+            Fidelity::Metadata
+        );
+        let SensorDataSerDe::Lidar(reduced) = written(&out) else {
+            panic!("not a lidar frame")
+        };
+        assert!(reduced.detections.is_empty());
+        assert_eq!(reduced.channel_count, lidar.channel_count);
+        assert_eq!(
+            reduced.annotations.get(ORIGINAL_LEN_KEY),
+            Some(&AnnotationValueSerDe::Int(lidar.len as i64))
+        );
+    }
+
+    #[test]
+    fn frames_within_the_deadline_are_written_in_full() {
+        let budget = SerializationBudget::new(Duration::from_secs(60));
+        for frame in [
+            SensorDataSerDe::Image(image(120, 640)),
+            SensorDataSerDe::Imu(crate::fixtures::imu_frame()),
+        ] {
+            let mut out = Vec::new();
+            assert_eq!(budget.write_json(&frame, &mut out).unwrap(), Fidelity::Full);
+            assert_eq!(out, serde_json::to_vec(&frame).unwrap());
+        }
+        // small frames are never reduced, however late
+        let imu = SensorDataSerDe::Imu(crate::fixtures::imu_frame());
+        let late = SerializationBudget::new(Duration::ZERO);
+        assert_eq!(
+            late.write_json(&imu, &mut Vec::new()).unwrap(),
+            Fidelity::Full
+        );
+    }
+}
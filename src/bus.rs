@@ -0,0 +1,431 @@
+//! In-process publish/subscribe over [`SensorDataSerDe`].
+//!
+//! A frame is moved into an `Arc` once when published and every subscriber
+//! receives a pointer to that allocation, so one capture source can feed a
+//! recorder, a live viewer and a metrics consumer without copying frames.
+//!
+//! Each subscriber has its own queue. With a bounded [`OverloadPolicy`], a
+//! full queue sheds its lowest-[`Priority`] frames first and counts what
+//! it dropped in a [`DropReport`].
+
+use crate::pipeline::{SharedFrame, Sink};
+use crate::{GapReason, GapRecordSerDe, SensorDataSerDe, SensorKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+    /// Never dropped, even if the queue has to grow past its capacity.
+    Critical,
+}
+
+/// Queue capacity and per-sensor priorities applied to every subscriber.
+///
+/// By default queues are unbounded. Priorities default to: camera, depth,
+/// segmentation, optical flow and DVS frames low, lidar, semantic lidar,
+/// radar and raw data normal, IMU, GNSS and V2X high, and events
+/// (collision, lane invasion, obstacle) critical.
+#[derive(Clone, Debug, Default)]
+pub struct OverloadPolicy {
+    capacity: Option<usize>,
+    priorities: BTreeMap<SensorKind, Priority>,
+}
+
+impl OverloadPolicy {
+    pub fn bounded(capacity: usize) -> Self {
+        Self {
+            capacity: Some(capacity),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_priority(mut self, kind: SensorKind, priority: Priority) -> Self {
+        self.priorities.insert(kind, priority);
+        self
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    pub fn priority(&self, kind: SensorKind) -> Priority {
+        if let Some(p) = self.priorities.get(&kind) {
+            return *p;
+        }
+        match kind {
+            SensorKind::Image
+            | SensorKind::OpticalFlow
+            | SensorKind::SemanticSegmentation
+            | SensorKind::InstanceSegmentation
+            | SensorKind::Depth
+            | SensorKind::Dvs => Priority::Low,
+            SensorKind::Lidar | SensorKind::SemanticLidar | SensorKind::Radar | SensorKind::Raw => {
+                Priority::Normal
+            }
+            SensorKind::Imu | SensorKind::Gnss | SensorKind::V2x => Priority::High,
+            SensorKind::Collision | SensorKind::LaneInvasion | SensorKind::ObstacleDetection => {
+                Priority::Critical
+            }
+        }
+    }
+}
+
+/// What a subscriber's queue offered and dropped since the last reset.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DropReport {
+    pub offered: u64,
+    pub dropped: BTreeMap<SensorKind, u64>,
+}
+
+impl DropReport {
+    pub fn total_dropped(&self) -> u64 {
+        self.dropped.values().sum()
+    }
+
+    /// One [`GapReason::Overload`] record per sensor kind that lost frames,
+    /// for a writer to put into its stream, e.g. after
+    /// [`Subscriber::take_drop_report`].
+    pub fn gaps(&self) -> Vec<GapRecordSerDe> {
+        self.dropped
+            .iter()
+            .filter(|(_, n)| **n > 0)
+            .map(|(kind, n)| GapRecordSerDe::new(*kind, *n, GapReason::Overload))
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    frames: VecDeque<SharedFrame>,
+    closed: bool,
+    report: DropReport,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    state: Mutex<QueueState>,
+    ready: Condvar,
+}
+
+impl Queue {
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn push(&self, frame: SharedFrame, policy: &OverloadPolicy) {
+        let mut state = self.lock();
+        state.report.offered += 1;
+
+        if let Some(capacity) = policy.capacity
+            && state.frames.len() >= capacity
+        {
+            let incoming = policy.priority(frame.kind());
+            // lowest priority first, oldest among equals
+            let victim = state
+                .frames
+                .iter()
+                .enumerate()
+                .map(|(i, f)| (policy.priority(f.kind()), i))
+                .filter(|(p, _)| *p < Priority::Critical)
+                .min();
+            match victim {
+                Some((p, i)) if p <= incoming => {
+                    let evicted = state.frames.remove(i).expect("index from iter");
+                    *state.report.dropped.entry(evicted.kind()).or_default() += 1;
+                }
+                _ if incoming == Priority::Critical => {}
+                _ => {
+                    *state.report.dropped.entry(frame.kind()).or_default() += 1;
+                    return;
+                }
+            }
+        }
+
+        state.frames.push_back(frame);
+        drop(state);
+        self.ready.notify_one();
+    }
+
+    fn close(&self) {
+        self.lock().closed = true;
+        self.ready.notify_all();
+    }
+}
+
+#[derive(Debug, Default)]
+struct BusInner {
+    policy: OverloadPolicy,
+    queues: Mutex<Vec<Arc<Queue>>>,
+}
+
+impl Drop for BusInner {
+    fn drop(&mut self) {
+        let queues = self
+            .queues
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        for queue in queues.iter() {
+            queue.close();
+        }
+    }
+}
+
+/// Cheap to clone; all clones publish to the same set of subscribers.
+#[derive(Clone, Debug, Default)]
+pub struct SensorBus {
+    inner: Arc<BusInner>,
+}
+
+impl SensorBus {
+    /// A bus with unbounded subscriber queues.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_policy(policy: OverloadPolicy) -> Self {
+        Self {
+            inner: Arc::new(BusInner {
+                policy,
+                queues: Mutex::default(),
+            }),
+        }
+    }
+
+    pub fn policy(&self) -> &OverloadPolicy {
+        &self.inner.policy
+    }
+
+    /// Register a new consumer. It only sees frames published from now on.
+    pub fn subscribe(&self) -> Subscriber {
+        let queue = Arc::new(Queue::default());
+        self.lock().push(Arc::clone(&queue));
+        Subscriber { queue }
+    }
+
+    /// Publish a frame; returns the number of subscribers it was offered to.
+    pub fn publish(&self, data: impl Into<SensorDataSerDe>) -> usize {
+        self.publish_shared(Arc::new(data.into()))
+    }
+
+    /// Publish a frame that is already shared.
+    pub fn publish_shared(&self, data: SharedFrame) -> usize {
+        let mut queues = self.lock();
+        // dropped subscribers are pruned here
+        queues.retain(|q| Arc::strong_count(q) > 1);
+        for queue in queues.iter() {
+            queue.push(Arc::clone(&data), &self.inner.policy);
+        }
+        queues.len()
+    }
+
+    /// Run `sink` on its own thread until the bus closes; the handle returns
+    /// the sink after it was flushed.
+    pub fn spawn_sink<S: Sink + Send + 'static>(&self, mut sink: S) -> JoinHandle<S> {
+        let subscriber = self.subscribe();
+        thread::spawn(move || {
+            subscriber.drain_into(&mut sink);
+            sink
+        })
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Frames waiting in each live subscriber's queue, in subscription order.
+    pub fn queue_depths(&self) -> Vec<usize> {
+        self.lock()
+            .iter()
+            .filter(|q| Arc::strong_count(q) > 1)
+            .map(|q| q.lock().frames.len())
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Arc<Queue>>> {
+        self.inner
+            .queues
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Receiving end of a [`SensorBus`] subscription.
+///
+/// Iterating blocks for the next frame and ends once every `SensorBus`
+/// clone has been dropped and the queue is drained.
+#[derive(Debug)]
+pub struct Subscriber {
+    queue: Arc<Queue>,
+}
+
+impl Subscriber {
+    pub fn recv(&self) -> Option<SharedFrame> {
+        let state = self.queue.lock();
+        let mut state = self
+            .queue
+            .ready
+            .wait_while(state, |s| s.frames.is_empty() && !s.closed)
+            .unwrap_or_else(PoisonError::into_inner);
+        state.frames.pop_front()
+    }
+
+    pub fn try_recv(&self) -> Option<SharedFrame> {
+        self.queue.lock().frames.pop_front()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<SharedFrame> {
+        let state = self.queue.lock();
+        let (mut state, _) = self
+            .queue
+            .ready
+            .wait_timeout_while(state, timeout, |s| s.frames.is_empty() && !s.closed)
+            .unwrap_or_else(PoisonError::into_inner);
+        state.frames.pop_front()
+    }
+
+    /// Frames currently waiting in this subscriber's queue.
+    pub fn pending(&self) -> usize {
+        self.queue.lock().frames.len()
+    }
+
+    pub fn drop_report(&self) -> DropReport {
+        self.queue.lock().report.clone()
+    }
+
+    /// Return the report and start counting from zero.
+    pub fn take_drop_report(&self) -> DropReport {
+        std::mem::take(&mut self.queue.lock().report)
+    }
+
+    /// Feed every frame to `sink` until the bus closes, then flush it.
+    pub fn drain_into(self, sink: &mut impl Sink) {
+        for frame in self {
+            sink.consume(frame);
+        }
+        sink.flush();
+    }
+}
+
+impl Iterator for Subscriber {
+    type Item = SharedFrame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use crate::fixtures::{collision_event, image_frame, imu_frame, lidar_frame};
+
+    fn image() -> SharedFrame {
+        Arc::new(SensorDataSerDe::Image(image_frame()))
+    }
+
+    fn lidar() -> SharedFrame {
+        Arc::new(SensorDataSerDe::Lidar(lidar_frame()))
+    }
+
+    fn imu() -> SharedFrame {
+        Arc::new(SensorDataSerDe::Imu(imu_frame()))
+    }
+
+    fn collision() -> SharedFrame {
+        Arc::new(SensorDataSerDe::Collision(collision_event()))
+    }
+
+    fn queued(subscriber: &Subscriber) -> Vec<SharedFrame> {
+        std::iter::from_fn(|| subscriber.try_recv()).collect()
+    }
+
+    #[test]
+    fn sheds_the_oldest_of_the_lowest_priority() {
+        let bus = SensorBus::with_policy(OverloadPolicy::bounded(3));
+        let subscriber = bus.subscribe();
+        let (first, lidar, second, imu) = (image(), lidar(), image(), imu());
+        for frame in [&first, &lidar, &second, &imu] {
+            bus.publish_shared(Arc::clone(frame));
+        }
+        let left = queued(&subscriber);
+        assert_eq!(left.len(), 3);
+        assert!(Arc::ptr_eq(&left[0], &lidar));
+        assert!(Arc::ptr_eq(&left[1], &second));
+        assert!(Arc::ptr_eq(&left[2], &imu));
+        let report = subscriber.drop_report();
+        assert_eq!(report.offered, 4);
+        assert_eq!(report.dropped, BTreeMap::from([(SensorKind::Image, 1)]));
+    }
+
+    #[test]
+    fn drops_an_incoming_frame_below_everything_queued() {
+        let bus = SensorBus::with_policy(OverloadPolicy::bounded(2));
+        let subscriber = bus.subscribe();
+        let (imu, lidar) = (imu(), lidar());
+        bus.publish_shared(Arc::clone(&imu));
+        bus.publish_shared(Arc::clone(&lidar));
+        bus.publish_shared(image());
+        let left = queued(&subscriber);
+        assert!(Arc::ptr_eq(&left[0], &imu) && Arc::ptr_eq(&left[1], &lidar));
+        assert_eq!(
+            subscriber.drop_report().dropped,
+            BTreeMap::from([(SensorKind::Image, 1)])
+        );
+    }
+
+    #[test]
+    fn an_equal_priority_frame_replaces_the_oldest() {
+        let bus = SensorBus::with_policy(OverloadPolicy::bounded(2));
+        let subscriber = bus.subscribe();
+        let frames = [image(), image(), image()];
+        for frame in &frames {
+            bus.publish_shared(Arc::clone(frame));
+        }
+        let left = queued(&subscriber);
+        assert!(Arc::ptr_eq(&left[0], &frames[1]) && Arc::ptr_eq(&left[1], &frames[2]));
+    }
+
+    #[test]
+    fn critical_frames_grow_the_queue() {
+        let bus = SensorBus::with_policy(OverloadPolicy::bounded(2));
+        let subscriber = bus.subscribe();
+        for _ in 0..3 {
+            bus.publish_shared(collision());
+        }
+        assert_eq!(subscriber.pending(), 3);
+        assert_eq!(subscriber.drop_report().total_dropped(), 0);
+        // with only critical frames queued, anything else is dropped
+        bus.publish_shared(imu());
+        assert_eq!(subscriber.pending(), 3);
+        let report = subscriber.take_drop_report();
+        assert_eq!(report.dropped, BTreeMap::from([(SensorKind::Imu, 1)]));
+        assert_eq!(report.gaps().len(), 1);
+        assert_eq!(
+            (report.gaps()[0].sensor, report.gaps()[0].count),
+            (SensorKind::Imu, 1)
+        );
+        assert_eq!(subscriber.drop_report(), DropReport::default());
+    }
+
+    #[test]
+    fn priorities_can_be_overridden() {
+        let policy = OverloadPolicy::bounded(1).with_priority(SensorKind::Image, Priority::High);
+        assert_eq!(policy.priority(SensorKind::Image), Priority::High);
+        let bus = SensorBus::with_policy(policy);
+        let subscriber = bus.subscribe();
+        let image = image();
+        bus.publish_shared(Arc::clone(&image));
+        bus.publish_shared(lidar());
+        assert!(Arc::ptr_eq(&subscriber.try_recv().unwrap(), &image));
+        assert_eq!(
+            subscriber.drop_report().dropped,
+            BTreeMap::from([(SensorKind::Lidar, 1)])
+        );
+    }
+}
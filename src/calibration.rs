@@ -0,0 +1,92 @@
+//! Intensity attenuation and drop-off model of CARLA's ray-cast lidar.
+//!
+//! CARLA computes each point's intensity as `exp(-a * d)` (with `a` the
+//! atmosphere attenuation rate and `d` the distance to the sensor) and then
+//! randomly drops points, more likely so for low intensities. These helpers
+//! re-apply or undo that model on recorded frames.
+
+mod export;
+
+pub use export::*;
+
+use crate::{LidarMeasurementSerDe, LidarNoiseModelSerDe};
+use carla::sensor::data::LidarDetection as CarlaLidarDetection;
+
+/// Fraction of the intensity left after travelling `distance` meters.
+#[inline]
+pub fn attenuation(model: &LidarNoiseModelSerDe, distance: f32) -> f32 {
+    (-model.atmosphere_attenuation_rate * distance).exp()
+}
+
+/// Probability that CARLA keeps a point, given its intensity.
+pub fn keep_probability(model: &LidarNoiseModelSerDe, intensity: f32) -> f32 {
+    let general = 1.0 - model.dropoff_general_rate;
+    let limit = model.dropoff_intensity_limit;
+    if limit <= 0.0 || intensity > limit {
+        return general.clamp(0.0, 1.0);
+    }
+    let alpha = model.dropoff_zero_intensity / limit;
+    let beta = 1.0 - model.dropoff_zero_intensity;
+    (general * (alpha * intensity + beta)).clamp(0.0, 1.0)
+}
+
+// points are in the sensor frame, so the range is the point's norm
+#[inline]
+fn range(d: &CarlaLidarDetection) -> f32 {
+    (d.point.x * d.point.x + d.point.y * d.point.y + d.point.z * d.point.z).sqrt()
+}
+
+/// Multiply every intensity by the atmospheric attenuation at its range.
+pub fn apply_attenuation(m: &mut LidarMeasurementSerDe, model: &LidarNoiseModelSerDe) {
+    for d in m.detections.iter_mut() {
+        d.intensity *= attenuation(model, range(d));
+    }
+}
+
+/// Divide out the atmospheric attenuation, recovering the unattenuated intensity.
+pub fn undo_attenuation(m: &mut LidarMeasurementSerDe, model: &LidarNoiseModelSerDe) {
+    for d in m.detections.iter_mut() {
+        d.intensity /= attenuation(model, range(d));
+    }
+}
+
+/// Randomly drop points following CARLA's drop-off rules.
+///
+/// `uniform` must return samples in `[0, 1)`; passing a seeded generator makes
+/// the result reproducible. Dropped points cannot be restored, so there is no
+/// inverse; use [`keep_probability`] to re-weight surviving points instead.
+pub fn apply_dropoff(
+    m: &mut LidarMeasurementSerDe,
+    model: &LidarNoiseModelSerDe,
+    mut uniform: impl FnMut() -> f32,
+) {
+    m.detections
+        .retain(|d| uniform() < keep_probability(model, d.intensity));
+    m.len = m.detections.len();
+    m.is_empty = m.detections.is_empty();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intensity_decays_with_distance() {
+        let model = LidarNoiseModelSerDe::default();
+        assert_eq!(attenuation(&model, 0.0), 1.0);
+        assert!((attenuation(&model, 100.0) - (-0.4f32).exp()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn dim_points_are_dropped_more_often() {
+        let model = LidarNoiseModelSerDe::default();
+        assert!((keep_probability(&model, 0.9) - 0.55).abs() < 1e-6);
+        assert!((keep_probability(&model, 0.0) - 0.55 * 0.6).abs() < 1e-6);
+        assert!(keep_probability(&model, 0.4) < keep_probability(&model, 0.8));
+        let no_limit = LidarNoiseModelSerDe {
+            dropoff_intensity_limit: 0.0,
+            ..model
+        };
+        assert!((keep_probability(&no_limit, 0.0) - 0.55).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,308 @@
+//! Calibration file export (Kalibr camchain, OpenCV FileStorage).
+//!
+//! CARLA uses a left-handed frame (x forward, y right, z up). Exported
+//! extrinsics are right-handed: the rig frame is FLU (x forward, y left,
+//! z up), cameras use the optical frame (x right, y down, z forward) and
+//! other sensors use FLU. Plain JSON needs no exporter, the
+//! [`SensorDescriptionSerDe`] list serializes directly.
+
+use crate::{LensDistortionSerDe, SensorDescriptionSerDe};
+use nalgebra::{Matrix3, Matrix4, Vector4};
+use std::fmt;
+
+// CARLA camera axes -> optical axes
+fn carla_to_optical() -> Matrix4<f32> {
+    Matrix4::new(
+        0.0, 1.0, 0.0, 0.0, //
+        0.0, 0.0, -1.0, 0.0, //
+        1.0, 0.0, 0.0, 0.0, //
+        0.0, 0.0, 0.0, 1.0,
+    )
+}
+
+// CARLA axes -> FLU axes
+fn carla_to_flu() -> Matrix4<f32> {
+    Matrix4::from_diagonal(&Vector4::new(1.0, -1.0, 1.0, 1.0))
+}
+
+fn rigid_inverse(m: &Matrix4<f32>) -> Matrix4<f32> {
+    let r_t = m.fixed_view::<3, 3>(0, 0).transpose();
+    let t = -(r_t * m.fixed_view::<3, 1>(0, 3));
+    let mut out = Matrix4::identity();
+    out.fixed_view_mut::<3, 3>(0, 0).copy_from(&r_t);
+    out.fixed_view_mut::<3, 1>(0, 3).copy_from(&t);
+    out
+}
+
+/// Right-handed transform taking points from the sensor frame to the rig frame.
+pub fn rig_from_sensor(desc: &SensorDescriptionSerDe) -> Matrix4<f32> {
+    let sensor = if desc.is_camera() {
+        carla_to_optical()
+    } else {
+        carla_to_flu()
+    };
+    carla_to_flu() * desc.mount.to_homogeneous() * sensor.transpose()
+}
+
+fn write_yaml_rows(f: &mut impl fmt::Write, key: &str, m: &Matrix4<f32>) -> fmt::Result {
+    writeln!(f, "  {key}:")?;
+    for r in 0..4 {
+        writeln!(
+            f,
+            "  - [{:.9}, {:.9}, {:.9}, {:.9}]",
+            m[(r, 0)],
+            m[(r, 1)],
+            m[(r, 2)],
+            m[(r, 3)]
+        )?;
+    }
+    Ok(())
+}
+
+/// Write a Kalibr `camchain.yaml` (or `camchain-imucam.yaml` when the rig has
+/// an IMU) for all cameras in `sensors`, in slice order.
+pub fn write_kalibr_camchain(
+    sensors: &[SensorDescriptionSerDe],
+    f: &mut impl fmt::Write,
+) -> fmt::Result {
+    let imu = sensors
+        .iter()
+        .find(|s| s.type_id == "sensor.other.imu")
+        .map(rig_from_sensor);
+
+    let mut prev: Option<Matrix4<f32>> = None;
+    let cameras = sensors
+        .iter()
+        .filter_map(|s| s.camera.as_ref().map(|c| (s, c)));
+    for (n, (desc, cam)) in cameras.enumerate() {
+        let pose = rig_from_sensor(desc);
+        let sensor_from_rig = rigid_inverse(&pose);
+
+        writeln!(f, "cam{n}:")?;
+        writeln!(f, "  camera_model: pinhole")?;
+        writeln!(
+            f,
+            "  intrinsics: [{:.9}, {:.9}, {:.9}, {:.9}]",
+            cam.fx, cam.fy, cam.cx, cam.cy
+        )?;
+        // Kalibr's radtan has no k3
+        let (model, coeffs) = match cam.distortion {
+            None => ("radtan", vec![0.0; 4]),
+            Some(LensDistortionSerDe::BrownConrady { k1, k2, p1, p2, .. }) => {
+                ("radtan", vec![k1, k2, p1, p2])
+            }
+            Some(lens @ LensDistortionSerDe::KannalaBrandt { .. }) => {
+                ("equidistant", lens.coefficients())
+            }
+        };
+        writeln!(f, "  distortion_model: {model}")?;
+        writeln!(f, "  distortion_coeffs: {coeffs:?}")?;
+        writeln!(f, "  resolution: [{}, {}]", cam.width, cam.height)?;
+        writeln!(f, "  rostopic: /{}/image_raw", desc.name())?;
+        if let Some(imu) = &imu {
+            write_yaml_rows(f, "T_cam_imu", &(sensor_from_rig * imu))?;
+        }
+        if let Some(prev) = &prev {
+            write_yaml_rows(f, "T_cn_cnm1", &(sensor_from_rig * prev))?;
+        }
+        prev = Some(pose);
+    }
+    Ok(())
+}
+
+// OpenCV only accepts `[A-Za-z][A-Za-z0-9_-]*` as keys
+fn opencv_key(name: &str) -> String {
+    let mut key: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !key.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        key.insert(0, 's');
+    }
+    key
+}
+
+fn write_opencv_matrix(
+    f: &mut impl fmt::Write,
+    key: &str,
+    rows: usize,
+    cols: usize,
+    data: impl IntoIterator<Item = f32>,
+) -> fmt::Result {
+    writeln!(f, "{key}: !!opencv-matrix")?;
+    writeln!(f, "   rows: {rows}")?;
+    writeln!(f, "   cols: {cols}")?;
+    writeln!(f, "   dt: d")?;
+    write!(f, "   data: [")?;
+    for (i, v) in data.into_iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{v:.9}")?;
+    }
+    writeln!(f, "]")
+}
+
+/// Write an OpenCV `FileStorage` YAML document with, per sensor,
+/// `<name>_T_rig_sensor` and, for cameras, `<name>_camera_matrix`,
+/// `<name>_distortion_coefficients` and the image size. Fisheye cameras
+/// have 4 `cv::fisheye` coefficients and a `<name>_distortion_model`.
+pub fn write_opencv_storage(
+    sensors: &[SensorDescriptionSerDe],
+    f: &mut impl fmt::Write,
+) -> fmt::Result {
+    writeln!(f, "%YAML:1.0")?;
+    writeln!(f, "---")?;
+    for desc in sensors {
+        let key = opencv_key(&desc.name());
+        if let Some(cam) = &desc.camera {
+            let k: Matrix3<f32> = cam.camera_matrix();
+            writeln!(f, "{key}_image_width: {}", cam.width)?;
+            writeln!(f, "{key}_image_height: {}", cam.height)?;
+            // nalgebra is column-major, FileStorage wants row-major
+            write_opencv_matrix(
+                f,
+                &format!("{key}_camera_matrix"),
+                3,
+                3,
+                k.transpose().iter().copied(),
+            )?;
+            let coeffs = cam
+                .distortion
+                .map_or(vec![0.0; 5], |lens| lens.coefficients());
+            if let Some(LensDistortionSerDe::KannalaBrandt { .. }) = cam.distortion {
+                writeln!(f, "{key}_distortion_model: fisheye")?;
+            }
+            write_opencv_matrix(
+                f,
+                &format!("{key}_distortion_coefficients"),
+                1,
+                coeffs.len(),
+                coeffs,
+            )?;
+        }
+        let pose = rig_from_sensor(desc);
+        write_opencv_matrix(
+            f,
+            &format!("{key}_T_rig_sensor"),
+            4,
+            4,
+            pose.transpose().iter().copied(),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CameraInfoSerDe;
+    use nalgebra::{Isometry3, Vector3};
+
+    fn camera(role_name: &str, mount: Isometry3<f32>) -> SensorDescriptionSerDe {
+        SensorDescriptionSerDe {
+            id: 1,
+            type_id: "sensor.camera.rgb".into(),
+            role_name: role_name.into(),
+            mount,
+            camera: Some(CameraInfoSerDe::from_fov(800, 600, 90.0)),
+        }
+    }
+
+    fn imu() -> SensorDescriptionSerDe {
+        SensorDescriptionSerDe {
+            id: 2,
+            type_id: "sensor.other.imu".into(),
+            role_name: String::new(),
+            mount: Isometry3::translation(0.0, 0.0, 1.0),
+            camera: None,
+        }
+    }
+
+    fn apply(m: &Matrix4<f32>, v: [f32; 3]) -> Vector3<f32> {
+        (m * Vector4::new(v[0], v[1], v[2], 1.0)).xyz()
+    }
+
+    #[test]
+    fn camera_looks_along_the_rig_x_axis() {
+        let pose = rig_from_sensor(&camera("front", Isometry3::identity()));
+        let origin = apply(&pose, [0.0; 3]);
+        assert!((apply(&pose, [0.0, 0.0, 1.0]) - origin).relative_eq(&Vector3::x(), 1e-6, 1e-6));
+        // optical x is right, FLU y is left
+        assert!((apply(&pose, [1.0, 0.0, 0.0]) - origin).relative_eq(&-Vector3::y(), 1e-6, 1e-6));
+        assert!((apply(&pose, [0.0, 1.0, 0.0]) - origin).relative_eq(&-Vector3::z(), 1e-6, 1e-6));
+    }
+
+    #[test]
+    fn mount_is_converted_to_the_right_handed_rig() {
+        // CARLA y is right and a positive yaw turns right
+        let mount = Isometry3::new(
+            Vector3::new(1.0, 2.0, 3.0),
+            Vector3::z() * std::f32::consts::FRAC_PI_2,
+        );
+        let pose = rig_from_sensor(&camera("right", mount));
+        let origin = apply(&pose, [0.0; 3]);
+        assert!(origin.relative_eq(&Vector3::new(1.0, -2.0, 3.0), 1e-6, 1e-6));
+        let forward = apply(&pose, [0.0, 0.0, 1.0]) - origin;
+        assert!(forward.relative_eq(&-Vector3::y(), 1e-5, 1e-5), "{forward}");
+    }
+
+    #[test]
+    fn rigid_inverse_undoes_the_pose() {
+        let mount = Isometry3::new(Vector3::new(0.5, -1.0, 2.0), Vector3::new(0.1, 0.2, 0.3));
+        let pose = rig_from_sensor(&camera("cam", mount));
+        assert!((rigid_inverse(&pose) * pose).relative_eq(&Matrix4::identity(), 1e-5, 1e-5));
+    }
+
+    #[test]
+    fn camchain_lists_cameras_with_imu_and_baseline() {
+        let sensors = [
+            camera("left", Isometry3::translation(0.0, -0.5, 0.0)),
+            imu(),
+            camera("right", Isometry3::translation(0.0, 0.5, 0.0)),
+        ];
+        let mut yaml = String::new();
+        write_kalibr_camchain(&sensors, &mut yaml).unwrap();
+        assert!(yaml.starts_with("cam0:\n"), "{yaml}");
+        assert!(yaml.contains("cam1:\n"), "{yaml}");
+        assert!(yaml.contains("rostopic: /left/image_raw"), "{yaml}");
+        assert!(
+            yaml.contains(
+                "intrinsics: [400.000000000, 400.000000000, 400.000000000, 300.000000000]"
+            ),
+            "{yaml}"
+        );
+        assert_eq!(yaml.matches("T_cam_imu:").count(), 2, "{yaml}");
+        assert_eq!(yaml.matches("T_cn_cnm1:").count(), 1, "{yaml}");
+        // the right camera is 1 m right of the left one, along optical x
+        assert!(
+            yaml.contains("- [1.000000000, 0.000000000, 0.000000000, -1.000000000]"),
+            "{yaml}"
+        );
+    }
+
+    #[test]
+    fn opencv_keys_start_with_a_letter() {
+        assert_eq!(opencv_key("front-left"), "front-left");
+        assert_eq!(opencv_key("0 front.cam"), "s0_front_cam");
+        let mut yaml = String::new();
+        write_opencv_storage(&[camera("front", Isometry3::identity()), imu()], &mut yaml).unwrap();
+        assert!(yaml.starts_with("%YAML:1.0\n---\n"), "{yaml}");
+        assert!(yaml.contains("front_image_width: 800"), "{yaml}");
+        assert!(
+            yaml.contains("front_camera_matrix: !!opencv-matrix"),
+            "{yaml}"
+        );
+        assert!(
+            yaml.contains("sensor_2_T_rig_sensor: !!opencv-matrix"),
+            "{yaml}"
+        );
+        assert!(!yaml.contains("sensor_2_camera_matrix"), "{yaml}");
+    }
+}
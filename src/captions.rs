@@ -0,0 +1,130 @@
+//! Subtitle tracks (WebVTT, SRT) for reviewing recordings in a video player.
+//!
+//! Cue times are relative to the timestamp of the first frame of the
+//! encoded video, so pass the simulation timestamp of that frame to
+//! [`CaptionTrack::new`] and the captions line up with the video.
+
+use crate::SensorDataSerDe;
+use std::fmt::{self, Write};
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cue {
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct CaptionTrack {
+    video_start: f64,
+    cue_duration: Duration,
+    cues: Vec<Cue>,
+}
+
+impl CaptionTrack {
+    /// `video_start` is the simulation timestamp, in seconds, of the first
+    /// video frame.
+    pub fn new(video_start: f64) -> Self {
+        Self {
+            video_start,
+            cue_duration: Duration::from_secs(2),
+            cues: Vec::new(),
+        }
+    }
+
+    /// How long event cues stay on screen, 2 s by default.
+    pub fn with_cue_duration(mut self, duration: Duration) -> Self {
+        self.cue_duration = duration;
+        self
+    }
+
+    pub fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    /// Add a cue at simulation time `timestamp`. Cues before the start of
+    /// the video are clamped to its first frame.
+    pub fn push(&mut self, timestamp: f64, duration: Duration, text: impl Into<String>) {
+        let start = Duration::from_secs_f64((timestamp - self.video_start).max(0.0));
+        self.cues.push(Cue {
+            start,
+            end: start + duration,
+            text: text.into(),
+        });
+    }
+
+    /// Add a cue describing an event frame; returns `false` for frames that
+    /// are not events (camera, lidar, ...).
+    pub fn push_event(&mut self, timestamp: f64, frame: &SensorDataSerDe) -> bool {
+        match event_summary(frame) {
+            Some(text) => {
+                self.push(timestamp, self.cue_duration, text);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn write_webvtt(&self, out: &mut impl Write) -> fmt::Result {
+        writeln!(out, "WEBVTT")?;
+        for cue in self.sorted() {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{} --> {}",
+                timestamp(cue.start, '.'),
+                timestamp(cue.end, '.')
+            )?;
+            writeln!(out, "{}", cue.text)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_srt(&self, out: &mut impl Write) -> fmt::Result {
+        for (i, cue) in self.sorted().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{}", i + 1)?;
+            writeln!(
+                out,
+                "{} --> {}",
+                timestamp(cue.start, ','),
+                timestamp(cue.end, ',')
+            )?;
+            writeln!(out, "{}", cue.text)?;
+        }
+        Ok(())
+    }
+
+    // both formats require cues in start order
+    fn sorted(&self) -> impl Iterator<Item = &Cue> {
+        let mut cues: Vec<&Cue> = self.cues.iter().collect();
+        cues.sort_by_key(|c| c.start);
+        cues.into_iter()
+    }
+}
+
+/// One-line, human readable description of an event frame, `None` for
+/// frames that are not events.
+pub fn event_summary(frame: &SensorDataSerDe) -> Option<String> {
+    match frame {
+        SensorDataSerDe::Collision(_)
+        | SensorDataSerDe::LaneInvasion(_)
+        | SensorDataSerDe::ObstacleDetection(_) => Some(frame.to_string()),
+        _ => None,
+    }
+}
+
+// hh:mm:ss.mmm, with `,` as the decimal separator for SRT
+fn timestamp(t: Duration, sep: char) -> String {
+    let ms = t.as_millis();
+    format!(
+        "{:02}:{:02}:{:02}{sep}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
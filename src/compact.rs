@@ -0,0 +1,133 @@
+//! Frames without their derived count fields.
+//!
+//! Image, lidar, radar and optical-flow payloads carry `len`, `is_empty`
+//! and, for radar, `detection_amount`, which duplicate the length of their
+//! data. Wrapping a value with [`compact`] serializes it with those fields
+//! left out:
+//!
+//! ```ignore
+//! let json = serde_json::to_string(&compact(&frame))?;
+//! ```
+//!
+//! Readers of this crate derive missing counts from the data, so they read
+//! both layouts; older readers need the counts present, which is why the
+//! default layout keeps them. Like [`naming`](crate::naming), the wrapper
+//! sits between the value and any serializer, and it nests with the other
+//! wrappers, e.g. `FieldNaming::CamelCase.wrap(&compact(&frame))`.
+//!
+//! The payload types mark their count fields themselves, so a new payload
+//! type only has to mark its own. The wrapper switches them off for the
+//! current thread while the wrapped value serializes.
+
+use serde::ser::{Serialize, Serializer};
+use std::cell::Cell;
+
+thread_local! {
+    // set while a `Compact` value serializes on this thread
+    static COMPACT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether a derived count field is left out; the payload types name it in
+/// `#[serde(skip_serializing_if = "crate::compact::skip_derived")]`.
+pub(crate) fn skip_derived<T>(_: &T) -> bool {
+    COMPACT.get()
+}
+
+pub fn compact<T: Serialize + ?Sized>(value: &T) -> Compact<'_, T> {
+    Compact { value }
+}
+
+/// A value serialized without derived count fields, see [`compact`].
+#[derive(Clone, Copy, Debug)]
+pub struct Compact<'a, T: ?Sized> {
+    value: &'a T,
+}
+
+impl<T: Serialize + ?Sized> Serialize for Compact<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // restores the mode of an enclosing value, also when `serialize` panics
+        struct Restore(bool);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                COMPACT.set(self.0);
+            }
+        }
+        let _restore = Restore(COMPACT.replace(true));
+        self.value.serialize(serializer)
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use crate::SensorDataSerDe;
+    use crate::naming::FieldNaming;
+    use serde_json::Value;
+
+    // through text, as f32 values widen differently in `to_value`
+    fn value(frame: &impl Serialize) -> Value {
+        serde_json::from_str(&serde_json::to_string(frame).unwrap()).unwrap()
+    }
+
+    fn payload(frame: &Value) -> &serde_json::Map<String, Value> {
+        let (_, payload) = frame.as_object().unwrap().iter().next().unwrap();
+        payload.as_object().unwrap()
+    }
+
+    #[test]
+    fn leaves_out_derived_counts_and_reads_back() {
+        for frame in crate::fixtures::all() {
+            let full = value(&frame);
+            let small = value(&compact(&frame));
+            let mut expected = payload(&full).clone();
+            if matches!(
+                frame,
+                SensorDataSerDe::Image(_) | SensorDataSerDe::Lidar(_) | SensorDataSerDe::Radar(_)
+            ) {
+                assert!(expected.contains_key("len"));
+                ["len", "is_empty", "detection_amount"]
+                    .iter()
+                    .for_each(|key| drop(expected.remove(*key)));
+            }
+            assert_eq!(payload(&small), &expected, "{:?}", frame.kind());
+
+            let back: SensorDataSerDe = serde_json::from_value(small).unwrap();
+            assert_eq!(value(&back), full);
+        }
+    }
+
+    #[test]
+    fn nests_inside_field_naming() {
+        let frame = SensorDataSerDe::Radar(crate::fixtures::radar_frame());
+        let compact = compact(&frame);
+        let json = serde_json::to_value(FieldNaming::CamelCase.wrap(&compact)).unwrap();
+        let payload = payload(&json);
+        assert!(payload.contains_key("detections"));
+        assert!(!payload.contains_key("detectionAmount"));
+        assert!(!payload.contains_key("isEmpty"));
+    }
+
+    #[test]
+    fn leaves_the_counts_of_later_values_alone() {
+        let frame = SensorDataSerDe::Radar(crate::fixtures::radar_frame());
+        let both = value(&(compact(&frame), &frame));
+        assert!(!payload(&both[0]).contains_key("detection_amount"));
+        assert!(payload(&both[1]).contains_key("detection_amount"));
+        assert!(payload(&value(&frame)).contains_key("len"));
+    }
+
+    #[cfg(feature = "recording")]
+    #[test]
+    fn compact_recordings_read_back() {
+        use crate::recording::Recording;
+        let recording = Recording {
+            frames: crate::fixtures::all(),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        recording.write_jsonl_compact(&mut out).unwrap();
+        assert!(!String::from_utf8_lossy(&out).contains("\"detection_amount\""));
+        let back = Recording::read_jsonl(out.as_slice()).unwrap();
+        assert_eq!(back.frames.len(), recording.frames.len());
+    }
+}
@@ -0,0 +1,155 @@
+//! Weather and time-of-day buckets of frames, for balanced datasets.
+//!
+//! Each frame names the bucket of the weather it was captured in under
+//! [`CONDITIONS_KEY`], e.g. `rain/night`. A [`ConditionsTagger`] sets it
+//! from the current [`WeatherParametersSerDe`], `Recording::tag_conditions`
+//! from the weather in the recording header, and
+//! `Recording::sample_stratified` exports the same number of frames per
+//! bucket.
+
+use crate::pipeline::FrameTransform;
+use crate::{AnnotationValueSerDe, ProcessingStepSerDe, SensorDataSerDe, WeatherParametersSerDe};
+use std::fmt;
+
+pub const CONDITIONS_KEY: &str = "conditions";
+
+/// Fog outranks rain, which outranks clouds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WeatherBucket {
+    Clear,
+    Cloudy,
+    Rain,
+    Fog,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TimeOfDay {
+    /// The sun 15° or more above the horizon.
+    Day,
+    /// The sun less than 15° above the horizon.
+    Twilight,
+    /// The sun below the horizon.
+    Night,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConditionsBucket {
+    pub weather: WeatherBucket,
+    pub time_of_day: TimeOfDay,
+}
+
+impl ConditionsBucket {
+    pub fn of(weather: &WeatherParametersSerDe) -> Self {
+        let bucket = if weather.fog_density >= 40.0 {
+            WeatherBucket::Fog
+        } else if weather.precipitation >= 30.0 {
+            WeatherBucket::Rain
+        } else if weather.cloudiness >= 60.0 {
+            WeatherBucket::Cloudy
+        } else {
+            WeatherBucket::Clear
+        };
+        let time_of_day = if weather.is_night() {
+            TimeOfDay::Night
+        } else if weather.sun_altitude_angle < 15.0 {
+            TimeOfDay::Twilight
+        } else {
+            TimeOfDay::Day
+        };
+        Self {
+            weather: bucket,
+            time_of_day,
+        }
+    }
+
+    /// The inverse of the `Display` form, e.g. `rain/night`.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let (weather, time_of_day) = tag.split_once('/')?;
+        Some(Self {
+            weather: match weather {
+                "clear" => WeatherBucket::Clear,
+                "cloudy" => WeatherBucket::Cloudy,
+                "rain" => WeatherBucket::Rain,
+                "fog" => WeatherBucket::Fog,
+                _ => return None,
+            },
+            time_of_day: match time_of_day {
+                "day" => TimeOfDay::Day,
+                "twilight" => TimeOfDay::Twilight,
+                "night" => TimeOfDay::Night,
+                _ => return None,
+            },
+        })
+    }
+}
+
+/// `rain/night`
+impl fmt::Display for ConditionsBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let weather = match self.weather {
+            WeatherBucket::Clear => "clear",
+            WeatherBucket::Cloudy => "cloudy",
+            WeatherBucket::Rain => "rain",
+            WeatherBucket::Fog => "fog",
+        };
+        let time_of_day = match self.time_of_day {
+            TimeOfDay::Day => "day",
+            TimeOfDay::Twilight => "twilight",
+            TimeOfDay::Night => "night",
+        };
+        write!(f, "{weather}/{time_of_day}")
+    }
+}
+
+pub fn conditions(frame: &SensorDataSerDe) -> Option<ConditionsBucket> {
+    match frame.annotations().get(CONDITIONS_KEY)? {
+        AnnotationValueSerDe::Text(tag) => ConditionsBucket::parse(tag),
+        _ => None,
+    }
+}
+
+pub fn set_conditions(frame: &mut SensorDataSerDe, bucket: ConditionsBucket) {
+    frame.annotations_mut().insert(
+        CONDITIONS_KEY,
+        AnnotationValueSerDe::Text(bucket.to_string()),
+    );
+}
+
+/// Tags every frame with the bucket of the current weather; frames that
+/// already name one keep it. Call [`set_weather`](Self::set_weather) when
+/// the weather of a live run changes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConditionsTagger {
+    weather: WeatherParametersSerDe,
+}
+
+impl ConditionsTagger {
+    pub fn new(weather: WeatherParametersSerDe) -> Self {
+        Self { weather }
+    }
+
+    pub fn set_weather(&mut self, weather: WeatherParametersSerDe) {
+        self.weather = weather;
+    }
+
+    pub fn bucket(&self) -> ConditionsBucket {
+        ConditionsBucket::of(&self.weather)
+    }
+}
+
+impl FrameTransform for ConditionsTagger {
+    fn accepts(&self, frame: &SensorDataSerDe) -> bool {
+        conditions(frame).is_none()
+    }
+
+    fn apply(&mut self, frame: &mut SensorDataSerDe) {
+        if conditions(frame).is_none() {
+            set_conditions(frame, self.bucket());
+        }
+    }
+
+    fn describe(&self) -> ProcessingStepSerDe {
+        ProcessingStepSerDe::new("conditions_tag")
+            .with_param("conditions", self.bucket().to_string())
+    }
+}
@@ -0,0 +1,436 @@
+//! Declarative recording profiles.
+//!
+//! A [`RecorderConfig`] lists the sensors to spawn and capture, how often
+//! to keep their frames, how to encode them and where to write them, so a
+//! recording setup can be versioned next to the data it produced.
+//! [`RecorderConfig::load`] picks the format from the file extension:
+//! `.toml` with the `toml` feature, `.yaml` or `.yml` with the `yaml`
+//! feature and JSON otherwise. The types are plain serde structs; call
+//! [`RecorderConfig::validate`] after parsing them any other way.
+//!
+//! Deployments layer environment variables and `--set` flags on top of the
+//! file with [`RecorderConfig::resolve`]; [`ConfigWatcher`] picks up edits
+//! to the file while recording.
+
+mod overrides;
+mod reload;
+
+pub use overrides::*;
+pub use reload::*;
+
+use carla::geom::{Location, Rotation, Transform, TransformExt};
+use nalgebra::Isometry3;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::{error, fmt, fs, io};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecorderConfig {
+    #[serde(default)]
+    pub name: String,
+    pub sensors: Vec<SensorConfig>,
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+    #[serde(default)]
+    pub rotation: RotationConfig,
+    /// Labels to drop into the recording; entries added while recording
+    /// are emitted when the config is reloaded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SensorConfig {
+    /// Blueprint id, e.g. `sensor.camera.rgb`.
+    pub blueprint: String,
+    /// Unique within the config; sinks refer to sensors by it.
+    pub role_name: String,
+    #[serde(default)]
+    pub mount: MountConfig,
+    /// Blueprint attributes, e.g. `image_size_x = 1280`. Numbers and
+    /// booleans are accepted and kept in CARLA's string form.
+    #[serde(default, deserialize_with = "attribute_values")]
+    pub attributes: BTreeMap<String, String>,
+    /// Keep every n-th frame; 1 keeps all of them.
+    #[serde(default = "default_decimation")]
+    pub decimation: u32,
+    #[serde(default)]
+    pub codec: Codec,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_decimation() -> u32 {
+    1
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn attribute_values<'de, D: Deserializer<'de>>(d: D) -> Result<BTreeMap<String, String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Scalar {
+        String(String),
+        Int(i64),
+        Float(f64),
+        Bool(bool),
+    }
+
+    let raw = BTreeMap::<String, Scalar>::deserialize(d)?;
+    Ok(raw
+        .into_iter()
+        .map(|(k, v)| {
+            let v = match v {
+                Scalar::String(s) => s,
+                Scalar::Int(i) => i.to_string(),
+                Scalar::Float(f) => f.to_string(),
+                Scalar::Bool(b) => b.to_string(),
+            };
+            (k, v)
+        })
+        .collect())
+}
+
+impl SensorConfig {
+    /// Whether the frame with this per-sensor index survives decimation.
+    pub fn keeps(&self, index: u64) -> bool {
+        self.enabled && index.is_multiple_of(self.decimation.max(1) as u64)
+    }
+
+    pub fn is_camera(&self) -> bool {
+        self.blueprint.starts_with("sensor.camera.")
+    }
+}
+
+/// Sensor pose relative to its parent in CARLA's convention: meters and
+/// degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MountConfig {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+impl MountConfig {
+    pub fn isometry(&self) -> Isometry3<f32> {
+        let transform = Transform {
+            location: Location {
+                x: self.x,
+                y: self.y,
+                z: self.z,
+            },
+            rotation: Rotation {
+                roll: self.roll,
+                pitch: self.pitch,
+                yaw: self.yaw,
+            },
+        };
+        transform.to_na()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Codec {
+    /// The `*SerDe` type as JSON; works for every sensor.
+    #[default]
+    Json,
+    /// Uncompressed 24-bit BMP, cameras only.
+    Bmp,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SinkConfig {
+    /// JSON-lines file, one `SensorDataSerDe` per line.
+    JsonLines {
+        path: PathBuf,
+        /// Role names to write; empty means all sensors.
+        #[serde(default)]
+        sensors: Vec<String>,
+    },
+}
+
+/// When to start a new output file; unset limits never trigger.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RotationConfig {
+    pub max_frames: Option<u64>,
+    pub max_bytes: Option<u64>,
+    pub max_seconds: Option<f64>,
+}
+
+/// One problem found by [`RecorderConfig::validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Dotted path of the offending field, e.g. `sensors[2].decimation`.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl error::Error for ConfigError {}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    #[cfg(feature = "toml")]
+    Toml(toml::de::Error),
+    #[cfg(feature = "yaml")]
+    Yaml(serde_yaml::Error),
+    /// A `.toml` or `.yaml` file without the feature that parses it.
+    Unsupported(PathBuf),
+    Invalid(Vec<ConfigError>),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "cannot read config: {e}"),
+            Self::Parse(e) => write!(f, "cannot parse config: {e}"),
+            #[cfg(feature = "toml")]
+            Self::Toml(e) => write!(f, "cannot parse config: {e}"),
+            #[cfg(feature = "yaml")]
+            Self::Yaml(e) => write!(f, "cannot parse config: {e}"),
+            Self::Unsupported(path) => write!(
+                f,
+                "cannot parse {}: build with the feature for its format",
+                path.display()
+            ),
+            Self::Invalid(errors) => {
+                write!(f, "invalid config:")?;
+                for e in errors {
+                    write!(f, "\n  {e}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Parse(e) => Some(e),
+            #[cfg(feature = "toml")]
+            Self::Toml(e) => Some(e),
+            #[cfg(feature = "yaml")]
+            Self::Yaml(e) => Some(e),
+            Self::Unsupported(_) | Self::Invalid(_) => None,
+        }
+    }
+}
+
+impl RecorderConfig {
+    /// Read, parse and validate a config file, TOML or YAML by its
+    /// extension and JSON otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(LoadError::Io)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml(&text),
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => Self::from_yaml(&text),
+            #[cfg(not(feature = "toml"))]
+            Some("toml") => Err(LoadError::Unsupported(path.into())),
+            #[cfg(not(feature = "yaml"))]
+            Some("yaml" | "yml") => Err(LoadError::Unsupported(path.into())),
+            _ => Self::from_json(&text),
+        }
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, LoadError> {
+        let config: Self = serde_json::from_str(text).map_err(LoadError::Parse)?;
+        config.validate().map_err(LoadError::Invalid)?;
+        Ok(config)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(text: &str) -> Result<Self, LoadError> {
+        let config: Self = toml::from_str(text).map_err(LoadError::Toml)?;
+        config.validate().map_err(LoadError::Invalid)?;
+        Ok(config)
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(text: &str) -> Result<Self, LoadError> {
+        let config: Self = serde_yaml::from_str(text).map_err(LoadError::Yaml)?;
+        config.validate().map_err(LoadError::Invalid)?;
+        Ok(config)
+    }
+
+    pub fn sensor(&self, role_name: &str) -> Option<&SensorConfig> {
+        self.sensors.iter().find(|s| s.role_name == role_name)
+    }
+
+    /// Check the invariants serde cannot express; reports every problem,
+    /// not just the first one.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        let mut error = |path: String, message: &str| {
+            errors.push(ConfigError {
+                path,
+                message: message.into(),
+            })
+        };
+
+        if self.sensors.is_empty() {
+            error("sensors".into(), "at least one sensor is required");
+        }
+        let mut roles = BTreeSet::new();
+        for (i, s) in self.sensors.iter().enumerate() {
+            let path = |field: &str| format!("sensors[{i}].{field}");
+            if !s.blueprint.starts_with("sensor.") {
+                error(path("blueprint"), "not a sensor blueprint");
+            }
+            if s.role_name.is_empty() {
+                error(path("role_name"), "must not be empty");
+            } else if !roles.insert(s.role_name.as_str()) {
+                error(path("role_name"), "duplicate role name");
+            }
+            if s.decimation == 0 {
+                error(path("decimation"), "must be at least 1");
+            }
+            if s.codec == Codec::Bmp && !s.is_camera() {
+                error(path("codec"), "bmp is only supported for cameras");
+            }
+        }
+
+        for (i, sink) in self.sinks.iter().enumerate() {
+            match sink {
+                SinkConfig::JsonLines { path, sensors } => {
+                    if path.as_os_str().is_empty() {
+                        error(format!("sinks[{i}].path"), "must not be empty");
+                    }
+                    for (j, role) in sensors.iter().enumerate() {
+                        if !roles.contains(role.as_str()) {
+                            error(format!("sinks[{i}].sensors[{j}]"), "unknown sensor");
+                        }
+                    }
+                }
+            }
+        }
+
+        let r = &self.rotation;
+        if r.max_frames == Some(0) {
+            error("rotation.max_frames".into(), "must be positive");
+        }
+        if r.max_bytes == Some(0) {
+            error("rotation.max_bytes".into(), "must be positive");
+        }
+        if r.max_seconds.is_some_and(|s| s.is_nan() || s <= 0.0) {
+            error("rotation.max_seconds".into(), "must be positive");
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"{
+        "name": "front",
+        "sensors": [{
+            "blueprint": "sensor.camera.rgb",
+            "role_name": "front",
+            "attributes": {"image_size_x": 1280, "fov": 90.5},
+            "decimation": 2,
+            "codec": "bmp"
+        }],
+        "sinks": [{"type": "json_lines", "path": "front.jsonl", "sensors": ["front"]}],
+        "rotation": {"max_frames": 1000}
+    }"#;
+
+    fn load(name: &str, text: &str) -> Result<RecorderConfig, LoadError> {
+        let path = std::env::temp_dir().join(format!("config-{}-{name}", std::process::id()));
+        fs::write(&path, text).unwrap();
+        let config = RecorderConfig::load(&path);
+        fs::remove_file(&path).unwrap();
+        config
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn loads_toml_by_extension() {
+        let text = r#"
+            name = "front"
+
+            [[sensors]]
+            blueprint = "sensor.camera.rgb"
+            role_name = "front"
+            attributes = { image_size_x = 1280, fov = 90.5 }
+            decimation = 2
+            codec = "bmp"
+
+            [[sinks]]
+            type = "json_lines"
+            path = "front.jsonl"
+            sensors = ["front"]
+
+            [rotation]
+            max_frames = 1000
+        "#;
+        let expected = RecorderConfig::from_json(JSON).unwrap();
+        assert_eq!(load("a.toml", text).unwrap(), expected);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn loads_yaml_by_extension() {
+        let text = "
+name: front
+sensors:
+  - blueprint: sensor.camera.rgb
+    role_name: front
+    attributes: {image_size_x: 1280, fov: 90.5}
+    decimation: 2
+    codec: bmp
+sinks:
+  - type: json_lines
+    path: front.jsonl
+    sensors: [front]
+rotation:
+  max_frames: 1000
+";
+        let expected = RecorderConfig::from_json(JSON).unwrap();
+        assert_eq!(load("a.yaml", text).unwrap(), expected);
+        assert_eq!(load("a.yml", text).unwrap(), expected);
+    }
+
+    #[test]
+    fn validates_every_format() {
+        let invalid = JSON.replace("\"decimation\": 2", "\"decimation\": 0");
+        assert!(matches!(
+            load("a.json", &invalid),
+            Err(LoadError::Invalid(_))
+        ));
+        #[cfg(feature = "yaml")]
+        assert!(matches!(
+            load("a.yaml", &invalid),
+            Err(LoadError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,302 @@
+use super::{ConfigError, LoadError, RecorderConfig};
+use serde_json::Value;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverrideSource {
+    Env,
+    Cli,
+}
+
+/// A `key=value` override of one config field.
+///
+/// `key` is a dotted path into the config, e.g. `rotation.max_frames` or
+/// `sensors.front.decimation`; sensors are addressed by role name or index.
+/// `value` is taken as a string where the field holds one, e.g.
+/// `name=2024`; elsewhere it is parsed as JSON if possible (numbers,
+/// booleans, lists) and taken as a string otherwise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Override {
+    pub key: String,
+    pub value: String,
+    pub source: OverrideSource,
+}
+
+impl Override {
+    pub fn parse(arg: &str, source: OverrideSource) -> Result<Self, ConfigError> {
+        match arg.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok(Self {
+                key: key.trim().into(),
+                value: value.into(),
+                source,
+            }),
+            _ => Err(ConfigError {
+                path: arg.into(),
+                message: "expected key=value".into(),
+            }),
+        }
+    }
+}
+
+/// Overrides from environment variables named `<prefix>_<PATH>`, with `__`
+/// separating path segments: `CARLA_REC_SENSORS__FRONT__DECIMATION=2` sets
+/// `sensors.front.decimation`. The path keeps its case; fields and role
+/// names are matched ignoring it, the exact spelling first, and new keys
+/// are created in lower case. `<prefix>_CONFIG` is not an override, see
+/// [`RecorderConfig::resolve`].
+pub fn env_overrides(prefix: &str) -> Vec<Override> {
+    env_overrides_from(prefix, std::env::vars())
+}
+
+pub fn env_overrides_from(
+    prefix: &str,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Vec<Override> {
+    let prefix = format!("{prefix}_");
+    let mut overrides: Vec<Override> = vars
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let path = name.strip_prefix(&prefix)?;
+            (path != "CONFIG").then(|| Override {
+                key: path.replace("__", "."),
+                value,
+                source: OverrideSource::Env,
+            })
+        })
+        .collect();
+    // the environment has no order; make the result reproducible
+    overrides.sort_by(|a, b| a.key.cmp(&b.key));
+    overrides
+}
+
+/// Recorder command line, as understood by [`RecorderConfig::resolve`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CliArgs {
+    /// `--config <path>`
+    pub config: Option<PathBuf>,
+    /// `--set key=value`, repeatable
+    pub overrides: Vec<Override>,
+    /// `--print-effective-config`
+    pub print_effective_config: bool,
+    /// Everything else, in order, for the application to handle.
+    pub rest: Vec<String>,
+}
+
+impl CliArgs {
+    /// Parse arguments, without the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ConfigError> {
+        let mut cli = Self::default();
+        let mut args = args.into_iter();
+        let missing = |flag: &str| ConfigError {
+            path: flag.into(),
+            message: "missing value".into(),
+        };
+        while let Some(arg) = args.next() {
+            if let Some(path) = arg.strip_prefix("--config=") {
+                cli.config = Some(path.into());
+            } else if let Some(kv) = arg.strip_prefix("--set=") {
+                cli.overrides
+                    .push(Override::parse(kv, OverrideSource::Cli)?);
+            } else {
+                match arg.as_str() {
+                    "--config" => {
+                        cli.config = Some(args.next().ok_or_else(|| missing("--config"))?.into())
+                    }
+                    "--set" => {
+                        let kv = args.next().ok_or_else(|| missing("--set"))?;
+                        cli.overrides
+                            .push(Override::parse(&kv, OverrideSource::Cli)?);
+                    }
+                    "--print-effective-config" => cli.print_effective_config = true,
+                    _ => cli.rest.push(arg),
+                }
+            }
+        }
+        Ok(cli)
+    }
+}
+
+impl RecorderConfig {
+    /// Apply overrides in order, then re-validate.
+    pub fn apply_overrides<'a>(
+        &mut self,
+        overrides: impl IntoIterator<Item = &'a Override>,
+    ) -> Result<(), LoadError> {
+        let mut tree = serde_json::to_value(&*self).map_err(LoadError::Parse)?;
+        for o in overrides {
+            let fold_case = o.source == OverrideSource::Env;
+            let slot = lookup(&mut tree, &o.key, fold_case).map_err(|message| {
+                LoadError::Invalid(vec![ConfigError {
+                    path: o.key.clone(),
+                    message,
+                }])
+            })?;
+            let text = || Value::String(o.value.clone());
+            *slot = match slot {
+                Value::String(_) => text(),
+                _ => serde_json::from_str(&o.value).unwrap_or_else(|_| text()),
+            };
+        }
+        let config: Self = serde_json::from_value(tree).map_err(LoadError::Parse)?;
+        config.validate().map_err(LoadError::Invalid)?;
+        *self = config;
+        Ok(())
+    }
+
+    /// Layered configuration: the file named by `--config` (or
+    /// `<env_prefix>_CONFIG`), then environment overrides, then `--set`
+    /// flags, later layers winning.
+    pub fn resolve(
+        env_prefix: &str,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<(Self, CliArgs), LoadError> {
+        let cli = CliArgs::parse(args).map_err(|e| LoadError::Invalid(vec![e]))?;
+        let path = cli
+            .config
+            .clone()
+            .or_else(|| std::env::var_os(format!("{env_prefix}_CONFIG")).map(PathBuf::from))
+            .ok_or_else(|| {
+                LoadError::Invalid(vec![ConfigError {
+                    path: "--config".into(),
+                    message: "no config file given".into(),
+                }])
+            })?;
+
+        let mut config = Self::load(path)?;
+        config.apply_overrides(&env_overrides(env_prefix))?;
+        config.apply_overrides(&cli.overrides)?;
+        Ok((config, cli))
+    }
+
+    /// The resolved configuration as pretty JSON, for
+    /// `--print-effective-config`.
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).expect("config serializes to JSON")
+    }
+}
+
+// the node at `key`, matching names ignoring case if `fold_case`
+fn lookup<'v>(
+    mut node: &'v mut Value,
+    key: &str,
+    fold_case: bool,
+) -> Result<&'v mut Value, String> {
+    let matches = |name: &str, segment: &str| {
+        name == segment || fold_case && name.eq_ignore_ascii_case(segment)
+    };
+    for segment in key.split('.') {
+        node = match node {
+            // missing keys are created, e.g. a new blueprint attribute; a
+            // misspelled field still fails when the tree is deserialized
+            Value::Object(map) => {
+                let name = if map.contains_key(segment) {
+                    segment.to_string()
+                } else {
+                    match map.keys().find(|k| matches(k, segment)) {
+                        Some(k) => k.clone(),
+                        None if fold_case => segment.to_lowercase(),
+                        None => segment.to_string(),
+                    }
+                };
+                map.entry(name).or_insert(Value::Null)
+            }
+            Value::Array(items) => {
+                let index = match segment.parse::<usize>() {
+                    Ok(i) => Some(i),
+                    Err(_) => items
+                        .iter()
+                        .position(|v| role_name(v) == Some(segment))
+                        .or_else(|| {
+                            items
+                                .iter()
+                                .position(|v| role_name(v).is_some_and(|r| matches(r, segment)))
+                        }),
+                };
+                match index.and_then(|i| items.get_mut(i)) {
+                    Some(item) => item,
+                    None => return Err(format!("no element `{segment}`")),
+                }
+            }
+            _ => return Err(format!("`{segment}` is not inside an object or list")),
+        };
+    }
+    Ok(node)
+}
+
+fn role_name(item: &Value) -> Option<&str> {
+    item.get("role_name").and_then(Value::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RecorderConfig {
+        RecorderConfig::from_json(
+            r#"{
+                "name": "drive",
+                "sensors": [{
+                    "blueprint": "sensor.camera.rgb",
+                    "role_name": "Front",
+                    "attributes": {"image_size_x": 800}
+                }]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn cli(arg: &str) -> Override {
+        Override::parse(arg, OverrideSource::Cli).unwrap()
+    }
+
+    #[test]
+    fn strings_stay_strings() {
+        let mut config = config();
+        config
+            .apply_overrides(&[
+                cli("name=2024"),
+                cli("sensors.Front.decimation=3"),
+                cli("sensors.0.attributes.image_size_x=1280"),
+                cli("sensors.Front.attributes.gamma=2.2"),
+            ])
+            .unwrap();
+        assert_eq!(config.name, "2024");
+        let front = &config.sensors[0];
+        assert_eq!(front.decimation, 3);
+        assert_eq!(front.attributes["image_size_x"], "1280");
+        assert_eq!(front.attributes["gamma"], "2.2");
+    }
+
+    #[test]
+    fn env_keys_match_ignoring_case() {
+        let vars = [
+            (
+                "REC_SENSORS__FRONT__DECIMATION".to_string(),
+                "2".to_string(),
+            ),
+            (
+                "REC_SENSORS__FRONT__ATTRIBUTES__FOV".to_string(),
+                "110".to_string(),
+            ),
+            ("REC_CONFIG".to_string(), "ignored.json".to_string()),
+        ];
+        let overrides = env_overrides_from("REC", vars);
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides[0].key, "SENSORS.FRONT.ATTRIBUTES.FOV");
+        let mut config = config();
+        config.apply_overrides(&overrides).unwrap();
+        assert_eq!(config.sensors[0].decimation, 2);
+        assert_eq!(config.sensors[0].attributes["fov"], "110");
+    }
+
+    #[test]
+    fn cli_keys_are_exact() {
+        let mut config = config();
+        assert!(
+            config
+                .apply_overrides(&[cli("sensors.front.decimation=2")])
+                .is_err()
+        );
+        assert!(config.apply_overrides(&[cli("Name=x")]).is_err());
+    }
+}
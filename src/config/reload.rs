@@ -0,0 +1,219 @@
+use super::{ConfigError, LoadError, Override, RecorderConfig, SensorConfig};
+use crate::{AnnotationValueSerDe, SensorDataSerDe};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Annotation key under which [`ConfigChangeEvent::annotate`] stores the
+/// event.
+pub const CONFIG_CHANGE_KEY: &str = "config_change";
+
+/// One change applied by [`ConfigWatcher::poll`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConfigChange {
+    Decimation {
+        role_name: String,
+        from: u32,
+        to: u32,
+    },
+    Enabled {
+        role_name: String,
+        enabled: bool,
+    },
+    Marker {
+        label: String,
+    },
+}
+
+/// The changes of one reload.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigChangeEvent {
+    pub changes: Vec<ConfigChange>,
+}
+
+impl ConfigChangeEvent {
+    /// Record the event on a frame, as JSON text under
+    /// [`CONFIG_CHANGE_KEY`], so the recording stays readable by every
+    /// consumer of [`SensorDataSerDe`].
+    pub fn annotate(&self, frame: &mut SensorDataSerDe) {
+        let json = serde_json::to_string(self).expect("config change serializes to JSON");
+        frame
+            .annotations_mut()
+            .insert(CONFIG_CHANGE_KEY, AnnotationValueSerDe::Text(json));
+    }
+
+    /// The event stored on `frame` by [`annotate`](Self::annotate), if any.
+    pub fn from_frame(frame: &SensorDataSerDe) -> Option<Self> {
+        match frame.annotations().get(CONFIG_CHANGE_KEY)? {
+            AnnotationValueSerDe::Text(json) => serde_json::from_str(json).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl RecorderConfig {
+    /// The changes from `self` to `new` that can be applied to a running
+    /// session: sensor decimation, enabling or disabling sensors, and new
+    /// markers. Any other difference needs a restart and is reported as an
+    /// error.
+    pub fn live_changes(&self, new: &Self) -> Result<Vec<ConfigChange>, Vec<ConfigError>> {
+        let mut changes = Vec::new();
+        let mut errors = Vec::new();
+        let mut restart = |path: String| {
+            errors.push(ConfigError {
+                path,
+                message: "cannot change while recording".into(),
+            })
+        };
+
+        if self.name != new.name {
+            restart("name".into());
+        }
+        if self.sinks != new.sinks {
+            restart("sinks".into());
+        }
+        if self.rotation != new.rotation {
+            restart("rotation".into());
+        }
+        if self.sensors.len() != new.sensors.len() {
+            restart("sensors".into());
+        }
+        for (i, (old, new)) in self.sensors.iter().zip(&new.sensors).enumerate() {
+            let fixed = |s: &SensorConfig| SensorConfig {
+                decimation: 1,
+                enabled: true,
+                ..s.clone()
+            };
+            if fixed(old) != fixed(new) {
+                restart(format!("sensors[{i}]"));
+                continue;
+            }
+            if old.decimation != new.decimation {
+                changes.push(ConfigChange::Decimation {
+                    role_name: new.role_name.clone(),
+                    from: old.decimation,
+                    to: new.decimation,
+                });
+            }
+            if old.enabled != new.enabled {
+                changes.push(ConfigChange::Enabled {
+                    role_name: new.role_name.clone(),
+                    enabled: new.enabled,
+                });
+            }
+        }
+        for label in &new.markers {
+            if !self.markers.contains(label) {
+                changes.push(ConfigChange::Marker {
+                    label: label.clone(),
+                });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(changes)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Polls a config file and applies live changes to the running config.
+///
+/// There is no background thread: call [`poll`](Self::poll) from the
+/// recording loop, e.g. once per tick. A file that fails to load or asks for
+/// a change [`RecorderConfig::live_changes`] rejects leaves the running
+/// config untouched and is not reported again until it is modified.
+///
+/// The running config came from [`RecorderConfig::resolve`], so hand the
+/// watcher the same overrides with
+/// [`with_overrides`](Self::with_overrides); they are applied on top of
+/// every reload, and an edit of the file does not undo them.
+#[derive(Clone, Debug)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    config: RecorderConfig,
+    overrides: Vec<Override>,
+}
+
+impl ConfigWatcher {
+    /// Watch `path`, which `config` was loaded from.
+    pub fn new(path: impl Into<PathBuf>, config: RecorderConfig) -> Self {
+        let path = path.into();
+        let modified = modified(&path);
+        Self {
+            path,
+            modified,
+            config,
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Apply `overrides` in order to every reloaded file, e.g. the
+    /// environment overrides and then the `--set` flags, as
+    /// [`RecorderConfig::resolve`] does.
+    pub fn with_overrides(mut self, overrides: impl IntoIterator<Item = Override>) -> Self {
+        self.overrides = overrides.into_iter().collect();
+        self
+    }
+
+    pub fn config(&self) -> &RecorderConfig {
+        &self.config
+    }
+
+    /// Reload the file if it changed since the last poll. Returns the
+    /// applied changes, `None` if there were none.
+    pub fn poll(&mut self) -> Result<Option<ConfigChangeEvent>, LoadError> {
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return Ok(None);
+        }
+        self.modified = modified;
+
+        let mut new = RecorderConfig::load(&self.path)?;
+        new.apply_overrides(&self.overrides)?;
+        let changes = self.config.live_changes(&new).map_err(LoadError::Invalid)?;
+        self.config = new;
+        Ok((!changes.is_empty()).then_some(ConfigChangeEvent { changes }))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OverrideSource;
+
+    const CONFIG: &str = r#"{
+        "sensors": [{"blueprint": "sensor.camera.rgb", "role_name": "front"}],
+        "markers": []
+    }"#;
+
+    #[test]
+    fn reloads_keep_overrides() {
+        let path = std::env::temp_dir().join(format!("watched-{}.json", std::process::id()));
+        fs::write(&path, CONFIG).unwrap();
+        let set = Override::parse("sensors.front.decimation=2", OverrideSource::Cli).unwrap();
+        let mut config = RecorderConfig::load(&path).unwrap();
+        config.apply_overrides([&set]).unwrap();
+        let mut watcher = ConfigWatcher::new(&path, config).with_overrides([set]);
+
+        fs::write(&path, CONFIG.replace("[]", r#"["lap"]"#)).unwrap();
+        watcher.modified = None;
+        let event = watcher.poll().unwrap().unwrap();
+        assert_eq!(
+            event.changes,
+            [ConfigChange::Marker {
+                label: "lap".into()
+            }]
+        );
+        assert_eq!(watcher.config().sensors[0].decimation, 2);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,234 @@
+//! Lens distortion of rendered camera frames, so exported data looks like
+//! it came from a real camera with a known [`LensDistortionSerDe`].
+
+use crate::{
+    CameraInfoSerDe, DISTORTION_KEY, INTRINSICS_KEY, ImageEventSerDe, ImageHashSerDe,
+    LensDistortionSerDe, ProcessingStepSerDe, SensorDataSerDe, SensorDescriptionSerDe,
+    horizontal_fov, pipeline::FrameTransform, resize::bilinear,
+};
+use carla::sensor::data::Color;
+use ndarray::Array2;
+
+/// Resamples CARLA's pinhole frames as seen through a lens.
+///
+/// The output keeps the size and principal point of the input; its focal
+/// length is the input's times the focal scale, below 1 to see more of a
+/// wide-angle input through a fisheye. Pixels the input does not cover are
+/// black with an alpha of 0. The model goes under [`DISTORTION_KEY`] and
+/// the intrinsics under [`INTRINSICS_KEY`], so [`CameraInfoSerDe::from`]
+/// the image projects through the lens. A hash the image had is
+/// recomputed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageDistortion {
+    lens: LensDistortionSerDe,
+    focal_scale: f32,
+}
+
+impl ImageDistortion {
+    pub fn new(lens: LensDistortionSerDe) -> Self {
+        Self {
+            lens,
+            focal_scale: 1.0,
+        }
+    }
+
+    pub fn with_focal_scale(mut self, scale: f32) -> Self {
+        self.focal_scale = scale;
+        self
+    }
+
+    pub fn lens(&self) -> &LensDistortionSerDe {
+        &self.lens
+    }
+
+    /// The intrinsics of a camera with `info` after the distortion.
+    pub fn camera_info(&self, info: &CameraInfoSerDe) -> CameraInfoSerDe {
+        let fx = info.fx * self.focal_scale;
+        CameraInfoSerDe {
+            fov: horizontal_fov(info.width, fx),
+            fx,
+            fy: info.fy * self.focal_scale,
+            distortion: Some(self.lens),
+            ..*info
+        }
+    }
+
+    /// Update the intrinsics of the RGB cameras in `sensors`, e.g. those of
+    /// the recording header, to match distorted frames; the other cameras'
+    /// frames are not distorted, and neither are those of cameras with a
+    /// lens already.
+    pub fn update_sensors(&self, sensors: &mut [SensorDescriptionSerDe]) {
+        let rgb = sensors.iter_mut().filter(|s| s.is_rgb_camera());
+        for camera in rgb.filter_map(|s| s.camera.as_mut()) {
+            if camera.distortion.is_none() {
+                *camera = self.camera_info(camera);
+            }
+        }
+    }
+
+    /// Leaves an image that is distorted already as it is: the pinhole
+    /// projection its pixels would be resampled through no longer holds.
+    pub fn distort(&self, image: &mut ImageEventSerDe) {
+        let (h, w) = image.array.dim();
+        if h == 0 || w == 0 || is_distorted(image) {
+            return;
+        }
+        let input = CameraInfoSerDe::from(&*image);
+        let output = self.camera_info(&input);
+        let none = || Color {
+            b: 0,
+            g: 0,
+            r: 0,
+            a: 0,
+        };
+        let src = &image.array;
+        image.array = Array2::from_shape_fn((h, w), |(y, x)| {
+            let Some((u, v)) = output
+                .ray(x as f32 + 0.5, y as f32 + 0.5)
+                .and_then(|ray| input.project(&ray))
+            else {
+                return none();
+            };
+            if !(0.0..w as f32).contains(&u) || !(0.0..h as f32).contains(&v) {
+                return none();
+            }
+            let su = (u - 0.5).clamp(0.0, (w - 1) as f32);
+            let sv = (v - 0.5).clamp(0.0, (h - 1) as f32);
+            bilinear(src, su, sv)
+        });
+        image.fov_angle = output.fov;
+        image
+            .annotations
+            .insert(INTRINSICS_KEY, output.to_annotation());
+        image
+            .annotations
+            .insert(DISTORTION_KEY, self.lens.to_annotation());
+        if image.hash.is_some() {
+            image.hash = Some(ImageHashSerDe::from_array(image.array.view()));
+        }
+    }
+}
+
+fn is_distorted(image: &ImageEventSerDe) -> bool {
+    image.annotations.get(DISTORTION_KEY).is_some()
+}
+
+impl FrameTransform for ImageDistortion {
+    fn accepts(&self, frame: &SensorDataSerDe) -> bool {
+        matches!(frame, SensorDataSerDe::Image(image) if !is_distorted(image))
+    }
+
+    fn apply(&mut self, frame: &mut SensorDataSerDe) {
+        if let SensorDataSerDe::Image(image) = frame {
+            self.distort(image);
+        }
+    }
+
+    fn describe(&self) -> ProcessingStepSerDe {
+        ProcessingStepSerDe::new("image_distort")
+            .with_param("lens", self.lens.to_string())
+            .with_param("focal_scale", self.focal_scale as f64)
+    }
+}
+
+#[cfg(feature = "recording")]
+impl crate::recording::Recording {
+    /// Distort every camera frame without a lens yet and record it,
+    /// updating the camera intrinsics of the header too.
+    pub fn distort_images(&mut self, distortion: &ImageDistortion) {
+        let mut transform = *distortion;
+        self.apply(&mut transform);
+        if let Some(header) = &mut self.header {
+            distortion.update_sensors(&mut header.sensors);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AnnotationsSerDe;
+    use nalgebra::Isometry3;
+
+    const FISHEYE: LensDistortionSerDe = LensDistortionSerDe::KannalaBrandt {
+        k1: 0.1,
+        k2: 0.0,
+        k3: 0.0,
+        k4: 0.0,
+    };
+
+    fn image() -> ImageEventSerDe {
+        frame(4, 6)
+    }
+
+    // red counts columns, green rows
+    fn frame(height: usize, width: usize) -> ImageEventSerDe {
+        let array = Array2::from_shape_fn((height, width), |(y, x)| Color {
+            b: 0,
+            g: y as u8,
+            r: x as u8,
+            a: 255,
+        });
+        ImageEventSerDe {
+            height,
+            width,
+            len: height * width,
+            is_empty: false,
+            fov_angle: 90.0,
+            array,
+            augmentations: Vec::new(),
+            hash: None,
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+
+    #[test]
+    fn distorts_an_image_once() {
+        let distortion = ImageDistortion::new(FISHEYE).with_focal_scale(0.5);
+        let mut once = image();
+        distortion.distort(&mut once);
+        assert_eq!(CameraInfoSerDe::from(&once).distortion, Some(FISHEYE));
+        let frame = SensorDataSerDe::Image(once.clone());
+        assert!(!distortion.accepts(&frame));
+        let mut twice = once.clone();
+        distortion.distort(&mut twice);
+        assert_eq!(twice, once);
+    }
+
+    #[test]
+    fn keeps_the_shape_of_landscape_and_portrait_frames() {
+        let distortion = ImageDistortion::new(FISHEYE);
+        for (h, w) in [(8, 16), (16, 8)] {
+            let mut image = frame(h, w);
+            distortion.distort(&mut image);
+            assert_eq!(image.array.dim(), (h, w));
+            assert_eq!(image.validate(), Ok(()));
+            let info = CameraInfoSerDe::from(&image);
+            assert_eq!((info.width, info.height), (w, h));
+            assert_eq!((info.cx, info.cy), (w as f32 / 2.0, h as f32 / 2.0));
+            // the lens bends little near the center
+            let px = &image.array[(h / 2, w / 2)];
+            assert!(px.r.abs_diff(w as u8 / 2) <= 1, "{h}x{w}: r {}", px.r);
+            assert!(px.g.abs_diff(h as u8 / 2) <= 1, "{h}x{w}: g {}", px.g);
+        }
+    }
+
+    #[test]
+    fn updates_only_undistorted_rgb_cameras() {
+        let distortion = ImageDistortion::new(FISHEYE).with_focal_scale(0.5);
+        let sensor = |type_id: &str| SensorDescriptionSerDe {
+            id: 1,
+            type_id: type_id.into(),
+            role_name: String::new(),
+            mount: Isometry3::identity(),
+            camera: Some(CameraInfoSerDe::from_fov(800, 600, 90.0)),
+        };
+        let mut sensors = [sensor("sensor.camera.rgb"), sensor("sensor.camera.depth")];
+        distortion.update_sensors(&mut sensors);
+        let distorted = sensors[0].camera.unwrap();
+        assert_eq!(distorted.distortion, Some(FISHEYE));
+        distortion.update_sensors(&mut sensors);
+        assert_eq!(sensors[0].camera.unwrap(), distorted);
+        assert_eq!(sensors[1].camera.unwrap().distortion, None);
+    }
+}
@@ -0,0 +1,470 @@
+//! Scripted sensor failures, for testing how consumers cope with them.
+//!
+//! [`FaultInjector`] sits in front of a sink and applies the faults of a
+//! [`FaultSchedule`] to the frames of the scheduled sensor kinds. Every
+//! frame a fault touched carries its name under [`FAULT_KEY`], and
+//! [`FaultInjector::describe`] gives the schedule as a processing step for
+//! the recording header, so the ground truth of the test travels with the
+//! data.
+
+use crate::augment::NoiseRng;
+use crate::pipeline::{SharedFrame, Sink};
+use crate::{
+    AnnotationValueSerDe, ImageEventSerDe, ProcessingStepSerDe, SensorDataSerDe, SensorKind,
+    V2xPayloadSerDe,
+};
+use carla::sensor::data::Color;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+
+/// Annotation naming the fault applied to a frame, e.g. `freeze`.
+pub const FAULT_KEY: &str = "fault";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Fault {
+    /// The frame is withheld, as if the sensor had stopped sending.
+    Dropout,
+    /// The frame arrives with its data zeroed: black pixels, zero depth and
+    /// flow, no points or detections or events, zero IMU and GNSS readings.
+    Blank,
+    /// The frame is held back until `frames` further frames have passed.
+    Delay { frames: u64 },
+    /// The sensor repeats the last frame it sent before the fault, with the
+    /// current frame number and timestamp.
+    Freeze,
+    /// About `fraction` of the values are replaced: pixels by random
+    /// colors, segmentation labels and DVS events by random ones, numbers
+    /// by the same number with a random bit flipped. Numbers stay finite,
+    /// so corrupted frames still serialize.
+    Corrupt { fraction: f32 },
+}
+
+impl Fault {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Dropout => "dropout",
+            Self::Blank => "blank",
+            Self::Delay { .. } => "delay",
+            Self::Freeze => "freeze",
+            Self::Corrupt { .. } => "corrupt",
+        }
+    }
+}
+
+/// A fault on one sensor kind for the ticks `start..end`. Ticks are frame
+/// numbers, see [`SensorDataSerDe::frame_number`]; frames without one use
+/// their position in the stream.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FaultWindow {
+    pub sensor: SensorKind,
+    pub start: u64,
+    pub end: u64,
+    pub fault: Fault,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FaultSchedule {
+    pub seed: u64,
+    pub windows: Vec<FaultWindow>,
+}
+
+impl FaultSchedule {
+    /// `seed` drives [`Fault::Corrupt`].
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            windows: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, sensor: SensorKind, start: u64, end: u64, fault: Fault) -> Self {
+        self.windows.push(FaultWindow {
+            sensor,
+            start,
+            end,
+            fault,
+        });
+        self
+    }
+
+    /// The fault active for `sensor` at `tick`; the first matching window
+    /// wins.
+    pub fn fault_at(&self, sensor: SensorKind, tick: u64) -> Option<Fault> {
+        self.windows
+            .iter()
+            .find(|w| w.sensor == sensor && (w.start..w.end).contains(&tick))
+            .map(|w| w.fault)
+    }
+}
+
+/// Applies a [`FaultSchedule`] to the frames on their way to `inner`.
+#[derive(Debug)]
+pub struct FaultInjector<S> {
+    schedule: FaultSchedule,
+    inner: S,
+    rng: NoiseRng,
+    position: u64,
+    // last frame per kind sent without a fault, for Fault::Freeze
+    last_good: BTreeMap<SensorKind, SharedFrame>,
+    // delayed frames and the position they are released at
+    held: VecDeque<(u64, SharedFrame)>,
+    dropped: u64,
+}
+
+impl<S: Sink> FaultInjector<S> {
+    pub fn new(schedule: FaultSchedule, inner: S) -> Self {
+        Self {
+            rng: NoiseRng::new(schedule.seed),
+            schedule,
+            inner,
+            position: 0,
+            last_good: BTreeMap::new(),
+            held: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    pub fn schedule(&self) -> &FaultSchedule {
+        &self.schedule
+    }
+
+    /// Frames withheld by [`Fault::Dropout`] so far.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// The schedule as a processing step, one `window_<n>` parameter per
+    /// window.
+    pub fn describe(&self) -> ProcessingStepSerDe {
+        let mut step =
+            ProcessingStepSerDe::new("fault_inject").with_param("seed", self.schedule.seed as i64);
+        for (n, w) in self.schedule.windows.iter().enumerate() {
+            let mut text = format!("{} {:?} {}..{}", w.fault.name(), w.sensor, w.start, w.end);
+            match w.fault {
+                Fault::Delay { frames } => text += &format!(" frames={frames}"),
+                Fault::Corrupt { fraction } => text += &format!(" fraction={fraction}"),
+                _ => {}
+            }
+            step = step.with_param(format!("window_{n}"), text);
+        }
+        step
+    }
+
+    // forward the held frames due at `position`, oldest first
+    fn release(&mut self, position: u64) {
+        let mut i = 0;
+        while i < self.held.len() {
+            if self.held[i].0 <= position {
+                let (_, frame) = self.held.remove(i).expect("index in bounds");
+                self.inner.consume(frame);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn inject(&mut self, mut frame: SharedFrame, position: u64) {
+        let kind = frame.kind();
+        let tick = frame.frame_number().unwrap_or(position);
+        let Some(fault) = self.schedule.fault_at(kind, tick) else {
+            self.last_good.insert(kind, frame.clone());
+            self.inner.consume(frame);
+            return;
+        };
+
+        match fault {
+            Fault::Dropout => {
+                self.dropped += 1;
+                return;
+            }
+            Fault::Blank => blank(Arc::make_mut(&mut frame)),
+            Fault::Delay { .. } => {}
+            Fault::Freeze => {
+                if let Some(last) = self.last_good.get(&kind) {
+                    let mut frozen = SensorDataSerDe::clone(last);
+                    if let Some(n) = frame.frame_number() {
+                        frozen.set_frame_number(n);
+                    }
+                    if let Some(t) = frame.timestamp() {
+                        frozen.set_timestamp(t);
+                    }
+                    frame = Arc::new(frozen);
+                }
+            }
+            Fault::Corrupt { fraction } => {
+                corrupt(Arc::make_mut(&mut frame), fraction, &mut self.rng);
+            }
+        }
+        Arc::make_mut(&mut frame)
+            .annotations_mut()
+            .insert(FAULT_KEY, AnnotationValueSerDe::Text(fault.name().into()));
+
+        match fault {
+            Fault::Delay { frames } => self
+                .held
+                .push_back((position.saturating_add(frames), frame)),
+            _ => self.inner.consume(frame),
+        }
+    }
+}
+
+impl<S: Sink> Sink for FaultInjector<S> {
+    fn consume(&mut self, frame: SharedFrame) {
+        let position = self.position;
+        self.position += 1;
+        self.inject(frame, position);
+        self.release(position);
+    }
+
+    fn flush(&mut self) {
+        self.release(u64::MAX);
+        self.inner.flush();
+    }
+}
+
+fn blank(frame: &mut SensorDataSerDe) {
+    match frame {
+        SensorDataSerDe::Image(image) => blank_image(image),
+        SensorDataSerDe::Lidar(lidar) => {
+            lidar.detections.clear();
+            lidar.len = 0;
+            lidar.is_empty = true;
+        }
+        SensorDataSerDe::SemanticLidar(lidar) => {
+            lidar.detections.clear();
+            lidar.reconcile_counts();
+        }
+        SensorDataSerDe::Radar(radar) => {
+            radar.detections.clear();
+            radar.reconcile_counts();
+        }
+        SensorDataSerDe::Imu(imu) => {
+            imu.accelerometer = Default::default();
+            imu.gyroscope = Default::default();
+            imu.compass = 0.0;
+        }
+        SensorDataSerDe::OpticalFlow(flow) => flow.array.fill(Default::default()),
+        SensorDataSerDe::Depth(depth) => {
+            depth.depth.fill(0.0);
+            if let Some(raw) = &mut depth.raw {
+                blank_image(raw);
+            }
+        }
+        SensorDataSerDe::InstanceSegmentation(seg) => seg.array.fill(Default::default()),
+        SensorDataSerDe::SemanticSegmentation(seg) => {
+            seg.labels.fill(0);
+            if let Some(raw) = &mut seg.raw {
+                blank_image(raw);
+            }
+        }
+        SensorDataSerDe::Dvs(dvs) => dvs.events.clear(),
+        SensorDataSerDe::Raw(raw) => raw.data.fill(0),
+        SensorDataSerDe::V2x(v2x) => v2x.messages.clear(),
+        SensorDataSerDe::Gnss(gnss) => {
+            gnss.latitude = 0.0;
+            gnss.longitude = 0.0;
+            gnss.altitude = 0.0;
+        }
+        // events have no data apart from what identifies them
+        SensorDataSerDe::Collision(_)
+        | SensorDataSerDe::LaneInvasion(_)
+        | SensorDataSerDe::ObstacleDetection(_) => {}
+    }
+}
+
+fn blank_image(image: &mut ImageEventSerDe) {
+    image.array.fill(Color {
+        b: 0,
+        g: 0,
+        r: 0,
+        a: 255,
+    });
+}
+
+// a random bit of `v` flipped; the sign bit if another one would make it
+// infinite or NaN, which JSON cannot hold
+fn flip_bit_f32(v: f32, rng: &mut NoiseRng) -> f32 {
+    let flipped = f32::from_bits(v.to_bits() ^ 1 << (rng.next_u64() % 32));
+    if flipped.is_finite() || !v.is_finite() {
+        flipped
+    } else {
+        -v
+    }
+}
+
+fn flip_bit_f64(v: f64, rng: &mut NoiseRng) -> f64 {
+    let flipped = f64::from_bits(v.to_bits() ^ 1 << (rng.next_u64() % 64));
+    if flipped.is_finite() || !v.is_finite() {
+        flipped
+    } else {
+        -v
+    }
+}
+
+fn corrupt(frame: &mut SensorDataSerDe, fraction: f32, rng: &mut NoiseRng) {
+    let nan = |v: &mut f32, rng: &mut NoiseRng| {
+        if rng.uniform() < fraction {
+            *v = flip_bit_f32(*v, rng);
+        }
+    };
+    match frame {
+        SensorDataSerDe::Image(image) => {
+            for px in image.array.iter_mut() {
+                if rng.uniform() < fraction {
+                    let bits = rng.next_u64();
+                    px.b = bits as u8;
+                    px.g = (bits >> 8) as u8;
+                    px.r = (bits >> 16) as u8;
+                }
+            }
+        }
+        SensorDataSerDe::Lidar(lidar) => {
+            for d in &mut lidar.detections {
+                nan(&mut d.point.x, rng);
+                nan(&mut d.point.y, rng);
+                nan(&mut d.point.z, rng);
+                nan(&mut d.intensity, rng);
+            }
+        }
+        SensorDataSerDe::SemanticLidar(lidar) => {
+            for d in &mut lidar.detections {
+                nan(&mut d.point.x, rng);
+                nan(&mut d.point.y, rng);
+                nan(&mut d.point.z, rng);
+            }
+        }
+        SensorDataSerDe::Radar(radar) => {
+            for d in &mut radar.detections {
+                nan(&mut d.velocity, rng);
+                nan(&mut d.azimuth, rng);
+                nan(&mut d.altitude, rng);
+                nan(&mut d.depth, rng);
+            }
+        }
+        SensorDataSerDe::Imu(imu) => {
+            let imu = &mut imu.measurement;
+            for v in [&mut imu.accelerometer, &mut imu.gyroscope] {
+                nan(&mut v.x, rng);
+                nan(&mut v.y, rng);
+                nan(&mut v.z, rng);
+            }
+            nan(&mut imu.compass, rng);
+        }
+        SensorDataSerDe::OpticalFlow(flow) => {
+            for px in flow.array.iter_mut() {
+                nan(&mut px.x, rng);
+                nan(&mut px.y, rng);
+            }
+        }
+        SensorDataSerDe::Depth(depth) => {
+            for d in depth.depth.iter_mut() {
+                nan(d, rng);
+            }
+        }
+        SensorDataSerDe::InstanceSegmentation(seg) => {
+            for px in seg.array.iter_mut() {
+                if rng.uniform() < fraction {
+                    let bits = rng.next_u64();
+                    px.class = bits as u8;
+                    px.instance = (bits >> 8) as u16;
+                }
+            }
+        }
+        SensorDataSerDe::SemanticSegmentation(seg) => {
+            for tag in seg.labels.iter_mut() {
+                if rng.uniform() < fraction {
+                    *tag = rng.next_u64() as u8;
+                }
+            }
+        }
+        SensorDataSerDe::Dvs(dvs) => {
+            for e in &mut dvs.events {
+                if rng.uniform() < fraction {
+                    let bits = rng.next_u64();
+                    e.x = bits as u16 % dvs.width.max(1) as u16;
+                    e.y = (bits >> 16) as u16 % dvs.height.max(1) as u16;
+                    e.polarity = bits >> 63 == 1;
+                }
+            }
+        }
+        SensorDataSerDe::V2x(v2x) => {
+            for m in &mut v2x.messages {
+                nan(&mut m.power, rng);
+                if let V2xPayloadSerDe::Cam(cam) = &mut m.payload {
+                    let position = &mut cam.reference_position;
+                    for v in [&mut position.latitude, &mut position.longitude] {
+                        if rng.uniform() < fraction {
+                            *v = flip_bit_f64(*v, rng);
+                        }
+                    }
+                }
+            }
+        }
+        SensorDataSerDe::Raw(raw) => {
+            for b in &mut raw.data {
+                if rng.uniform() < fraction {
+                    *b = rng.next_u64() as u8;
+                }
+            }
+        }
+        SensorDataSerDe::Gnss(gnss) => {
+            for v in [&mut gnss.latitude, &mut gnss.longitude, &mut gnss.altitude] {
+                if rng.uniform() < fraction {
+                    *v = flip_bit_f64(*v, rng);
+                }
+            }
+        }
+        SensorDataSerDe::Collision(_)
+        | SensorDataSerDe::LaneInvasion(_)
+        | SensorDataSerDe::ObstacleDetection(_) => {}
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrupted_frames_stay_readable() {
+        let mut rng = NoiseRng::new(7);
+        for mut frame in crate::fixtures::all() {
+            corrupt(&mut frame, 1.0, &mut rng);
+            let json = serde_json::to_string(&frame).unwrap();
+            let back: SensorDataSerDe = serde_json::from_str(&json)
+                .unwrap_or_else(|e| panic!("{:?} unreadable: {e}", frame.kind()));
+            assert_eq!(back.kind(), frame.kind());
+        }
+    }
+
+    #[test]
+    fn flipped_bits_are_finite() {
+        let mut rng = NoiseRng::new(1);
+        for _ in 0..10_000 {
+            assert!(flip_bit_f32(f32::MAX, &mut rng).is_finite());
+            assert!(flip_bit_f64(-f64::MAX, &mut rng).is_finite());
+        }
+    }
+
+    #[test]
+    fn long_delay_does_not_overflow() {
+        let schedule = FaultSchedule::new(0).with(
+            SensorKind::Gnss,
+            0,
+            u64::MAX,
+            Fault::Delay { frames: u64::MAX },
+        );
+        let mut sent = Vec::new();
+        let mut injector = FaultInjector::new(schedule, |f: SharedFrame| sent.push(f));
+        for frame in crate::fixtures::all() {
+            injector.consume(Arc::new(frame));
+        }
+        injector.flush();
+        drop(injector);
+        assert_eq!(sent.len(), crate::fixtures::all().len());
+    }
+}
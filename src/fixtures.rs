@@ -0,0 +1,91 @@
+//! Tiny recordings of each sensor type, embedded in the crate.
+//!
+//! Meant for examples and tests that should run without a simulator or any
+//! files on disk. The recordings live in `fixtures/` as one JSON-encoded
+//! [`SensorDataSerDe`] each; the raw bytes are exposed next to the decoded
+//! accessors for code that wants to exercise its own deserialization.
+
+use crate::{
+    CollisionEventSerDe, GnssMeasurementSerDe, ImageEventSerDe, ImuFrameSerDe,
+    LaneInvasionEventSerDe, LidarMeasurementSerDe, ObstacleDetectionEventSerDe,
+    RadarMeasurementSerDe, SensorDataSerDe,
+};
+
+pub const IMAGE_JSON: &[u8] = include_bytes!("../fixtures/image.json");
+pub const LIDAR_JSON: &[u8] = include_bytes!("../fixtures/lidar.json");
+pub const RADAR_JSON: &[u8] = include_bytes!("../fixtures/radar.json");
+pub const IMU_JSON: &[u8] = include_bytes!("../fixtures/imu.json");
+pub const COLLISION_JSON: &[u8] = include_bytes!("../fixtures/collision.json");
+pub const LANE_INVASION_JSON: &[u8] = include_bytes!("../fixtures/lane_invasion.json");
+pub const OBSTACLE_DETECTION_JSON: &[u8] = include_bytes!("../fixtures/obstacle_detection.json");
+pub const GNSS_JSON: &[u8] = include_bytes!("../fixtures/gnss.json");
+
+fn decode(bytes: &[u8]) -> SensorDataSerDe {
+    // the fixtures are generated from this crate's own types
+    serde_json::from_slice(bytes).expect("embedded fixture is valid")
+}
+
+macro_rules! fixture {
+    ($(#[$doc:meta])* $name:ident, $bytes:ident, $variant:ident($ty:ty)) => {
+        $(#[$doc])*
+        pub fn $name() -> $ty {
+            match decode($bytes) {
+                SensorDataSerDe::$variant(v) => v,
+                other => panic!(
+                    concat!(stringify!($bytes), " holds {:?}, not ", stringify!($variant)),
+                    other.kind()
+                ),
+            }
+        }
+    };
+}
+
+fixture!(
+    /// A 4×4 RGBA camera frame with a color gradient, 90° field of view.
+    image_frame, IMAGE_JSON, Image(ImageEventSerDe)
+);
+fixture!(
+    /// Four returns over two channels.
+    lidar_frame, LIDAR_JSON, Lidar(LidarMeasurementSerDe)
+);
+fixture!(
+    /// Three detections: two from an approaching car, one static.
+    radar_frame, RADAR_JSON, Radar(RadarMeasurementSerDe)
+);
+fixture!(
+    /// A vehicle at rest, slowly turning left.
+    imu_frame, IMU_JSON, Imu(ImuFrameSerDe)
+);
+fixture!(
+    /// A head-on collision between two vehicles.
+    collision_event, COLLISION_JSON, Collision(CollisionEventSerDe)
+);
+fixture!(
+    /// Crossing one broken white line.
+    lane_invasion_event, LANE_INVASION_JSON, LaneInvasion(LaneInvasionEventSerDe)
+);
+fixture!(
+    /// A pedestrian 8 m ahead of the ego vehicle.
+    obstacle_detection_event, OBSTACLE_DETECTION_JSON, ObstacleDetection(ObstacleDetectionEventSerDe)
+);
+fixture!(
+    /// A fix near the origin of Town10's geo reference.
+    gnss_frame, GNSS_JSON, Gnss(GnssMeasurementSerDe)
+);
+
+/// One frame of every fixture, in [`crate::SensorKind`] order.
+pub fn all() -> Vec<SensorDataSerDe> {
+    [
+        IMAGE_JSON,
+        LIDAR_JSON,
+        RADAR_JSON,
+        IMU_JSON,
+        COLLISION_JSON,
+        LANE_INVASION_JSON,
+        OBSTACLE_DETECTION_JSON,
+        GNSS_JSON,
+    ]
+    .into_iter()
+    .map(decode)
+    .collect()
+}
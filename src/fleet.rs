@@ -0,0 +1,61 @@
+//! Several ego vehicles in one session.
+//!
+//! Each frame names the vehicle it was captured on in its annotations,
+//! under [`VEHICLE_KEY`], and the recording header lists every vehicle with
+//! its rig as a [`VehicleManifestSerDe`]. A [`VehicleTagger`] at the start
+//! of each vehicle's pipeline sets the key before the streams are merged,
+//! e.g. onto one [`crate::bus::SensorBus`]; `Recording::split_by_vehicle`
+//! separates them again.
+//!
+//! [`VehicleManifestSerDe`]: crate::VehicleManifestSerDe
+
+use crate::pipeline::FrameTransform;
+use crate::{AnnotationValueSerDe, ProcessingStepSerDe, SensorDataSerDe};
+
+pub const VEHICLE_KEY: &str = "vehicle";
+
+pub fn vehicle(frame: &SensorDataSerDe) -> Option<&str> {
+    match frame.annotations().get(VEHICLE_KEY)? {
+        AnnotationValueSerDe::Text(id) => Some(id),
+        _ => None,
+    }
+}
+
+pub fn set_vehicle(frame: &mut SensorDataSerDe, id: impl Into<String>) {
+    frame
+        .annotations_mut()
+        .insert(VEHICLE_KEY, AnnotationValueSerDe::Text(id.into()));
+}
+
+/// Marks every frame as captured on one vehicle; frames that already name
+/// a vehicle keep it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VehicleTagger {
+    id: String,
+}
+
+impl VehicleTagger {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self { id: id.into() }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl FrameTransform for VehicleTagger {
+    fn accepts(&self, frame: &SensorDataSerDe) -> bool {
+        vehicle(frame).is_none()
+    }
+
+    fn apply(&mut self, frame: &mut SensorDataSerDe) {
+        if vehicle(frame).is_none() {
+            set_vehicle(frame, self.id.clone());
+        }
+    }
+
+    fn describe(&self) -> ProcessingStepSerDe {
+        ProcessingStepSerDe::new("vehicle_tag").with_param("vehicle", self.id.as_str())
+    }
+}
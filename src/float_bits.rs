@@ -0,0 +1,726 @@
+//! Bit-exact float encoding, for recordings that must reproduce every
+//! value exactly on every platform.
+//!
+//! Decimal float output round-trips in serde_json, but NaN payloads, the
+//! sign of a NaN and infinities are lost (they are written as `null`), and
+//! other formats and tools may print fewer digits. Wrapping a value with
+//! [`FloatEncoding::wrap`] writes every `f32` and `f64` field as its bit
+//! pattern instead:
+//!
+//! ```ignore
+//! let json = serde_json::to_string(&FloatEncoding::HexFloat.wrap(&frame))?;
+//! let mut de = serde_json::Deserializer::from_str(&json);
+//! let back: SensorDataSerDe = float_bits::deserialize(&mut de)?;
+//! assert_eq!(back, frame);
+//! ```
+//!
+//! Like [`naming`](crate::naming), the wrapper sits between the value and
+//! any serializer. [`deserialize`] takes any deserializer and reads both
+//! encodings as well as plain decimal floats.
+//!
+//! serde buffers `#[serde(flatten)]` fields and untagged or internally
+//! tagged enums before deserializing them, past [`deserialize`]: their
+//! floats come back as the integers of their bits. The recorded types
+//! have none of them.
+
+use crate::proxy::{Proxy, Rewrite};
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::ser::{Serialize, Serializer};
+use std::fmt;
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum FloatEncoding {
+    /// Floats as unsigned integers holding their bits, `1065353216` for
+    /// `1.0f32`. Compact in every format.
+    #[default]
+    Bits,
+    /// Floats as hexadecimal float strings in text formats, `"0x1p+0"` for
+    /// `1.0`, `"inf"`, `"-inf"` and `"nan(0x7fc00000)"`; as bits in binary
+    /// formats. Readable, and exact like the bits. See [`hex_f64`].
+    HexFloat,
+}
+
+impl FloatEncoding {
+    pub fn wrap<T: Serialize + ?Sized>(self, value: &T) -> BitExact<'_, T> {
+        BitExact {
+            value,
+            encoding: self,
+        }
+    }
+
+    fn hex(self, serializer: &impl Serializer) -> bool {
+        self == Self::HexFloat && serializer.is_human_readable()
+    }
+}
+
+/// A value serialized with the floats of a [`FloatEncoding`].
+#[derive(Clone, Copy, Debug)]
+pub struct BitExact<'a, T: ?Sized> {
+    value: &'a T,
+    encoding: FloatEncoding,
+}
+
+impl<T: Serialize + ?Sized> Serialize for BitExact<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(Proxy::new(serializer, self.encoding))
+    }
+}
+
+/// Decode a value written with either [`FloatEncoding`], or with plain
+/// decimal floats.
+///
+/// In text formats an integer where a float is expected is taken as a bit
+/// pattern, so decimal input must write its floats with a fraction or
+/// exponent, as serde_json does.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: de::Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(BitExactDeserializer(deserializer))
+}
+
+// The layout of an IEEE 754 binary format.
+#[derive(Clone, Copy)]
+struct Layout {
+    mantissa_bits: u32,
+    exponent_bits: u32,
+}
+
+const F32: Layout = Layout {
+    mantissa_bits: 23,
+    exponent_bits: 8,
+};
+
+const F64: Layout = Layout {
+    mantissa_bits: 52,
+    exponent_bits: 11,
+};
+
+impl Layout {
+    fn bias(self) -> i32 {
+        (1 << (self.exponent_bits - 1)) - 1
+    }
+
+    fn max_exponent(self) -> u64 {
+        (1 << self.exponent_bits) - 1
+    }
+
+    fn sign_shift(self) -> u32 {
+        self.mantissa_bits + self.exponent_bits
+    }
+
+    // hex digits of the fraction, and the shift aligning it to them
+    fn fraction_digits(self) -> (usize, u32) {
+        let digits = self.mantissa_bits.div_ceil(4);
+        (digits as usize, digits * 4 - self.mantissa_bits)
+    }
+
+    fn format(self, bits: u64) -> String {
+        let negative = bits >> self.sign_shift() & 1 == 1;
+        let exponent = bits >> self.mantissa_bits & self.max_exponent();
+        let mantissa = bits & ((1 << self.mantissa_bits) - 1);
+        let sign = if negative { "-" } else { "" };
+        if exponent == self.max_exponent() {
+            return if mantissa == 0 {
+                format!("{sign}inf")
+            } else {
+                let width = (self.sign_shift() + 1) as usize / 4;
+                format!("nan(0x{bits:0width$x})")
+            };
+        }
+        let (lead, power) = match (exponent, mantissa) {
+            (0, 0) => (0, 0),
+            (0, _) => (0, 1 - self.bias()),
+            _ => (1, exponent as i32 - self.bias()),
+        };
+        let (digits, shift) = self.fraction_digits();
+        let fraction = format!("{:0digits$x}", mantissa << shift);
+        let fraction = fraction.trim_end_matches('0');
+        let dot = if fraction.is_empty() { "" } else { "." };
+        format!("{sign}0x{lead}{dot}{fraction}p{power:+}")
+    }
+
+    // Only the forms `format` writes: a leading 1, or a leading 0 for zero
+    // and subnormals, and at most as many fraction digits as fit.
+    fn parse(self, s: &str) -> Option<u64> {
+        if let Some(hex) = s.strip_prefix("nan(0x").and_then(|s| s.strip_suffix(')')) {
+            let bits = u64::from_str_radix(hex, 16).ok()?;
+            let is_nan = bits >> self.sign_shift() <= 1
+                && bits >> self.mantissa_bits & self.max_exponent() == self.max_exponent()
+                && bits & ((1 << self.mantissa_bits) - 1) != 0;
+            return is_nan.then_some(bits);
+        }
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let sign = u64::from(negative) << self.sign_shift();
+        if s == "inf" {
+            return Some(sign | self.max_exponent() << self.mantissa_bits);
+        }
+        let (significand, power) = s.strip_prefix("0x")?.split_once('p')?;
+        let power: i32 = power.parse().ok()?;
+        let (lead, fraction) = match significand.split_once('.') {
+            Some((lead, fraction)) if !fraction.is_empty() => (lead, fraction),
+            Some(_) => return None,
+            None => (significand, ""),
+        };
+        let (digits, shift) = self.fraction_digits();
+        if fraction.len() > digits || !fraction.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let fraction = if fraction.is_empty() {
+            0
+        } else {
+            u64::from_str_radix(fraction, 16).ok()? << (4 * (digits - fraction.len()))
+        };
+        if fraction & ((1 << shift) - 1) != 0 {
+            return None;
+        }
+        let mantissa = fraction >> shift;
+        let magnitude = match lead {
+            "1" => {
+                let exponent = u64::try_from(power.checked_add(self.bias())?).ok()?;
+                if exponent == 0 || exponent >= self.max_exponent() {
+                    return None;
+                }
+                exponent << self.mantissa_bits | mantissa
+            }
+            "0" if mantissa == 0 => 0,
+            "0" if power == 1 - self.bias() => mantissa,
+            _ => return None,
+        };
+        Some(sign | magnitude)
+    }
+}
+
+/// `v` as a hexadecimal float, `"0x1.8p+0"` for 1.5; see
+/// [`FloatEncoding::HexFloat`].
+pub fn hex_f32(v: f32) -> String {
+    F32.format(v.to_bits().into())
+}
+
+/// `v` as a hexadecimal float, `"0x1.8p+0"` for 1.5, `"-0x0p+0"` for
+/// -0.0 and `"0x0.0000000000001p-1022"` for the smallest subnormal. NaNs
+/// are written with all their bits, sign included: `"nan(0x7ff8000000000000)"`.
+pub fn hex_f64(v: f64) -> String {
+    F64.format(v.to_bits())
+}
+
+/// Read a float written by [`hex_f32`].
+pub fn parse_hex_f32(s: &str) -> Option<f32> {
+    F32.parse(s).map(|bits| f32::from_bits(bits as u32))
+}
+
+/// Read a float written by [`hex_f64`].
+pub fn parse_hex_f64(s: &str) -> Option<f64> {
+    F64.parse(s).map(f64::from_bits)
+}
+
+impl Rewrite for FloatEncoding {
+    fn serialize_f32<S: Serializer>(self, serializer: S, v: f32) -> Result<S::Ok, S::Error> {
+        if self.hex(&serializer) {
+            serializer.serialize_str(&hex_f32(v))
+        } else {
+            serializer.serialize_u32(v.to_bits())
+        }
+    }
+
+    fn serialize_f64<S: Serializer>(self, serializer: S, v: f64) -> Result<S::Ok, S::Error> {
+        if self.hex(&serializer) {
+            serializer.serialize_str(&hex_f64(v))
+        } else {
+            serializer.serialize_u64(v.to_bits())
+        }
+    }
+}
+
+// The deserializing half: every nested deserializer is wrapped in turn, so
+// the float a derived impl asks for, however deep, comes through
+// `FloatVisitor`.
+struct BitExactDeserializer<D>(D);
+
+struct Wrap<T>(T);
+
+macro_rules! forward_deserialize {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+                self.0.$method(Wrap(visitor))
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for BitExactDeserializer<D> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32
+        deserialize_u64 deserialize_u128 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_option deserialize_unit
+        deserialize_seq deserialize_map deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        let visitor = FloatVisitor {
+            visitor,
+            layout: F32,
+        };
+        if self.0.is_human_readable() {
+            self.0.deserialize_any(visitor)
+        } else {
+            self.0.deserialize_u32(visitor)
+        }
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        let visitor = FloatVisitor {
+            visitor,
+            layout: F64,
+        };
+        if self.0.is_human_readable() {
+            self.0.deserialize_any(visitor)
+        } else {
+            self.0.deserialize_u64(visitor)
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        self.0.deserialize_unit_struct(name, Wrap(visitor))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        self.0.deserialize_newtype_struct(name, Wrap(visitor))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        self.0.deserialize_tuple(len, Wrap(visitor))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        self.0.deserialize_tuple_struct(name, len, Wrap(visitor))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        self.0.deserialize_struct(name, fields, Wrap(visitor))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        self.0.deserialize_enum(name, variants, Wrap(visitor))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.0.is_human_readable()
+    }
+}
+
+// Turns bits, hex floats and decimals into the float `visitor` expects.
+struct FloatVisitor<V> {
+    visitor: V,
+    layout: Layout,
+}
+
+impl<V> FloatVisitor<V> {
+    fn is_f32(&self) -> bool {
+        self.layout.mantissa_bits == F32.mantissa_bits
+    }
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for FloatVisitor<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a float, its bits or a hexadecimal float")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<V::Value, E> {
+        if self.is_f32() {
+            let bits = u32::try_from(v)
+                .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))?;
+            self.visitor.visit_f32(f32::from_bits(bits))
+        } else {
+            self.visitor.visit_f64(f64::from_bits(v))
+        }
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<V::Value, E> {
+        self.visitor.visit_i64(v)
+    }
+
+    fn visit_f32<E: de::Error>(self, v: f32) -> Result<V::Value, E> {
+        self.visitor.visit_f32(v)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<V::Value, E> {
+        self.visitor.visit_f64(v)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<V::Value, E> {
+        let bits = self
+            .layout
+            .parse(v)
+            .ok_or_else(|| E::invalid_value(de::Unexpected::Str(v), &self))?;
+        if self.is_f32() {
+            self.visitor.visit_f32(f32::from_bits(bits as u32))
+        } else {
+            self.visitor.visit_f64(f64::from_bits(bits))
+        }
+    }
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for Wrap<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.expecting(f)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<V::Value, E> {
+        self.0.visit_bool(v)
+    }
+
+    fn visit_i8<E: de::Error>(self, v: i8) -> Result<V::Value, E> {
+        self.0.visit_i8(v)
+    }
+
+    fn visit_i16<E: de::Error>(self, v: i16) -> Result<V::Value, E> {
+        self.0.visit_i16(v)
+    }
+
+    fn visit_i32<E: de::Error>(self, v: i32) -> Result<V::Value, E> {
+        self.0.visit_i32(v)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<V::Value, E> {
+        self.0.visit_i64(v)
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<V::Value, E> {
+        self.0.visit_i128(v)
+    }
+
+    fn visit_u8<E: de::Error>(self, v: u8) -> Result<V::Value, E> {
+        self.0.visit_u8(v)
+    }
+
+    fn visit_u16<E: de::Error>(self, v: u16) -> Result<V::Value, E> {
+        self.0.visit_u16(v)
+    }
+
+    fn visit_u32<E: de::Error>(self, v: u32) -> Result<V::Value, E> {
+        self.0.visit_u32(v)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<V::Value, E> {
+        self.0.visit_u64(v)
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<V::Value, E> {
+        self.0.visit_u128(v)
+    }
+
+    fn visit_f32<E: de::Error>(self, v: f32) -> Result<V::Value, E> {
+        self.0.visit_f32(v)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<V::Value, E> {
+        self.0.visit_f64(v)
+    }
+
+    fn visit_char<E: de::Error>(self, v: char) -> Result<V::Value, E> {
+        self.0.visit_char(v)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<V::Value, E> {
+        self.0.visit_str(v)
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<V::Value, E> {
+        self.0.visit_borrowed_str(v)
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<V::Value, E> {
+        self.0.visit_string(v)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<V::Value, E> {
+        self.0.visit_bytes(v)
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<V::Value, E> {
+        self.0.visit_borrowed_bytes(v)
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<V::Value, E> {
+        self.0.visit_byte_buf(v)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<V::Value, E> {
+        self.0.visit_none()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        self.0.visit_some(BitExactDeserializer(deserializer))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+        self.0.visit_unit()
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<V::Value, D::Error> {
+        self.0
+            .visit_newtype_struct(BitExactDeserializer(deserializer))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        self.0.visit_seq(Wrap(seq))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+        self.0.visit_map(Wrap(map))
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+        self.0.visit_enum(Wrap(data))
+    }
+}
+
+impl<'de, T: DeserializeSeed<'de>> DeserializeSeed<'de> for Wrap<T> {
+    type Value = T::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T::Value, D::Error> {
+        self.0.deserialize(BitExactDeserializer(deserializer))
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for Wrap<A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, A::Error> {
+        self.0.next_element_seed(Wrap(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.0.size_hint()
+    }
+}
+
+// As when serializing, map keys are read as they are.
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for Wrap<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        self.0.next_key_seed(seed)
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value, A::Error> {
+        self.0.next_value_seed(Wrap(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.0.size_hint()
+    }
+}
+
+impl<'de, A: EnumAccess<'de>> EnumAccess<'de> for Wrap<A> {
+    type Error = A::Error;
+    type Variant = Wrap<A::Variant>;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<(T::Value, Wrap<A::Variant>), A::Error> {
+        let (value, variant) = self.0.variant_seed(seed)?;
+        Ok((value, Wrap(variant)))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for Wrap<A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.0.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, A::Error> {
+        self.0.newtype_variant_seed(Wrap(seed))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        self.0.tuple_variant(len, Wrap(visitor))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        self.0.struct_variant(fields, Wrap(visitor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Sample {
+        Point { x: f32, y: Option<f64> },
+        Pair(f32, f64),
+        Empty,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Frame {
+        timestamp: f64,
+        samples: Vec<Sample>,
+        gain: [f32; 2],
+    }
+
+    fn frame() -> Frame {
+        Frame {
+            timestamp: 0.1 + 0.2,
+            samples: vec![
+                Sample::Point {
+                    x: -0.0,
+                    y: Some(f64::MIN_POSITIVE / 4.0),
+                },
+                Sample::Point { x: 1.5, y: None },
+                Sample::Pair(f32::INFINITY, f64::NEG_INFINITY),
+                Sample::Empty,
+            ],
+            gain: [f32::MAX, f32::from_bits(1)],
+        }
+    }
+
+    #[test]
+    fn hex_floats_round_trip() {
+        assert_eq!(hex_f32(1.5), "0x1.8p+0");
+        assert_eq!(hex_f64(-0.0), "-0x0p+0");
+        assert_eq!(hex_f64(f64::from_bits(1)), "0x0.0000000000001p-1022");
+        assert_eq!(hex_f32(f32::NEG_INFINITY), "-inf");
+        let nan = f32::from_bits(0xffc0_0001);
+        assert_eq!(hex_f32(nan), "nan(0xffc00001)");
+        for v in [
+            0.0,
+            -0.0,
+            1.0,
+            0.1,
+            f32::MAX,
+            f32::MIN_POSITIVE,
+            f32::from_bits(1),
+            nan,
+        ] {
+            assert_eq!(parse_hex_f32(&hex_f32(v)).unwrap().to_bits(), v.to_bits());
+        }
+        for v in [
+            0.1 + 0.2,
+            -1e300,
+            f64::from_bits(0x000f_ffff_ffff_ffff),
+            f64::NAN,
+        ] {
+            assert_eq!(parse_hex_f64(&hex_f64(v)).unwrap().to_bits(), v.to_bits());
+        }
+    }
+
+    #[test]
+    fn malformed_hex_floats_are_rejected() {
+        for s in [
+            "",
+            "1.5",
+            "0x2p+0",
+            "0x1.p+0",
+            "0x1.8",
+            "0x1p+128",
+            "nan(0x7f800000)",
+        ] {
+            assert_eq!(parse_hex_f32(s), None, "{s}");
+        }
+        assert_eq!(parse_hex_f64("0x1.00000000000001p+0"), None);
+    }
+
+    #[test]
+    fn both_encodings_round_trip_through_json() {
+        let frame = frame();
+        for encoding in [FloatEncoding::Bits, FloatEncoding::HexFloat] {
+            let json = serde_json::to_string(&encoding.wrap(&frame)).unwrap();
+            let mut de = serde_json::Deserializer::from_str(&json);
+            let back: Frame = deserialize(&mut de).unwrap();
+            assert_eq!(back, frame, "{encoding:?}: {json}");
+        }
+    }
+
+    #[test]
+    fn nan_payloads_survive() {
+        let nan = f64::from_bits(0xfff0_0000_dead_beef);
+        for encoding in [FloatEncoding::Bits, FloatEncoding::HexFloat] {
+            let json = serde_json::to_string(&encoding.wrap(&nan)).unwrap();
+            let mut de = serde_json::Deserializer::from_str(&json);
+            let back: f64 = deserialize(&mut de).unwrap();
+            assert_eq!(back.to_bits(), nan.to_bits(), "{encoding:?}");
+        }
+    }
+
+    #[test]
+    fn floats_are_written_as_bits() {
+        let json = serde_json::to_string(&FloatEncoding::Bits.wrap(&[1.0f32])).unwrap();
+        assert_eq!(json, "[1065353216]");
+        let json = serde_json::to_string(&FloatEncoding::HexFloat.wrap(&[1.0f32])).unwrap();
+        assert_eq!(json, r#"["0x1p+0"]"#);
+    }
+
+    #[test]
+    fn plain_decimal_floats_are_read() {
+        // serde_json writes infinities as null
+        let mut frame = frame();
+        frame.samples.retain(|s| !matches!(s, Sample::Pair(..)));
+        let json = serde_json::to_string(&frame).unwrap();
+        let mut de = serde_json::Deserializer::from_str(&json);
+        let back: Frame = deserialize(&mut de).unwrap();
+        assert_eq!(back, frame);
+    }
+}
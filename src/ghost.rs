@@ -0,0 +1,159 @@
+//! Debug markers for showing recorded runs inside a live world.
+//!
+//! [`GhostScene`] turns the actor poses and events of one or more
+//! recordings into points, lines and labels in world coordinates, one color
+//! per run, so two drives can be compared in the simulator they came from.
+//! The carla crate has no binding for CARLA's `DebugHelper` yet, so drawing
+//! goes through [`MarkerSink`], implemented by whatever can reach it (a
+//! patched client, a Python bridge fed the serialized markers, ...).
+
+use crate::{ActorSerDe, SensorDataSerDe};
+use carla::rpc::ActorId;
+use nalgebra::{Point3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Length of the line drawn along a collision impulse, in meters.
+const IMPULSE_LINE: f32 = 2.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Shape {
+    Point(Point3<f32>),
+    Line { from: Point3<f32>, to: Point3<f32> },
+    Text { at: Point3<f32>, text: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Marker {
+    pub shape: Shape,
+    pub color: [u8; 3],
+    /// Seconds the marker stays; negative keeps it, as in CARLA.
+    pub life_time: f32,
+}
+
+/// Draws markers into a world, e.g. with `DebugHelper::draw_point`.
+pub trait MarkerSink {
+    fn draw(&mut self, marker: &Marker);
+}
+
+impl MarkerSink for Vec<Marker> {
+    fn draw(&mut self, marker: &Marker) {
+        self.push(marker.clone());
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GhostScene {
+    pub markers: Vec<Marker>,
+    life_time: f32,
+}
+
+impl GhostScene {
+    /// A scene whose markers stay until the world is reloaded.
+    pub fn new() -> Self {
+        Self {
+            markers: Vec::new(),
+            life_time: -1.0,
+        }
+    }
+
+    pub fn with_life_time(mut self, seconds: f32) -> Self {
+        self.life_time = seconds;
+        self
+    }
+
+    /// Add one run's markers in `color`: a trajectory per actor seen in the
+    /// event frames, the collisions with their impulse direction, and a line
+    /// to every detected obstacle.
+    pub fn add_run(&mut self, frames: &[SensorDataSerDe], color: [u8; 3]) -> &mut Self {
+        let mut trajectories: BTreeMap<ActorId, Vec<Point3<f32>>> = BTreeMap::new();
+        let mut track = |actor: &ActorSerDe| {
+            let points = trajectories.entry(actor.id).or_default();
+            let at = position(actor);
+            if points.last() != Some(&at) {
+                points.push(at);
+            }
+        };
+        let mut events = Vec::new();
+        for frame in frames {
+            match frame {
+                SensorDataSerDe::Collision(c) => {
+                    track(&c.actor);
+                    if let Some(other) = &c.other_actor {
+                        track(other);
+                    }
+                    let at = position(&c.actor);
+                    let impulse = Vector3::from(c.normal_impulse);
+                    if let Some(direction) = impulse.try_normalize(f32::EPSILON) {
+                        events.push(Shape::Line {
+                            from: at,
+                            to: at + direction * IMPULSE_LINE,
+                        });
+                    }
+                    events.push(Shape::Point(at));
+                    events.push(Shape::Text {
+                        at,
+                        text: c.to_string(),
+                    });
+                }
+                SensorDataSerDe::ObstacleDetection(o) => {
+                    track(&o.actor);
+                    track(&o.other_actor);
+                    let to = position(&o.other_actor);
+                    events.push(Shape::Line {
+                        from: position(&o.actor),
+                        to,
+                    });
+                    events.push(Shape::Text {
+                        at: to,
+                        text: o.to_string(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        for points in trajectories.values() {
+            match points.as_slice() {
+                [single] => self.push(Shape::Point(*single), color),
+                points => {
+                    for pair in points.windows(2) {
+                        let shape = Shape::Line {
+                            from: pair[0],
+                            to: pair[1],
+                        };
+                        self.push(shape, color);
+                    }
+                }
+            }
+        }
+        for shape in events {
+            self.push(shape, color);
+        }
+        self
+    }
+
+    pub fn draw(&self, sink: &mut impl MarkerSink) {
+        for marker in &self.markers {
+            sink.draw(marker);
+        }
+    }
+
+    fn push(&mut self, shape: Shape, color: [u8; 3]) {
+        self.markers.push(Marker {
+            shape,
+            color,
+            life_time: self.life_time,
+        });
+    }
+}
+
+impl Default for GhostScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn position(actor: &ActorSerDe) -> Point3<f32> {
+    Point3::from(actor.transform.translation.vector)
+}
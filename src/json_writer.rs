@@ -0,0 +1,309 @@
+//! Hand-written JSON for IMU and GNSS frames, for kHz-rate publishers.
+//!
+//! [`ImuJsonWriter`] and [`GnssJsonWriter`] format into a buffer they keep
+//! between calls, so once the buffer has grown to the size of a message,
+//! writing one allocates nothing. The output is byte for byte what
+//! `serde_json` writes for an [`ImuMeasurementSerDe`], a
+//! [`GnssMeasurementSerDe`] or their [`SensorDataSerDe`](crate::SensorDataSerDe)
+//! frames, with the serde and `Value` machinery out of the way.
+
+use crate::{
+    AnnotationValueSerDe, AnnotationsSerDe, GnssMeasurementSerDe, ImuFrameSerDe,
+    ImuMeasurementSerDe, ImuNoiseModelSerDe, Vector3DSerDe,
+};
+use std::fmt::{self, Write};
+
+#[derive(Clone, Debug, Default)]
+pub struct ImuJsonWriter {
+    buf: String,
+}
+
+impl ImuJsonWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encode a bare measurement; the returned text is valid until the next
+    /// call.
+    pub fn write_measurement(&mut self, imu: &ImuMeasurementSerDe) -> &str {
+        self.buf.clear();
+        // writing to a String cannot fail
+        let _ = write_imu(&mut self.buf, imu, &AnnotationsSerDe::default());
+        &self.buf
+    }
+
+    /// Encode the frame with its annotations as a
+    /// [`crate::SensorDataSerDe::Imu`], i.e. one line of a recording.
+    pub fn write_frame(&mut self, imu: &ImuFrameSerDe) -> &str {
+        self.buf.clear();
+        self.buf.push_str("{\"Imu\":");
+        let _ = write_imu(&mut self.buf, &imu.measurement, &imu.annotations);
+        self.buf.push('}');
+        &self.buf
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GnssJsonWriter {
+    buf: String,
+}
+
+impl GnssJsonWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encode a bare measurement, annotations included; the returned text is
+    /// valid until the next call.
+    pub fn write_measurement(&mut self, gnss: &GnssMeasurementSerDe) -> &str {
+        self.buf.clear();
+        let _ = write_gnss(&mut self.buf, gnss);
+        &self.buf
+    }
+
+    /// Encode the measurement as a [`crate::SensorDataSerDe::Gnss`], i.e. one
+    /// line of a recording.
+    pub fn write_frame(&mut self, gnss: &GnssMeasurementSerDe) -> &str {
+        self.buf.clear();
+        self.buf.push_str("{\"Gnss\":");
+        let _ = write_gnss(&mut self.buf, gnss);
+        self.buf.push('}');
+        &self.buf
+    }
+}
+
+fn write_gnss(out: &mut String, gnss: &GnssMeasurementSerDe) -> fmt::Result {
+    out.push_str("{\"latitude\":");
+    write_f64(out, gnss.latitude)?;
+    out.push_str(",\"longitude\":");
+    write_f64(out, gnss.longitude)?;
+    out.push_str(",\"altitude\":");
+    write_f64(out, gnss.altitude)?;
+    write_annotations(out, &gnss.annotations)?;
+    out.push('}');
+    Ok(())
+}
+
+fn write_imu(
+    out: &mut String,
+    imu: &ImuMeasurementSerDe,
+    annotations: &AnnotationsSerDe,
+) -> fmt::Result {
+    out.push_str("{\"accelerometer\":");
+    write_vector(out, &imu.accelerometer)?;
+    out.push_str(",\"gyroscope\":");
+    write_vector(out, &imu.gyroscope)?;
+    out.push_str(",\"compass\":");
+    write_f32(out, imu.compass)?;
+    if let Some(model) = &imu.noise_model {
+        out.push_str(",\"noise_model\":");
+        write_noise_model(out, model)?;
+    }
+    write_annotations(out, annotations)?;
+    out.push('}');
+    Ok(())
+}
+
+// the `annotations` field, left out when there are none
+fn write_annotations(out: &mut String, annotations: &AnnotationsSerDe) -> fmt::Result {
+    if annotations.is_empty() {
+        return Ok(());
+    }
+    out.push_str(",\"annotations\":{");
+    for (i, (key, value)) in annotations.0.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_str(out, key);
+        out.push(':');
+        write_annotation(out, value)?;
+    }
+    out.push('}');
+    Ok(())
+}
+
+fn write_noise_model(out: &mut String, m: &ImuNoiseModelSerDe) -> fmt::Result {
+    out.push_str("{\"accelerometer_stddev\":");
+    write_vector(out, &m.accelerometer_stddev)?;
+    out.push_str(",\"accelerometer_bias\":");
+    write_vector(out, &m.accelerometer_bias)?;
+    out.push_str(",\"gyroscope_stddev\":");
+    write_vector(out, &m.gyroscope_stddev)?;
+    out.push_str(",\"gyroscope_bias\":");
+    write_vector(out, &m.gyroscope_bias)?;
+    out.push_str(",\"compass_stddev\":");
+    write_f32(out, m.compass_stddev)?;
+    write!(out, ",\"seed\":{}}}", m.seed)
+}
+
+fn write_annotation(out: &mut String, value: &AnnotationValueSerDe) -> fmt::Result {
+    match value {
+        AnnotationValueSerDe::Bool(b) => write!(out, "{{\"Bool\":{b}}}"),
+        AnnotationValueSerDe::Int(i) => write!(out, "{{\"Int\":{i}}}"),
+        AnnotationValueSerDe::Float(f) => {
+            out.push_str("{\"Float\":");
+            write_f64(out, *f)?;
+            out.push('}');
+            Ok(())
+        }
+        AnnotationValueSerDe::Text(s) => {
+            out.push_str("{\"Text\":");
+            write_str(out, s);
+            out.push('}');
+            Ok(())
+        }
+        AnnotationValueSerDe::Vector(v) => {
+            out.push_str("{\"Vector\":");
+            write_f32s(out, v)?;
+            out.push('}');
+            Ok(())
+        }
+        AnnotationValueSerDe::Tensor { shape, data } => {
+            out.push_str("{\"Tensor\":{\"shape\":[");
+            for (i, n) in shape.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write!(out, "{n}")?;
+            }
+            out.push_str("],\"data\":");
+            write_f32s(out, data)?;
+            out.push_str("}}");
+            Ok(())
+        }
+        AnnotationValueSerDe::Bytes(bytes) => {
+            out.push_str("{\"Bytes\":[");
+            for (i, b) in bytes.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write!(out, "{b}")?;
+            }
+            out.push_str("]}");
+            Ok(())
+        }
+    }
+}
+
+fn write_vector(out: &mut String, v: &Vector3DSerDe) -> fmt::Result {
+    out.push_str("{\"x\":");
+    write_f32(out, v.x)?;
+    out.push_str(",\"y\":");
+    write_f32(out, v.y)?;
+    out.push_str(",\"z\":");
+    write_f32(out, v.z)?;
+    out.push('}');
+    Ok(())
+}
+
+fn write_f32s(out: &mut String, values: &[f32]) -> fmt::Result {
+    out.push('[');
+    for (i, v) in values.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_f32(out, *v)?;
+    }
+    out.push(']');
+    Ok(())
+}
+
+// Same formatting as serde_json, including `null` for non-finite numbers.
+fn write_f32(out: &mut String, v: f32) -> fmt::Result {
+    if v.is_finite() {
+        out.push_str(ryu::Buffer::new().format_finite(v));
+    } else {
+        out.push_str("null");
+    }
+    Ok(())
+}
+
+fn write_f64(out: &mut String, v: f64) -> fmt::Result {
+    if v.is_finite() {
+        out.push_str(ryu::Buffer::new().format_finite(v));
+    } else {
+        out.push_str("null");
+    }
+    Ok(())
+}
+
+fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            c if c < ' ' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use crate::SensorDataSerDe;
+
+    fn annotations() -> AnnotationsSerDe {
+        let mut annotations = AnnotationsSerDe::default();
+        annotations.insert("flag", true);
+        annotations.insert("count", -3i64);
+        annotations.insert("score", 0.1 + 0.2);
+        annotations.insert("lost", f64::NAN);
+        annotations.insert("note", "tab\t\"quoted\"\\ \u{1} é");
+        annotations.insert("embedding", vec![1.5f32, -0.0, f32::INFINITY]);
+        annotations.insert(
+            "logits",
+            AnnotationValueSerDe::Tensor {
+                shape: vec![1, 2],
+                data: vec![0.25, 1e-7],
+            },
+        );
+        annotations.insert("raw", AnnotationValueSerDe::Bytes(vec![0, 255]));
+        annotations
+    }
+
+    #[test]
+    fn imu_matches_serde_json() {
+        let mut frame = crate::fixtures::imu_frame();
+        let mut writer = ImuJsonWriter::new();
+        let bare = serde_json::to_string(&frame.measurement).unwrap();
+        assert_eq!(writer.write_measurement(&frame.measurement), bare);
+
+        frame.measurement.noise_model = Some(ImuNoiseModelSerDe {
+            accelerometer_stddev: Vector3DSerDe {
+                x: 0.01,
+                y: 0.02,
+                z: 1e-9,
+            },
+            compass_stddev: 0.5,
+            seed: u64::MAX,
+            ..Default::default()
+        });
+        frame.annotations = annotations();
+        let json = serde_json::to_string(&SensorDataSerDe::Imu(frame.clone())).unwrap();
+        assert_eq!(writer.write_frame(&frame), json);
+    }
+
+    #[test]
+    fn gnss_matches_serde_json() {
+        let mut gnss = crate::fixtures::gnss_frame();
+        let mut writer = GnssJsonWriter::new();
+        let json = serde_json::to_string(&SensorDataSerDe::Gnss(gnss.clone())).unwrap();
+        assert_eq!(writer.write_frame(&gnss), json);
+        assert_eq!(json.as_bytes(), crate::fixtures::GNSS_JSON.trim_ascii_end());
+
+        gnss.altitude = -f64::MAX;
+        gnss.annotations = annotations();
+        let bare = serde_json::to_string(&gnss).unwrap();
+        assert_eq!(writer.write_measurement(&gnss), bare);
+    }
+}
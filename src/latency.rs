@@ -0,0 +1,213 @@
+//! Network-like delivery delays for live streams.
+//!
+//! [`LatencySink`] holds every frame for a latency drawn from its sensor
+//! kind's [`Latency`] before passing it on, so consumers can be tested
+//! against late, bunched and, with jitter, reordered frames. Frames are
+//! delivered from [`Sink::consume`], [`LatencySink::poll`] and
+//! [`Sink::flush`]; there is no background thread, so a producer that goes
+//! quiet should call `poll` now and then.
+
+use crate::augment::NoiseRng;
+use crate::pipeline::{SharedFrame, Sink};
+use crate::{ProcessingStepSerDe, SensorKind};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::time::{Duration, Instant};
+
+/// A delay distribution, in milliseconds. Samples below zero count as
+/// zero; samples no [`Duration`] holds, e.g. of an infinite mean, are
+/// skipped and the frame is not delayed.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Latency {
+    Fixed { ms: f64 },
+    Uniform { min_ms: f64, max_ms: f64 },
+    Normal { mean_ms: f64, stddev_ms: f64 },
+}
+
+impl Latency {
+    /// `None` if the sample is infinite or too large for a [`Duration`].
+    pub fn sample(&self, rng: &mut NoiseRng) -> Option<Duration> {
+        let ms = match *self {
+            Self::Fixed { ms } => ms,
+            Self::Uniform { min_ms, max_ms } => min_ms + (max_ms - min_ms) * rng.uniform() as f64,
+            Self::Normal { mean_ms, stddev_ms } => mean_ms + stddev_ms * rng.gaussian() as f64,
+        };
+        Duration::try_from_secs_f64(ms.max(0.0) / 1000.0).ok()
+    }
+}
+
+impl Default for Latency {
+    fn default() -> Self {
+        Self::Fixed { ms: 0.0 }
+    }
+}
+
+/// Delays frames on their way to `inner`.
+#[derive(Debug)]
+pub struct LatencySink<S> {
+    inner: S,
+    default: Latency,
+    by_kind: BTreeMap<SensorKind, Latency>,
+    in_order: bool,
+    seed: u64,
+    rng: NoiseRng,
+    // due time, arrival order, frame
+    pending: BinaryHeap<Reverse<Pending>>,
+    arrivals: u64,
+    // latest due time handed out, for in-order delivery
+    last_due: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Pending {
+    due: Instant,
+    order: u64,
+    frame: SharedFrame,
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        (self.due, self.order) == (other.due, other.order)
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.due, self.order).cmp(&(other.due, other.order))
+    }
+}
+
+impl<S: Sink> LatencySink<S> {
+    /// No delay for any sensor until configured otherwise.
+    pub fn new(inner: S, seed: u64) -> Self {
+        Self {
+            inner,
+            default: Latency::default(),
+            by_kind: BTreeMap::new(),
+            in_order: false,
+            seed,
+            rng: NoiseRng::new(seed),
+            pending: BinaryHeap::new(),
+            arrivals: 0,
+            last_due: None,
+        }
+    }
+
+    /// Latency for sensor kinds without their own.
+    pub fn with_default(mut self, latency: Latency) -> Self {
+        self.default = latency;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: SensorKind, latency: Latency) -> Self {
+        self.by_kind.insert(kind, latency);
+        self
+    }
+
+    /// Never deliver a frame before one that arrived earlier, like a single
+    /// TCP connection: a late frame holds back the ones behind it.
+    pub fn in_order(mut self) -> Self {
+        self.in_order = true;
+        self
+    }
+
+    /// Frames waiting for their delivery time.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Deliver the frames that are due.
+    pub fn poll(&mut self) {
+        self.deliver_due(Instant::now());
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    pub fn describe(&self) -> ProcessingStepSerDe {
+        let mut step = ProcessingStepSerDe::new("latency")
+            .with_param("seed", self.seed as i64)
+            .with_param("in_order", self.in_order)
+            .with_param("default", format!("{:?}", self.default));
+        for (kind, latency) in &self.by_kind {
+            step = step.with_param(format!("{kind:?}"), format!("{latency:?}"));
+        }
+        step
+    }
+
+    fn deliver_due(&mut self, now: Instant) {
+        while self.pending.peek().is_some_and(|Reverse(p)| p.due <= now) {
+            let Reverse(p) = self.pending.pop().expect("peeked");
+            self.inner.consume(p.frame);
+        }
+    }
+}
+
+impl<S: Sink> Sink for LatencySink<S> {
+    fn consume(&mut self, frame: SharedFrame) {
+        let now = Instant::now();
+        let latency = self.by_kind.get(&frame.kind()).unwrap_or(&self.default);
+        let mut due = latency
+            .sample(&mut self.rng)
+            .and_then(|delay| now.checked_add(delay))
+            .unwrap_or(now);
+        if self.in_order
+            && let Some(last) = self.last_due
+        {
+            due = due.max(last);
+        }
+        self.last_due = Some(due);
+        self.pending.push(Reverse(Pending {
+            due,
+            order: self.arrivals,
+            frame,
+        }));
+        self.arrivals += 1;
+        self.deliver_due(now);
+    }
+
+    /// Waits out the remaining delays, then flushes `inner`.
+    fn flush(&mut self) {
+        while let Some(Reverse(p)) = self.pending.peek() {
+            let wait = p.due.saturating_duration_since(Instant::now());
+            if !wait.is_zero() {
+                std::thread::sleep(wait);
+            }
+            self.poll();
+        }
+        self.inner.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_samples_no_duration_holds() {
+        let mut rng = NoiseRng::new(7);
+        let fixed = |ms| Latency::Fixed { ms };
+        assert_eq!(fixed(-5.0).sample(&mut rng), Some(Duration::ZERO));
+        assert_eq!(
+            fixed(250.0).sample(&mut rng),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(fixed(f64::INFINITY).sample(&mut rng), None);
+        assert_eq!(fixed(1e300).sample(&mut rng), None);
+        let normal = Latency::Normal {
+            mean_ms: f64::MAX,
+            stddev_ms: 1.0,
+        };
+        assert_eq!(normal.sample(&mut rng), None);
+    }
+}
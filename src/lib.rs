@@ -1,3 +1,42 @@
+pub mod access;
+pub mod augment;
+#[cfg(feature = "budget")]
+pub mod budget;
+pub mod bus;
+pub mod calibration;
+pub mod captions;
+pub mod compact;
+pub mod conditions;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod distortion;
+pub mod faults;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+pub mod fleet;
+pub mod float_bits;
+pub mod ghost;
+#[cfg(feature = "json-writer")]
+pub mod json_writer;
+pub mod latency;
+pub mod monitor;
+pub mod naming;
+pub mod pipeline;
+pub mod plot;
+mod proxy;
+#[cfg(feature = "recording")]
+pub mod recording;
+#[cfg(feature = "carla-client")]
+pub mod replay;
+pub mod report;
+pub mod resize;
+pub mod rolling_shutter;
+pub mod selection;
 mod serde;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+pub mod transport;
 
 pub use serde::*;
@@ -0,0 +1,295 @@
+//! Live statistics of a recording session, for dashboards.
+//!
+//! [`SessionMonitor`] is a [`Sink`]: subscribe it to the bus like any other
+//! consumer. It tracks per-sensor frame rates, bus queue depths, the disk
+//! usage of the recording and the last event seen, and renders them as a
+//! plain-text dashboard. Pausing and markers are exposed as state and
+//! [`SessionMonitor::handle_key`] so any terminal front end can drive them;
+//! the monitor does not read the keyboard itself. With the `tui` feature,
+//! [`TerminalDashboard`] is such a front end.
+
+use crate::bus::SensorBus;
+use crate::captions::event_summary;
+use crate::pipeline::{SharedFrame, Sink};
+use crate::{SensorDataSerDe, SensorKind};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use std::{fs, io};
+
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(feature = "tui")]
+pub use tui::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marker {
+    /// Time since the monitor was created.
+    pub at: Duration,
+    pub label: String,
+}
+
+#[derive(Clone, Debug, Default)]
+struct SensorStats {
+    total: u64,
+    // arrival times inside the rate window
+    recent: VecDeque<Instant>,
+    last_seen: Option<Instant>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SessionMonitor {
+    started: Instant,
+    window: Duration,
+    sensors: BTreeMap<SensorKind, SensorStats>,
+    queue_depths: Vec<usize>,
+    disk_usage: Option<u64>,
+    last_event: Option<(Instant, String)>,
+    paused: bool,
+    markers: Vec<Marker>,
+}
+
+impl Default for SessionMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionMonitor {
+    /// Rates are averaged over the last second by default.
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            window: Duration::from_secs(1),
+            sensors: BTreeMap::new(),
+            queue_depths: Vec::new(),
+            disk_usage: None,
+            last_event: None,
+            paused: false,
+            markers: Vec::new(),
+        }
+    }
+
+    pub fn with_rate_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn record(&mut self, frame: &SensorDataSerDe) {
+        self.record_at(frame, Instant::now());
+    }
+
+    pub fn record_at(&mut self, frame: &SensorDataSerDe, now: Instant) {
+        let stats = self.sensors.entry(frame.kind()).or_default();
+        stats.total += 1;
+        stats.last_seen = Some(now);
+        stats.recent.push_back(now);
+        while let Some(&t) = stats.recent.front() {
+            if now.duration_since(t) <= self.window {
+                break;
+            }
+            stats.recent.pop_front();
+        }
+        if let Some(text) = event_summary(frame) {
+            self.last_event = Some((now, text));
+        }
+    }
+
+    /// Frames per second of `kind` over the rate window, which ends now:
+    /// a sensor that stopped sending drops to 0.
+    pub fn rate(&self, kind: SensorKind) -> f64 {
+        self.rate_at(kind, Instant::now())
+    }
+
+    /// [`rate`](Self::rate) over the window ending at `now`.
+    pub fn rate_at(&self, kind: SensorKind, now: Instant) -> f64 {
+        let Some(stats) = self.sensors.get(&kind) else {
+            return 0.0;
+        };
+        let stale = stats
+            .recent
+            .partition_point(|&t| now.saturating_duration_since(t) > self.window);
+        (stats.recent.len() - stale) as f64 / self.window.as_secs_f64()
+    }
+
+    pub fn total(&self, kind: SensorKind) -> u64 {
+        self.sensors.get(&kind).map_or(0, |s| s.total)
+    }
+
+    /// Time since the last frame of `kind`, `None` if none arrived yet.
+    pub fn since_last(&self, kind: SensorKind) -> Option<Duration> {
+        self.sensors.get(&kind)?.last_seen.map(|t| t.elapsed())
+    }
+
+    /// Snapshot the bus's subscriber queue depths.
+    pub fn observe_bus(&mut self, bus: &SensorBus) {
+        self.queue_depths = bus.queue_depths();
+    }
+
+    pub fn queue_depths(&self) -> &[usize] {
+        &self.queue_depths
+    }
+
+    /// Snapshot the size of the recording at `path`, a file or a directory
+    /// of segments, counted recursively.
+    pub fn observe_disk(&mut self, path: &Path) -> io::Result<()> {
+        self.disk_usage = Some(disk_usage(path)?);
+        Ok(())
+    }
+
+    pub fn set_disk_usage(&mut self, bytes: u64) {
+        self.disk_usage = Some(bytes);
+    }
+
+    /// Bytes on disk as of the last snapshot, `None` before the first.
+    pub fn disk_usage(&self) -> Option<u64> {
+        self.disk_usage
+    }
+
+    /// A flag for the recorder to honor; the monitor keeps counting while
+    /// paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn add_marker(&mut self, label: impl Into<String>) {
+        self.markers.push(Marker {
+            at: self.started.elapsed(),
+            label: label.into(),
+        });
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    /// Default key bindings: `p` toggles pause, `m` drops a marker. Returns
+    /// whether the key was handled.
+    pub fn handle_key(&mut self, key: char) -> bool {
+        match key {
+            'p' => self.paused = !self.paused,
+            'm' => {
+                let n = self.markers.len() + 1;
+                self.add_marker(format!("marker {n}"));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn write_dashboard(&self, out: &mut impl Write) -> fmt::Result {
+        let state = if self.paused { "PAUSED" } else { "recording" };
+        writeln!(
+            out,
+            "{state} for {:.1} s, {} marker(s)",
+            self.started.elapsed().as_secs_f64(),
+            self.markers.len()
+        )?;
+        writeln!(
+            out,
+            "{:<18} {:>9} {:>10} {:>10}",
+            "sensor", "rate [Hz]", "frames", "last [ms]"
+        )?;
+        for (kind, stats) in &self.sensors {
+            let last = stats
+                .last_seen
+                .map_or_else(|| "-".into(), |t| t.elapsed().as_millis().to_string());
+            writeln!(
+                out,
+                "{:<18} {:>9.1} {:>10} {:>10}",
+                format!("{kind:?}"),
+                self.rate(*kind),
+                stats.total,
+                last
+            )?;
+        }
+        if !self.queue_depths.is_empty() {
+            writeln!(out, "queues: {:?}", self.queue_depths)?;
+        }
+        if let Some(bytes) = self.disk_usage {
+            writeln!(out, "disk: {}", format_bytes(bytes))?;
+        }
+        if let Some((t, text)) = &self.last_event {
+            writeln!(
+                out,
+                "last event ({:.1} s ago): {text}",
+                t.elapsed().as_secs_f64()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn disk_usage(path: &Path) -> io::Result<u64> {
+    let meta = fs::metadata(path)?;
+    if !meta.is_dir() {
+        return Ok(meta.len());
+    }
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        total += disk_usage(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+// `512 B`, `3.4 MiB`
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+impl Sink for SessionMonitor {
+    fn consume(&mut self, frame: SharedFrame) {
+        self.record(&frame);
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_drops_when_frames_stop() {
+        let imu = SensorDataSerDe::Imu(crate::fixtures::imu_frame());
+        let start = Instant::now();
+        let mut monitor = SessionMonitor::new();
+        for i in 0..10 {
+            monitor.record_at(&imu, start + Duration::from_millis(100 * i));
+        }
+        let last = start + Duration::from_millis(900);
+        assert_eq!(monitor.rate_at(SensorKind::Imu, last), 10.0);
+        let later = last + Duration::from_millis(500);
+        assert_eq!(monitor.rate_at(SensorKind::Imu, later), 6.0);
+        let stopped = last + Duration::from_secs(2);
+        assert_eq!(monitor.rate_at(SensorKind::Imu, stopped), 0.0);
+    }
+
+    #[test]
+    fn disk_usage_sums_a_directory() {
+        let dir = std::env::temp_dir().join(format!("monitor-disk-{}", std::process::id()));
+        fs::create_dir_all(dir.join("segments")).unwrap();
+        fs::write(dir.join("header.jsonl"), [0; 100]).unwrap();
+        fs::write(dir.join("segments/0.jsonl"), [0; 2000]).unwrap();
+        let mut monitor = SessionMonitor::new();
+        monitor.observe_disk(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(monitor.disk_usage(), Some(2100));
+        let mut out = String::new();
+        monitor.write_dashboard(&mut out).unwrap();
+        assert!(out.contains("disk: 2.1 KiB"), "{out}");
+    }
+}
@@ -0,0 +1,241 @@
+//! A ratatui front end of [`SessionMonitor`].
+
+use super::{SessionMonitor, format_bytes};
+use ratatui::Frame;
+use ratatui::Terminal;
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, Wrap};
+use std::io::{self, Stdout};
+use std::time::Duration;
+
+/// The dashboard on the terminal the process runs in, in raw mode on the
+/// alternate screen until dropped.
+///
+/// ```ignore
+/// let mut dashboard = TerminalDashboard::new()?;
+/// while dashboard.handle_input(&mut monitor, Duration::from_millis(250))? {
+///     monitor.observe_bus(&bus);
+///     dashboard.draw(&monitor)?;
+/// }
+/// ```
+pub struct TerminalDashboard<B: Backend = CrosstermBackend<Stdout>> {
+    terminal: Terminal<B>,
+    raw: bool,
+}
+
+impl TerminalDashboard {
+    pub fn new() -> io::Result<Self> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        if let Err(e) = execute!(stdout, EnterAlternateScreen) {
+            let _ = disable_raw_mode();
+            return Err(e);
+        }
+        Ok(Self {
+            terminal: Terminal::new(CrosstermBackend::new(stdout))?,
+            raw: true,
+        })
+    }
+}
+
+impl<B: Backend> TerminalDashboard<B> {
+    /// A dashboard drawing to `backend`, which is left as it is, e.g. a
+    /// `TestBackend`.
+    pub fn with_backend(backend: B) -> io::Result<Self> {
+        Ok(Self {
+            terminal: Terminal::new(backend)?,
+            raw: false,
+        })
+    }
+
+    pub fn draw(&mut self, monitor: &SessionMonitor) -> io::Result<()> {
+        self.terminal.draw(|frame| render(frame, monitor))?;
+        Ok(())
+    }
+
+    /// Wait up to `timeout` for a key and apply it, see [`handle_event`].
+    /// Returns `false` once the user asked to quit.
+    pub fn handle_input(
+        &mut self,
+        monitor: &mut SessionMonitor,
+        timeout: Duration,
+    ) -> io::Result<bool> {
+        if !event::poll(timeout)? {
+            return Ok(true);
+        }
+        Ok(handle_event(monitor, &event::read()?))
+    }
+
+    pub fn backend(&self) -> &B {
+        self.terminal.backend()
+    }
+}
+
+impl<B: Backend> Drop for TerminalDashboard<B> {
+    fn drop(&mut self) {
+        if self.raw {
+            let _ = disable_raw_mode();
+            let _ = execute!(io::stdout(), LeaveAlternateScreen);
+            let _ = self.terminal.show_cursor();
+        }
+    }
+}
+
+/// Apply a terminal event: `q` or Esc quits, which returns `false`; other
+/// keys go to [`SessionMonitor::handle_key`].
+pub fn handle_event(monitor: &mut SessionMonitor, event: &Event) -> bool {
+    let Event::Key(key) = event else {
+        return true;
+    };
+    if key.kind != KeyEventKind::Press {
+        return true;
+    }
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => false,
+        KeyCode::Char(c) => {
+            monitor.handle_key(c);
+            true
+        }
+        _ => true,
+    }
+}
+
+/// Draw `monitor` over the whole of `frame`.
+pub fn render(frame: &mut Frame, monitor: &SessionMonitor) {
+    let [status, sensors, queues, last_event, help] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(3),
+        Constraint::Length(3),
+        Constraint::Length(4),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let state = if monitor.paused {
+        "PAUSED"
+    } else {
+        "recording"
+    };
+    let disk = monitor.disk_usage.map_or_else(|| "-".into(), format_bytes);
+    let mut text = format!(
+        "{state} for {:.1} s, {} marker(s), disk {disk}",
+        monitor.started.elapsed().as_secs_f64(),
+        monitor.markers.len()
+    );
+    if let Some(marker) = monitor.markers.last() {
+        text += &format!(
+            ", last: {} at {:.1} s",
+            marker.label,
+            marker.at.as_secs_f64()
+        );
+    }
+    frame.render_widget(
+        Paragraph::new(text).block(Block::default().borders(Borders::ALL).title("session")),
+        status,
+    );
+
+    let rows = monitor.sensors.iter().map(|(kind, stats)| {
+        let last = stats
+            .last_seen
+            .map_or_else(|| "-".into(), |t| t.elapsed().as_millis().to_string());
+        Row::new([
+            format!("{kind:?}"),
+            format!("{:.1}", monitor.rate(*kind)),
+            stats.total.to_string(),
+            last,
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Min(18),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(10),
+        ],
+    )
+    .header(
+        Row::new(["sensor", "rate [Hz]", "frames", "last [ms]"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::ALL).title("sensors"));
+    frame.render_widget(table, sensors);
+
+    let depths = if monitor.queue_depths.is_empty() {
+        "-".to_string()
+    } else {
+        format!("{:?}", monitor.queue_depths)
+    };
+    frame.render_widget(
+        Paragraph::new(depths).block(Block::default().borders(Borders::ALL).title("queues")),
+        queues,
+    );
+
+    let event = match &monitor.last_event {
+        Some((t, text)) => format!("{:.1} s ago: {text}", t.elapsed().as_secs_f64()),
+        None => "-".into(),
+    };
+    frame.render_widget(
+        Paragraph::new(event)
+            .wrap(Wrap { trim: true })
+            .block(Block::default().borders(Borders::ALL).title("last event")),
+        last_event,
+    );
+
+    frame.render_widget(Line::from("p pause/resume   m marker   q quit"), help);
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use crate::{SensorDataSerDe, SensorKind};
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::{KeyEvent, KeyModifiers};
+
+    fn key(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn keys_pause_mark_and_quit() {
+        let mut monitor = SessionMonitor::new();
+        assert!(handle_event(&mut monitor, &key('p')));
+        assert!(monitor.is_paused());
+        assert!(handle_event(&mut monitor, &key('m')));
+        assert_eq!(monitor.markers().len(), 1);
+        assert!(!handle_event(&mut monitor, &key('q')));
+        let esc = Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert!(!handle_event(&mut monitor, &esc));
+    }
+
+    #[test]
+    fn renders_sensors_and_disk_usage() {
+        let mut monitor = SessionMonitor::new();
+        monitor.record(&SensorDataSerDe::Imu(crate::fixtures::imu_frame()));
+        monitor.set_disk_usage(3 << 20);
+        monitor.set_paused(true);
+        let mut dashboard = TerminalDashboard::with_backend(TestBackend::new(80, 20)).unwrap();
+        dashboard.draw(&monitor).unwrap();
+        let screen: String = dashboard
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("PAUSED"), "{screen}");
+        assert!(screen.contains("disk 3.0 MiB"), "{screen}");
+        assert!(
+            screen.contains(&format!("{:?}", SensorKind::Imu)),
+            "{screen}"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Field naming profiles for consumers with fixed JSON conventions.
+//!
+//! The `*SerDe` types serialize their fields in snake_case. Wrapping a
+//! value with [`FieldNaming::wrap`] serializes it with its field names
+//! renamed instead, e.g. camelCase for web frontends or SCREAMING_SNAKE_CASE
+//! for tools that expect it:
+//!
+//! ```ignore
+//! let json = serde_json::to_string(&FieldNaming::CamelCase.wrap(&frame))?;
+//! ```
+//!
+//! The wrapper sits between the value and any serializer, so it works with
+//! every serde format. Only struct field names are renamed; map keys
+//! (annotation keys, for instance) and enum variant names are data and are
+//! written as is. Deserialization always expects snake_case.
+
+use crate::proxy::{Proxy, Rewrite};
+use serde::ser::{Serialize, Serializer};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FieldNaming {
+    /// The field names as declared, `fov_angle`.
+    #[default]
+    SnakeCase,
+    /// `fovAngle`
+    CamelCase,
+    /// `FOV_ANGLE`
+    ScreamingSnakeCase,
+}
+
+impl FieldNaming {
+    pub fn wrap<T: Serialize + ?Sized>(self, value: &T) -> Named<'_, T> {
+        Named {
+            value,
+            naming: self,
+        }
+    }
+
+    /// `name` in this profile; `name` is expected in snake_case.
+    pub fn rename(self, name: &str) -> String {
+        match self {
+            Self::SnakeCase => name.to_owned(),
+            Self::CamelCase => {
+                let mut out = String::with_capacity(name.len());
+                let mut upper = false;
+                for c in name.chars() {
+                    if c == '_' && !out.is_empty() {
+                        upper = true;
+                    } else if upper {
+                        out.extend(c.to_uppercase());
+                        upper = false;
+                    } else {
+                        out.push(c);
+                    }
+                }
+                out
+            }
+            Self::ScreamingSnakeCase => name.to_uppercase(),
+        }
+    }
+
+    // Serializers want `&'static str` field names. The set of field names
+    // is fixed at compile time, so interning the renamed ones is bounded:
+    // each is leaked once, process-wide. Every thread looks them up in a
+    // cache of its own first, so concurrent encoders do not share a lock.
+    fn rename_static(self, name: &'static str) -> &'static str {
+        type Names = HashMap<(FieldNaming, &'static str), &'static str>;
+        static INTERNED: OnceLock<Mutex<Names>> = OnceLock::new();
+        thread_local! {
+            static CACHED: RefCell<Names> = RefCell::default();
+        }
+        if self == Self::SnakeCase {
+            return name;
+        }
+        CACHED.with_borrow_mut(|cached| {
+            *cached.entry((self, name)).or_insert_with(|| {
+                let mut interned = INTERNED
+                    .get_or_init(Default::default)
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                interned
+                    .entry((self, name))
+                    .or_insert_with(|| Box::leak(self.rename(name).into_boxed_str()))
+            })
+        })
+    }
+}
+
+/// A value serialized with the field names of a [`FieldNaming`] profile.
+#[derive(Clone, Copy, Debug)]
+pub struct Named<'a, T: ?Sized> {
+    value: &'a T,
+    naming: FieldNaming,
+}
+
+impl<T: Serialize + ?Sized> Serialize for Named<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(Proxy::new(serializer, self.naming))
+    }
+}
+
+impl Rewrite for FieldNaming {
+    fn field(self, key: &'static str) -> &'static str {
+        self.rename_static(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    enum Event {
+        LaneChange { lane_id: i32 },
+    }
+
+    #[derive(Serialize)]
+    struct Frame {
+        fov_angle: f32,
+        noise_model: Option<Noise>,
+        events: Vec<Event>,
+        annotations: BTreeMap<&'static str, u8>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        raw_data: Option<u8>,
+    }
+
+    #[derive(Serialize)]
+    struct Noise {
+        stddev_x: f32,
+    }
+
+    fn frame() -> Frame {
+        Frame {
+            fov_angle: 90.0,
+            noise_model: Some(Noise { stddev_x: 0.5 }),
+            events: vec![Event::LaneChange { lane_id: -1 }],
+            annotations: BTreeMap::from([("weather_bucket", 3)]),
+            raw_data: None,
+        }
+    }
+
+    #[test]
+    fn renames_field_names() {
+        assert_eq!(FieldNaming::CamelCase.rename("fov_angle"), "fovAngle");
+        assert_eq!(FieldNaming::CamelCase.rename("x"), "x");
+        assert_eq!(
+            FieldNaming::CamelCase.rename("_private_field"),
+            "_privateField"
+        );
+        assert_eq!(
+            FieldNaming::ScreamingSnakeCase.rename("fov_angle"),
+            "FOV_ANGLE"
+        );
+        assert_eq!(FieldNaming::SnakeCase.rename("fov_angle"), "fov_angle");
+    }
+
+    #[test]
+    fn camel_case_renames_nested_fields_only() {
+        let json = serde_json::to_string(&FieldNaming::CamelCase.wrap(&frame())).unwrap();
+        assert_eq!(
+            json,
+            r#"{"fovAngle":90.0,"noiseModel":{"stddevX":0.5},"events":[{"LaneChange":{"laneId":-1}}],"annotations":{"weather_bucket":3}}"#
+        );
+    }
+
+    #[test]
+    fn screaming_snake_case_renames_nested_fields_only() {
+        let naming = FieldNaming::ScreamingSnakeCase;
+        let json = serde_json::to_string(&naming.wrap(&frame())).unwrap();
+        assert_eq!(
+            json,
+            r#"{"FOV_ANGLE":90.0,"NOISE_MODEL":{"STDDEV_X":0.5},"EVENTS":[{"LaneChange":{"LANE_ID":-1}}],"ANNOTATIONS":{"weather_bucket":3}}"#
+        );
+    }
+
+    #[test]
+    fn renamed_names_are_interned_once_across_threads() {
+        let names: Vec<usize> = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    FieldNaming::CamelCase.rename_static("lane_id").as_ptr() as usize
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .collect();
+        assert!(names.windows(2).all(|w| w[0] == w[1]));
+        let name = "lane_id";
+        assert_eq!(
+            FieldNaming::SnakeCase.rename_static(name).as_ptr(),
+            name.as_ptr()
+        );
+    }
+}
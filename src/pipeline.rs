@@ -0,0 +1,195 @@
+//! Shared frames, sinks and in-place transforms.
+//!
+//! Frames travel as [`SharedFrame`]s so any number of consumers can hold
+//! the same payload. Transforms go through [`FrameTransform::apply_shared`],
+//! which only deep-copies a frame when another consumer still holds it.
+
+use crate::augment::{ImageAugmenter, ImuAugmenter};
+use crate::{
+    AnnotationValueSerDe, ImageEventSerDe, ProcessingStepSerDe, SensorDataSerDe, Vector3DSerDe,
+};
+use std::sync::Arc;
+
+pub type SharedFrame = Arc<SensorDataSerDe>;
+
+/// A consumer of frames, e.g. a file writer or a network publisher.
+pub trait Sink {
+    fn consume(&mut self, frame: SharedFrame);
+
+    /// Called once after the last frame.
+    fn flush(&mut self) {}
+}
+
+impl<F: FnMut(SharedFrame)> Sink for F {
+    fn consume(&mut self, frame: SharedFrame) {
+        self(frame)
+    }
+}
+
+/// A stage that modifies frames in place.
+pub trait FrameTransform {
+    /// Whether the transform would touch this frame. Frames it rejects are
+    /// passed through without being copied.
+    fn accepts(&self, _frame: &SensorDataSerDe) -> bool {
+        true
+    }
+
+    fn apply(&mut self, frame: &mut SensorDataSerDe);
+
+    /// The entry a recording's processing history gets for this stage.
+    /// Defaults to the type name without parameters.
+    fn describe(&self) -> ProcessingStepSerDe {
+        let name = std::any::type_name::<Self>();
+        let name = name.split('<').next().unwrap_or(name);
+        ProcessingStepSerDe::new(name.rsplit("::").next().unwrap_or(name))
+    }
+
+    /// Copy-on-write variant: clones the payload only if it is shared.
+    fn apply_shared(&mut self, frame: &mut SharedFrame) {
+        if self.accepts(frame) {
+            self.apply(Arc::make_mut(frame));
+        }
+    }
+}
+
+impl FrameTransform for ImageAugmenter {
+    fn accepts(&self, frame: &SensorDataSerDe) -> bool {
+        matches!(frame, SensorDataSerDe::Image(_))
+    }
+
+    fn apply(&mut self, frame: &mut SensorDataSerDe) {
+        if let SensorDataSerDe::Image(image) = frame {
+            ImageAugmenter::apply(self, image);
+        }
+    }
+
+    /// The seed and every stage, `stage.0 = Vignette` with its fields as
+    /// `stage.0.strength`.
+    fn describe(&self) -> ProcessingStepSerDe {
+        let mut step = ProcessingStepSerDe::new("image_augment")
+            .with_param("seed", self.seed() as i64)
+            .with_param("stages", self.stages().len() as i64);
+        for (i, stage) in self.stages().iter().enumerate() {
+            let (name, fields) = stage.params();
+            step = step.with_param(format!("stage.{i}"), name);
+            for (field, value) in fields {
+                step = step.with_param(format!("stage.{i}.{field}"), value);
+            }
+        }
+        step
+    }
+}
+
+impl FrameTransform for ImuAugmenter {
+    fn accepts(&self, frame: &SensorDataSerDe) -> bool {
+        matches!(frame, SensorDataSerDe::Imu(_))
+    }
+
+    fn apply(&mut self, frame: &mut SensorDataSerDe) {
+        if let SensorDataSerDe::Imu(imu) = frame {
+            ImuAugmenter::apply(self, imu);
+        }
+    }
+
+    fn describe(&self) -> ProcessingStepSerDe {
+        let m = self.model();
+        let xyz = |v: Vector3DSerDe| vec![v.x, v.y, v.z];
+        ProcessingStepSerDe::new("imu_augment")
+            .with_param("accelerometer_stddev", xyz(m.accelerometer_stddev))
+            .with_param("accelerometer_bias", xyz(m.accelerometer_bias))
+            .with_param("gyroscope_stddev", xyz(m.gyroscope_stddev))
+            .with_param("gyroscope_bias", xyz(m.gyroscope_bias))
+            .with_param("compass_stddev", m.compass_stddev)
+            .with_param("seed", m.seed as i64)
+    }
+}
+
+/// Runs a model, or any function, on camera frames and stores what it
+/// returns as the annotation `key`, e.g. for online auto-labeling.
+///
+/// The model sees the frame after earlier transforms in the chain; frames
+/// it returns `None` for are left unannotated.
+pub struct ImageAnnotator<F> {
+    key: String,
+    model: F,
+}
+
+impl<F> ImageAnnotator<F>
+where
+    F: FnMut(&ImageEventSerDe) -> Option<AnnotationValueSerDe>,
+{
+    pub fn new(key: impl Into<String>, model: F) -> Self {
+        Self {
+            key: key.into(),
+            model,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl<F> FrameTransform for ImageAnnotator<F>
+where
+    F: FnMut(&ImageEventSerDe) -> Option<AnnotationValueSerDe>,
+{
+    fn accepts(&self, frame: &SensorDataSerDe) -> bool {
+        matches!(frame, SensorDataSerDe::Image(_))
+    }
+
+    fn apply(&mut self, frame: &mut SensorDataSerDe) {
+        if let SensorDataSerDe::Image(image) = frame
+            && let Some(value) = (self.model)(image)
+        {
+            image.annotations.insert(self.key.clone(), value);
+        }
+    }
+
+    fn describe(&self) -> ProcessingStepSerDe {
+        ProcessingStepSerDe::new("image_annotate").with_param("key", self.key.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ImageAugmentationStageSerDe, ImuNoiseModelSerDe};
+
+    #[test]
+    fn augmenters_describe_every_parameter() {
+        let image = ImageAugmenter::new(7)
+            .then(ImageAugmentationStageSerDe::Vignette { strength: 0.5 })
+            .then(ImageAugmentationStageSerDe::WhiteBalance {
+                r: 1.0,
+                g: 0.5,
+                b: 2.0,
+            });
+        let step = FrameTransform::describe(&image);
+        assert_eq!(step.params.get("seed"), Some(&AnnotationValueSerDe::Int(7)));
+        assert_eq!(step.params.get("stage.0"), Some(&"Vignette".into()));
+        assert_eq!(step.params.get("stage.0.strength"), Some(&0.5f32.into()));
+        assert_eq!(step.params.get("stage.1.b"), Some(&2.0f32.into()));
+
+        let model = ImuNoiseModelSerDe {
+            accelerometer_bias: Vector3DSerDe {
+                x: 0.25,
+                y: 0.0,
+                z: -1.0,
+            },
+            compass_stddev: 0.5,
+            seed: 42,
+            ..Default::default()
+        };
+        let step = FrameTransform::describe(&ImuAugmenter::new(model));
+        assert_eq!(
+            step.params.get("accelerometer_bias"),
+            Some(&vec![0.25, 0.0, -1.0].into())
+        );
+        assert_eq!(step.params.get("compass_stddev"), Some(&0.5f32.into()));
+        assert_eq!(
+            step.params.get("seed"),
+            Some(&AnnotationValueSerDe::Int(42))
+        );
+    }
+}
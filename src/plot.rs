@@ -0,0 +1,262 @@
+//! Line charts of time series and maps of GNSS traces as standalone SVG.
+//!
+//! [`Chart`] renders any `(time, value)` series; [`ImuCharts`] builds the
+//! usual accelerometer and gyroscope charts straight from a stream of
+//! frames. [`GnssMap`] draws GNSS traces to scale in the Web Mercator
+//! projection of OSM slippy tiles. The HTML report embeds the same SVG.
+//! With the `plot` feature, [`Chart::draw`] and [`GnssMap::draw`] draw them
+//! with plotters instead, e.g. into a PNG, the map over slippy tiles from a
+//! [`TileSource`].
+
+use crate::{ImuMeasurementSerDe, SensorDataSerDe};
+use std::fmt::{self, Write};
+
+mod map;
+#[cfg(feature = "plot")]
+mod png;
+#[cfg(feature = "plot")]
+mod tiles;
+
+pub use map::*;
+#[cfg(feature = "plot")]
+pub use png::*;
+#[cfg(feature = "plot")]
+pub use tiles::*;
+
+const PALETTE: [&str; 6] = [
+    "#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b",
+];
+const MARGIN_LEFT: f64 = 56.0;
+const MARGIN_RIGHT: f64 = 12.0;
+const MARGIN_TOP: f64 = 28.0;
+const MARGIN_BOTTOM: f64 = 32.0;
+
+#[derive(Clone, Debug, Default)]
+pub struct Series {
+    pub label: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+impl Series {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            points: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, t: f64, value: f64) {
+        self.points.push((t, value));
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Chart {
+    pub title: String,
+    pub y_label: String,
+    pub width: u32,
+    pub height: u32,
+    pub series: Vec<Series>,
+}
+
+impl Chart {
+    /// An empty 640×200 chart.
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            y_label: String::new(),
+            width: 640,
+            height: 200,
+            series: Vec::new(),
+        }
+    }
+
+    pub fn with_y_label(mut self, label: impl Into<String>) -> Self {
+        self.y_label = label.into();
+        self
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_series(mut self, series: Series) -> Self {
+        self.series.push(series);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.series.iter().all(|s| s.points.is_empty())
+    }
+
+    pub fn write_svg(&self, out: &mut impl Write) -> fmt::Result {
+        let (w, h) = (self.width as f64, self.height as f64);
+        let (pw, ph) = (
+            (w - MARGIN_LEFT - MARGIN_RIGHT).max(1.0),
+            (h - MARGIN_TOP - MARGIN_BOTTOM).max(1.0),
+        );
+        let ((t0, t1), (v0, v1)) = self.bounds();
+        let x = |t: f64| MARGIN_LEFT + (t - t0) / (t1 - t0) * pw;
+        let y = |v: f64| MARGIN_TOP + ph - (v - v0) / (v1 - v0) * ph;
+
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" font-size=\"11\">"
+        )?;
+        writeln!(
+            out,
+            "<rect x=\"{MARGIN_LEFT}\" y=\"{MARGIN_TOP}\" width=\"{pw}\" height=\"{ph}\" \
+             fill=\"#fafafa\" stroke=\"#ccc\"/>"
+        )?;
+        writeln!(
+            out,
+            "<text x=\"{MARGIN_LEFT}\" y=\"16\" font-size=\"13\">{}</text>",
+            Escaped(&self.title)
+        )?;
+
+        // min/mid/max ticks on both axes
+        for i in 0..3 {
+            let f = i as f64 / 2.0;
+            let (t, v) = (t0 + f * (t1 - t0), v0 + f * (v1 - v0));
+            writeln!(
+                out,
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{t:.2}</text>",
+                x(t),
+                h - MARGIN_BOTTOM + 14.0
+            )?;
+            writeln!(
+                out,
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{v:.3}</text>",
+                MARGIN_LEFT - 4.0,
+                y(v) + 4.0
+            )?;
+        }
+        writeln!(
+            out,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">t [s]</text>",
+            MARGIN_LEFT + pw / 2.0,
+            h - 4.0
+        )?;
+        if !self.y_label.is_empty() {
+            writeln!(
+                out,
+                "<text x=\"12\" y=\"{:.1}\" text-anchor=\"middle\" \
+                 transform=\"rotate(-90 12 {:.1})\">{}</text>",
+                MARGIN_TOP + ph / 2.0,
+                MARGIN_TOP + ph / 2.0,
+                Escaped(&self.y_label)
+            )?;
+        }
+
+        for (i, series) in self.series.iter().enumerate() {
+            let color = PALETTE[i % PALETTE.len()];
+            write!(
+                out,
+                "<polyline fill=\"none\" stroke=\"{color}\" stroke-width=\"1.2\" points=\""
+            )?;
+            for &(t, v) in &series.points {
+                write!(out, "{:.1},{:.1} ", x(t), y(v))?;
+            }
+            writeln!(out, "\"/>")?;
+            writeln!(
+                out,
+                "<text x=\"{:.1}\" y=\"16\" fill=\"{color}\" text-anchor=\"end\">{}</text>",
+                w - MARGIN_RIGHT - 70.0 * (self.series.len() - 1 - i) as f64,
+                Escaped(&series.label)
+            )?;
+        }
+        writeln!(out, "</svg>")
+    }
+
+    // never degenerate, so the scale functions can divide by the span
+    fn bounds(&self) -> ((f64, f64), (f64, f64)) {
+        let points = || self.series.iter().flat_map(|s| s.points.iter());
+        let (t0, t1) = points().fold((f64::MAX, f64::MIN), |(lo, hi), &(t, _)| {
+            (lo.min(t), hi.max(t))
+        });
+        let (v0, v1) = points().fold((f64::MAX, f64::MIN), |(lo, hi), &(_, v)| {
+            (lo.min(v), hi.max(v))
+        });
+        let widen = |lo: f64, hi: f64| {
+            if lo > hi {
+                (0.0, 1.0)
+            } else if hi - lo < 1e-9 {
+                (lo - 0.5, hi + 0.5)
+            } else {
+                (lo, hi)
+            }
+        };
+        (widen(t0, t1), widen(v0, v1))
+    }
+}
+
+/// Per-axis accelerometer and gyroscope charts of an IMU stream.
+#[derive(Clone, Debug)]
+pub struct ImuCharts {
+    pub accelerometer: Chart,
+    pub gyroscope: Chart,
+}
+
+impl Default for ImuCharts {
+    fn default() -> Self {
+        let axes = || vec![Series::new("x"), Series::new("y"), Series::new("z")];
+        Self {
+            accelerometer: Chart {
+                series: axes(),
+                ..Chart::new("Accelerometer").with_y_label("m/s²")
+            },
+            gyroscope: Chart {
+                series: axes(),
+                ..Chart::new("Gyroscope").with_y_label("rad/s")
+            },
+        }
+    }
+}
+
+impl ImuCharts {
+    /// Collect the IMU frames of a timestamped stream; other frames are
+    /// skipped.
+    pub fn from_frames<'a>(frames: impl IntoIterator<Item = (f64, &'a SensorDataSerDe)>) -> Self {
+        let mut charts = Self::default();
+        for (t, frame) in frames {
+            if let SensorDataSerDe::Imu(imu) = frame {
+                charts.push(t, imu);
+            }
+        }
+        charts
+    }
+
+    pub fn push(&mut self, t: f64, imu: &ImuMeasurementSerDe) {
+        let (a, g) = (imu.accelerometer, imu.gyroscope);
+        for (s, v) in self.accelerometer.series.iter_mut().zip([a.x, a.y, a.z]) {
+            s.push(t, v as f64);
+        }
+        for (s, v) in self.gyroscope.series.iter_mut().zip([g.x, g.y, g.z]) {
+            s.push(t, v as f64);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accelerometer.is_empty()
+    }
+}
+
+pub(crate) struct Escaped<'a>(pub(crate) &'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '&' => f.write_str("&amp;")?,
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '"' => f.write_str("&quot;")?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
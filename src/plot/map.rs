@@ -0,0 +1,252 @@
+//! GNSS traces on a Web Mercator map, the projection of OSM slippy tiles.
+
+use super::{Escaped, PALETTE};
+use crate::{GnssMeasurementSerDe, SensorDataSerDe};
+use std::f64::consts::PI;
+use std::fmt::{self, Write};
+
+/// Side of a slippy map tile in pixels.
+pub const TILE_SIZE: u32 = 256;
+
+/// Deepest zoom level OSM serves tiles for.
+pub const MAX_ZOOM: u8 = 19;
+
+// Web Mercator ends here, the map is square
+const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+// pixels kept free around the traces
+const PADDING: f64 = 16.0;
+
+/// `latitude` and `longitude`, in degrees, as a position in tiles at
+/// `zoom`; the integer parts name the tile as in `{zoom}/{x}/{y}.png`.
+pub fn tile_position(latitude: f64, longitude: f64, zoom: u8) -> (f64, f64) {
+    let n = f64::from(1u32 << zoom);
+    let lat = latitude.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let x = (longitude + 180.0) / 360.0 * n;
+    let y = (1.0 - lat.tan().asinh() / PI) / 2.0 * n;
+    (x, y)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Trace {
+    pub label: String,
+    /// `(latitude, longitude)` in degrees.
+    pub points: Vec<(f64, f64)>,
+}
+
+impl Trace {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            points: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, gnss: &GnssMeasurementSerDe) {
+        self.points.push((gnss.latitude, gnss.longitude));
+    }
+}
+
+/// GNSS traces drawn to scale, zoomed to the deepest slippy zoom level they
+/// fit in. [`write_svg`](Self::write_svg) draws them on a blank map; with
+/// the `plot` feature, [`draw`](Self::draw) draws them over map tiles.
+#[derive(Clone, Debug)]
+pub struct GnssMap {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub traces: Vec<Trace>,
+}
+
+impl GnssMap {
+    /// An empty 640×480 map.
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            width: 640,
+            height: 480,
+            traces: Vec::new(),
+        }
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_trace(mut self, trace: Trace) -> Self {
+        self.traces.push(trace);
+        self
+    }
+
+    /// One trace of the GNSS frames in a stream; other frames are skipped.
+    pub fn from_frames<'a>(frames: impl IntoIterator<Item = &'a SensorDataSerDe>) -> Self {
+        let mut trace = Trace::new("GNSS");
+        for frame in frames {
+            if let SensorDataSerDe::Gnss(gnss) = frame {
+                trace.push(gnss);
+            }
+        }
+        Self::new("GNSS trace").with_trace(trace)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.traces.iter().all(|t| t.points.is_empty())
+    }
+
+    /// The zoom level and the position of the map's top left corner, in
+    /// pixels of that zoom level.
+    pub fn view(&self) -> MapView {
+        let points = || self.traces.iter().flat_map(|t| t.points.iter());
+        let (mut x0, mut y0, mut x1, mut y1) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+        for &(lat, lon) in points() {
+            let (x, y) = tile_position(lat, lon, 0);
+            (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x), y1.max(y));
+        }
+        if x0 > x1 {
+            (x0, y0, x1, y1) = (0.0, 0.0, 1.0, 1.0);
+        }
+        let fit = |size: u32| (f64::from(size) - 2.0 * PADDING).max(1.0) / f64::from(TILE_SIZE);
+        let zoom = (0..=MAX_ZOOM)
+            .rev()
+            .find(|&z| {
+                let scale = f64::from(1u32 << z);
+                (x1 - x0) * scale <= fit(self.width) && (y1 - y0) * scale <= fit(self.height)
+            })
+            .unwrap_or(0);
+        let scale = f64::from(TILE_SIZE) * f64::from(1u32 << zoom);
+        MapView {
+            zoom,
+            left: (x0 + x1) / 2.0 * scale - f64::from(self.width) / 2.0,
+            top: (y0 + y1) / 2.0 * scale - f64::from(self.height) / 2.0,
+        }
+    }
+
+    pub fn write_svg(&self, out: &mut impl Write) -> fmt::Result {
+        let (w, h) = (self.width, self.height);
+        let view = self.view();
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" font-size=\"11\">"
+        )?;
+        writeln!(
+            out,
+            "<rect width=\"{w}\" height=\"{h}\" fill=\"#f2efe9\" stroke=\"#ccc\"/>"
+        )?;
+        for (i, trace) in self.traces.iter().enumerate() {
+            let color = PALETTE[i % PALETTE.len()];
+            write!(
+                out,
+                "<polyline fill=\"none\" stroke=\"{color}\" stroke-width=\"2\" points=\""
+            )?;
+            for &(lat, lon) in &trace.points {
+                let (x, y) = view.pixel(lat, lon);
+                write!(out, "{x:.1},{y:.1} ")?;
+            }
+            writeln!(out, "\"/>")?;
+            if let Some(&(lat, lon)) = trace.points.first() {
+                let (x, y) = view.pixel(lat, lon);
+                writeln!(
+                    out,
+                    "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"4\" fill=\"{color}\"/>"
+                )?;
+            }
+            writeln!(
+                out,
+                "<text x=\"8\" y=\"{}\" fill=\"{color}\">{}</text>",
+                32 + 14 * i,
+                Escaped(&trace.label)
+            )?;
+        }
+        writeln!(
+            out,
+            "<text x=\"8\" y=\"16\" font-size=\"13\">{}</text>",
+            Escaped(&self.title)
+        )?;
+        writeln!(out, "</svg>")
+    }
+}
+
+/// Where a [`GnssMap`] sits on the slippy map.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MapView {
+    pub zoom: u8,
+    /// The map's left and top edges, in pixels from the world's top left
+    /// corner at `zoom`.
+    pub left: f64,
+    pub top: f64,
+}
+
+impl MapView {
+    /// Where `latitude` and `longitude` fall on the map, in pixels.
+    pub fn pixel(&self, latitude: f64, longitude: f64) -> (f64, f64) {
+        let (x, y) = tile_position(latitude, longitude, self.zoom);
+        let size = f64::from(TILE_SIZE);
+        (x * size - self.left, y * size - self.top)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(points: &[(f64, f64)]) -> Trace {
+        Trace {
+            label: "ego".into(),
+            points: points.to_vec(),
+        }
+    }
+
+    #[test]
+    fn projects_onto_slippy_tiles() {
+        assert_eq!(tile_position(0.0, 0.0, 0), (0.5, 0.5));
+        assert_eq!(tile_position(MAX_LATITUDE, -180.0, 3).0, 0.0);
+        assert!(tile_position(MAX_LATITUDE, 0.0, 3).1.abs() < 1e-9);
+        // Karlsruhe palace, tile 17/68595/45005
+        let (x, y) = tile_position(49.0135, 8.4044, 17);
+        assert_eq!((x as u32, y as u32), (68595, 45005));
+    }
+
+    #[test]
+    fn zooms_in_until_the_traces_fill_the_map() {
+        let near = GnssMap::new("near").with_trace(trace(&[(49.0, 8.0), (49.0005, 8.001)]));
+        let far = GnssMap::new("far").with_trace(trace(&[(49.0, 8.0), (49.05, 8.1)]));
+        let (near_view, far_view) = (near.view(), far.view());
+        assert!(near_view.zoom > far_view.zoom);
+
+        for map in [&near, &far] {
+            let view = map.view();
+            for &(lat, lon) in &map.traces[0].points {
+                let (x, y) = view.pixel(lat, lon);
+                assert!((PADDING - 1.0..=map.width as f64 - PADDING + 1.0).contains(&x));
+                assert!((PADDING - 1.0..=map.height as f64 - PADDING + 1.0).contains(&y));
+            }
+            // one level deeper no longer fits
+            let deeper = MapView {
+                zoom: view.zoom + 1,
+                ..view
+            };
+            let (a, b) = (map.traces[0].points[0], map.traces[0].points[1]);
+            let (ax, ay) = deeper.pixel(a.0, a.1);
+            let (bx, by) = deeper.pixel(b.0, b.1);
+            assert!(
+                (ax - bx).abs() > map.width as f64 - 2.0 * PADDING
+                    || (ay - by).abs() > map.height as f64 - 2.0 * PADDING
+            );
+        }
+    }
+
+    #[test]
+    fn a_single_fix_is_centered_at_the_deepest_zoom() {
+        let map = GnssMap::new("fix").with_trace(trace(&[(49.000012, 8.000034)]));
+        let view = map.view();
+        assert_eq!(view.zoom, MAX_ZOOM);
+        let (x, y) = view.pixel(49.000012, 8.000034);
+        assert!((x - 320.0).abs() < 1e-6 && (y - 240.0).abs() < 1e-6);
+        let mut svg = String::new();
+        map.write_svg(&mut svg).unwrap();
+        assert!(svg.contains("<circle cx=\"320.0\" cy=\"240.0\""), "{svg}");
+    }
+}
@@ -0,0 +1,121 @@
+//! plotters rendering of [`Chart`] and [`ImuCharts`].
+
+use super::{Chart, ImuCharts, PALETTE};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::fmt;
+use std::path::Path;
+
+/// Drawing a chart failed, e.g. the file could not be written or no font
+/// was found for the labels.
+#[derive(Debug)]
+pub enum PlotError {
+    Draw(String),
+}
+
+impl fmt::Display for PlotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Draw(e) => write!(f, "cannot draw chart: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PlotError {}
+
+impl<E: std::error::Error + Send + Sync> From<DrawingAreaErrorKind<E>> for PlotError {
+    fn from(e: DrawingAreaErrorKind<E>) -> Self {
+        Self::Draw(e.to_string())
+    }
+}
+
+impl Chart {
+    /// Draw the chart over the whole of `area`, with the colors and scale
+    /// of [`write_svg`](Self::write_svg).
+    pub fn draw<DB: DrawingBackend>(
+        &self,
+        area: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        area.fill(&WHITE)?;
+        let ((t0, t1), (v0, v1)) = self.bounds();
+        let mut chart = ChartBuilder::on(area)
+            .caption(&self.title, ("sans-serif", 13))
+            .margin(8)
+            .x_label_area_size(28)
+            .y_label_area_size(56)
+            .build_cartesian_2d(t0..t1, v0..v1)?;
+        chart
+            .configure_mesh()
+            .x_labels(3)
+            .y_labels(3)
+            .x_desc("t [s]")
+            .y_desc(self.y_label.as_str())
+            .draw()?;
+        for (i, series) in self.series.iter().enumerate() {
+            let color = rgb(PALETTE[i % PALETTE.len()]);
+            chart
+                .draw_series(LineSeries::new(series.points.iter().copied(), color))?
+                .label(series.label.as_str())
+                .legend(move |(x, y)| PathElement::new([(x, y), (x + 16, y)], color));
+        }
+        if self.series.iter().any(|s| !s.label.is_empty()) {
+            chart
+                .configure_series_labels()
+                .background_style(WHITE.mix(0.8))
+                .border_style(BLACK)
+                .draw()?;
+        }
+        Ok(())
+    }
+
+    /// Write the chart as a PNG of its size.
+    pub fn write_png(&self, path: impl AsRef<Path>) -> Result<(), PlotError> {
+        let area = BitMapBackend::new(path.as_ref(), (self.width, self.height)).into_drawing_area();
+        self.draw(&area)?;
+        area.present()?;
+        Ok(())
+    }
+}
+
+impl ImuCharts {
+    /// Write both charts as one PNG, the accelerometer above the gyroscope.
+    pub fn write_png(&self, path: impl AsRef<Path>) -> Result<(), PlotError> {
+        let (a, g) = (&self.accelerometer, &self.gyroscope);
+        let size = (a.width.max(g.width), a.height + g.height);
+        let area = BitMapBackend::new(path.as_ref(), size).into_drawing_area();
+        let (top, bottom) = area.split_vertically(a.height);
+        a.draw(&top)?;
+        g.draw(&bottom)?;
+        area.present()?;
+        Ok(())
+    }
+}
+
+// `#rrggbb`, as the palette has them
+pub(super) fn rgb(hex: &str) -> RGBColor {
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+    RGBColor(channel(1), channel(3), channel(5))
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use crate::SensorDataSerDe;
+    use crate::fixtures::imu_frame;
+
+    #[test]
+    fn writes_imu_charts_as_png() {
+        let frame = SensorDataSerDe::Imu(imu_frame());
+        let charts = ImuCharts::from_frames((0..20).map(|i| (i as f64 * 0.05, &frame)));
+        let path = std::env::temp_dir().join(format!("imu-charts-{}.png", std::process::id()));
+        charts.write_png(&path).unwrap();
+        let png = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+    }
+
+    #[test]
+    fn palette_colors_parse() {
+        assert_eq!(rgb("#1f77b4"), RGBColor(0x1f, 0x77, 0xb4));
+    }
+}
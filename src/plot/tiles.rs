@@ -0,0 +1,237 @@
+//! plotters rendering of [`GnssMap`] over slippy map tiles.
+
+use super::{GnssMap, PALETTE, PlotError, TILE_SIZE, rgb};
+use plotters::coord::Shift;
+use plotters::element::BitMapElement;
+use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use std::path::{Path, PathBuf};
+
+/// The map tiles a [`GnssMap`] is drawn over.
+pub trait TileSource {
+    /// The RGB pixels of tile `x`, `y` at `zoom`, [`TILE_SIZE`] rows of
+    /// [`TILE_SIZE`] pixels, or `None` where there is no tile; the map is
+    /// left blank there.
+    fn tile(&mut self, zoom: u8, x: u32, y: u32) -> Option<Vec<u8>>;
+
+    /// Credit the tiles require, drawn in the bottom right corner, e.g.
+    /// `© OpenStreetMap contributors`.
+    fn attribution(&self) -> &str {
+        ""
+    }
+}
+
+/// No tiles, the traces on a blank map.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoTiles;
+
+impl TileSource for NoTiles {
+    fn tile(&mut self, _: u8, _: u32, _: u32) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// Tiles stored as `{root}/{zoom}/{x}/{y}.png`, the layout of OSM tile
+/// servers and of offline tile caches. Nothing is downloaded; tiles that
+/// are missing or do not decode are left blank.
+#[derive(Clone, Debug)]
+pub struct TileDirectory {
+    root: PathBuf,
+    attribution: String,
+}
+
+impl TileDirectory {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            attribution: String::new(),
+        }
+    }
+
+    pub fn with_attribution(mut self, attribution: impl Into<String>) -> Self {
+        self.attribution = attribution.into();
+        self
+    }
+}
+
+impl TileSource for TileDirectory {
+    fn tile(&mut self, zoom: u8, x: u32, y: u32) -> Option<Vec<u8>> {
+        let path = self.root.join(format!("{zoom}/{x}/{y}.png"));
+        let tile = image::open(path).ok()?.to_rgb8();
+        (tile.dimensions() == (TILE_SIZE, TILE_SIZE)).then(|| tile.into_raw())
+    }
+
+    fn attribution(&self) -> &str {
+        &self.attribution
+    }
+}
+
+impl GnssMap {
+    /// Draw the map over the whole of `area`, on the tiles of `tiles` and
+    /// at the scale of [`view`](Self::view).
+    pub fn draw<DB: DrawingBackend>(
+        &self,
+        area: &DrawingArea<DB, Shift>,
+        tiles: &mut dyn TileSource,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        area.fill(&RGBColor(0xf2, 0xef, 0xe9))?;
+        let view = self.view();
+        let (w, h) = (self.width as i64, self.height as i64);
+        let (left, top) = (view.left.floor() as i64, view.top.floor() as i64);
+        let size = TILE_SIZE as i64;
+        let count = 1i64 << view.zoom;
+        for ty in top.div_euclid(size).max(0)..=((top + h - 1).div_euclid(size)).min(count - 1) {
+            for tx in left.div_euclid(size)..=(left + w - 1).div_euclid(size) {
+                // the map wraps around at the antimeridian
+                let Some(pixels) = tiles.tile(view.zoom, tx.rem_euclid(count) as u32, ty as u32)
+                else {
+                    continue;
+                };
+                if pixels.len() != (size * size * 3) as usize {
+                    continue;
+                }
+                let (x, y) = (tx * size - left, ty * size - top);
+                // crop to the map, plotters only clips bitmaps at the top left
+                let (x0, y0) = (x.max(0), y.max(0));
+                let (x1, y1) = ((x + size).min(w), (y + size).min(h));
+                let mut visible = Vec::with_capacity(((x1 - x0) * (y1 - y0) * 3) as usize);
+                for row in (y0 - y)..(y1 - y) {
+                    let start = ((row * size + x0 - x) * 3) as usize;
+                    visible.extend_from_slice(&pixels[start..start + ((x1 - x0) * 3) as usize]);
+                }
+                let dims = ((x1 - x0) as u32, (y1 - y0) as u32);
+                if let Some(bitmap) = BitMapElement::<(i32, i32)>::with_owned_buffer(
+                    (x0 as i32, y0 as i32),
+                    dims,
+                    visible,
+                ) {
+                    area.draw(&bitmap)?;
+                }
+            }
+        }
+
+        let pixel = |&(lat, lon): &(f64, f64)| {
+            let (x, y) = view.pixel(lat, lon);
+            (x.round() as i32, y.round() as i32)
+        };
+        for (i, trace) in self.traces.iter().enumerate() {
+            let color = rgb(PALETTE[i % PALETTE.len()]);
+            let points: Vec<_> = trace.points.iter().map(pixel).collect();
+            area.draw(&PathElement::new(points.clone(), color.stroke_width(2)))?;
+            if let Some(&start) = points.first() {
+                area.draw(&Circle::new(start, 4, color.filled()))?;
+            }
+            area.draw(&Text::new(
+                trace.label.as_str(),
+                (8, 24 + 14 * i as i32),
+                ("sans-serif", 11).into_font().color(&color),
+            ))?;
+        }
+        area.draw(&Text::new(
+            self.title.as_str(),
+            (8, 6),
+            ("sans-serif", 13).into_font(),
+        ))?;
+        let attribution = tiles.attribution();
+        if !attribution.is_empty() {
+            let style = TextStyle::from(("sans-serif", 10).into_font())
+                .pos(Pos::new(HPos::Right, VPos::Bottom));
+            area.draw(&Text::new(
+                attribution,
+                (self.width as i32 - 4, self.height as i32 - 4),
+                style,
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Write the map as a PNG of its size.
+    pub fn write_png(
+        &self,
+        path: impl AsRef<Path>,
+        tiles: &mut dyn TileSource,
+    ) -> Result<(), PlotError> {
+        let area = BitMapBackend::new(path.as_ref(), (self.width, self.height)).into_drawing_area();
+        self.draw(&area, tiles)?;
+        area.present()?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use crate::SensorDataSerDe;
+
+    // every tile a different flat color, so the blits can be told apart
+    struct Checkers(Vec<(u8, u32, u32)>);
+
+    impl TileSource for Checkers {
+        fn tile(&mut self, zoom: u8, x: u32, y: u32) -> Option<Vec<u8>> {
+            self.0.push((zoom, x, y));
+            let shade = ((x + y) % 2 * 120) as u8;
+            Some([shade, 200, 255 - shade].repeat((TILE_SIZE * TILE_SIZE) as usize))
+        }
+    }
+
+    fn trace() -> GnssMap {
+        let mut frame = crate::fixtures::gnss_frame();
+        let frames: Vec<_> = (0..20)
+            .map(|i| {
+                frame.latitude += 1e-4 * i as f64;
+                frame.longitude += 2e-4;
+                SensorDataSerDe::Gnss(frame.clone())
+            })
+            .collect();
+        GnssMap::from_frames(&frames)
+    }
+
+    #[test]
+    fn draws_the_tiles_under_the_view() {
+        let map = trace();
+        let view = map.view();
+        let mut tiles = Checkers(Vec::new());
+        let mut pixels = vec![0; (map.width * map.height * 3) as usize];
+        {
+            let area = BitMapBackend::with_buffer(&mut pixels, (map.width, map.height))
+                .into_drawing_area();
+            map.draw(&area, &mut tiles).unwrap();
+            area.present().unwrap();
+        }
+        assert!(tiles.0.iter().all(|&(zoom, ..)| zoom == view.zoom));
+        let columns =
+            ((view.left + map.width as f64) / 256.0).floor() - (view.left / 256.0).floor();
+        let rows = ((view.top + map.height as f64) / 256.0).floor() - (view.top / 256.0).floor();
+        assert_eq!(tiles.0.len(), ((columns + 1.0) * (rows + 1.0)) as usize);
+
+        // the bottom right corner shows its tile, not a shifted neighbour
+        let (x, y) = (map.width - 1, map.height - 1);
+        let (tx, ty) = (
+            ((view.left + x as f64) / 256.0).floor() as u32,
+            ((view.top + y as f64) / 256.0).floor() as u32,
+        );
+        let at = ((y * map.width + x) * 3) as usize;
+        assert_eq!(pixels[at], ((tx + ty) % 2 * 120) as u8);
+    }
+
+    #[test]
+    fn writes_maps_as_png() {
+        let path = std::env::temp_dir().join(format!("gnss-map-{}.png", std::process::id()));
+        trace().write_png(&path, &mut NoTiles).unwrap();
+        let png = std::fs::read(&path).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+
+        // a stored tile reads back
+        let root = std::env::temp_dir().join(format!("gnss-tiles-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("0/0")).unwrap();
+        let tile = image::RgbImage::from_pixel(TILE_SIZE, TILE_SIZE, image::Rgb([10, 20, 30]));
+        tile.save(root.join("0/0/0.png")).unwrap();
+        let mut tiles = TileDirectory::new(&root);
+        assert_eq!(tiles.tile(0, 0, 0).unwrap()[..3], [10, 20, 30]);
+        assert_eq!(tiles.tile(1, 0, 0), None);
+        trace().write_png(&path, &mut tiles).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,331 @@
+use crate::RecordingHeaderSerDe;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+/// Relative tolerance for numbers, so mount poses that went through a
+/// float round trip do not show up as drift.
+const TOLERANCE: f64 = 1e-6;
+
+/// One field that differs between two headers; `None` where the field is
+/// missing on that side.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HeaderDifference {
+    /// Dotted path, with sensors addressed by role name and type, vehicles
+    /// by id and spawned actors by blueprint and role name, e.g.
+    /// `sensors.front (sensor.camera.rgb).camera.fov` or
+    /// `spawned.vehicle.tesla.model3 (hero).attributes.color.value`.
+    pub path: String,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+}
+
+/// What changed between two recordings' setup, see [`diff_headers`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct HeaderDiff {
+    pub differences: Vec<HeaderDifference>,
+}
+
+impl HeaderDiff {
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl fmt::Display for HeaderDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |v: &Option<Value>| v.as_ref().map_or("(missing)".into(), Value::to_string);
+        for d in &self.differences {
+            writeln!(f, "{}: {} -> {}", d.path, show(&d.left), show(&d.right))?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the sensor rig, run metadata and provenance of two recordings.
+///
+/// Processing history and actor ids are ignored: they are expected to
+/// differ between runs and between a raw recording and its derivatives.
+/// Sensors are matched by role name and type, fleet vehicles by id and
+/// spawned actors by blueprint and role name, so reordering the rig is not
+/// a difference. Entries sharing a key, such as sensors of one type without
+/// a role name, are matched in order and get a ` #2` and so on.
+pub fn diff_headers(left: &RecordingHeaderSerDe, right: &RecordingHeaderSerDe) -> HeaderDiff {
+    let tree = |h: &RecordingHeaderSerDe| {
+        let mut v = serde_json::to_value(h).expect("header serializes to JSON");
+        if let Some(p) = v.get_mut("provenance").and_then(Value::as_object_mut) {
+            p.remove("history");
+        }
+        key_sensors(&mut v);
+        key_spawned(&mut v);
+        if let Some(vehicles) = v.get_mut("vehicles").and_then(Value::as_array_mut) {
+            vehicles.iter_mut().for_each(key_sensors);
+        }
+        v
+    };
+    let mut diff = HeaderDiff::default();
+    walk(
+        String::new(),
+        Some(&tree(left)),
+        Some(&tree(right)),
+        &mut diff,
+    );
+    diff
+}
+
+// sensors keyed by role name and type, without their actor ids
+fn key_sensors(rig: &mut Value) {
+    let Some(sensors) = rig.get_mut("sensors").and_then(Value::as_array_mut) else {
+        return;
+    };
+    let sensors = sensors.drain(..).map(|mut sensor| {
+        if let Some(s) = sensor.as_object_mut() {
+            s.remove("id");
+        }
+        let field = |name: &str| {
+            sensor
+                .get(name)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let (role, type_id) = (field("role_name"), field("type_id"));
+        let key = if role.is_empty() {
+            type_id
+        } else {
+            format!("{role} ({type_id})")
+        };
+        (key, sensor)
+    });
+    rig["sensors"] = unique_keys(sensors.collect());
+}
+
+// spawned actors as their blueprints keyed by blueprint id and role
+// name, without their actor ids
+fn key_spawned(header: &mut Value) {
+    let Some(spawned) = header.get_mut("spawned").and_then(Value::as_array_mut) else {
+        return;
+    };
+    let spawned = spawned.drain(..).map(|mut actor| {
+        let blueprint = actor
+            .get_mut("blueprint")
+            .map(Value::take)
+            .unwrap_or_default();
+        let id = blueprint
+            .get("id")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let key = match spawned_role_name(&blueprint) {
+            Some(role) => format!("{id} ({role})"),
+            None => id.to_string(),
+        };
+        (key, blueprint)
+    });
+    header["spawned"] = unique_keys(spawned.collect());
+}
+
+// an object of the entries, further entries with a key getting a ` #2` on
+fn unique_keys(entries: Vec<(String, Value)>) -> Value {
+    let mut keyed = serde_json::Map::new();
+    for (key, value) in entries {
+        let mut unique = key.clone();
+        for n in 2.. {
+            if !keyed.contains_key(&unique) {
+                break;
+            }
+            unique = format!("{key} #{n}");
+        }
+        keyed.insert(unique, value);
+    }
+    Value::Object(keyed)
+}
+
+fn spawned_role_name(blueprint: &Value) -> Option<&str> {
+    blueprint
+        .get("attributes")?
+        .as_array()?
+        .iter()
+        .find(|a| a.get("id").and_then(Value::as_str) == Some("role_name"))?
+        .pointer("/value/value")?
+        .as_str()
+        .filter(|role| !role.is_empty())
+}
+
+fn walk(path: String, left: Option<&Value>, right: Option<&Value>, diff: &mut HeaderDiff) {
+    let child = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match (left, right) {
+        (Some(Value::Object(l)), Some(Value::Object(r))) => {
+            let mut keys: Vec<&String> = l.keys().chain(r.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                walk(child(key), l.get(key), r.get(key), diff);
+            }
+        }
+        (Some(Value::Array(l)), Some(Value::Array(r))) if keyed(l) && keyed(r) => {
+            let mut names: Vec<String> = l.iter().chain(r).filter_map(name).collect();
+            names.sort();
+            names.dedup();
+            for n in names {
+                walk(child(&n), find(l, &n), find(r, &n), diff);
+            }
+        }
+        (Some(Value::Array(l)), Some(Value::Array(r))) if l.len() == r.len() => {
+            for (i, (l, r)) in l.iter().zip(r).enumerate() {
+                walk(child(&i.to_string()), Some(l), Some(r), diff);
+            }
+        }
+        (Some(Value::Number(l)), Some(Value::Number(r))) => {
+            let (l, r) = (l.as_f64().unwrap_or(0.0), r.as_f64().unwrap_or(0.0));
+            if (l - r).abs() > TOLERANCE * l.abs().max(r.abs()).max(1.0) {
+                push(path, left, right, diff);
+            }
+        }
+        (l, r) if l != r => push(path, l, r, diff),
+        _ => {}
+    }
+}
+
+fn push(path: String, left: Option<&Value>, right: Option<&Value>, diff: &mut HeaderDiff) {
+    diff.differences.push(HeaderDifference {
+        path,
+        left: left.cloned(),
+        right: right.cloned(),
+    });
+}
+
+// arrays of entries with distinct ids, such as fleet vehicles
+fn keyed(items: &[Value]) -> bool {
+    let mut names: Vec<String> = items.iter().filter_map(name).collect();
+    let all = !items.is_empty() && names.len() == items.len();
+    names.sort();
+    names.dedup();
+    all && names.len() == items.len()
+}
+
+fn find<'v>(items: &'v [Value], n: &str) -> Option<&'v Value> {
+    items.iter().find(|v| name(v).as_deref() == Some(n))
+}
+
+// a non-empty string or numeric id
+fn name(v: &Value) -> Option<String> {
+    match v.get("id")? {
+        Value::String(id) if !id.is_empty() => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ActorAttributeSerDe, ActorAttributeValueSerDe, ActorBlueprintSerDe, SensorDescriptionSerDe,
+        SpawnedActorSerDe,
+    };
+    use nalgebra::{Isometry3, Vector3};
+
+    fn sensor(id: u32, type_id: &str, role: &str, z: f32) -> SensorDescriptionSerDe {
+        SensorDescriptionSerDe {
+            id,
+            type_id: type_id.into(),
+            role_name: role.into(),
+            mount: Isometry3::translation(0.0, 0.0, z),
+            camera: None,
+        }
+    }
+
+    fn spawned(id: u32, blueprint: &str, role: &str, color: &str) -> SpawnedActorSerDe {
+        let attribute = |id: &str, value: &str| ActorAttributeSerDe {
+            id: id.into(),
+            value: ActorAttributeValueSerDe::String(value.into()),
+        };
+        SpawnedActorSerDe {
+            id,
+            blueprint: ActorBlueprintSerDe {
+                id: blueprint.into(),
+                tags: Vec::new(),
+                attributes: vec![attribute("role_name", role), attribute("color", color)],
+            },
+        }
+    }
+
+    #[test]
+    fn spawned_actors_match_by_blueprint_and_role() {
+        let left = RecordingHeaderSerDe {
+            spawned: vec![
+                spawned(24, "vehicle.tesla.model3", "hero", "255,0,0"),
+                spawned(25, "vehicle.audi.tt", "", "0,0,255"),
+                spawned(26, "vehicle.audi.tt", "", "0,0,255"),
+            ],
+            ..RecordingHeaderSerDe::default()
+        };
+        let right = RecordingHeaderSerDe {
+            spawned: vec![
+                spawned(81, "vehicle.audi.tt", "", "0,0,255"),
+                spawned(82, "vehicle.audi.tt", "", "0,0,255"),
+                spawned(80, "vehicle.tesla.model3", "hero", "255,0,0"),
+            ],
+            ..RecordingHeaderSerDe::default()
+        };
+        assert!(diff_headers(&left, &right).is_empty());
+
+        let mut repainted = right.clone();
+        repainted.spawned[2] = spawned(80, "vehicle.tesla.model3", "hero", "0,255,0");
+        let diff = diff_headers(&left, &repainted);
+        assert_eq!(diff.differences.len(), 1);
+        assert_eq!(
+            diff.differences[0].path,
+            "spawned.vehicle.tesla.model3 (hero).attributes.color.value.value"
+        );
+    }
+
+    #[test]
+    fn sensors_match_by_role_and_type() {
+        let left = RecordingHeaderSerDe {
+            sensors: vec![
+                sensor(1, "sensor.camera.rgb", "front", 1.0),
+                sensor(2, "sensor.other.imu", "", 0.5),
+                sensor(3, "sensor.lidar.ray_cast", "", 2.0),
+            ],
+            ..RecordingHeaderSerDe::default()
+        };
+        let mut right = RecordingHeaderSerDe {
+            sensors: vec![
+                sensor(13, "sensor.lidar.ray_cast", "", 2.0),
+                sensor(11, "sensor.camera.rgb", "front", 1.0),
+                sensor(12, "sensor.other.imu", "", 0.5),
+            ],
+            ..RecordingHeaderSerDe::default()
+        };
+        assert!(diff_headers(&left, &right).is_empty());
+
+        // unnamed sensors are told apart by type, not lumped together
+        right.sensors[0].mount.translation = Vector3::new(0.0, 0.0, 2.5).into();
+        let diff = diff_headers(&left, &right);
+        assert_eq!(diff.differences.len(), 1);
+        assert!(
+            diff.differences[0]
+                .path
+                .starts_with("sensors.sensor.lidar.ray_cast.mount"),
+            "{diff}"
+        );
+    }
+
+    #[test]
+    fn numeric_ids_key_arrays() {
+        let entry = |id: u32, value: &str| serde_json::json!({"id": id, "value": value});
+        let mut diff = HeaderDiff::default();
+        let left = serde_json::json!([entry(1, "a"), entry(2, "b")]);
+        let right = serde_json::json!([entry(2, "b"), entry(1, "c")]);
+        walk("items".into(), Some(&left), Some(&right), &mut diff);
+        assert_eq!(diff.differences.len(), 1);
+        assert_eq!(diff.differences[0].path, "items.1.value");
+    }
+}
@@ -0,0 +1,390 @@
+use super::{GapLine, HeaderLine};
+use crate::compact::compact;
+use crate::pipeline::{SharedFrame, Sink};
+use crate::{GapRecordSerDe, RecordingHeaderSerDe, SensorDataSerDe};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// When a [`JournalWriter`] forces its lines to disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// After every line: a power failure loses at most the frame being
+    /// written.
+    #[default]
+    EveryFrame,
+    /// After every `n` lines: at most `n` frames are lost.
+    EveryFrames(u32),
+    /// When `Duration` has passed since the last sync: at most that much
+    /// recording is lost.
+    Interval(Duration),
+    /// Only on [`JournalWriter::sync`], leaving the rest to the OS.
+    Manual,
+}
+
+/// Appends a JSON-lines recording to a file so a crash or power failure
+/// loses at most what [`SyncPolicy`] allows.
+///
+/// Every line is encoded in full before it is written with a single
+/// write, and synced as the policy says, so the file always ends on a frame
+/// boundary or, after a crash, in the middle of the last frame.
+/// [`JournalWriter::open`] cuts such a torn line off before appending, and
+/// [`Recording::recover_jsonl`](super::Recording::recover_jsonl) reads a
+/// torn file as it is.
+///
+/// The writer holds an exclusive lock on the file, on Linux, macOS and
+/// Windows alike, so a second writer for the same recording fails with
+/// [`io::ErrorKind::WouldBlock`] instead of interleaving lines. Syncing
+/// uses `F_FULLFSYNC` on macOS and `FlushFileBuffers` on Windows, which
+/// reach the disk where a plain `fsync` may stop at its cache.
+#[derive(Debug)]
+pub struct JournalWriter {
+    file: File,
+    path: PathBuf,
+    policy: SyncPolicy,
+    line: Vec<u8>,
+    unsynced: u32,
+    last_sync: Instant,
+    committed: u64,
+    // file length after the last complete line
+    end: u64,
+    torn_bytes: u64,
+    compact: bool,
+}
+
+impl JournalWriter {
+    /// Start a recording at `path`, replacing any file there, with
+    /// `header` as its first line. The file and its directory entry are
+    /// synced before this returns.
+    pub fn create(
+        path: impl AsRef<Path>,
+        header: Option<&RecordingHeaderSerDe>,
+    ) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        // truncated only once locked, another writer's file stays intact
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        file.try_lock()?;
+        file.set_len(0)?;
+        sync_parent(&path)?;
+        let mut writer = Self::new(file, path, 0, 0);
+        if let Some(header) = header {
+            writer.encode(&HeaderLine { header })?;
+            writer.commit(true)?;
+        }
+        Ok(writer)
+    }
+
+    /// Continue the recording at `path`, creating it if needed. A torn
+    /// last line, left by a crash, is cut off first; see
+    /// [`JournalWriter::torn_bytes`].
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        file.try_lock()?;
+        sync_parent(&path)?;
+        let len = file.metadata()?.len();
+        let keep = last_line_end(&mut file, len)?;
+        if keep < len {
+            file.set_len(keep)?;
+            file.sync_data()?;
+        }
+        file.seek(SeekFrom::Start(keep))?;
+        Ok(Self::new(file, path, keep, len - keep))
+    }
+
+    fn new(file: File, path: PathBuf, end: u64, torn_bytes: u64) -> Self {
+        Self {
+            file,
+            path,
+            policy: SyncPolicy::default(),
+            line: Vec::new(),
+            unsynced: 0,
+            last_sync: Instant::now(),
+            committed: 0,
+            end,
+            torn_bytes,
+            compact: false,
+        }
+    }
+
+    pub fn with_sync(mut self, policy: SyncPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Leave the derived count fields out of frames, see
+    /// [`crate::compact`]; off by default, as older readers need them.
+    pub fn with_compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Lines written by this writer; the synced ones are on disk.
+    pub fn committed(&self) -> u64 {
+        self.committed
+    }
+
+    /// Bytes of a torn last line that [`JournalWriter::open`] cut off.
+    pub fn torn_bytes(&self) -> u64 {
+        self.torn_bytes
+    }
+
+    pub fn append(&mut self, frame: &SensorDataSerDe) -> io::Result<()> {
+        if self.compact {
+            self.encode(&compact(frame))?;
+        } else {
+            self.encode(frame)?;
+        }
+        self.commit(false)
+    }
+
+    /// Note that frames were dropped at this point of the recording.
+    pub fn append_gap(&mut self, gap: &GapRecordSerDe) -> io::Result<()> {
+        self.encode(&GapLine { gap })?;
+        self.commit(false)
+    }
+
+    /// Force everything written so far to disk.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data()?;
+        self.unsynced = 0;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    fn encode(&mut self, value: &impl serde::Serialize) -> io::Result<()> {
+        self.line.clear();
+        serde_json::to_writer(&mut self.line, value)?;
+        self.line.push(b'\n');
+        Ok(())
+    }
+
+    fn commit(&mut self, force_sync: bool) -> io::Result<()> {
+        if let Err(e) = self.file.write_all(&self.line) {
+            // e.g. a full disk
+            self.rollback()?;
+            return Err(e);
+        }
+        self.end += self.line.len() as u64;
+        self.committed += 1;
+        self.unsynced += 1;
+        let due = match self.policy {
+            SyncPolicy::EveryFrame => true,
+            SyncPolicy::EveryFrames(n) => self.unsynced >= n.max(1),
+            SyncPolicy::Interval(interval) => self.last_sync.elapsed() >= interval,
+            SyncPolicy::Manual => false,
+        };
+        if due || force_sync {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    // Drop what a failed write left after the last complete line, so the
+    // next line starts on a boundary.
+    fn rollback(&mut self) -> io::Result<()> {
+        self.file.set_len(self.end)?;
+        self.file.seek(SeekFrom::Start(self.end))?;
+        Ok(())
+    }
+}
+
+// Where the last complete line of the file's first `len` bytes ends.
+fn last_line_end(file: &mut File, len: u64) -> io::Result<u64> {
+    let mut end = len;
+    let mut chunk = [0; 4096];
+    while end > 0 {
+        let start = end.saturating_sub(chunk.len() as u64);
+        let chunk = &mut chunk[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        if let Some(i) = chunk.iter().rposition(|&b| b == b'\n') {
+            return Ok(start + i as u64 + 1);
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+// Make a new file's directory entry durable.
+fn sync_parent(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// A [`JournalWriter`] as the end of a pipeline.
+///
+/// Write errors are kept, not raised; the first one is returned by
+/// [`JournalSink::take_error`]. [`Sink::flush`] syncs the file.
+#[derive(Debug)]
+pub struct JournalSink {
+    writer: JournalWriter,
+    last_error: Option<io::Error>,
+}
+
+impl JournalSink {
+    pub fn new(writer: JournalWriter) -> Self {
+        Self {
+            writer,
+            last_error: None,
+        }
+    }
+
+    pub fn writer(&self) -> &JournalWriter {
+        &self.writer
+    }
+
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.last_error.take()
+    }
+
+    pub fn into_inner(self) -> JournalWriter {
+        self.writer
+    }
+}
+
+impl Sink for JournalSink {
+    fn consume(&mut self, frame: SharedFrame) {
+        if let Err(e) = self.writer.append(&frame) {
+            self.last_error.get_or_insert(e);
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Err(e) = self.writer.sync() {
+            self.last_error.get_or_insert(e);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use crate::fixtures::imu_frame;
+    use crate::recording::Recording;
+
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("journal-{}-{name}.jsonl", std::process::id()))
+    }
+
+    fn read(path: &Path) -> Recording {
+        Recording::read_jsonl(&std::fs::read(path).unwrap()[..]).unwrap()
+    }
+
+    #[test]
+    fn open_cuts_a_torn_last_line() {
+        let path = path("torn");
+        let frame = SensorDataSerDe::Imu(imu_frame());
+        let mut seeded = serde_json::to_vec(&frame).unwrap();
+        seeded.push(b'\n');
+        let torn = br#"{"Imu":{"accelerometer":[0.1,"#;
+        seeded.extend_from_slice(torn);
+        std::fs::write(&path, &seeded).unwrap();
+
+        let mut writer = JournalWriter::open(&path).unwrap();
+        assert_eq!(writer.torn_bytes(), torn.len() as u64);
+        writer.append(&frame).unwrap();
+        drop(writer);
+        assert_eq!(read(&path).frames.len(), 2);
+
+        // a file ending on a boundary is kept whole
+        let writer = JournalWriter::open(&path).unwrap();
+        assert_eq!(writer.torn_bytes(), 0);
+        drop(writer);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_failed_write_is_rolled_back() {
+        let path = path("rollback");
+        let frame = SensorDataSerDe::Imu(imu_frame());
+        let mut writer = JournalWriter::create(&path, None).unwrap();
+        writer.append(&frame).unwrap();
+        // what a write cut short by a full disk leaves
+        writer.file.write_all(br#"{"Imu":{"acc"#).unwrap();
+        writer.rollback().unwrap();
+        writer.append(&frame).unwrap();
+        assert_eq!(writer.committed(), 2);
+        drop(writer);
+        assert_eq!(read(&path).frames.len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn sync_policies_count_unsynced_lines() {
+        let path = path("policy");
+        let frame = SensorDataSerDe::Imu(imu_frame());
+        let header = RecordingHeaderSerDe::default();
+        let mut writer = JournalWriter::create(&path, Some(&header))
+            .unwrap()
+            .with_sync(SyncPolicy::EveryFrames(3));
+        // the header is synced on create
+        assert_eq!((writer.committed(), writer.unsynced), (1, 0));
+        writer.append(&frame).unwrap();
+        writer.append(&frame).unwrap();
+        assert_eq!(writer.unsynced, 2);
+        writer.append(&frame).unwrap();
+        assert_eq!(writer.unsynced, 0);
+
+        let mut writer = writer.with_sync(SyncPolicy::Manual);
+        writer.append(&frame).unwrap();
+        writer.append(&frame).unwrap();
+        assert_eq!(writer.unsynced, 2);
+        writer.sync().unwrap();
+        assert_eq!(writer.unsynced, 0);
+
+        let mut writer = writer.with_sync(SyncPolicy::Interval(Duration::from_secs(3600)));
+        writer.append(&frame).unwrap();
+        assert_eq!(writer.unsynced, 1);
+        let mut writer = writer.with_sync(SyncPolicy::Interval(Duration::ZERO));
+        writer.append(&frame).unwrap();
+        assert_eq!(writer.unsynced, 0);
+
+        let mut writer = writer.with_sync(SyncPolicy::EveryFrame);
+        writer.append(&frame).unwrap();
+        assert_eq!((writer.committed(), writer.unsynced), (9, 0));
+        drop(writer);
+        let recording = read(&path);
+        assert!(recording.header.is_some());
+        assert_eq!(recording.frames.len(), 8);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_second_writer_would_block() {
+        let path = path("lock");
+        let writer = JournalWriter::create(&path, None).unwrap();
+        for second in [
+            JournalWriter::open(&path),
+            JournalWriter::create(&path, None),
+        ] {
+            assert_eq!(second.unwrap_err().kind(), io::ErrorKind::WouldBlock);
+        }
+        drop(writer);
+        JournalWriter::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
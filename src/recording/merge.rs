@@ -0,0 +1,329 @@
+use super::Recording;
+use crate::{
+    ClockSyncSerDe, GapRecordSerDe, ProcessingStepSerDe, SensorDataSerDe, SensorDescriptionSerDe,
+};
+use std::iter::{Enumerate, Peekable};
+use std::{error, fmt, vec};
+
+/// One client's recording, with how its frame numbers and clock relate to
+/// the merged session.
+///
+/// Clients attached to the same CARLA server see the same frame numbers
+/// and need no offsets. A client that was recording against a restarted
+/// server, or whose frames were renumbered, needs a `frame_offset`; the
+/// offsets are never guessed.
+#[derive(Clone, Debug)]
+pub struct MergeInput {
+    pub recording: Recording,
+    /// Added to the input's frame numbers.
+    pub frame_offset: i64,
+    /// Added to the input's timestamps, in seconds.
+    pub clock_offset: f64,
+}
+
+impl MergeInput {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            frame_offset: 0,
+            clock_offset: 0.0,
+        }
+    }
+
+    pub fn with_frame_offset(mut self, offset: i64) -> Self {
+        self.frame_offset = offset;
+        self
+    }
+
+    pub fn with_clock_offset(mut self, seconds: f64) -> Self {
+        self.clock_offset = seconds;
+        self
+    }
+}
+
+/// Why [`merge_recordings`] rejected its inputs. `input` and `index` are
+/// positions in the input list and in that input's frames.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MergeError {
+    /// The frame has no [`crate::FRAME_NUMBER_KEY`] annotation.
+    MissingFrameNumber { input: usize, index: usize },
+    /// The frame number is below the previous one of the same input.
+    OutOfOrder { input: usize, index: usize },
+    /// The frame offset moves the frame number below zero.
+    NegativeFrameNumber { input: usize, index: usize },
+    /// The frame offset moves the frame number past `i64::MAX`.
+    FrameNumberOverflow { input: usize, index: usize },
+    /// Two inputs describe the sensor with this role name differently.
+    ConflictingSensor { role_name: String },
+    /// Two inputs describe the fleet vehicle with this id differently.
+    ConflictingVehicle { id: String },
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingFrameNumber { input, index } => {
+                write!(f, "input {input}, frame {index}: no frame number")
+            }
+            Self::OutOfOrder { input, index } => {
+                write!(
+                    f,
+                    "input {input}, frame {index}: frame number goes backwards"
+                )
+            }
+            Self::NegativeFrameNumber { input, index } => {
+                write!(f, "input {input}, frame {index}: negative after offset")
+            }
+            Self::FrameNumberOverflow { input, index } => {
+                write!(f, "input {input}, frame {index}: overflows after offset")
+            }
+            Self::ConflictingSensor { role_name } => {
+                write!(f, "sensor `{role_name}` differs between inputs")
+            }
+            Self::ConflictingVehicle { id } => write!(f, "vehicle `{id}` differs between inputs"),
+        }
+    }
+}
+
+impl error::Error for MergeError {}
+
+/// Merge the recordings of several client processes into one session,
+/// ordered by frame number, using [`SensorDataSerDe::frame_number`] as the
+/// sync key.
+///
+/// Frames with the same frame number keep the order of the inputs. The
+/// rewritten frame numbers and timestamps include the offsets. The header
+/// is the first input's, with the sensors and fleet vehicles of all inputs
+/// and the metadata keys the first input lacks; gaps stay in front of the
+/// frame that followed them. The clock mappings of all inputs are shifted
+/// by their clock offsets, the first input's mapping winning for a clock
+/// several inputs were aligned with.
+pub fn merge_recordings(inputs: Vec<MergeInput>) -> Result<Recording, MergeError> {
+    let mut header = inputs
+        .first()
+        .and_then(|i| i.recording.header.clone())
+        .unwrap_or_default();
+    header.sensors.clear();
+    header.vehicles.clear();
+    header.clocks.clear();
+    let mut step = ProcessingStepSerDe::new("merge").with_param("inputs", inputs.len() as i64);
+    for (n, input) in inputs.iter().enumerate() {
+        if input.frame_offset != 0 {
+            step = step.with_param(format!("frame_offset_{n}"), input.frame_offset);
+        }
+        if input.clock_offset != 0.0 {
+            step = step.with_param(format!("clock_offset_{n}"), input.clock_offset);
+        }
+        let Some(other) = &input.recording.header else {
+            continue;
+        };
+        merge_sensors(&mut header.sensors, &other.sensors)?;
+        for sync in &other.clocks {
+            if header.clock(&sync.clock).is_none() {
+                header.clocks.push(ClockSyncSerDe {
+                    reference_sim: sync.reference_sim + input.clock_offset,
+                    ..sync.clone()
+                });
+            }
+        }
+        for vehicle in &other.vehicles {
+            match header.vehicles.iter().find(|v| v.id == vehicle.id) {
+                Some(v) if v == vehicle => {}
+                Some(_) => {
+                    return Err(MergeError::ConflictingVehicle {
+                        id: vehicle.id.clone(),
+                    });
+                }
+                None => header.vehicles.push(vehicle.clone()),
+            }
+        }
+        for (key, value) in &other.metadata.0 {
+            header
+                .metadata
+                .0
+                .entry(key.clone())
+                .or_insert(value.clone());
+        }
+    }
+    header.provenance.record(step);
+
+    let mut queues = Vec::with_capacity(inputs.len());
+    for (n, input) in inputs.into_iter().enumerate() {
+        let mut frames = Vec::with_capacity(input.recording.frames.len());
+        let mut previous = 0;
+        for (index, mut frame) in input.recording.frames.into_iter().enumerate() {
+            let number = frame
+                .frame_number()
+                .ok_or(MergeError::MissingFrameNumber { input: n, index })?;
+            if number < previous {
+                return Err(MergeError::OutOfOrder { input: n, index });
+            }
+            previous = number;
+            let shifted = i64::try_from(number)
+                .ok()
+                .and_then(|number| number.checked_add(input.frame_offset))
+                .ok_or(MergeError::FrameNumberOverflow { input: n, index })?;
+            let shifted = u64::try_from(shifted)
+                .map_err(|_| MergeError::NegativeFrameNumber { input: n, index })?;
+            frame.set_frame_number(shifted);
+            if let Some(t) = frame.timestamp() {
+                frame.set_timestamp(t + input.clock_offset);
+            }
+            frames.push(frame);
+        }
+        queues.push(Queue {
+            frames: frames.into_iter().enumerate().peekable(),
+            gaps: input.recording.gaps.into_iter().peekable(),
+        });
+    }
+
+    let mut merged = Recording::new(header);
+    loop {
+        let next = queues
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(n, q)| Some((q.frames.peek()?.1.frame_number()?, n)))
+            .min();
+        let Some((_, n)) = next else { break };
+        let queue = &mut queues[n];
+        let (index, frame) = queue.frames.next().expect("peeked");
+        while let Some((_, gap)) = queue.gaps.next_if(|(at, _)| *at <= index) {
+            merged.push_gap(gap);
+        }
+        merged.frames.push(frame);
+    }
+    for queue in queues {
+        for (_, gap) in queue.gaps {
+            merged.push_gap(gap);
+        }
+    }
+    Ok(merged)
+}
+
+// the frames of one input not merged yet, with their original index
+struct Queue {
+    frames: Peekable<Enumerate<vec::IntoIter<SensorDataSerDe>>>,
+    gaps: Peekable<vec::IntoIter<(usize, GapRecordSerDe)>>,
+}
+
+// Sensors are matched by role name; actor ids differ between clients, and
+// sensors without a role name cannot be matched at all.
+fn merge_sensors(
+    sensors: &mut Vec<SensorDescriptionSerDe>,
+    other: &[SensorDescriptionSerDe],
+) -> Result<(), MergeError> {
+    for sensor in other {
+        let same = sensors
+            .iter()
+            .find(|s| !s.role_name.is_empty() && s.role_name == sensor.role_name);
+        match same {
+            Some(s) if same_except_id(s, sensor) => {}
+            Some(_) => {
+                return Err(MergeError::ConflictingSensor {
+                    role_name: sensor.role_name.clone(),
+                });
+            }
+            None => sensors.push(sensor.clone()),
+        }
+    }
+    Ok(())
+}
+
+fn same_except_id(a: &SensorDescriptionSerDe, b: &SensorDescriptionSerDe) -> bool {
+    let b = SensorDescriptionSerDe {
+        id: a.id,
+        ..b.clone()
+    };
+    *a == b
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use crate::{ExternalClockSerDe, RecordingHeaderSerDe};
+
+    fn recording(numbers: &[u64]) -> Recording {
+        let mut recording = Recording::new(RecordingHeaderSerDe::default());
+        for &n in numbers {
+            let mut frame = SensorDataSerDe::Imu(crate::fixtures::imu_frame());
+            frame.set_frame_number(n);
+            recording.frames.push(frame);
+        }
+        recording
+    }
+
+    #[test]
+    fn orders_by_shifted_frame_number() {
+        let merged = merge_recordings(vec![
+            MergeInput::new(recording(&[10, 12])),
+            MergeInput::new(recording(&[1, 3])).with_frame_offset(10),
+        ])
+        .unwrap();
+        let numbers: Vec<_> = merged
+            .frames
+            .iter()
+            .filter_map(|f| f.frame_number())
+            .collect();
+        assert_eq!(numbers, [10, 11, 12, 13]);
+    }
+
+    #[test]
+    fn rejects_offsets_out_of_range() {
+        let overflow = MergeInput::new(recording(&[1])).with_frame_offset(i64::MAX);
+        assert_eq!(
+            merge_recordings(vec![overflow]).unwrap_err(),
+            MergeError::FrameNumberOverflow { input: 0, index: 0 }
+        );
+        let negative = MergeInput::new(recording(&[1])).with_frame_offset(-2);
+        assert_eq!(
+            merge_recordings(vec![negative]).unwrap_err(),
+            MergeError::NegativeFrameNumber { input: 0, index: 0 }
+        );
+    }
+
+    #[test]
+    fn keeps_the_clocks_of_every_input() {
+        let sync = |clock, reference_sim| ClockSyncSerDe {
+            clock,
+            reference_sim,
+            reference_external: 100.0,
+            drift_ppm: 0.0,
+            residual: None,
+            samples: 0,
+        };
+        let mut first = recording(&[1]);
+        first.header = Some(RecordingHeaderSerDe {
+            clocks: vec![sync(ExternalClockSerDe::Ros, 1.0)],
+            ..Default::default()
+        });
+        let mut second = recording(&[1]);
+        second.header = Some(RecordingHeaderSerDe {
+            clocks: vec![
+                sync(ExternalClockSerDe::Ros, 5.0),
+                sync(ExternalClockSerDe::Ptp, 2.0),
+            ],
+            ..Default::default()
+        });
+        let merged = merge_recordings(vec![
+            MergeInput::new(first),
+            MergeInput::new(second).with_clock_offset(0.5),
+        ])
+        .unwrap();
+        let header = merged.header.unwrap();
+        assert_eq!(header.clocks.len(), 2);
+        assert_eq!(
+            header
+                .clock(&ExternalClockSerDe::Ros)
+                .unwrap()
+                .reference_sim,
+            1.0
+        );
+        assert_eq!(
+            header
+                .clock(&ExternalClockSerDe::Ptp)
+                .unwrap()
+                .reference_sim,
+            2.5
+        );
+    }
+}
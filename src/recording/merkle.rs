@@ -0,0 +1,337 @@
+use super::{FooterLine, from_hex, is_record_line, to_hex};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::io::{self, BufRead, Write};
+
+/// SHA-256 of a frame line, or of two nodes of a [`MerkleTree`].
+pub type FrameHash = [u8; 32];
+
+/// The leaf hash of a frame line as written, without its newline.
+///
+/// Leaves and inner nodes are hashed with different prefixes, as in
+/// RFC 6962, so a node can never pass for a frame.
+pub fn frame_hash(line: &[u8]) -> FrameHash {
+    Sha256::new()
+        .chain_update([0])
+        .chain_update(line)
+        .finalize()
+        .into()
+}
+
+fn node_hash(left: &FrameHash, right: &FrameHash) -> FrameHash {
+    Sha256::new()
+        .chain_update([1])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+// Pairs are hashed, a last odd node moves up unchanged.
+fn next_level(level: &[FrameHash]) -> Vec<FrameHash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            [odd] => *odd,
+            _ => unreachable!("chunks of two"),
+        })
+        .collect()
+}
+
+/// A Merkle tree over the frames of one recording segment, so a single
+/// frame can be shown to belong to it without the other frames.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MerkleTree {
+    leaves: Vec<FrameHash>,
+}
+
+impl MerkleTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_leaves(leaves: Vec<FrameHash>) -> Self {
+        Self { leaves }
+    }
+
+    /// Add the next frame line, as written, without its newline.
+    pub fn push_frame(&mut self, line: &[u8]) {
+        self.leaves.push(frame_hash(line));
+    }
+
+    pub fn leaves(&self) -> &[FrameHash] {
+        &self.leaves
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// The hash over all frames; that of no input for an empty tree.
+    pub fn root(&self) -> FrameHash {
+        if self.leaves.is_empty() {
+            return Sha256::digest([]).into();
+        }
+        let mut level = self.leaves.clone();
+        while level.len() > 1 {
+            level = next_level(&level);
+        }
+        level[0]
+    }
+
+    /// The proof that frame `index`, counted from 0, is part of the tree.
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        if index >= self.leaves.len() {
+            return None;
+        }
+        let mut siblings = Vec::new();
+        let mut level = self.leaves.clone();
+        let mut i = index;
+        while level.len() > 1 {
+            if let Some(sibling) = level.get(i ^ 1) {
+                siblings.push(*sibling);
+            }
+            level = next_level(&level);
+            i /= 2;
+        }
+        Some(MerkleProof {
+            index: index as u64,
+            frames: self.leaves.len() as u64,
+            siblings,
+        })
+    }
+}
+
+/// The path from one frame to the root of its segment's [`MerkleTree`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Position of the frame among the segment's frames, from 0.
+    pub index: u64,
+    /// Frames in the segment.
+    pub frames: u64,
+    /// Hashes of the sibling nodes, bottom up.
+    #[serde(with = "hex_hashes")]
+    pub siblings: Vec<FrameHash>,
+}
+
+impl MerkleProof {
+    /// Whether `line`, the frame as written, is frame [`index`](Self::index)
+    /// of the segment of `frames` frames whose tree has `root`.
+    ///
+    /// The root alone does not fix the number of frames: a proof for the
+    /// third of three frames also leads to the root as the second of two.
+    /// `root` and `frames` must both come from the footer the reader
+    /// trusts, see [`SegmentFooter::verify`].
+    pub fn verify(&self, line: &[u8], root: &FrameHash, frames: u64) -> bool {
+        if self.frames != frames || self.index >= self.frames {
+            return false;
+        }
+        let mut hash = frame_hash(line);
+        let mut siblings = self.siblings.iter();
+        let (mut i, mut n) = (self.index, self.frames);
+        while n > 1 {
+            if i ^ 1 < n {
+                let Some(sibling) = siblings.next() else {
+                    return false;
+                };
+                hash = if i % 2 == 0 {
+                    node_hash(&hash, sibling)
+                } else {
+                    node_hash(sibling, &hash)
+                };
+            }
+            i /= 2;
+            n = n.div_ceil(2);
+        }
+        siblings.next().is_none() && hash == *root
+    }
+}
+
+/// The last line of a segment, `{"Footer": ...}`: the Merkle root of its
+/// frames and the leaf hashes it was built from.
+///
+/// With the leaves at hand a proof for any frame is built without reading
+/// the frames; checking one needs only that frame, its proof, the root and
+/// the number of leaves.
+///
+/// The footer itself is not authenticated: whoever can rewrite the segment
+/// can rewrite its footer to match. A proof shows that a frame belongs to
+/// the footer the reader trusts, so get the root from a trusted source,
+/// e.g. a signed recording (the `signing` feature) or a copy published when
+/// the segment was closed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentFooter {
+    #[serde(with = "hex_hash")]
+    pub root: FrameHash,
+    #[serde(with = "hex_hashes")]
+    pub leaves: Vec<FrameHash>,
+}
+
+impl SegmentFooter {
+    pub fn new(tree: &MerkleTree) -> Self {
+        Self {
+            root: tree.root(),
+            leaves: tree.leaves().to_vec(),
+        }
+    }
+
+    pub fn tree(&self) -> MerkleTree {
+        MerkleTree::from_leaves(self.leaves.clone())
+    }
+
+    /// The root matches the leaves.
+    pub fn is_consistent(&self) -> bool {
+        self.tree().root() == self.root
+    }
+
+    /// Whether `line` is frame `proof.index` of this segment, with the
+    /// footer's root and leaf count; false if the root does not match the
+    /// leaves.
+    pub fn verify(&self, line: &[u8], proof: &MerkleProof) -> bool {
+        self.is_consistent() && proof.verify(line, &self.root, self.leaves.len() as u64)
+    }
+
+    /// The proof for frame `index`, built from the leaves.
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        self.tree().proof(index)
+    }
+
+    /// Write the footer line; call it once the segment's last frame is
+    /// written.
+    pub fn write_line(&self, mut writer: impl Write) -> io::Result<()> {
+        serde_json::to_writer(&mut writer, &FooterLine { footer: self })?;
+        writer.write_all(b"\n")
+    }
+}
+
+/// The footer for the frames of a recording segment. Header, gap, footer
+/// and blank lines are not frames and are left out.
+pub fn merkle_footer(mut reader: impl BufRead) -> io::Result<SegmentFooter> {
+    let mut tree = MerkleTree::new();
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if is_frame_line(line) {
+            tree.push_frame(line);
+        }
+    }
+    Ok(SegmentFooter::new(&tree))
+}
+
+/// The footer of a segment, if it has one; the last one if there are more.
+pub fn read_footer(reader: impl BufRead) -> io::Result<Option<SegmentFooter>> {
+    let mut footer = None;
+    for line in reader.lines() {
+        let line = line?;
+        if let Ok(FooterLine { footer: f }) = serde_json::from_str(&line) {
+            footer = Some(f);
+        }
+    }
+    Ok(footer)
+}
+
+fn is_frame_line(line: &[u8]) -> bool {
+    let Ok(line) = std::str::from_utf8(line) else {
+        return true;
+    };
+    !(line.trim().is_empty() || is_record_line(line))
+}
+
+mod hex_hash {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(hash: &FrameHash, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&to_hex(hash))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<FrameHash, D::Error> {
+        let hex = String::deserialize(d)?;
+        from_hex(&hex).ok_or_else(|| serde::de::Error::custom("expected 64 hex digits"))
+    }
+}
+
+mod hex_hashes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(hashes: &[FrameHash], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(hashes.iter().map(|h| to_hex(h)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<FrameHash>, D::Error> {
+        Vec::<String>::deserialize(d)?
+            .iter()
+            .map(|hex| {
+                from_hex(hex).ok_or_else(|| serde::de::Error::custom("expected 64 hex digits"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(lines: &[&str]) -> MerkleTree {
+        let mut tree = MerkleTree::new();
+        lines.iter().for_each(|l| tree.push_frame(l.as_bytes()));
+        tree
+    }
+
+    #[test]
+    fn proofs_verify_every_frame() {
+        for n in 1..=7 {
+            let lines: Vec<String> = (0..n).map(|i| format!("{{\"frame\":{i}}}")).collect();
+            let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+            let footer = SegmentFooter::new(&tree(&lines));
+            for (i, line) in lines.iter().enumerate() {
+                let proof = footer.proof(i).unwrap();
+                assert!(footer.verify(line.as_bytes(), &proof), "{i} of {n}");
+                assert!(!footer.verify(b"{\"frame\":99}", &proof));
+            }
+            assert!(footer.proof(n).is_none());
+        }
+    }
+
+    #[test]
+    fn proofs_are_bound_to_the_frame_count() {
+        let footer = SegmentFooter::new(&tree(&["a", "b", "c"]));
+        let proof = footer.proof(2).unwrap();
+        assert!(footer.verify(b"c", &proof));
+        // c is also the second leaf of a two-leaf tree over node(a, b)
+        let forged = MerkleProof {
+            index: 1,
+            frames: 2,
+            ..proof.clone()
+        };
+        assert!(forged.verify(b"c", &footer.root, 2));
+        assert!(!forged.verify(b"c", &footer.root, 3));
+        assert!(!footer.verify(b"c", &forged));
+    }
+
+    #[test]
+    fn footers_must_match_their_leaves() {
+        let mut footer = SegmentFooter::new(&tree(&["a", "b"]));
+        let proof = footer.proof(0).unwrap();
+        footer.leaves[1] = frame_hash(b"x");
+        assert!(!footer.is_consistent());
+        assert!(!footer.verify(b"a", &proof));
+    }
+
+    #[test]
+    fn footer_lines_round_trip() {
+        let footer = SegmentFooter::new(&tree(&["a", "b", "c"]));
+        let mut out = Vec::new();
+        footer.write_line(&mut out).unwrap();
+        assert_eq!(read_footer(out.as_slice()).unwrap(), Some(footer));
+    }
+}
@@ -0,0 +1,49 @@
+use std::path::{Path, PathBuf};
+
+/// Extension of recording files.
+pub const RECORDING_EXTENSION: &str = "jsonl";
+
+// device names Windows reserves in every directory, whatever the extension
+const WINDOWS_RESERVED: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+
+/// Whether `name` can be a recording's file stem on Linux, macOS and
+/// Windows alike: no path separators or characters Windows rejects, no
+/// reserved device name such as `NUL` or `COM1`, no leading dot and no
+/// trailing dot or space, which Windows would strip.
+pub fn is_portable_name(name: &str) -> bool {
+    const FORBIDDEN: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+    if name.is_empty()
+        // leaves room for the extension within the usual 255-byte limit
+        || name.len() > 255 - RECORDING_EXTENSION.len() - 1
+        || name.starts_with('.')
+        || name.ends_with(['.', ' '])
+        || name.chars().any(|c| c.is_control() || FORBIDDEN.contains(&c))
+    {
+        return false;
+    }
+    let device = name.split('.').next().unwrap_or(name).trim_end();
+    let numbered = |prefix: &str| {
+        device.len() == 4
+            && device
+                .get(..3)
+                .is_some_and(|d| d.eq_ignore_ascii_case(prefix))
+            && matches!(device.as_bytes()[3], b'1'..=b'9')
+    };
+    !(WINDOWS_RESERVED
+        .iter()
+        .any(|r| device.eq_ignore_ascii_case(r))
+        || numbered("COM")
+        || numbered("LPT"))
+}
+
+/// The file of recording `name` in `dir`, if the name is portable.
+pub fn recording_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    is_portable_name(name).then(|| dir.join(format!("{name}.{RECORDING_EXTENSION}")))
+}
+
+/// The recording name of `path`, if it is a recording file with a portable
+/// name.
+pub fn recording_name(path: &Path) -> Option<&str> {
+    let name = path.file_stem()?.to_str()?;
+    (path.extension()? == RECORDING_EXTENSION && is_portable_name(name)).then_some(name)
+}
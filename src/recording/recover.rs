@@ -0,0 +1,237 @@
+use super::{LineDecoder, Recording, is_footer_line};
+use crate::SensorKind;
+use std::fmt;
+use std::io::{self, BufRead};
+
+/// A line [`Recording::recover_jsonl`] could not decode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DamagedLine {
+    /// Byte offset of the line in the input.
+    pub offset: u64,
+    /// Length in bytes, without the newline.
+    pub len: usize,
+    /// The kind of frame the line started to encode, if that much of it
+    /// survived.
+    pub kind: Option<SensorKind>,
+    pub error: String,
+}
+
+/// What [`Recording::recover_jsonl`] had to leave out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// Bytes read, damaged lines included.
+    pub bytes: u64,
+    /// Undecodable lines, in input order.
+    pub damaged: Vec<DamagedLine>,
+    /// Where the input was cut off: the offset of a last line that has no
+    /// newline and does not decode, i.e. a frame whose writing was
+    /// interrupted.
+    pub truncated_at: Option<u64>,
+}
+
+impl RecoveryReport {
+    /// Nothing was lost.
+    pub fn is_clean(&self) -> bool {
+        self.damaged.is_empty()
+    }
+
+    /// Bytes in damaged lines.
+    pub fn lost_bytes(&self) -> u64 {
+        self.damaged.iter().map(|l| l.len as u64).sum()
+    }
+
+    /// Damaged lines whose kind survived, per kind.
+    pub fn lost_by_kind(&self) -> Vec<(SensorKind, usize)> {
+        let mut counts: Vec<(SensorKind, usize)> = Vec::new();
+        for kind in self.damaged.iter().filter_map(|l| l.kind) {
+            match counts.iter_mut().find(|(k, _)| *k == kind) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((kind, 1)),
+            }
+        }
+        counts.sort();
+        counts
+    }
+}
+
+/// `Recovery: 2 damaged lines (1 Lidar), 8123 bytes lost, cut off at byte 1048576`
+impl fmt::Display for RecoveryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            return write!(f, "Recovery: clean, {} bytes", self.bytes);
+        }
+        write!(f, "Recovery: {} damaged lines", self.damaged.len())?;
+        let by_kind = self.lost_by_kind();
+        if !by_kind.is_empty() {
+            let kinds: Vec<String> = by_kind
+                .iter()
+                .map(|(kind, n)| format!("{n} {kind:?}"))
+                .collect();
+            write!(f, " ({})", kinds.join(", "))?;
+        }
+        write!(f, ", {} bytes lost", self.lost_bytes())?;
+        if let Some(at) = self.truncated_at {
+            write!(f, ", cut off at byte {at}")?;
+        }
+        Ok(())
+    }
+}
+
+// The kind named by the first key of `{"Lidar": ...`, however little of
+// the frame follows.
+fn leading_kind(line: &[u8]) -> Option<SensorKind> {
+    let rest = line.trim_ascii_start().strip_prefix(b"{")?;
+    let rest = rest.trim_ascii_start().strip_prefix(b"\"")?;
+    let end = rest.iter().position(|&b| b == b'"')?;
+    // the key with its quotes
+    let key = line.len() - rest.len() - 1..line.len() - rest.len() + end + 1;
+    serde_json::from_slice(&line[key]).ok()
+}
+
+impl Recording {
+    /// Decode what can be decoded of a recording that may be damaged, e.g.
+    /// cut off mid-frame by a crash while recording.
+    ///
+    /// Lines that do not decode are skipped and listed in the report;
+    /// everything else is read as by [`Recording::read_jsonl`]. Only I/O
+    /// errors fail.
+    pub fn recover_jsonl(mut reader: impl BufRead) -> io::Result<(Self, RecoveryReport)> {
+        let mut recording = Self::default();
+        let mut report = RecoveryReport::default();
+        let mut decoder = LineDecoder::default();
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            let read = reader.read_until(b'\n', &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            let offset = report.bytes;
+            report.bytes += read as u64;
+            let terminated = buf.last() == Some(&b'\n');
+            let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+            // filesystems may leave a crashed file padded with NULs
+            let error = if line.contains(&0) {
+                "NUL bytes".to_owned()
+            } else if line.trim_ascii().is_empty() {
+                continue;
+            } else {
+                match std::str::from_utf8(line) {
+                    Err(e) => e.to_string(),
+                    Ok(line) => {
+                        if decoder.encoding_line(line) {
+                            continue;
+                        }
+                        if recording.header.is_none()
+                            && recording.frames.is_empty()
+                            && let Some(header) = decoder.header(line)
+                        {
+                            recording.header = Some(header);
+                            continue;
+                        }
+                        if let Some(gap) = decoder.gap(line) {
+                            recording.push_gap(gap);
+                            continue;
+                        }
+                        if is_footer_line(line) {
+                            continue;
+                        }
+                        match decoder.frame(line) {
+                            Ok(frame) => {
+                                recording.frames.push(frame);
+                                continue;
+                            }
+                            Err(e) => e.to_string(),
+                        }
+                    }
+                }
+            };
+            if !terminated {
+                report.truncated_at = Some(offset);
+            }
+            report.damaged.push(DamagedLine {
+                offset,
+                len: line.len(),
+                kind: leading_kind(line),
+                error,
+            });
+        }
+        Ok((recording, report))
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use crate::fixtures::{IMU_JSON, LIDAR_JSON};
+
+    fn line(json: &[u8]) -> Vec<u8> {
+        let mut line = json.trim_ascii().to_vec();
+        line.push(b'\n');
+        line
+    }
+
+    #[test]
+    fn a_torn_last_line_is_cut_off() {
+        let mut bytes = line(IMU_JSON);
+        let offset = bytes.len() as u64;
+        bytes.extend_from_slice(&LIDAR_JSON.trim_ascii()[..20]);
+        let (recording, report) = Recording::recover_jsonl(&bytes[..]).unwrap();
+        assert_eq!(recording.frames.len(), 1);
+        assert_eq!(report.bytes, bytes.len() as u64);
+        assert_eq!(report.truncated_at, Some(offset));
+        assert_eq!(report.damaged.len(), 1);
+        assert_eq!(report.damaged[0].offset, offset);
+        assert_eq!(report.damaged[0].len, 20);
+        assert_eq!(report.lost_by_kind(), [(SensorKind::Lidar, 1)]);
+    }
+
+    #[test]
+    fn a_damaged_line_inside_the_file_is_not_a_cut() {
+        let mut bytes = line(br#"{"Lidar":{"horizontal_angle":"#);
+        bytes.extend(line(IMU_JSON));
+        let (recording, report) = Recording::recover_jsonl(&bytes[..]).unwrap();
+        assert_eq!(recording.frames.len(), 1);
+        assert_eq!(report.truncated_at, None);
+        assert_eq!(report.damaged[0].kind, Some(SensorKind::Lidar));
+    }
+
+    #[test]
+    fn nul_padding_and_invalid_utf8_are_damage() {
+        let mut bytes = line(IMU_JSON);
+        bytes.extend_from_slice(&[0; 16]);
+        bytes.push(b'\n');
+        bytes.extend_from_slice(b"{\"Imu\xff\xfe\n");
+        bytes.extend(line(IMU_JSON));
+        let (recording, report) = Recording::recover_jsonl(&bytes[..]).unwrap();
+        assert_eq!(recording.frames.len(), 2);
+        assert_eq!(report.damaged.len(), 2);
+        assert_eq!(report.damaged[0].error, "NUL bytes");
+        assert_eq!(report.damaged[0].len, 16);
+        assert!(report.damaged[1].error.contains("utf-8"), "{report:?}");
+        assert_eq!(report.lost_bytes(), 16 + 7);
+        assert_eq!(report.truncated_at, None);
+    }
+
+    #[test]
+    fn leading_kind_needs_only_the_first_key() {
+        assert_eq!(leading_kind(br#"{"Lidar":"#), Some(SensorKind::Lidar));
+        assert_eq!(leading_kind(br#" { "Radar" : {"#), Some(SensorKind::Radar));
+        assert_eq!(leading_kind(br#"{"Lid"#), None);
+        assert_eq!(leading_kind(br#"{"Bogus":{}}"#), None);
+        assert_eq!(leading_kind(b"[1, 2]"), None);
+    }
+
+    #[test]
+    fn clean_input_reports_clean() {
+        let (recording, report) = Recording::recover_jsonl(&line(IMU_JSON)[..]).unwrap();
+        assert_eq!(recording.frames.len(), 1);
+        assert!(report.is_clean());
+        assert_eq!(
+            report.to_string(),
+            format!("Recovery: clean, {} bytes", report.bytes)
+        );
+    }
+}
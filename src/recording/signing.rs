@@ -0,0 +1,388 @@
+use super::{Recording, from_hex, to_hex};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Extension [`signature_path`] appends to a recording's file name.
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+/// Where the signatures of the recording at `recording` are kept:
+/// `run-01.jsonl` is signed by `run-01.jsonl.sig`.
+pub fn signature_path(recording: &Path) -> PathBuf {
+    let mut path = recording.as_os_str().to_owned();
+    path.push(".");
+    path.push(SIGNATURE_EXTENSION);
+    PathBuf::from(path)
+}
+
+// Starts the closing record of a signature file.
+const CLOSE_PREFIX: &str = "end ";
+
+// What each signature covers: the signature before it, then the line.
+fn signed_message(message: &mut Vec<u8>, previous: &[u8; 64], line: &[u8]) {
+    message.clear();
+    message.extend_from_slice(previous);
+    message.extend_from_slice(line);
+}
+
+// What the closing signature covers: the last signature, a NUL no JSON
+// line contains, then the line count.
+fn close_message(message: &mut Vec<u8>, previous: &[u8; 64], lines: u64) {
+    signed_message(message, previous, b"\0close");
+    message.extend_from_slice(&lines.to_le_bytes());
+}
+
+/// Signs the lines of a recording one by one, as they are written.
+///
+/// Every signature covers its line and the signature before it, so a
+/// verifier notices not only an edited line but also a dropped, inserted
+/// or reordered one. The last signature thus vouches for everything up to
+/// its line, e.g. a whole segment of a rotated recording, and
+/// [`FrameSigner::close`] for where the recording ends.
+pub struct FrameSigner {
+    key: SigningKey,
+    previous: [u8; 64],
+    message: Vec<u8>,
+}
+
+impl FrameSigner {
+    pub fn new(key: SigningKey) -> Self {
+        Self {
+            key,
+            previous: [0; 64],
+            message: Vec::new(),
+        }
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    /// Sign the next line, without its newline.
+    pub fn sign(&mut self, line: &[u8]) -> Signature {
+        signed_message(&mut self.message, &self.previous, line);
+        let signature = self.key.sign(&self.message);
+        self.previous = signature.to_bytes();
+        signature
+    }
+
+    /// Sign the end of the recording after `lines` lines, so a verifier
+    /// notices lines cut off together with their signatures.
+    pub fn close(&mut self, lines: u64) -> Signature {
+        close_message(&mut self.message, &self.previous, lines);
+        self.key.sign(&self.message)
+    }
+}
+
+// The secret key stays out of logs.
+impl fmt::Debug for FrameSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameSigner")
+            .field("verifying_key", &self.verifying_key())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Checks lines against the signatures a [`FrameSigner`] made, in the
+/// same order.
+#[derive(Debug)]
+pub struct FrameVerifier {
+    key: VerifyingKey,
+    previous: [u8; 64],
+    message: Vec<u8>,
+}
+
+impl FrameVerifier {
+    pub fn new(key: VerifyingKey) -> Self {
+        Self {
+            key,
+            previous: [0; 64],
+            message: Vec::new(),
+        }
+    }
+
+    /// Whether `signature` is the signature of the next line. The chain
+    /// moves on either way, so one edited line fails alone.
+    pub fn verify(&mut self, line: &[u8], signature: &Signature) -> bool {
+        signed_message(&mut self.message, &self.previous, line);
+        self.previous = signature.to_bytes();
+        self.key.verify(&self.message, signature).is_ok()
+    }
+
+    /// Whether `signature` is [`FrameSigner::close`] after `lines` lines,
+    /// the ones verified so far.
+    pub fn verify_close(&mut self, lines: u64, signature: &Signature) -> bool {
+        close_message(&mut self.message, &self.previous, lines);
+        self.key.verify(&self.message, signature).is_ok()
+    }
+}
+
+/// What [`verify_jsonl`] found.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SignatureReport {
+    /// Lines of the recording, blank ones included.
+    pub lines: u64,
+    /// Numbers, from 1, of the lines whose signature does not match.
+    pub invalid: Vec<u64>,
+    /// Lines at the end of the recording that have no signature.
+    pub unsigned: u64,
+    /// Signatures left over after the last line, e.g. of lines cut off.
+    pub surplus: u64,
+    /// Whether the signatures end with a valid closing record for exactly
+    /// these lines. Without it, a recording cut off together with its
+    /// signatures would pass.
+    pub closed: bool,
+}
+
+impl SignatureReport {
+    /// Every line is signed, every signature matches and the closing
+    /// record confirms the end.
+    pub fn is_valid(&self) -> bool {
+        self.invalid.is_empty() && self.unsigned == 0 && self.surplus == 0 && self.closed
+    }
+}
+
+/// `Signatures: 1200 lines, 2 invalid (first line 57), 0 unsigned, 0 surplus,
+/// closed`
+impl fmt::Display for SignatureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_valid() {
+            return write!(f, "Signatures: {} lines, all valid", self.lines);
+        }
+        write!(
+            f,
+            "Signatures: {} lines, {} invalid",
+            self.lines,
+            self.invalid.len()
+        )?;
+        if let Some(first) = self.invalid.first() {
+            write!(f, " (first line {first})")?;
+        }
+        write!(f, ", {} unsigned, {} surplus", self.unsigned, self.surplus)?;
+        f.write_str(if self.closed {
+            ", closed"
+        } else {
+            ", not closed"
+        })
+    }
+}
+
+/// Sign every line of `recording`, writing one hex-encoded signature per
+/// line to `signatures` and then the closing record, `end`, the number of
+/// lines and the [`FrameSigner::close`] signature; returns the number of
+/// lines. Keep the output next to the recording, at [`signature_path`].
+pub fn sign_jsonl(
+    mut recording: impl BufRead,
+    key: &SigningKey,
+    mut signatures: impl Write,
+) -> io::Result<u64> {
+    let mut signer = FrameSigner::new(key.clone());
+    let mut line = Vec::new();
+    let mut lines = 0;
+    while next_line(&mut recording, &mut line)? {
+        let signature = signer.sign(&line);
+        writeln!(signatures, "{}", to_hex(&signature.to_bytes()))?;
+        lines += 1;
+    }
+    let close = signer.close(lines);
+    writeln!(
+        signatures,
+        "{CLOSE_PREFIX}{lines} {}",
+        to_hex(&close.to_bytes())
+    )?;
+    signatures.flush()?;
+    Ok(lines)
+}
+
+/// Check every line of `recording` against `signatures`, as written by
+/// [`sign_jsonl`], closing record included. Only I/O errors and malformed
+/// signature lines fail.
+pub fn verify_jsonl(
+    mut recording: impl BufRead,
+    mut signatures: impl BufRead,
+    key: &VerifyingKey,
+) -> io::Result<SignatureReport> {
+    let mut verifier = FrameVerifier::new(*key);
+    let mut report = SignatureReport::default();
+    let (mut line, mut hex) = (Vec::new(), Vec::new());
+    let mut close = None;
+    while next_line(&mut recording, &mut line)? {
+        report.lines += 1;
+        if close.is_some() || !next_line(&mut signatures, &mut hex)? {
+            report.unsigned += 1;
+            continue;
+        }
+        match parse_entry(&hex, report.lines)? {
+            Entry::Line(signature) => {
+                if !verifier.verify(&line, &signature) {
+                    report.invalid.push(report.lines);
+                }
+            }
+            Entry::Close(lines, signature) => {
+                close = Some((lines, signature));
+                report.unsigned += 1;
+            }
+        }
+    }
+    while close.is_none() && next_line(&mut signatures, &mut hex)? {
+        match parse_entry(&hex, report.lines + report.surplus + 1)? {
+            Entry::Line(_) => report.surplus += 1,
+            Entry::Close(lines, signature) => close = Some((lines, signature)),
+        }
+    }
+    while next_line(&mut signatures, &mut hex)? {
+        report.surplus += 1;
+    }
+    report.closed = close.is_some_and(|(lines, signature)| {
+        lines == report.lines && verifier.verify_close(lines, &signature)
+    });
+    Ok(report)
+}
+
+impl Recording {
+    /// Decode a recording as [`Recording::read_jsonl`] does, after checking
+    /// it against its `signatures`. Fails with [`io::ErrorKind::InvalidData`]
+    /// unless every line is signed and every signature matches.
+    pub fn read_jsonl_verified(
+        mut reader: impl BufRead,
+        signatures: impl BufRead,
+        key: &VerifyingKey,
+    ) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let report = verify_jsonl(&bytes[..], signatures, key)?;
+        if !report.is_valid() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                report.to_string(),
+            ));
+        }
+        Self::read_jsonl(&bytes[..]).map_err(io::Error::other)
+    }
+}
+
+// The next line into `line`, without its line ending; false at the end.
+fn next_line(reader: &mut impl BufRead, line: &mut Vec<u8>) -> io::Result<bool> {
+    line.clear();
+    if reader.read_until(b'\n', line)? == 0 {
+        return Ok(false);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    }
+    Ok(true)
+}
+
+// One line of a signature file.
+enum Entry {
+    Line(Signature),
+    /// The line count and signature of the closing record.
+    Close(u64, Signature),
+}
+
+fn parse_entry(hex: &[u8], line: u64) -> io::Result<Entry> {
+    let entry = std::str::from_utf8(hex).ok().and_then(|hex| {
+        let hex = hex.trim();
+        match hex.strip_prefix(CLOSE_PREFIX) {
+            Some(close) => {
+                let (lines, hex) = close.split_once(' ')?;
+                Some(Entry::Close(lines.parse().ok()?, parse_signature(hex)?))
+            }
+            None => Some(Entry::Line(parse_signature(hex)?)),
+        }
+    });
+    entry.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("signature of line {line} is not 128 hex digits"),
+        )
+    })
+}
+
+fn parse_signature(hex: &str) -> Option<Signature> {
+    from_hex(hex).map(|bytes| Signature::from_bytes(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORDING: &str = "{\"a\":1}\n{\"b\":2}\n{\"c\":3}\n";
+
+    fn key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    fn sign(recording: &str) -> String {
+        let mut signatures = Vec::new();
+        sign_jsonl(recording.as_bytes(), &key(), &mut signatures).unwrap();
+        String::from_utf8(signatures).unwrap()
+    }
+
+    fn verify(recording: &str, signatures: &str) -> SignatureReport {
+        verify_jsonl(
+            recording.as_bytes(),
+            signatures.as_bytes(),
+            &key().verifying_key(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn signed_recording_verifies() {
+        let report = verify(RECORDING, &sign(RECORDING));
+        assert!(report.is_valid(), "{report}");
+        assert_eq!(report.lines, 3);
+    }
+
+    #[test]
+    fn edited_line_fails_alone() {
+        let signatures = sign(RECORDING);
+        let report = verify(&RECORDING.replace("\"b\":2", "\"b\":9"), &signatures);
+        assert_eq!(report.invalid, [2]);
+        assert!(report.closed);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn truncation_of_both_files_fails() {
+        let signatures = sign(RECORDING);
+        let recording: String = RECORDING
+            .lines()
+            .take(2)
+            .map(|l| format!("{l}\n"))
+            .collect();
+        let signatures: String = signatures
+            .lines()
+            .take(2)
+            .map(|l| format!("{l}\n"))
+            .collect();
+        let report = verify(&recording, &signatures);
+        assert!(report.invalid.is_empty());
+        assert!(!report.closed);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn closing_record_covers_the_line_count() {
+        let signatures = sign(RECORDING);
+        let recording: String = RECORDING
+            .lines()
+            .take(2)
+            .map(|l| format!("{l}\n"))
+            .collect();
+        let mut lines: Vec<&str> = signatures.lines().collect();
+        lines.remove(2);
+        let forged = lines.join("\n").replace("end 3 ", "end 2 ");
+        let report = verify(&recording, &forged);
+        assert!(!report.closed);
+
+        let report = verify(&format!("{RECORDING}{{}}\n"), &signatures);
+        assert_eq!(report.unsigned, 1);
+        assert!(!report.is_valid());
+    }
+}
@@ -0,0 +1,267 @@
+use super::Recording;
+use crate::{
+    AnnotationValueSerDe, ProcessingStepSerDe, SensorDataSerDe, SensorKind, TIMESTAMP_KEY,
+};
+use std::fmt;
+
+/// Annotation keeping a frame's timestamp from before
+/// [`Recording::correct_skew`] moved it.
+pub const ORIGINAL_TIMESTAMP_KEY: &str = "original_timestamp";
+
+/// A constant offset found between the camera's and the IMU's timestamps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClockSkew {
+    /// The sensor whose timestamps are off; the IMU is the reference.
+    pub sensor: SensorKind,
+    /// Seconds the sensor's timestamps are ahead of the IMU's; negative if
+    /// they lag.
+    pub offset: f64,
+    /// Pearson correlation of the two motion signals at `offset`, from -1
+    /// to 1. Below about 0.5 the estimate is doubtful.
+    pub correlation: f64,
+}
+
+/// `Image clock +0.033 s off the IMU (correlation 0.87)`
+impl fmt::Display for ClockSkew {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} clock {:+.3} s off the IMU (correlation {:.2})",
+            self.sensor, self.offset, self.correlation
+        )
+    }
+}
+
+/// Estimates the offset of a recording's camera timestamps against its IMU
+/// timestamps.
+///
+/// The IMU's angular speed and the camera's frame-to-frame change, the
+/// mean absolute difference of consecutive frames, both rise when the
+/// vehicle turns or shakes. Both signals are resampled on a common grid
+/// and cross-correlated; the lag with the highest correlation is the
+/// offset. The recording needs one camera and one IMU, e.g. one vehicle's
+/// part of [`Recording::split_by_vehicle`], with the timestamps
+/// [`SensorDataSerDe::capture`] keeps, and enough motion to correlate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkewEstimator {
+    max_offset: f64,
+    step: Option<f64>,
+}
+
+impl Default for SkewEstimator {
+    fn default() -> Self {
+        Self {
+            max_offset: 0.5,
+            step: None,
+        }
+    }
+}
+
+impl SkewEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Largest offset searched, in seconds; 0.5 by default.
+    pub fn with_max_offset(mut self, seconds: f64) -> Self {
+        self.max_offset = seconds.abs();
+        self
+    }
+
+    /// Resolution of the search grid, in seconds; by default the median
+    /// interval of the IMU samples. The result is refined below it.
+    pub fn with_step(mut self, seconds: f64) -> Self {
+        self.step = Some(seconds);
+        self
+    }
+
+    /// The camera's offset, if the recording has enough timestamped IMU
+    /// and camera frames that overlap.
+    pub fn estimate(&self, recording: &Recording) -> Option<ClockSkew> {
+        let imu = imu_signal(&recording.frames);
+        let camera = camera_signal(&recording.frames);
+        let step = match self.step {
+            Some(step) => step,
+            None => median_interval(&imu)?,
+        };
+        if !step.is_finite() || step <= 0.0 {
+            return None;
+        }
+        let start = imu.first()?.0.max(camera.first()?.0);
+        let end = imu.last()?.0.min(camera.last()?.0);
+        let max_lag = (self.max_offset / step).round() as i64;
+        let samples = ((end - start) / step).floor() as i64 + 1;
+        // at least a few samples must overlap even at the largest lag
+        if samples < 2 * max_lag + 8 {
+            return None;
+        }
+        let reference = resample(&imu, start, step, samples as usize);
+        let other = resample(&camera, start, step, samples as usize);
+
+        let scores: Vec<(i64, f64)> = (-max_lag..=max_lag)
+            .filter_map(|lag| Some((lag, lagged_correlation(&reference, &other, lag)?)))
+            .collect();
+        let best = scores
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.1.total_cmp(&b.1))?
+            .0;
+        let (lag, correlation) = scores[best];
+        // parabola through the best lag and its neighbours
+        let refine = match (
+            best.checked_sub(1).and_then(|i| scores.get(i)),
+            scores.get(best + 1),
+        ) {
+            (Some(&(_, before)), Some(&(_, after))) => {
+                let curvature = before - 2.0 * correlation + after;
+                if curvature < 0.0 {
+                    0.5 * (before - after) / curvature
+                } else {
+                    0.0
+                }
+            }
+            _ => 0.0,
+        };
+        Some(ClockSkew {
+            sensor: SensorKind::Image,
+            offset: (lag as f64 + refine) * step,
+            correlation,
+        })
+    }
+}
+
+impl Recording {
+    /// Move the timestamps of the `skew.sensor` frames back by
+    /// `skew.offset`, keeping each one's first original timestamp under
+    /// [`ORIGINAL_TIMESTAMP_KEY`], and record it. Returns the number of
+    /// frames moved.
+    pub fn correct_skew(&mut self, skew: &ClockSkew) -> usize {
+        let input_hash = self.input_hash();
+        let mut moved = 0;
+        for frame in &mut self.frames {
+            if frame.kind() != skew.sensor {
+                continue;
+            }
+            let Some(timestamp) = frame.timestamp() else {
+                continue;
+            };
+            let annotations = frame.annotations_mut();
+            if annotations.get(ORIGINAL_TIMESTAMP_KEY).is_none() {
+                annotations.insert(
+                    ORIGINAL_TIMESTAMP_KEY,
+                    AnnotationValueSerDe::Float(timestamp),
+                );
+            }
+            annotations.insert(
+                TIMESTAMP_KEY,
+                AnnotationValueSerDe::Float(timestamp - skew.offset),
+            );
+            moved += 1;
+        }
+        let step = ProcessingStepSerDe::new("correct_skew")
+            .with_param("sensor", format!("{:?}", skew.sensor))
+            .with_param("offset", skew.offset)
+            .with_param("correlation", skew.correlation);
+        self.record(step, input_hash);
+        moved
+    }
+}
+
+// (timestamp, angular speed) of every timestamped IMU frame, in time order
+fn imu_signal(frames: &[SensorDataSerDe]) -> Vec<(f64, f64)> {
+    let mut signal: Vec<(f64, f64)> = frames
+        .iter()
+        .filter_map(|frame| match frame {
+            SensorDataSerDe::Imu(imu) => {
+                let g = imu.gyroscope;
+                let speed = (g.x * g.x + g.y * g.y + g.z * g.z).sqrt();
+                Some((frame.timestamp()?, speed as f64))
+            }
+            _ => None,
+        })
+        .collect();
+    signal.sort_by(|a, b| a.0.total_cmp(&b.0));
+    signal
+}
+
+// (midpoint, mean absolute change) of consecutive same-sized camera
+// frames, in time order
+fn camera_signal(frames: &[SensorDataSerDe]) -> Vec<(f64, f64)> {
+    let mut images: Vec<_> = frames
+        .iter()
+        .filter_map(|frame| match frame {
+            SensorDataSerDe::Image(image) => Some((frame.timestamp()?, image)),
+            _ => None,
+        })
+        .collect();
+    images.sort_by(|a, b| a.0.total_cmp(&b.0));
+    images
+        .windows(2)
+        .filter_map(|pair| {
+            let [(t0, a), (t1, b)] = pair else {
+                return None;
+            };
+            if a.array.dim() != b.array.dim() || a.array.is_empty() {
+                return None;
+            }
+            let change: u64 = a
+                .array
+                .iter()
+                .zip(b.array.iter())
+                .map(|(p, q)| {
+                    u64::from(p.r.abs_diff(q.r))
+                        + u64::from(p.g.abs_diff(q.g))
+                        + u64::from(p.b.abs_diff(q.b))
+                })
+                .sum();
+            Some((0.5 * (t0 + t1), change as f64 / a.array.len() as f64))
+        })
+        .collect()
+}
+
+fn median_interval(signal: &[(f64, f64)]) -> Option<f64> {
+    let mut intervals: Vec<f64> = signal.windows(2).map(|w| w[1].0 - w[0].0).collect();
+    if intervals.is_empty() {
+        return None;
+    }
+    intervals.sort_by(f64::total_cmp);
+    Some(intervals[intervals.len() / 2])
+}
+
+// `signal` linearly interpolated at `start + i * step`
+fn resample(signal: &[(f64, f64)], start: f64, step: f64, samples: usize) -> Vec<f64> {
+    let mut next = 0;
+    (0..samples)
+        .map(|i| {
+            let t = start + i as f64 * step;
+            while next + 1 < signal.len() && signal[next + 1].0 < t {
+                next += 1;
+            }
+            let (t0, v0) = signal[next];
+            match signal.get(next + 1) {
+                Some(&(t1, v1)) if t1 > t0 => {
+                    v0 + (v1 - v0) * ((t - t0) / (t1 - t0)).clamp(0.0, 1.0)
+                }
+                _ => v0,
+            }
+        })
+        .collect()
+}
+
+// Pearson correlation of `a[i]` with `b[i + lag]` where both exist
+fn lagged_correlation(a: &[f64], b: &[f64], lag: i64) -> Option<f64> {
+    let pairs: Vec<(f64, f64)> = (0..a.len() as i64)
+        .filter_map(|i| Some((a[i as usize], *b.get(usize::try_from(i + lag).ok()?)?)))
+        .collect();
+    let n = pairs.len() as f64;
+    let (mean_a, mean_b) = pairs
+        .iter()
+        .fold((0.0, 0.0), |(sa, sb), (x, y)| (sa + x / n, sb + y / n));
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in &pairs {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a) * (x - mean_a);
+        var_b += (y - mean_b) * (y - mean_b);
+    }
+    (var_a > 0.0 && var_b > 0.0).then(|| cov / (var_a * var_b).sqrt())
+}
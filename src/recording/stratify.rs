@@ -0,0 +1,78 @@
+use super::Recording;
+use crate::augment::NoiseRng;
+use crate::conditions::{self, ConditionsBucket, ConditionsTagger};
+use crate::{ProcessingStepSerDe, fleet};
+use std::collections::BTreeMap;
+
+impl Recording {
+    /// Tag every frame with the [`ConditionsBucket`] of the header's weather
+    /// and record it; frames that name a bucket keep it. Returns the bucket,
+    /// or `None` and changes nothing if the header has no weather.
+    pub fn tag_conditions(&mut self) -> Option<ConditionsBucket> {
+        let weather = self.header.as_ref()?.weather?;
+        let mut tagger = ConditionsTagger::new(weather);
+        self.apply(&mut tagger);
+        Some(tagger.bucket())
+    }
+
+    /// Up to `per_bucket` ticks of each [`ConditionsBucket`], drawn at
+    /// random with `seed`, for a dataset that is balanced across weather
+    /// and time of day.
+    ///
+    /// A tick is all frames of one vehicle with the same frame number, so
+    /// the sensors of a tick stay together; frames without a frame number
+    /// are a tick each. Untagged ticks form a bucket of their own. The
+    /// frames keep their order; the sample has the header, with the step
+    /// recorded, and no gaps.
+    pub fn sample_stratified(&self, per_bucket: usize, seed: u64) -> Recording {
+        let mut ticks: Vec<Vec<usize>> = Vec::new();
+        let mut tick_of = BTreeMap::new();
+        for (i, frame) in self.frames.iter().enumerate() {
+            match frame.frame_number() {
+                Some(n) => {
+                    let tick = *tick_of
+                        .entry((fleet::vehicle(frame), n))
+                        .or_insert_with(|| {
+                            ticks.push(Vec::new());
+                            ticks.len() - 1
+                        });
+                    ticks[tick].push(i);
+                }
+                None => ticks.push(vec![i]),
+            }
+        }
+
+        let mut buckets = BTreeMap::<Option<ConditionsBucket>, Vec<usize>>::new();
+        for (tick, frames) in ticks.iter().enumerate() {
+            let bucket = frames
+                .iter()
+                .find_map(|&i| conditions::conditions(&self.frames[i]));
+            buckets.entry(bucket).or_default().push(tick);
+        }
+
+        // partial Fisher-Yates per bucket
+        let mut rng = NoiseRng::new(seed);
+        let mut keep: Vec<usize> = Vec::new();
+        for candidates in buckets.values_mut() {
+            let n = per_bucket.min(candidates.len());
+            for k in 0..n {
+                let j = k + (rng.next_u64() % (candidates.len() - k) as u64) as usize;
+                candidates.swap(k, j);
+            }
+            keep.extend(candidates[..n].iter().flat_map(|&tick| &ticks[tick]));
+        }
+        keep.sort_unstable();
+
+        let mut sample = Recording {
+            header: self.header.clone(),
+            frames: keep.iter().map(|&i| self.frames[i].clone()).collect(),
+            gaps: Vec::new(),
+        };
+        let step = ProcessingStepSerDe::new("sample_stratified")
+            .with_param("per_bucket", per_bucket as i64)
+            .with_param("seed", seed as i64)
+            .with_param("buckets", buckets.len() as i64);
+        sample.record(step, self.input_hash());
+        sample
+    }
+}
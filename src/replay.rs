@@ -0,0 +1,129 @@
+//! Drive a live simulator with recorded controls.
+//!
+//! [`ControlReplay`] applies a logged stream of [`ControlSampleSerDe`] to
+//! vehicles of a running server, one world tick per recorded frame, so a
+//! drive can be reproduced closed-loop: the sensors see the replayed
+//! vehicles react to the new world, not a playback of the old one. The
+//! world must be in synchronous mode with the fixed step of the recording,
+//! see [`crate::SimulationSettingsSerDe`].
+
+use crate::ControlSampleSerDe;
+use carla::client::{Vehicle, World};
+use carla::rpc::VehicleControl;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Recorded controls and the live vehicles they are applied to.
+#[derive(Debug, Default)]
+pub struct ControlReplay {
+    samples: Vec<ControlSampleSerDe>,
+    vehicles: BTreeMap<String, Vehicle>,
+}
+
+/// What [`ControlReplay::run`] did.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplayReport {
+    pub ticks: u64,
+    /// Controls handed to a vehicle.
+    pub applied: u64,
+    /// Controls skipped because no vehicle was bound to their name.
+    pub skipped: u64,
+    /// Frame number of the live world after the last tick.
+    pub last_frame: Option<u64>,
+}
+
+impl ControlReplay {
+    /// Samples may come in any order; they are replayed by frame number.
+    pub fn new(mut samples: Vec<ControlSampleSerDe>) -> Self {
+        samples.sort_by_key(|s| s.frame);
+        Self {
+            samples,
+            vehicles: BTreeMap::new(),
+        }
+    }
+
+    /// Apply the samples recorded for `name` to `vehicle`. Actor ids change
+    /// between runs, so vehicles are matched by the name in the samples.
+    pub fn bind(mut self, name: impl Into<String>, vehicle: Vehicle) -> Self {
+        self.vehicles.insert(name.into(), vehicle);
+        self
+    }
+
+    /// Names in the samples without a bound vehicle.
+    pub fn unbound(&self) -> BTreeSet<&str> {
+        self.samples
+            .iter()
+            .map(|s| s.vehicle.as_str())
+            .filter(|name| !self.vehicles.contains_key(*name))
+            .collect()
+    }
+
+    /// Recorded frames from the first to the last sample.
+    pub fn frames(&self) -> Option<(u64, u64)> {
+        Some((self.samples.first()?.frame, self.samples.last()?.frame))
+    }
+
+    /// The frame numbers with samples, in order; [`run`](Self::run) ticks
+    /// once for each.
+    pub fn recorded_frames(&self) -> impl Iterator<Item = u64> + '_ {
+        self.frame_batches().map(|batch| batch[0].frame)
+    }
+
+    fn frame_batches(&self) -> impl Iterator<Item = &[ControlSampleSerDe]> {
+        self.samples.chunk_by(|a, b| a.frame == b.frame)
+    }
+
+    pub fn run(&self, world: &mut World) -> ReplayReport {
+        self.run_with(world, |_, _| {})
+    }
+
+    /// Like [`run`](Self::run), calling `on_tick(recorded, live)` with both
+    /// frame numbers after every tick, e.g. to collect sensor data. Frames
+    /// without samples are not ticked: the replay steps from one recorded
+    /// frame to the next, the vehicles keeping their last control.
+    pub fn run_with(&self, world: &mut World, mut on_tick: impl FnMut(u64, u64)) -> ReplayReport {
+        let mut report = ReplayReport::default();
+        for batch in self.frame_batches() {
+            let frame = batch[0].frame;
+            for sample in batch {
+                match self.vehicles.get(&sample.vehicle) {
+                    Some(vehicle) => {
+                        vehicle.apply_control(&VehicleControl::from(&sample.control));
+                        report.applied += 1;
+                    }
+                    None => report.skipped += 1,
+                }
+            }
+            let live = world.tick();
+            report.ticks += 1;
+            report.last_frame = Some(live);
+            on_tick(frame, live);
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(frame: u64, vehicle: &str) -> ControlSampleSerDe {
+        ControlSampleSerDe {
+            frame,
+            vehicle: vehicle.into(),
+            control: Default::default(),
+        }
+    }
+
+    #[test]
+    fn ticks_only_recorded_frames() {
+        let replay = ControlReplay::new(vec![
+            sample(5_000_000, "ego"),
+            sample(12, "ego"),
+            sample(12, "npc"),
+            sample(40, "ego"),
+        ]);
+        assert_eq!(replay.frames(), Some((12, 5_000_000)));
+        let frames: Vec<_> = replay.recorded_frames().collect();
+        assert_eq!(frames, vec![12, 40, 5_000_000]);
+    }
+}
@@ -0,0 +1,247 @@
+//! Static, self-contained HTML summary of a recording.
+//!
+//! Feed every frame of a run to [`Report::add`] together with its
+//! simulation timestamp, then render it with [`Report::write_html`]. The
+//! output has no external references: thumbnails are inlined as BMP data
+//! URIs and charts and the GNSS trace as SVG from [`crate::plot`], so the
+//! file can be attached to a CI run as is.
+
+use crate::captions::event_summary;
+use crate::plot::{Escaped, GnssMap, ImuCharts, Trace};
+use crate::{SensorDataSerDe, SensorKind};
+use carla::sensor::data::Color;
+use ndarray::Array2;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+const THUMBNAIL_WIDTH: usize = 160;
+
+#[derive(Clone, Debug)]
+pub struct Report {
+    title: String,
+    max_thumbnails: usize,
+    thumbnail_stride: usize,
+    images_seen: usize,
+    // encoded BMPs
+    thumbnails: Vec<(f64, Vec<u8>)>,
+    counts: BTreeMap<SensorKind, u64>,
+    time_range: Option<(f64, f64)>,
+    imu: ImuCharts,
+    gnss: GnssMap,
+    incidents: Vec<(f64, SensorKind, String)>,
+}
+
+impl Report {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            max_thumbnails: 12,
+            thumbnail_stride: 1,
+            images_seen: 0,
+            thumbnails: Vec::new(),
+            counts: BTreeMap::new(),
+            time_range: None,
+            imu: ImuCharts::default(),
+            gnss: GnssMap::new("GNSS trace").with_trace(Trace::new("GNSS")),
+            incidents: Vec::new(),
+        }
+    }
+
+    /// Upper bound on the camera thumbnails kept, 12 by default. They are
+    /// spread evenly over the recording.
+    pub fn with_max_thumbnails(mut self, n: usize) -> Self {
+        self.max_thumbnails = n;
+        self
+    }
+
+    pub fn add(&mut self, timestamp: f64, frame: &SensorDataSerDe) {
+        *self.counts.entry(frame.kind()).or_default() += 1;
+        self.time_range = Some(match self.time_range {
+            Some((lo, hi)) => (lo.min(timestamp), hi.max(timestamp)),
+            None => (timestamp, timestamp),
+        });
+
+        match frame {
+            SensorDataSerDe::Image(image) => {
+                if self.images_seen.is_multiple_of(self.thumbnail_stride) && self.max_thumbnails > 0
+                {
+                    self.thumbnails.push((
+                        timestamp,
+                        encode_bmp(&downscale(&image.array, THUMBNAIL_WIDTH)),
+                    ));
+                    // keep every other one and sample half as often from now on
+                    if self.thumbnails.len() > self.max_thumbnails {
+                        let mut i = 0;
+                        self.thumbnails.retain(|_| {
+                            i += 1;
+                            i % 2 == 1
+                        });
+                        self.thumbnail_stride *= 2;
+                    }
+                }
+                self.images_seen += 1;
+            }
+            SensorDataSerDe::Imu(imu) => self.imu.push(timestamp, imu),
+            SensorDataSerDe::Gnss(gnss) => self.gnss.traces[0].push(gnss),
+            _ => {
+                if let Some(text) = event_summary(frame) {
+                    self.incidents.push((timestamp, frame.kind(), text));
+                }
+            }
+        }
+    }
+
+    pub fn write_html(&self, out: &mut impl Write) -> fmt::Result {
+        let title = Escaped(&self.title);
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(
+            out,
+            "<html><head><meta charset=\"utf-8\"><title>{title}</title>"
+        )?;
+        writeln!(
+            out,
+            "<style>body{{font-family:sans-serif;margin:2em}}table{{border-collapse:collapse}}\
+             td,th{{border:1px solid #ccc;padding:4px 8px;text-align:left}}\
+             figure{{display:inline-block;margin:4px}}figcaption{{font-size:small}}</style>"
+        )?;
+        writeln!(out, "</head><body>")?;
+        writeln!(out, "<h1>{title}</h1>")?;
+
+        writeln!(out, "<h2>Overview</h2>")?;
+        if let Some((lo, hi)) = self.time_range {
+            writeln!(out, "<p>{:.3} s to {:.3} s ({:.3} s)</p>", lo, hi, hi - lo)?;
+        }
+        writeln!(out, "<table><tr><th>Sensor</th><th>Frames</th></tr>")?;
+        for (kind, n) in &self.counts {
+            writeln!(out, "<tr><td>{kind:?}</td><td>{n}</td></tr>")?;
+        }
+        writeln!(out, "</table>")?;
+
+        writeln!(out, "<h2>Incidents</h2>")?;
+        if self.incidents.is_empty() {
+            writeln!(out, "<p>None.</p>")?;
+        } else {
+            writeln!(
+                out,
+                "<table><tr><th>Time [s]</th><th>Sensor</th><th>Description</th></tr>"
+            )?;
+            for (t, kind, text) in &self.incidents {
+                writeln!(
+                    out,
+                    "<tr><td>{t:.3}</td><td>{kind:?}</td><td>{}</td></tr>",
+                    Escaped(text)
+                )?;
+            }
+            writeln!(out, "</table>")?;
+        }
+
+        if !self.thumbnails.is_empty() {
+            writeln!(out, "<h2>Camera</h2>")?;
+            for (t, bmp) in &self.thumbnails {
+                write!(out, "<figure><img src=\"data:image/bmp;base64,")?;
+                write_base64(out, bmp)?;
+                writeln!(out, "\"><figcaption>{t:.3} s</figcaption></figure>")?;
+            }
+        }
+
+        if !self.imu.is_empty() {
+            writeln!(out, "<h2>IMU</h2>")?;
+            self.imu.accelerometer.write_svg(out)?;
+            self.imu.gyroscope.write_svg(out)?;
+        }
+
+        // drawn without map tiles, so the file keeps no external references
+        if !self.gnss.is_empty() {
+            writeln!(out, "<h2>GNSS</h2>")?;
+            self.gnss.write_svg(out)?;
+        }
+
+        writeln!(out, "</body></html>")
+    }
+}
+
+// nearest-neighbour downscale to at most `max_width` columns
+pub(crate) fn downscale(array: &Array2<Color>, max_width: usize) -> Array2<Color> {
+    let (h, w) = array.dim();
+    if w <= max_width {
+        return array.clone();
+    }
+    let th = (h * max_width / w).max(1);
+    Array2::from_shape_fn((th, max_width), |(y, x)| {
+        let c = &array[(y * h / th, x * w / max_width)];
+        Color {
+            b: c.b,
+            g: c.g,
+            r: c.r,
+            a: c.a,
+        }
+    })
+}
+
+// 24-bit bottom-up BMP, rows padded to 4 bytes
+pub(crate) fn encode_bmp(array: &Array2<Color>) -> Vec<u8> {
+    let (h, w) = array.dim();
+    let stride = (w * 3).div_ceil(4) * 4;
+    let size = 54 + stride * h;
+    let mut out = Vec::with_capacity(size);
+    out.extend_from_slice(b"BM");
+    out.extend_from_slice(&(size as u32).to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&54u32.to_le_bytes());
+    out.extend_from_slice(&40u32.to_le_bytes());
+    out.extend_from_slice(&(w as i32).to_le_bytes());
+    out.extend_from_slice(&(h as i32).to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&24u16.to_le_bytes());
+    out.extend_from_slice(&[0; 24]);
+    for y in (0..h).rev() {
+        for c in array.row(y) {
+            out.extend_from_slice(&[c.b, c.g, c.r]);
+        }
+        out.resize(out.len() + stride - w * 3, 0);
+    }
+    out
+}
+
+fn write_base64(out: &mut impl Write, bytes: &[u8]) -> fmt::Result {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.write_char(TABLE[(n >> (18 - 6 * i) & 63) as usize] as char)?;
+            } else {
+                out.write_char('=')?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_gnss_fixes() {
+        let mut report = Report::new("run");
+        let mut html = String::new();
+        report.write_html(&mut html).unwrap();
+        assert!(!html.contains("<h2>GNSS</h2>"));
+
+        let mut gnss = crate::fixtures::gnss_frame();
+        for i in 0..5 {
+            gnss.longitude += 1e-4;
+            report.add(i as f64, &SensorDataSerDe::Gnss(gnss.clone()));
+        }
+        html.clear();
+        report.write_html(&mut html).unwrap();
+        let map = &html[html.find("<h2>GNSS</h2>").unwrap()..];
+        assert!(map.contains("<polyline") && map.contains("</svg>"));
+    }
+}
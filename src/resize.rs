@@ -0,0 +1,313 @@
+//! Resizing and letterboxing of camera frames, e.g. to the input size of a
+//! network, with the intrinsics following along so projections into the
+//! exported images stay valid.
+
+use crate::{
+    CameraInfoSerDe, INTRINSICS_KEY, ImageEventSerDe, ImageHashSerDe, ProcessingStepSerDe,
+    SensorDataSerDe, SensorDescriptionSerDe, pipeline::FrameTransform,
+};
+use carla::sensor::data::Color;
+use ndarray::Array2;
+
+/// How an image is fit into the target size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fit {
+    /// Scale each axis on its own; the aspect ratio changes, so `fx` and
+    /// `fy` differ afterwards.
+    Stretch,
+    /// Scale both axes alike until the image fits, then pad the rest with
+    /// the `[r, g, b]` fill color, centered.
+    Letterbox { fill: [u8; 3] },
+}
+
+/// Where the scaled image lands in the target.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResizeGeometry {
+    pub scale_x: f32,
+    pub scale_y: f32,
+    /// Padding left of and above the scaled image, in pixels.
+    pub left: usize,
+    pub top: usize,
+    /// Size of the scaled image, without the padding.
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Resizes camera frames to a fixed size and rewrites their intrinsics.
+///
+/// Pixels are sampled bilinearly. The new `[fx, fy, cx, cy]` are kept
+/// under [`INTRINSICS_KEY`] and the fov is updated, so
+/// [`CameraInfoSerDe::from`] an image gives its intrinsics after the
+/// resize. A hash the image had is recomputed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageResize {
+    width: usize,
+    height: usize,
+    fit: Fit,
+}
+
+impl ImageResize {
+    pub fn stretch(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            fit: Fit::Stretch,
+        }
+    }
+
+    /// Letterbox with black padding.
+    pub fn letterbox(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            fit: Fit::Letterbox { fill: [0, 0, 0] },
+        }
+    }
+
+    /// Pad with `[r, g, b]` instead, e.g. the gray `[114, 114, 114]` YOLO
+    /// models are trained with. Turns a stretch into a letterbox.
+    pub fn with_fill(mut self, fill: [u8; 3]) -> Self {
+        self.fit = Fit::Letterbox { fill };
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn fit(&self) -> Fit {
+        self.fit
+    }
+
+    /// Scale and padding for a `width` x `height` source image.
+    pub fn geometry(&self, width: usize, height: usize) -> ResizeGeometry {
+        let (w, h) = (width.max(1) as f32, height.max(1) as f32);
+        match self.fit {
+            Fit::Stretch => ResizeGeometry {
+                scale_x: self.width as f32 / w,
+                scale_y: self.height as f32 / h,
+                left: 0,
+                top: 0,
+                width: self.width,
+                height: self.height,
+            },
+            Fit::Letterbox { .. } => {
+                let scale = (self.width as f32 / w).min(self.height as f32 / h);
+                let inner_w = ((w * scale).round() as usize).min(self.width);
+                let inner_h = ((h * scale).round() as usize).min(self.height);
+                ResizeGeometry {
+                    scale_x: inner_w as f32 / w,
+                    scale_y: inner_h as f32 / h,
+                    left: (self.width - inner_w) / 2,
+                    top: (self.height - inner_h) / 2,
+                    width: inner_w,
+                    height: inner_h,
+                }
+            }
+        }
+    }
+
+    /// The intrinsics of a camera with `info` after the resize.
+    pub fn camera_info(&self, info: &CameraInfoSerDe) -> CameraInfoSerDe {
+        let g = self.geometry(info.width, info.height);
+        info.scaled(g.width, g.height)
+            .padded(g.left, g.top, self.width, self.height)
+    }
+
+    /// Update the intrinsics of the RGB cameras in `sensors`, e.g. those of
+    /// the recording header, to match resized frames; the other cameras'
+    /// frames are not resized.
+    pub fn update_sensors(&self, sensors: &mut [SensorDescriptionSerDe]) {
+        let rgb = sensors.iter_mut().filter(|s| s.is_rgb_camera());
+        for camera in rgb.filter_map(|s| s.camera.as_mut()) {
+            *camera = self.camera_info(camera);
+        }
+    }
+
+    pub fn resize(&self, image: &mut ImageEventSerDe) {
+        let (h, w) = image.array.dim();
+        if h == 0 || w == 0 || self.width == 0 || self.height == 0 {
+            return;
+        }
+        let info = self.camera_info(&CameraInfoSerDe::from(&*image));
+        let g = self.geometry(w, h);
+        let [r, green, b] = match self.fit {
+            Fit::Letterbox { fill } => fill,
+            Fit::Stretch => [0; 3],
+        };
+        let fill = || Color {
+            b,
+            g: green,
+            r,
+            a: 255,
+        };
+        let src = &image.array;
+        image.array = Array2::from_shape_fn((self.height, self.width), |(y, x)| {
+            let (Some(ix), Some(iy)) = (x.checked_sub(g.left), y.checked_sub(g.top)) else {
+                return fill();
+            };
+            if ix >= g.width || iy >= g.height {
+                return fill();
+            }
+            // pixel centers map onto pixel centers
+            let sx = ((ix as f32 + 0.5) / g.scale_x - 0.5).clamp(0.0, (w - 1) as f32);
+            let sy = ((iy as f32 + 0.5) / g.scale_y - 0.5).clamp(0.0, (h - 1) as f32);
+            bilinear(src, sx, sy)
+        });
+        image.height = self.height;
+        image.width = self.width;
+        image.reconcile_shape();
+        image.fov_angle = info.fov;
+        image
+            .annotations
+            .insert(INTRINSICS_KEY, info.to_annotation());
+        if image.hash.is_some() {
+            image.hash = Some(ImageHashSerDe::from_array(image.array.view()));
+        }
+    }
+}
+
+impl FrameTransform for ImageResize {
+    fn accepts(&self, frame: &SensorDataSerDe) -> bool {
+        matches!(frame, SensorDataSerDe::Image(_))
+    }
+
+    fn apply(&mut self, frame: &mut SensorDataSerDe) {
+        if let SensorDataSerDe::Image(image) = frame {
+            self.resize(image);
+        }
+    }
+
+    fn describe(&self) -> ProcessingStepSerDe {
+        let step = ProcessingStepSerDe::new("image_resize")
+            .with_param("width", self.width as i64)
+            .with_param("height", self.height as i64);
+        match self.fit {
+            Fit::Stretch => step.with_param("fit", "stretch"),
+            Fit::Letterbox { fill } => step.with_param("fit", "letterbox").with_param(
+                "fill",
+                format!("#{:02x}{:02x}{:02x}", fill[0], fill[1], fill[2]),
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "recording")]
+impl crate::recording::Recording {
+    /// Resize every camera frame and record it, updating the camera
+    /// intrinsics of the header too.
+    pub fn resize_images(&mut self, resize: &ImageResize) {
+        let mut transform = *resize;
+        self.apply(&mut transform);
+        if let Some(header) = &mut self.header {
+            resize.update_sensors(&mut header.sensors);
+        }
+    }
+}
+
+pub(crate) fn bilinear(src: &Array2<Color>, x: f32, y: f32) -> Color {
+    let (h, w) = src.dim();
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+    let mix = |channel: fn(&Color) -> u8| {
+        let top = channel(&src[(y0, x0)]) as f32 * (1.0 - fx) + channel(&src[(y0, x1)]) as f32 * fx;
+        let bottom =
+            channel(&src[(y1, x0)]) as f32 * (1.0 - fx) + channel(&src[(y1, x1)]) as f32 * fx;
+        (top * (1.0 - fy) + bottom * fy).round().clamp(0.0, 255.0) as u8
+    };
+    Color {
+        b: mix(|c| c.b),
+        g: mix(|c| c.g),
+        r: mix(|c| c.r),
+        a: mix(|c| c.a),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AnnotationsSerDe;
+    use nalgebra::Isometry3;
+
+    fn sensor(type_id: &str) -> SensorDescriptionSerDe {
+        SensorDescriptionSerDe {
+            id: 1,
+            type_id: type_id.into(),
+            role_name: String::new(),
+            mount: Isometry3::identity(),
+            camera: Some(CameraInfoSerDe::from_fov(800, 600, 90.0)),
+        }
+    }
+
+    fn frame(height: usize, width: usize) -> ImageEventSerDe {
+        ImageEventSerDe {
+            height,
+            width,
+            len: height * width,
+            is_empty: false,
+            fov_angle: 90.0,
+            array: Array2::from_elem(
+                (height, width),
+                Color {
+                    b: 0,
+                    g: 0,
+                    r: 0,
+                    a: 255,
+                },
+            ),
+            augmentations: Vec::new(),
+            hash: None,
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+
+    #[test]
+    fn letterbox_centers_the_scaled_image() {
+        let g = ImageResize::letterbox(640, 640).geometry(800, 600);
+        assert_eq!((g.width, g.height), (640, 480));
+        assert_eq!((g.left, g.top), (0, 80));
+        assert_eq!(g.scale_x, 0.8);
+        assert_eq!(g.scale_y, 0.8);
+    }
+
+    #[test]
+    fn resized_intrinsics_match_the_header() {
+        let resize = ImageResize::letterbox(640, 640);
+        let mut image = frame(600, 800);
+        resize.resize(&mut image);
+        let mut sensors = [sensor("sensor.camera.rgb")];
+        resize.update_sensors(&mut sensors);
+        let (info, camera) = (CameraInfoSerDe::from(&image), sensors[0].camera.unwrap());
+        assert_eq!((info.width, info.height), (640, 640));
+        assert!((info.fx - camera.fx).abs() < 1e-3);
+        assert!((info.cy - camera.cy).abs() < 1e-3);
+    }
+
+    #[test]
+    fn resizes_landscape_to_portrait() {
+        let resize = ImageResize::stretch(600, 800);
+        let mut image = frame(600, 800);
+        resize.resize(&mut image);
+        assert_eq!(image.array.dim(), (800, 600));
+        assert_eq!((image.height, image.width), (800, 600));
+        assert_eq!(image.validate(), Ok(()));
+        let info = CameraInfoSerDe::from(&image);
+        assert_eq!((info.width, info.height), (600, 800));
+        assert!((info.cx - 300.0).abs() < 1e-3);
+        assert!((info.cy - 400.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn updates_only_rgb_cameras() {
+        let resize = ImageResize::stretch(400, 300);
+        let mut sensors = [sensor("sensor.camera.rgb"), sensor("sensor.camera.depth")];
+        resize.update_sensors(&mut sensors);
+        assert_eq!(sensors[0].camera.unwrap().width, 400);
+        assert_eq!(sensors[1].camera.unwrap().width, 800);
+    }
+}
@@ -0,0 +1,229 @@
+//! Rolling-shutter artifacts on CARLA's global-shutter camera frames, to
+//! test perception against the skew and wobble of real CMOS sensors.
+
+use crate::{
+    AnnotationValueSerDe, CameraInfoSerDe, ImageEventSerDe, ImageHashSerDe, ProcessingStepSerDe,
+    SensorDataSerDe, pipeline::FrameTransform, resize::bilinear,
+};
+use carla::sensor::data::Color;
+use nalgebra::{Isometry3, Rotation3, UnitQuaternion, Vector3};
+use ndarray::Array2;
+
+/// Annotation of a frame given a rolling shutter: the readout time in
+/// seconds, then the angular velocity used, in rad/s on the camera's CARLA
+/// axes.
+pub const ROLLING_SHUTTER_KEY: &str = "rolling_shutter";
+
+/// Resamples camera frames as if their rows were read out one after the
+/// other while the vehicle turned.
+///
+/// Row `y` of `height` rows is exposed `(y / height - 0.5) * readout`
+/// seconds after the frame's timestamp, so the middle row stays where the
+/// global shutter put it. The motion is the angular velocity on CARLA's
+/// axes (x forward, y right, z up) as CARLA's IMU reports it, positive z
+/// turning right. IMU frames passing through the transform replace it, so
+/// a recording's own ego motion drives the images that follow; set the
+/// camera's mount relative to the IMU with [`with_mounts`]. Only rotation
+/// is simulated: without depth, translation cannot be, and its artifacts
+/// are small beyond a few meters. Pixels the input does not cover are
+/// black with an alpha of 0; a hash the image had is recomputed.
+///
+/// [`with_mounts`]: Self::with_mounts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RollingShutter {
+    readout: f64,
+    angular_velocity: Vector3<f32>,
+    imu_to_camera: UnitQuaternion<f32>,
+    follow_imu: bool,
+}
+
+impl RollingShutter {
+    /// A shutter taking `readout` seconds from the first row to the last,
+    /// e.g. 0.03 for a typical automotive sensor.
+    pub fn new(readout: f64) -> Self {
+        Self {
+            readout,
+            angular_velocity: Vector3::zeros(),
+            imu_to_camera: UnitQuaternion::identity(),
+            follow_imu: true,
+        }
+    }
+
+    /// A fixed angular velocity in rad/s, on the IMU's axes; IMU frames no
+    /// longer replace it.
+    pub fn with_angular_velocity(mut self, angular_velocity: Vector3<f32>) -> Self {
+        self.angular_velocity = angular_velocity;
+        self.follow_imu = false;
+        self
+    }
+
+    /// The mounts of the camera and the IMU on the vehicle, e.g. from the
+    /// sensor descriptions of the recording header.
+    pub fn with_mounts(mut self, camera: &Isometry3<f32>, imu: &Isometry3<f32>) -> Self {
+        self.imu_to_camera = camera.rotation.inverse() * imu.rotation;
+        self
+    }
+
+    /// The angular velocity the next frame gets, in rad/s on the IMU's axes.
+    pub fn angular_velocity(&self) -> Vector3<f32> {
+        self.angular_velocity
+    }
+
+    pub fn shutter(&self, image: &mut ImageEventSerDe) {
+        let (h, w) = image.array.dim();
+        if h == 0 || w == 0 {
+            return;
+        }
+        let info = CameraInfoSerDe::from(&*image);
+        let omega = self.imu_to_camera * self.angular_velocity;
+        // CARLA's axes are left-handed and the optical ones right-handed,
+        // so the pseudovector flips sign on the way
+        let optical = Vector3::new(-omega.y, omega.z, -omega.x);
+        let none = || Color {
+            b: 0,
+            g: 0,
+            r: 0,
+            a: 0,
+        };
+        let src = &image.array;
+        let mut rotation = Rotation3::identity();
+        let mut row = usize::MAX;
+        image.array = Array2::from_shape_fn((h, w), |(y, x)| {
+            if y != row {
+                let t = ((y as f64 + 0.5) / h as f64 - 0.5) * self.readout;
+                rotation = Rotation3::new(optical * t as f32);
+                row = y;
+            }
+            let Some((u, v)) = info
+                .ray(x as f32 + 0.5, y as f32 + 0.5)
+                .and_then(|ray| info.project(&(rotation * ray)))
+            else {
+                return none();
+            };
+            if !(0.0..w as f32).contains(&u) || !(0.0..h as f32).contains(&v) {
+                return none();
+            }
+            let su = (u - 0.5).clamp(0.0, (w - 1) as f32);
+            let sv = (v - 0.5).clamp(0.0, (h - 1) as f32);
+            bilinear(src, su, sv)
+        });
+        image.annotations.insert(
+            ROLLING_SHUTTER_KEY,
+            AnnotationValueSerDe::Vector(vec![self.readout as f32, omega.x, omega.y, omega.z]),
+        );
+        if image.hash.is_some() {
+            image.hash = Some(ImageHashSerDe::from_array(image.array.view()));
+        }
+    }
+}
+
+impl FrameTransform for RollingShutter {
+    fn accepts(&self, frame: &SensorDataSerDe) -> bool {
+        match frame {
+            SensorDataSerDe::Image(_) => true,
+            SensorDataSerDe::Imu(_) => self.follow_imu,
+            _ => false,
+        }
+    }
+
+    fn apply(&mut self, frame: &mut SensorDataSerDe) {
+        match frame {
+            SensorDataSerDe::Image(image) => self.shutter(image),
+            SensorDataSerDe::Imu(imu) if self.follow_imu => {
+                self.angular_velocity = imu.gyroscope.into();
+            }
+            _ => {}
+        }
+    }
+
+    fn describe(&self) -> ProcessingStepSerDe {
+        let (roll, pitch, yaw) = self.imu_to_camera.euler_angles();
+        let step = ProcessingStepSerDe::new("rolling_shutter")
+            .with_param("readout", self.readout)
+            .with_param(
+                "imu_to_camera",
+                format!(
+                    "r{:.2}° p{:.2}° y{:.2}°",
+                    roll.to_degrees(),
+                    pitch.to_degrees(),
+                    yaw.to_degrees()
+                ),
+            );
+        if self.follow_imu {
+            step.with_param("angular_velocity", "imu")
+        } else {
+            let w = self.angular_velocity;
+            step.with_param(
+                "angular_velocity",
+                format!("({:.4}, {:.4}, {:.4})", w.x, w.y, w.z),
+            )
+        }
+    }
+}
+
+#[cfg(feature = "recording")]
+impl crate::recording::Recording {
+    /// Give every camera frame a rolling shutter, in frame order so IMU
+    /// frames drive the images after them.
+    pub fn rolling_shutter(&mut self, shutter: &RollingShutter) {
+        let mut transform = *shutter;
+        self.apply(&mut transform);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AnnotationsSerDe;
+
+    // a landscape frame whose red channel counts columns by 20
+    fn image() -> ImageEventSerDe {
+        let array = Array2::from_shape_fn((4, 10), |(y, x)| Color {
+            b: 0,
+            g: y as u8,
+            r: 20 * x as u8,
+            a: 255,
+        });
+        ImageEventSerDe {
+            height: 4,
+            width: 10,
+            len: 40,
+            is_empty: false,
+            fov_angle: 90.0,
+            array,
+            augmentations: Vec::new(),
+            hash: None,
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+
+    #[test]
+    fn standing_still_leaves_the_frame_alone() {
+        let mut frame = image();
+        RollingShutter::new(0.03)
+            .with_angular_velocity(Vector3::zeros())
+            .shutter(&mut frame);
+        let original = image();
+        assert_eq!(frame.array.dim(), (4, 10));
+        for (a, b) in frame.array.iter().zip(&original.array) {
+            assert_eq!((a.r, a.g, a.a), (b.r, b.g, b.a));
+        }
+        assert!(frame.annotations.get(ROLLING_SHUTTER_KEY).is_some());
+    }
+
+    #[test]
+    fn turning_skews_the_rows_in_opposite_directions() {
+        let mut frame = image();
+        RollingShutter::new(0.1)
+            .with_angular_velocity(Vector3::new(0.0, 0.0, 4.0))
+            .shutter(&mut frame);
+        assert_eq!(frame.array.dim(), (4, 10));
+        assert_eq!(frame.validate(), Ok(()));
+        let shift = |y: usize| frame.array[(y, 5)].r as i32 - 100;
+        assert!(shift(0) * shift(3) < 0, "{} {}", shift(0), shift(3));
+        // rows stay rows while yawing
+        for y in 0..4 {
+            assert_eq!(frame.array[(y, 5)].g as usize, y);
+        }
+    }
+}
@@ -0,0 +1,329 @@
+//! Active-learning selection of the frames worth labeling.
+//!
+//! A [`FrameScorer`] rates each frame; [`TopKSelector`] keeps the `k`
+//! best-rated frames of a run and hands them to its inner sink, e.g. the
+//! dataset writer, on [`Sink::flush`]. [`NoveltyScorer`],
+//! [`DetectionDensityScorer`] and [`IncidentProximityScorer`] cover the
+//! usual heuristics and [`CombinedScorer`] weighs them against each
+//! other; any `FnMut(&SensorDataSerDe) -> Option<f64>` is a scorer too.
+
+use crate::captions::event_summary;
+use crate::pipeline::{SharedFrame, Sink};
+use crate::{AnnotationValueSerDe, ImageHashSerDe, ProcessingStepSerDe, SensorDataSerDe};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, VecDeque};
+
+/// Rates frames for labeling.
+pub trait FrameScorer {
+    /// Higher is more worth labeling; `None` for frames that are no
+    /// candidates, e.g. IMU samples. Called on every frame in order, so
+    /// scorers may learn from the frames they do not rate.
+    fn score(&mut self, frame: &SensorDataSerDe) -> Option<f64>;
+}
+
+impl<F: FnMut(&SensorDataSerDe) -> Option<f64>> FrameScorer for F {
+    fn score(&mut self, frame: &SensorDataSerDe) -> Option<f64> {
+        self(frame)
+    }
+}
+
+/// Rates camera frames by how far their perceptual hash is from the most
+/// similar of the recent frames, 0 for a duplicate to 1 for a frame unlike
+/// any of them. Uses the frame's hash if it has one.
+#[derive(Clone, Debug)]
+pub struct NoveltyScorer {
+    capacity: usize,
+    recent: VecDeque<ImageHashSerDe>,
+}
+
+impl Default for NoveltyScorer {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl NoveltyScorer {
+    /// Compare against the last `capacity` camera frames.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            recent: VecDeque::new(),
+        }
+    }
+}
+
+impl FrameScorer for NoveltyScorer {
+    fn score(&mut self, frame: &SensorDataSerDe) -> Option<f64> {
+        let SensorDataSerDe::Image(image) = frame else {
+            return None;
+        };
+        let hash = image
+            .hash
+            .unwrap_or_else(|| ImageHashSerDe::from_array(image.array.view()));
+        let nearest = self
+            .recent
+            .iter()
+            .map(|seen| hash.dhash_distance(seen) + hash.phash_distance(seen))
+            .min()
+            .unwrap_or(128);
+        if self.recent.len() == self.capacity {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(hash);
+        Some(nearest as f64 / 128.0)
+    }
+}
+
+/// Rates camera frames by the number of detections a model stored under
+/// an annotation, e.g. by [`crate::pipeline::ImageAnnotator`]: an `Int` or
+/// `Float` count, the rows of a `Tensor`, or the length of a `Vector` over
+/// the values per detection. Frames without the annotation rate 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DetectionDensityScorer {
+    key: String,
+    stride: usize,
+}
+
+impl DetectionDensityScorer {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            stride: 1,
+        }
+    }
+
+    /// Values per detection in a `Vector` annotation, e.g. 6 for boxes
+    /// with a class and a score.
+    pub fn with_stride(mut self, stride: usize) -> Self {
+        self.stride = stride.max(1);
+        self
+    }
+}
+
+impl FrameScorer for DetectionDensityScorer {
+    fn score(&mut self, frame: &SensorDataSerDe) -> Option<f64> {
+        let SensorDataSerDe::Image(image) = frame else {
+            return None;
+        };
+        let count = match image.annotations.get(&self.key) {
+            Some(AnnotationValueSerDe::Int(n)) => *n as f64,
+            Some(AnnotationValueSerDe::Float(n)) => *n,
+            Some(AnnotationValueSerDe::Vector(v)) => (v.len() / self.stride) as f64,
+            Some(AnnotationValueSerDe::Tensor { shape, .. }) => {
+                shape.first().copied().unwrap_or(0) as f64
+            }
+            _ => 0.0,
+        };
+        Some(count)
+    }
+}
+
+/// Rates camera frames by how soon after an incident, a collision, lane
+/// invasion or obstacle detection, they were captured: 1 at the incident,
+/// falling off by `1/e` per `window` seconds, 0 before the first. Needs
+/// the frame timestamps [`SensorDataSerDe::capture`] keeps; only incidents
+/// seen earlier in the stream count.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IncidentProximityScorer {
+    window: f64,
+    last_incident: Option<f64>,
+}
+
+impl IncidentProximityScorer {
+    /// Panics unless `window` is a positive number of seconds.
+    pub fn new(window: f64) -> Self {
+        assert!(
+            window.is_finite() && window > 0.0,
+            "incident window of {window} s is not positive"
+        );
+        Self {
+            window,
+            last_incident: None,
+        }
+    }
+}
+
+impl FrameScorer for IncidentProximityScorer {
+    fn score(&mut self, frame: &SensorDataSerDe) -> Option<f64> {
+        let SensorDataSerDe::Image(_) = frame else {
+            if let Some(t) = frame.timestamp()
+                && event_summary(frame).is_some()
+            {
+                self.last_incident = Some(t);
+            }
+            return None;
+        };
+        let since = frame
+            .timestamp()
+            .zip(self.last_incident)
+            .map(|(t, i)| t - i);
+        Some(match since {
+            Some(dt) if dt >= 0.0 => (-dt / self.window).exp(),
+            _ => 0.0,
+        })
+    }
+}
+
+/// The weighted sum of several scorers; `None` if all of them are.
+#[derive(Default)]
+pub struct CombinedScorer {
+    scorers: Vec<(f64, Box<dyn FrameScorer + Send>)>,
+}
+
+impl CombinedScorer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, weight: f64, scorer: impl FrameScorer + Send + 'static) -> Self {
+        self.scorers.push((weight, Box::new(scorer)));
+        self
+    }
+}
+
+impl FrameScorer for CombinedScorer {
+    fn score(&mut self, frame: &SensorDataSerDe) -> Option<f64> {
+        // every scorer sees every frame, so none misses an incident
+        self.scorers
+            .iter_mut()
+            .filter_map(|(weight, scorer)| scorer.score(frame).map(|s| *weight * s))
+            .fold(None, |sum, s| Some(sum.unwrap_or(0.0) + s))
+    }
+}
+
+/// Keeps the `k` best-rated frames on their way to `inner`.
+///
+/// Frames the scorer does not rate, or rates NaN or infinite, are
+/// dropped. The kept frames reach
+/// `inner` on [`Sink::flush`], in the order they arrived; of frames with
+/// the same score the earlier is kept.
+pub struct TopKSelector<S, F> {
+    inner: S,
+    scorer: F,
+    k: usize,
+    // worst kept frame on top
+    kept: BinaryHeap<Reverse<Candidate>>,
+    arrivals: u64,
+    rated: u64,
+}
+
+struct Candidate {
+    score: f64,
+    order: u64,
+    frame: SharedFrame,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // better is greater: higher score, then earlier arrival
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then(other.order.cmp(&self.order))
+    }
+}
+
+impl<S: Sink, F: FrameScorer> TopKSelector<S, F> {
+    pub fn new(inner: S, scorer: F, k: usize) -> Self {
+        Self {
+            inner,
+            scorer,
+            k,
+            kept: BinaryHeap::new(),
+            arrivals: 0,
+            rated: 0,
+        }
+    }
+
+    /// The scores of the frames kept so far, best first.
+    pub fn scores(&self) -> Vec<f64> {
+        let mut kept: Vec<_> = self.kept.iter().map(|Reverse(c)| c.score).collect();
+        kept.sort_by(|a, b| b.total_cmp(a));
+        kept
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    pub fn describe(&self) -> ProcessingStepSerDe {
+        ProcessingStepSerDe::new("top_k_select")
+            .with_param("k", self.k as i64)
+            .with_param("rated", self.rated as i64)
+    }
+}
+
+impl<S: Sink, F: FrameScorer> Sink for TopKSelector<S, F> {
+    fn consume(&mut self, frame: SharedFrame) {
+        let Some(score) = self.scorer.score(&frame).filter(|s| s.is_finite()) else {
+            return;
+        };
+        self.rated += 1;
+        let candidate = Candidate {
+            score,
+            order: self.arrivals,
+            frame,
+        };
+        self.arrivals += 1;
+        if self.kept.len() < self.k {
+            self.kept.push(Reverse(candidate));
+        } else if let Some(mut worst) = self.kept.peek_mut()
+            && candidate > worst.0
+        {
+            *worst = Reverse(candidate);
+        }
+    }
+
+    /// Hands the kept frames to `inner` and flushes it.
+    fn flush(&mut self) {
+        let mut kept: Vec<_> = self.kept.drain().map(|Reverse(c)| c).collect();
+        kept.sort_by_key(|c| c.order);
+        for candidate in kept {
+            self.inner.consume(candidate.frame);
+        }
+        self.inner.flush();
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use crate::fixtures::image_frame;
+    use crate::test_support::CollectingSink;
+    use std::sync::Arc;
+
+    #[test]
+    fn non_finite_scores_are_never_kept() {
+        let mut scores = [f64::NAN, 1.0, f64::INFINITY, 2.0].into_iter();
+        let mut selector = TopKSelector::new(
+            CollectingSink::default(),
+            move |_: &SensorDataSerDe| scores.next(),
+            2,
+        );
+        for _ in 0..4 {
+            selector.consume(Arc::new(SensorDataSerDe::Image(image_frame())));
+        }
+        assert_eq!(selector.scores(), vec![2.0, 1.0]);
+        selector.flush();
+        assert_eq!(selector.into_inner().frames.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "not positive")]
+    fn zero_incident_window_is_rejected() {
+        IncidentProximityScorer::new(0.0);
+    }
+}
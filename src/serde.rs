@@ -1,19 +1,102 @@
 mod actor;
+mod actor_blueprint;
+mod annotations;
+mod array_rows;
+mod bounding_box;
+mod camera_info;
+mod class_statistics;
+mod clock_sync;
 mod collision;
+mod depth_image;
+mod dvs_event_array;
+mod envelope;
+mod episode_settings;
+mod gap_record;
+mod geo_location;
+mod gnss_measurement;
 mod image;
+mod image_augmentation;
+mod image_hash;
+mod imu_measurement;
+mod imu_noise_model;
+mod instance_segmentation_image;
 mod lane_invasion;
+mod lens_distortion;
 mod lidar_measurement;
+mod lidar_noise_model;
 mod nalgebra;
+mod object_visibility;
 mod obstacle_detection;
+mod optical_flow_image;
+mod panorama_image;
 mod radar_measurement;
-mod imu_measurement;
+mod raw_sensor_data;
+mod recording_header;
+mod semantic_lidar_measurement;
+mod semantic_segmentation_image;
+mod sensor_data;
+mod sensor_description;
+mod simulation_settings;
+mod stereo_frame;
+mod traffic_light;
+mod traffic_seed;
+mod transform;
+mod v2x_event;
+mod vehicle_control;
+mod vehicle_light_state;
+mod vehicle_physics_control;
+mod walker_control;
+mod waypoint;
+mod weather_parameters;
+mod world_snapshot;
 
 pub use actor::*;
+pub use actor_blueprint::*;
+pub use annotations::*;
+pub use bounding_box::*;
+pub use camera_info::*;
+pub use class_statistics::*;
+pub use clock_sync::*;
 pub use collision::*;
+pub use depth_image::*;
+pub use dvs_event_array::*;
+pub use envelope::*;
+pub use episode_settings::*;
+pub use gap_record::*;
+pub use geo_location::*;
+pub use gnss_measurement::*;
 pub use image::*;
+pub use image_augmentation::*;
+pub use image_hash::*;
+pub use imu_measurement::*;
+pub use imu_noise_model::*;
+pub use instance_segmentation_image::*;
 pub use lane_invasion::*;
+pub use lens_distortion::*;
 pub use lidar_measurement::*;
+pub use lidar_noise_model::*;
 pub use nalgebra::*;
+pub use object_visibility::*;
 pub use obstacle_detection::*;
+pub use optical_flow_image::*;
+pub use panorama_image::*;
 pub use radar_measurement::*;
-pub use imu_measurement::*;
+pub use raw_sensor_data::*;
+pub use recording_header::*;
+pub use semantic_lidar_measurement::*;
+pub use semantic_segmentation_image::*;
+pub use sensor_data::*;
+pub use sensor_description::*;
+pub use simulation_settings::*;
+pub use stereo_frame::*;
+pub use traffic_light::*;
+pub use traffic_seed::*;
+pub use transform::*;
+pub use v2x_event::*;
+pub use vehicle_control::*;
+pub use vehicle_light_state::*;
+pub use vehicle_physics_control::*;
+pub use walker_control::*;
+pub use waypoint::*;
+pub use weather_parameters::*;
+pub use world_snapshot::*;
@@ -1,9 +1,11 @@
-use crate::Vector3DSerDe;
+use super::actor_blueprint::{actor_attributes, find_attribute};
+use crate::{ActorAttributeSerDe, ActorAttributeValueSerDe, Vector3DSerDe};
 use carla::client::ActorBase;
 use nalgebra::{Isometry3, Translation3};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ActorSerDe {
     pub id: carla::rpc::ActorId,
     pub type_id: String,
@@ -12,6 +14,22 @@ pub struct ActorSerDe {
     pub transform: Isometry3<f32>,
     pub velocity: Vector3DSerDe,
     pub acceleration: Vector3DSerDe,
+    /// Blueprint attributes of the actor, e.g. `role_name` or `color`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<ActorAttributeSerDe>,
+}
+
+impl ActorSerDe {
+    pub fn attribute(&self, id: &str) -> Option<&ActorAttributeValueSerDe> {
+        find_attribute(&self.attributes, id)
+    }
+
+    pub fn role_name(&self) -> Option<&str> {
+        match self.attribute("role_name")? {
+            ActorAttributeValueSerDe::String(name) if !name.is_empty() => Some(name),
+            _ => None,
+        }
+    }
 }
 
 impl From<carla::client::Actor> for ActorSerDe {
@@ -24,6 +42,18 @@ impl From<carla::client::Actor> for ActorSerDe {
             transform: v.transform(),
             velocity: v.velocity().into(),
             acceleration: v.acceleration().into(),
+            attributes: actor_attributes(&v),
+        }
+    }
+}
+
+/// `vehicle.tesla.model3 #24`, with ` (hero)` if it has a role name
+impl fmt::Display for ActorSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} #{}", self.type_id, self.id)?;
+        if let Some(name) = self.role_name() {
+            write!(f, " ({name})")?;
         }
+        Ok(())
     }
 }
@@ -0,0 +1,153 @@
+use carla::client::{ActorAttributeValueKind, ActorBase, ActorBlueprint, BlueprintLibrary};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The typed value of a blueprint attribute.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ActorAttributeValueSerDe {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    String(String),
+    RgbColor([u8; 3]),
+}
+
+impl ActorAttributeValueSerDe {
+    /// The value the way `ActorBlueprint::set_attribute` takes it, colors
+    /// as `r,g,b`.
+    pub fn to_attribute_string(&self) -> String {
+        match self {
+            Self::Bool(v) => v.to_string(),
+            Self::Int(v) => v.to_string(),
+            Self::Float(v) => v.to_string(),
+            Self::String(v) => v.clone(),
+            Self::RgbColor([r, g, b]) => format!("{r},{g},{b}"),
+        }
+    }
+}
+
+impl From<ActorAttributeValueKind> for ActorAttributeValueSerDe {
+    fn from(v: ActorAttributeValueKind) -> Self {
+        match v {
+            ActorAttributeValueKind::Bool(v) => Self::Bool(v),
+            ActorAttributeValueKind::Int(v) => Self::Int(v),
+            ActorAttributeValueKind::F32(v) => Self::Float(v),
+            ActorAttributeValueKind::String(v) => Self::String(v),
+            ActorAttributeValueKind::Color(c) => Self::RgbColor([c.r, c.g, c.b]),
+        }
+    }
+}
+
+/// One attribute of a blueprint, e.g. `color` or `role_name`.
+///
+/// The value is a field of its own rather than flattened into the
+/// attribute: serde buffers flattened fields, which the bit-exact float
+/// encodings of [`crate::float_bits`] then no longer see.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActorAttributeSerDe {
+    pub id: String,
+    pub value: ActorAttributeValueSerDe,
+}
+
+/// A blueprint with the attribute values an actor was spawned with.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActorBlueprintSerDe {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<ActorAttributeSerDe>,
+}
+
+/// The attribute values of a spawned actor, the one representation
+/// [`ActorBlueprintSerDe`], [`ActorSerDe`](crate::ActorSerDe) and
+/// [`TrafficEntitySerDe`](crate::TrafficEntitySerDe) keep them in.
+pub(crate) fn actor_attributes<A: ActorBase>(actor: &A) -> Vec<ActorAttributeSerDe> {
+    actor
+        .attributes()
+        .iter()
+        .filter_map(|attr| {
+            Some(ActorAttributeSerDe {
+                id: attr.id(),
+                value: attr.value()?.into(),
+            })
+        })
+        .collect()
+}
+
+/// The value of the attribute `id` among `attributes`.
+pub(crate) fn find_attribute<'a>(
+    attributes: &'a [ActorAttributeSerDe],
+    id: &str,
+) -> Option<&'a ActorAttributeValueSerDe> {
+    attributes
+        .iter()
+        .find(|attr| attr.id == id)
+        .map(|attr| &attr.value)
+}
+
+impl ActorBlueprintSerDe {
+    /// The blueprint `actor` was spawned from, with its attribute values;
+    /// the tags come from `library`, as actors do not keep them.
+    pub fn capture<A: ActorBase>(actor: &A, library: &BlueprintLibrary) -> Self {
+        let id = actor.type_id();
+        Self {
+            tags: library.find(&id).map(|bp| bp.tags()).unwrap_or_default(),
+            id,
+            attributes: actor_attributes(actor),
+        }
+    }
+
+    pub fn attribute(&self, id: &str) -> Option<&ActorAttributeValueSerDe> {
+        find_attribute(&self.attributes, id)
+    }
+}
+
+impl From<ActorBlueprint> for ActorBlueprintSerDe {
+    fn from(bp: ActorBlueprint) -> Self {
+        Self::from(&bp)
+    }
+}
+
+/// Without attributes: the carla crate does not list those of a blueprint,
+/// only of a spawned actor, see [`ActorBlueprintSerDe::capture`].
+impl From<&ActorBlueprint> for ActorBlueprintSerDe {
+    fn from(bp: &ActorBlueprint) -> Self {
+        Self {
+            id: bp.id(),
+            tags: bp.tags(),
+            attributes: Vec::new(),
+        }
+    }
+}
+
+/// An actor of a session and the blueprint it was spawned from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpawnedActorSerDe {
+    pub id: carla::rpc::ActorId,
+    pub blueprint: ActorBlueprintSerDe,
+}
+
+impl SpawnedActorSerDe {
+    pub fn capture<A: ActorBase>(actor: &A, library: &BlueprintLibrary) -> Self {
+        Self {
+            id: actor.id(),
+            blueprint: ActorBlueprintSerDe::capture(actor, library),
+        }
+    }
+}
+
+/// `color = 255,0,0`
+impl fmt::Display for ActorAttributeSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.id, self.value.to_attribute_string())
+    }
+}
+
+/// `vehicle.tesla.model3, 14 attributes`
+impl fmt::Display for ActorBlueprintSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {} attributes", self.id, self.attributes.len())
+    }
+}
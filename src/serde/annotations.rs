@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// One piece of user side-data attached to a frame.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AnnotationValueSerDe {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    /// e.g. an embedding or a class-score vector
+    Vector(Vec<f32>),
+    /// Dense row-major tensor, e.g. a raw model output.
+    Tensor {
+        shape: Vec<usize>,
+        data: Vec<f32>,
+    },
+    Bytes(Vec<u8>),
+}
+
+macro_rules! impl_from_value {
+    ($($variant:ident($ty:ty)),* $(,)?) => {
+        $(
+            impl From<$ty> for AnnotationValueSerDe {
+                fn from(v: $ty) -> Self {
+                    Self::$variant(v.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_value!(
+    Bool(bool),
+    Int(i64),
+    Int(i32),
+    Float(f64),
+    Float(f32),
+    Text(String),
+    Text(&str),
+    Vector(Vec<f32>),
+    Vector(&[f32]),
+    Bytes(Vec<u8>),
+);
+
+impl AnnotationValueSerDe {
+    pub fn as_vector(&self) -> Option<&[f32]> {
+        match self {
+            Self::Vector(v) => Some(v),
+            Self::Tensor { data, .. } => Some(data),
+            _ => None,
+        }
+    }
+}
+
+/// Named annotations of a frame, e.g. `"clip_embedding"` or `"yolo_boxes"`.
+///
+/// Serialized as a plain map and omitted when empty, so frames without
+/// annotations keep their previous encoding.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AnnotationsSerDe(pub BTreeMap<String, AnnotationValueSerDe>);
+
+impl AnnotationsSerDe {
+    /// Set an annotation, returning the value it replaced.
+    pub fn insert(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<AnnotationValueSerDe>,
+    ) -> Option<AnnotationValueSerDe> {
+        self.0.insert(key.into(), value.into())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&AnnotationValueSerDe> {
+        self.0.get(key)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<AnnotationValueSerDe> {
+        self.0.remove(key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &AnnotationValueSerDe)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+/// `3 annotations: clip_embedding, town, yolo_boxes`; the values are left
+/// out, they can be whole tensors.
+impl fmt::Display for AnnotationsSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let noun = if self.len() == 1 {
+            "annotation"
+        } else {
+            "annotations"
+        };
+        write!(f, "{} {noun}", self.len())?;
+        for (i, key) in self.0.keys().enumerate() {
+            let sep = if i == 0 { ": " } else { ", " };
+            write!(f, "{sep}{key}")?;
+        }
+        Ok(())
+    }
+}
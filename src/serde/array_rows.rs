@@ -0,0 +1,61 @@
+//! `Array2<T>` as a list of rows, for element types with their own serde
+//! impls. Use with `#[serde(with = "super::array_rows")]`.
+
+use ndarray::{Array2, ArrayBase, Data, Ix2};
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+
+struct Row<'a, T, D: Data<Elem = T>>(&'a ArrayBase<D, ndarray::Ix1>);
+
+impl<T: Serialize, D: Data<Elem = T>> Serialize for Row<'_, T, D> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut inner = s.serialize_seq(Some(self.0.len()))?;
+        for x in self.0.iter() {
+            inner.serialize_element(x)?;
+        }
+        inner.end()
+    }
+}
+
+pub fn serialize<T, D, S>(arr: &ArrayBase<D, Ix2>, s: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    D: Data<Elem = T>,
+    S: Serializer,
+{
+    let mut outer = s.serialize_seq(Some(arr.nrows()))?;
+    for row in arr.rows() {
+        outer.serialize_element(&Row(&row))?;
+    }
+    outer.end()
+}
+
+pub fn deserialize<'de, T, D>(d: D) -> Result<Array2<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    struct Outer<T>(PhantomData<T>);
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for Outer<T> {
+        type Value = Array2<T>;
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a list of equal-length rows")
+        }
+        fn visit_seq<A: SeqAccess<'de>>(self, mut outer: A) -> Result<Self::Value, A::Error> {
+            let mut rows: Vec<Vec<T>> = Vec::new();
+            while let Some(inner) = outer.next_element()? {
+                rows.push(inner);
+            }
+            let h = rows.len();
+            let w = rows.first().map_or(0, |r| r.len());
+            if rows.iter().any(|r| r.len() != w) {
+                return Err(de::Error::custom("ragged 2D array"));
+            }
+            let flat = rows.into_iter().flatten().collect();
+            Array2::from_shape_vec((h, w), flat).map_err(de::Error::custom)
+        }
+    }
+    d.deserialize_seq(Outer(PhantomData))
+}
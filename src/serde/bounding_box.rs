@@ -0,0 +1,122 @@
+use crate::{LocationSerDe, RotationSerDe, TransformSerDe, Vector3DSerDe};
+use carla::client::{BoundingBoxList, World};
+use carla::geom::BoundingBox;
+use nalgebra::Isometry3;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A box as CARLA reports it, e.g. for an actor, a junction or a level
+/// object: its center and orientation, and its half sizes in meters.
+///
+/// The carla crate exposes boxes through `Junction::bounding_box`,
+/// `EnvironmentObject::bounding_box` and `World::level_bounding_boxes`,
+/// see [`BoundingBoxSerDe::level`]; an actor's box has no binding yet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBoxSerDe {
+    /// Half the length, width and height.
+    pub extent: Vector3DSerDe,
+    /// The center, relative to the actor for actor boxes and in the world
+    /// for level boxes.
+    pub location: LocationSerDe,
+    pub rotation: RotationSerDe,
+}
+
+impl BoundingBoxSerDe {
+    /// The pose of the center.
+    pub fn transform(&self) -> TransformSerDe {
+        TransformSerDe {
+            location: self.location,
+            rotation: self.rotation,
+        }
+    }
+
+    /// The boxes of the level objects with the semantic tag `tag`, e.g.
+    /// `14` for cars, in the world frame.
+    pub fn level(world: &World, tag: u8) -> Vec<Self> {
+        Self::from_list(&world.level_bounding_boxes(tag))
+    }
+
+    pub fn from_list(list: &BoundingBoxList) -> Vec<Self> {
+        list.iter().map(Self::from).collect()
+    }
+
+    /// Length, width and height, in meters.
+    pub fn size(&self) -> Vector3DSerDe {
+        Vector3DSerDe {
+            x: 2.0 * self.extent.x,
+            y: 2.0 * self.extent.y,
+            z: 2.0 * self.extent.z,
+        }
+    }
+}
+
+impl From<BoundingBox<f32>> for BoundingBoxSerDe {
+    fn from(b: BoundingBox<f32>) -> Self {
+        Self::from(&b)
+    }
+}
+
+impl From<&BoundingBox<f32>> for BoundingBoxSerDe {
+    fn from(b: &BoundingBox<f32>) -> Self {
+        let transform = TransformSerDe::from(&b.transform);
+        Self {
+            extent: b.extent.into(),
+            location: transform.location,
+            rotation: transform.rotation,
+        }
+    }
+}
+
+impl From<BoundingBoxSerDe> for BoundingBox<f32> {
+    fn from(b: BoundingBoxSerDe) -> Self {
+        BoundingBox {
+            transform: Isometry3::from(b.transform()),
+            extent: b.extent.into(),
+        }
+    }
+}
+
+/// `Box (1.50, 0.00, 0.75) m, pitch 0.0°, yaw 90.0°, roll 0.0°, size 4.80 x 2.00 x 1.50 m`
+impl fmt::Display for BoundingBoxSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = self.size();
+        write!(
+            f,
+            "Box {}, size {:.2} x {:.2} x {:.2} m",
+            self.transform(),
+            size.x,
+            size.y,
+            size.z
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Translation3, UnitQuaternion, Vector3};
+
+    #[test]
+    fn converts_carla_boxes_both_ways() {
+        let carla = BoundingBox {
+            transform: Isometry3::from_parts(
+                Translation3::new(1.5, -2.0, 0.75),
+                UnitQuaternion::from_euler_angles(0.0, 0.0, std::f32::consts::FRAC_PI_2),
+            ),
+            extent: Vector3::new(2.4, 1.0, 0.75),
+        };
+        let b = BoundingBoxSerDe::from(&carla);
+        assert_eq!(
+            (b.location.x, b.location.y, b.location.z),
+            (1.5, -2.0, 0.75)
+        );
+        assert!((b.rotation.yaw - 90.0).abs() < 1e-4);
+        assert_eq!(b.size().x, 4.8);
+        let back = BoundingBox::from(b);
+        assert!(
+            (back.transform.translation.vector - carla.transform.translation.vector).norm() < 1e-5
+        );
+        assert!(back.transform.rotation.angle_to(&carla.transform.rotation) < 1e-5);
+        assert_eq!(back.extent, carla.extent);
+    }
+}
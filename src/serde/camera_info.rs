@@ -0,0 +1,169 @@
+use crate::{
+    AnnotationValueSerDe, DISTORTION_KEY, ImageEventSerBorrowed, ImageEventSerDe,
+    LensDistortionSerDe,
+};
+use nalgebra::{Matrix3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Annotation holding an image's `[fx, fy, cx, cy]` once they no longer
+/// follow from its size and fov, e.g. after [`ImageResize`].
+///
+/// [`ImageResize`]: crate::resize::ImageResize
+pub const INTRINSICS_KEY: &str = "intrinsics";
+
+/// Pinhole intrinsics of a CARLA camera.
+///
+/// CARLA cameras are ideal pinholes with the principal point at the image
+/// center, so everything is derived from the image size and horizontal fov.
+/// Only images distorted afterwards, see [`ImageDistortion`], have a
+/// `distortion`.
+///
+/// [`ImageDistortion`]: crate::distortion::ImageDistortion
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraInfoSerDe {
+    pub width: usize,
+    pub height: usize,
+    /// Horizontal field of view in degrees, of a pinhole with `fx`.
+    pub fov: f32,
+    pub fx: f32,
+    pub fy: f32,
+    pub cx: f32,
+    pub cy: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distortion: Option<LensDistortionSerDe>,
+}
+
+impl CameraInfoSerDe {
+    pub fn from_fov(width: usize, height: usize, fov: f32) -> Self {
+        let f = width as f32 / (2.0 * (fov.to_radians() / 2.0).tan());
+        Self {
+            width,
+            height,
+            fov,
+            fx: f,
+            fy: f,
+            cx: width as f32 / 2.0,
+            cy: height as f32 / 2.0,
+            distortion: None,
+        }
+    }
+
+    /// The intrinsics once the image is scaled to `width` x `height`.
+    pub fn scaled(&self, width: usize, height: usize) -> Self {
+        let sx = width as f32 / self.width as f32;
+        let sy = height as f32 / self.height as f32;
+        Self {
+            width,
+            height,
+            fov: horizontal_fov(width, self.fx * sx),
+            fx: self.fx * sx,
+            fy: self.fy * sy,
+            cx: self.cx * sx,
+            cy: self.cy * sy,
+            distortion: self.distortion,
+        }
+    }
+
+    /// The intrinsics once the image is placed at `left`, `top` on a
+    /// `width` x `height` canvas.
+    pub fn padded(&self, left: usize, top: usize, width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            fov: horizontal_fov(width, self.fx),
+            cx: self.cx + left as f32,
+            cy: self.cy + top as f32,
+            ..*self
+        }
+    }
+
+    /// `[fx, fy, cx, cy]`, as kept under [`INTRINSICS_KEY`].
+    pub fn to_annotation(&self) -> AnnotationValueSerDe {
+        AnnotationValueSerDe::Vector(vec![self.fx, self.fy, self.cx, self.cy])
+    }
+
+    /// Pixel a point in the optical frame (x right, y down, z forward)
+    /// lands on, through the lens; `None` if the camera does not see it.
+    pub fn project(&self, point: &Vector3<f32>) -> Option<(f32, f32)> {
+        let (x, y) = match &self.distortion {
+            Some(lens) => lens.distort(point)?,
+            None if point.z > 0.0 => (point.x / point.z, point.y / point.z),
+            None => return None,
+        };
+        Some((self.fx * x + self.cx, self.fy * y + self.cy))
+    }
+
+    /// The unit ray in the optical frame imaged at pixel `u`, `v`; the
+    /// inverse of [`project`](Self::project).
+    pub fn ray(&self, u: f32, v: f32) -> Option<Vector3<f32>> {
+        let (x, y) = ((u - self.cx) / self.fx, (v - self.cy) / self.fy);
+        match &self.distortion {
+            Some(lens) => lens.undistort(x, y),
+            None => Some(Vector3::new(x, y, 1.0).normalize()),
+        }
+    }
+
+    /// The 3x3 camera matrix `K`.
+    pub fn camera_matrix(&self) -> Matrix3<f32> {
+        Matrix3::new(
+            self.fx, 0.0, self.cx, //
+            0.0, self.fy, self.cy, //
+            0.0, 0.0, 1.0,
+        )
+    }
+}
+
+pub(crate) fn horizontal_fov(width: usize, fx: f32) -> f32 {
+    2.0 * (width as f32 / (2.0 * fx)).atan().to_degrees()
+}
+
+/// From the size and fov, or from [`INTRINSICS_KEY`] if the image has it,
+/// with the lens under [`DISTORTION_KEY`] if any.
+impl From<&ImageEventSerDe> for CameraInfoSerDe {
+    fn from(v: &ImageEventSerDe) -> Self {
+        let info = Self {
+            distortion: v
+                .annotations
+                .get(DISTORTION_KEY)
+                .and_then(|a| a.as_vector())
+                .and_then(LensDistortionSerDe::from_coefficients),
+            ..Self::from_fov(v.width, v.height, v.fov_angle)
+        };
+        match v
+            .annotations
+            .get(INTRINSICS_KEY)
+            .and_then(|a| a.as_vector())
+        {
+            Some(&[fx, fy, cx, cy]) => Self {
+                fx,
+                fy,
+                cx,
+                cy,
+                ..info
+            },
+            _ => info,
+        }
+    }
+}
+
+impl<'a> From<&ImageEventSerBorrowed<'a>> for CameraInfoSerDe {
+    fn from(v: &ImageEventSerBorrowed<'a>) -> Self {
+        Self::from_fov(v.width, v.height, v.fov_angle)
+    }
+}
+
+/// `800x600 fov=90° f=400.0`, plus the lens if distorted.
+impl fmt::Display for CameraInfoSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{} fov={}° f={:.1}",
+            self.width, self.height, self.fov, self.fx
+        )?;
+        if let Some(lens) = &self.distortion {
+            write!(f, " {lens}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use crate::SensorDataSerDe;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// How often one semantic tag shows up in a dataset.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct ClassFrequencySerDe {
+    pub pixels: u64,
+    /// Frames with at least one pixel of the tag.
+    pub frames: u64,
+}
+
+/// Frames in which two tags show up together, `a < b`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ClassPairSerDe {
+    pub a: u8,
+    pub b: u8,
+    pub frames: u64,
+}
+
+/// Pixel frequencies and co-occurrence of the semantic tags over the
+/// segmentation frames of a dataset, to spot class imbalance before
+/// training.
+///
+/// Feed it the frames of every recording of the set with [`add`], or
+/// build one per recording and [`merge`] them; both semantic and instance
+/// segmentation frames count, other frames are skipped.
+///
+/// [`add`]: Self::add
+/// [`merge`]: Self::merge
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ClassStatisticsSerDe {
+    pub frames: u64,
+    pub pixels: u64,
+    pub classes: BTreeMap<u8, ClassFrequencySerDe>,
+    /// Sorted by `a`, then `b`.
+    pub co_occurrence: Vec<ClassPairSerDe>,
+}
+
+impl ClassStatisticsSerDe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, frame: &SensorDataSerDe) {
+        let mut counts = [0u64; 256];
+        match frame {
+            SensorDataSerDe::SemanticSegmentation(image) => {
+                for &tag in &image.labels {
+                    counts[tag as usize] += 1;
+                }
+            }
+            SensorDataSerDe::InstanceSegmentation(image) => {
+                for px in &image.array {
+                    counts[px.class as usize] += 1;
+                }
+            }
+            _ => return,
+        }
+        self.frames += 1;
+        let present: Vec<u8> = (0..=u8::MAX).filter(|&t| counts[t as usize] > 0).collect();
+        for &tag in &present {
+            let class = self.classes.entry(tag).or_default();
+            class.pixels += counts[tag as usize];
+            class.frames += 1;
+            self.pixels += counts[tag as usize];
+        }
+        for (i, &a) in present.iter().enumerate() {
+            for &b in &present[i + 1..] {
+                self.add_pair(a, b, 1);
+            }
+        }
+    }
+
+    /// Add the statistics of another part of the dataset.
+    pub fn merge(&mut self, other: &ClassStatisticsSerDe) {
+        self.frames += other.frames;
+        self.pixels += other.pixels;
+        for (tag, freq) in &other.classes {
+            let class = self.classes.entry(*tag).or_default();
+            class.pixels += freq.pixels;
+            class.frames += freq.frames;
+        }
+        for pair in &other.co_occurrence {
+            self.add_pair(pair.a, pair.b, pair.frames);
+        }
+    }
+
+    fn add_pair(&mut self, a: u8, b: u8, frames: u64) {
+        let (a, b) = (a.min(b), a.max(b));
+        match self
+            .co_occurrence
+            .binary_search_by_key(&(a, b), |p| (p.a, p.b))
+        {
+            Ok(i) => self.co_occurrence[i].frames += frames,
+            Err(i) => self
+                .co_occurrence
+                .insert(i, ClassPairSerDe { a, b, frames }),
+        }
+    }
+
+    /// Share of all pixels with `tag`, 0 to 1.
+    pub fn pixel_share(&self, tag: u8) -> f64 {
+        match self.classes.get(&tag) {
+            Some(class) if self.pixels > 0 => class.pixels as f64 / self.pixels as f64,
+            _ => 0.0,
+        }
+    }
+
+    /// Frames in which `a` and `b` both show up.
+    pub fn co_occurrences(&self, a: u8, b: u8) -> u64 {
+        if a == b {
+            return self.classes.get(&a).map_or(0, |c| c.frames);
+        }
+        let key = (a.min(b), a.max(b));
+        self.co_occurrence
+            .binary_search_by_key(&key, |p| (p.a, p.b))
+            .map_or(0, |i| self.co_occurrence[i].frames)
+    }
+
+    /// Pixels of the most frequent tag per pixel of the rarest one; `None`
+    /// with fewer than two tags.
+    pub fn imbalance(&self) -> Option<f64> {
+        let pixels = self.classes.values().map(|c| c.pixels);
+        let (min, max) = (pixels.clone().min()?, pixels.max()?);
+        (self.classes.len() > 1).then(|| max as f64 / min as f64)
+    }
+
+    /// Tags below `share` of all pixels, rarest first.
+    pub fn rare_classes(&self, share: f64) -> Vec<u8> {
+        let mut rare: Vec<u8> = self
+            .classes
+            .keys()
+            .copied()
+            .filter(|&tag| self.pixel_share(tag) < share)
+            .collect();
+        rare.sort_by_key(|tag| self.classes[tag].pixels);
+        rare
+    }
+}
+
+/// `23 classes over 1200 frames, imbalance 4150x`
+impl fmt::Display for ClassStatisticsSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} classes over {} frames",
+            self.classes.len(),
+            self.frames
+        )?;
+        if let Some(ratio) = self.imbalance() {
+            write!(f, ", imbalance {ratio:.0}x")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A clock outside the simulator that recordings get aligned with.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ExternalClockSerDe {
+    /// ROS time, the `header.stamp` of a bridged ROS system.
+    Ros,
+    /// PTP wall clock of the capture host, in TAI seconds.
+    Ptp,
+    /// Any other clock, e.g. the log clock of another simulator.
+    Other(String),
+}
+
+impl fmt::Display for ExternalClockSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ros => f.write_str("ros"),
+            Self::Ptp => f.write_str("ptp"),
+            Self::Other(name) => f.write_str(name),
+        }
+    }
+}
+
+/// Linear mapping from simulation time to an external clock, both in
+/// seconds:
+///
+/// `external = reference_external + (sim - reference_sim) * (1 + drift_ppm * 1e-6)`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClockSyncSerDe {
+    pub clock: ExternalClockSerDe,
+    pub reference_sim: f64,
+    pub reference_external: f64,
+    /// How much faster the external clock runs, in parts per million.
+    #[serde(default)]
+    pub drift_ppm: f64,
+    /// Standard deviation of the fit, in seconds, when the mapping was
+    /// estimated from samples.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub residual: Option<f64>,
+    /// Number of sample pairs the mapping was estimated from.
+    #[serde(default)]
+    pub samples: u32,
+}
+
+impl ClockSyncSerDe {
+    /// A mapping without drift through one sample pair.
+    pub fn offset(clock: ExternalClockSerDe, sim: f64, external: f64) -> Self {
+        Self {
+            clock,
+            reference_sim: sim,
+            reference_external: external,
+            drift_ppm: 0.0,
+            residual: None,
+            samples: 1,
+        }
+    }
+
+    /// Least-squares fit through `(sim, external)` pairs. `None` for fewer
+    /// than two pairs or pairs that all share one simulation time.
+    pub fn fit(clock: ExternalClockSerDe, pairs: &[(f64, f64)]) -> Option<Self> {
+        if pairs.len() < 2 {
+            return None;
+        }
+        let n = pairs.len() as f64;
+        let mean_sim = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_ext = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+        let (mut sxx, mut sxy) = (0.0, 0.0);
+        for (sim, ext) in pairs {
+            sxx += (sim - mean_sim) * (sim - mean_sim);
+            sxy += (sim - mean_sim) * (ext - mean_ext);
+        }
+        if sxx == 0.0 {
+            return None;
+        }
+        let rate = sxy / sxx;
+        let mut mapping = Self {
+            clock,
+            reference_sim: mean_sim,
+            reference_external: mean_ext,
+            drift_ppm: (rate - 1.0) * 1e6,
+            residual: None,
+            samples: pairs.len() as u32,
+        };
+        let squares: f64 = pairs
+            .iter()
+            .map(|(sim, ext)| (mapping.to_external(*sim) - ext).powi(2))
+            .sum();
+        mapping.residual = Some((squares / n).sqrt());
+        Some(mapping)
+    }
+
+    /// Rate of the external clock relative to simulation time.
+    pub fn rate(&self) -> f64 {
+        1.0 + self.drift_ppm * 1e-6
+    }
+
+    pub fn to_external(&self, sim: f64) -> f64 {
+        self.reference_external + (sim - self.reference_sim) * self.rate()
+    }
+
+    pub fn to_sim(&self, external: f64) -> f64 {
+        self.reference_sim + (external - self.reference_external) / self.rate()
+    }
+}
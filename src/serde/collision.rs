@@ -1,12 +1,16 @@
-use crate::{ActorSerDe, Vector3DSerDe};
+use crate::{ActorSerDe, AnnotationsSerDe, Vector3DSerDe};
 use carla::sensor::data::CollisionEvent;
+use nalgebra::Vector3;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct CollisionEventSerDe {
     pub actor: ActorSerDe,
     pub other_actor: Option<ActorSerDe>,
     pub normal_impulse: Vector3DSerDe,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
 }
 
 impl From<CollisionEvent> for CollisionEventSerDe {
@@ -15,6 +19,22 @@ impl From<CollisionEvent> for CollisionEventSerDe {
             actor: value.actor().into(),
             other_actor: value.other_actor().map(Into::into),
             normal_impulse: value.normal_impulse().into(),
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+}
+
+/// `Collision with vehicle.audi.tt (impulse 1251 N·s)`
+impl fmt::Display for CollisionEventSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let impulse = Vector3::from(self.normal_impulse).norm();
+        match &self.other_actor {
+            Some(other) => write!(
+                f,
+                "Collision with {} (impulse {impulse:.0} N·s)",
+                other.type_id
+            ),
+            None => write!(f, "Collision (impulse {impulse:.0} N·s)"),
         }
     }
 }
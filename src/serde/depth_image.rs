@@ -0,0 +1,298 @@
+use super::image::{write_full_matrix, write_preview_matrix};
+use crate::{AnnotationsSerDe, ImageEventSerDe, ImageShapeError};
+use carla::sensor::data::{Color, Image as ImageEvent};
+use ndarray::{Array2, ArrayView1};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const PREVIEW_W: usize = 3;
+const PREVIEW_H: usize = 3;
+
+/// Distance of CARLA's depth camera far plane, in meters; the largest
+/// depth it encodes.
+pub const DEPTH_FAR_PLANE: f32 = 1000.0;
+
+// largest 24-bit value, the far plane
+const DEPTH_MAX_ENCODED: f32 = 16_777_215.0;
+
+/// Meters encoded in one pixel of a `sensor.camera.depth` frame:
+/// `(r + g * 256 + b * 256²) / (256³ - 1)` of the far plane.
+pub fn decode_depth(px: &Color) -> f32 {
+    let encoded = px.r as u32 + ((px.g as u32) << 8) + ((px.b as u32) << 16);
+    DEPTH_FAR_PLANE * (encoded as f32 / DEPTH_MAX_ENCODED)
+}
+
+/// A `sensor.camera.depth` frame as metric depth.
+///
+/// `depth` holds meters, `height x width`, decoded when the frame is
+/// converted. The encoded BGRA frame is kept in `raw` only when asked for,
+/// see [`DepthImageSerDe::with_raw`].
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "DepthImageRaw")]
+pub struct DepthImageSerDe {
+    pub height: usize,
+    pub width: usize,
+    pub fov_angle: f32,
+    #[serde(with = "super::array_rows")]
+    pub depth: Array2<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<ImageEventSerDe>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+// wire form of DepthImageSerDe, before the shape check
+#[derive(Deserialize)]
+struct DepthImageRaw {
+    height: usize,
+    width: usize,
+    fov_angle: f32,
+    #[serde(with = "super::array_rows")]
+    depth: Array2<f32>,
+    #[serde(default)]
+    raw: Option<ImageEventSerDe>,
+    #[serde(default)]
+    annotations: AnnotationsSerDe,
+}
+
+impl TryFrom<DepthImageRaw> for DepthImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(v: DepthImageRaw) -> Result<Self, Self::Error> {
+        let image = Self {
+            height: v.height,
+            width: v.width,
+            fov_angle: v.fov_angle,
+            depth: v.depth,
+            raw: v.raw,
+            annotations: v.annotations,
+        };
+        image.validate()?;
+        Ok(image)
+    }
+}
+
+/// Fails if the array does not hold `height x width` pixels.
+impl TryFrom<&ImageEventSerDe> for DepthImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(image: &ImageEventSerDe) -> Result<Self, Self::Error> {
+        // the array is in buffer order whichever shape it has
+        let meters = image.array.iter().map(decode_depth).collect();
+        Ok(Self {
+            height: image.height,
+            width: image.width,
+            fov_angle: image.fov_angle,
+            depth: depth(image.height, image.width, meters, image.array.dim())?,
+            raw: None,
+            annotations: image.annotations.clone(),
+        })
+    }
+}
+
+impl TryFrom<&ImageEvent> for DepthImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(image: &ImageEvent) -> Result<Self, Self::Error> {
+        let meters = image.as_slice().iter().map(decode_depth).collect();
+        let shape = image.as_array().dim();
+        Ok(Self {
+            height: image.height(),
+            width: image.width(),
+            fov_angle: image.fov_angle(),
+            depth: depth(image.height(), image.width(), meters, shape)?,
+            raw: None,
+            annotations: AnnotationsSerDe::default(),
+        })
+    }
+}
+
+impl TryFrom<ImageEvent> for DepthImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(image: ImageEvent) -> Result<Self, Self::Error> {
+        Self::try_from(&image)
+    }
+}
+
+fn depth(
+    height: usize,
+    width: usize,
+    meters: Vec<f32>,
+    shape: (usize, usize),
+) -> Result<Array2<f32>, ImageShapeError> {
+    Array2::from_shape_vec((height, width), meters).map_err(|_| ImageShapeError {
+        height,
+        width,
+        len: height.saturating_mul(width),
+        is_empty: height == 0 || width == 0,
+        shape,
+    })
+}
+
+impl DepthImageSerDe {
+    /// Also keep the encoded frame the depth was decoded from.
+    pub fn with_raw(mut self, image: ImageEventSerDe) -> Self {
+        self.raw = Some(image);
+        self
+    }
+
+    /// Decode `image`, keeping it in `raw` if `keep_raw` is set; fails if
+    /// its array does not hold `height x width` pixels.
+    pub fn decode(image: ImageEventSerDe, keep_raw: bool) -> Result<Self, ImageShapeError> {
+        let decoded = Self::try_from(&image)?;
+        Ok(if keep_raw {
+            decoded.with_raw(image)
+        } else {
+            decoded
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.depth.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.depth.is_empty()
+    }
+
+    /// Meters at column `x`, row `y`.
+    pub fn depth_at(&self, x: usize, y: usize) -> Option<f32> {
+        self.depth.get((y, x)).copied()
+    }
+
+    /// Nearest and farthest depth; NaN pixels are skipped.
+    pub fn range(&self) -> Option<(f32, f32)> {
+        self.depth
+            .iter()
+            .filter(|d| !d.is_nan())
+            .fold(None, |range, &d| match range {
+                None => Some((d, d)),
+                Some((lo, hi)) => Some((lo.min(d), hi.max(d))),
+            })
+    }
+
+    /// Check that `depth` is `height x width`.
+    pub fn validate(&self) -> Result<(), ImageShapeError> {
+        let shape = self.depth.dim();
+        if shape == (self.height, self.width) {
+            Ok(())
+        } else {
+            Err(ImageShapeError {
+                height: self.height,
+                width: self.width,
+                len: self.height * self.width,
+                is_empty: self.height * self.width == 0,
+                shape,
+            })
+        }
+    }
+
+    /// Overwrite the size fields with what `depth` holds.
+    pub fn reconcile_shape(&mut self) {
+        (self.height, self.width) = self.depth.dim();
+    }
+}
+
+// ------------------------ Custom Debug impls ------------------------
+
+#[inline]
+fn write_meters(d: &f32, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{d:.2}")
+}
+
+impl fmt::Debug for DepthImageSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (h, w) = self.depth.dim();
+        let mut ds = f.debug_struct("DepthImageSerDe");
+        ds.field("height", &self.height)
+            .field("width", &self.width)
+            .field("fov_angle", &self.fov_angle)
+            .field("raw", &self.raw.as_ref().map(|_| "…"))
+            .field("annotations", &self.annotations);
+        ds.finish_non_exhaustive()?;
+
+        write!(f, "\ndepth ")?;
+        if f.alternate() {
+            write!(f, "(full {}x{}) = ", h, w)?;
+            write_full_matrix(f, self.depth.rows(), write_meters)
+        } else {
+            write!(
+                f,
+                "(preview {}x{}, showing {}x{}) = ",
+                h,
+                w,
+                PREVIEW_H.min(h),
+                PREVIEW_W.min(w)
+            )?;
+            write_preview_matrix(
+                f,
+                self.depth.rows(),
+                h,
+                PREVIEW_H.min(h),
+                PREVIEW_W.min(w),
+                write_meters,
+                |row: &ArrayView1<'_, f32>| row.len(),
+            )
+        }
+    }
+}
+
+/// `Depth 800x600 fov=90°, 1.20–1000.00 m`
+impl fmt::Display for DepthImageSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Depth {}x{} fov={}°",
+            self.width, self.height, self.fov_angle
+        )?;
+        if let Some((near, far)) = self.range() {
+            write!(f, ", {near:.2}–{far:.2} m")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(height: usize, width: usize, array: Array2<Color>) -> ImageEventSerDe {
+        ImageEventSerDe {
+            height,
+            width,
+            len: height * width,
+            is_empty: height * width == 0,
+            fov_angle: 90.0,
+            array,
+            augmentations: Vec::new(),
+            hash: None,
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+
+    fn pixel(r: u8) -> Color {
+        Color {
+            b: 0,
+            g: 0,
+            r,
+            a: 255,
+        }
+    }
+
+    #[test]
+    fn decodes_matching_shape() {
+        let array = Array2::from_shape_fn((2, 3), |(y, x)| pixel((y * 3 + x) as u8));
+        let decoded = DepthImageSerDe::decode(image(2, 3, array), true).unwrap();
+        assert_eq!(decoded.depth.dim(), (2, 3));
+        assert!(decoded.raw.is_some());
+    }
+
+    #[test]
+    fn rejects_size_mismatch() {
+        let array = Array2::from_elem((2, 2), pixel(1));
+        let err = DepthImageSerDe::try_from(&image(2, 3, array)).unwrap_err();
+        assert_eq!(err.len, 6);
+        assert_eq!(err.shape, (2, 2));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::AnnotationsSerDe;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// How many events to show in non-alternate ({:?}) mode
+const PREVIEW_EVENTS: usize = 5;
+
+// x: u16, y: u16, t: i64, pol: bool, packed
+const RAW_EVENT_SIZE: usize = 13;
+
+/// One brightness change seen by a dynamic vision sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct DvsEventSerDe {
+    pub x: u16,
+    pub y: u16,
+    /// Simulation time of the change, in nanoseconds.
+    pub timestamp_ns: i64,
+    /// `true` for an increase in brightness.
+    pub polarity: bool,
+}
+
+/// The events of one `sensor.camera.dvs` frame.
+///
+/// The carla crate has no binding for `DVSEventArray` yet; build it from
+/// the sensor's raw data with [`DvsEventArraySerDe::from_raw`].
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct DvsEventArraySerDe {
+    pub width: usize,
+    pub height: usize,
+    pub fov_angle: f32,
+    pub events: Vec<DvsEventSerDe>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+/// Raw DVS data whose length is no multiple of the event size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DvsRawLengthError {
+    pub len: usize,
+}
+
+impl fmt::Display for DvsRawLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes of DVS data are not a whole number of {RAW_EVENT_SIZE}-byte events",
+            self.len
+        )
+    }
+}
+
+impl std::error::Error for DvsRawLengthError {}
+
+impl DvsEventArraySerDe {
+    /// Decode CARLA's `raw_data` of a DVS frame: packed little-endian
+    /// events of `x: u16, y: u16, t: i64, pol: bool`.
+    pub fn from_raw(
+        width: usize,
+        height: usize,
+        fov_angle: f32,
+        raw: &[u8],
+    ) -> Result<Self, DvsRawLengthError> {
+        if !raw.len().is_multiple_of(RAW_EVENT_SIZE) {
+            return Err(DvsRawLengthError { len: raw.len() });
+        }
+        let events = raw
+            .chunks_exact(RAW_EVENT_SIZE)
+            .map(|e| DvsEventSerDe {
+                x: u16::from_le_bytes([e[0], e[1]]),
+                y: u16::from_le_bytes([e[2], e[3]]),
+                timestamp_ns: i64::from_le_bytes(e[4..12].try_into().expect("8 bytes")),
+                polarity: e[12] != 0,
+            })
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            fov_angle,
+            events,
+            annotations: AnnotationsSerDe::default(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Per-pixel event count, positive minus negative, row-major.
+    pub fn accumulate(&self) -> Vec<i32> {
+        let mut counts = vec![0; self.width * self.height];
+        for e in &self.events {
+            let (x, y) = (e.x as usize, e.y as usize);
+            if x < self.width && y < self.height {
+                counts[y * self.width + x] += if e.polarity { 1 } else { -1 };
+            }
+        }
+        counts
+    }
+}
+
+// ======================= Debug helpers (no allocations) =======================
+
+#[inline]
+fn write_event(e: &DvsEventSerDe, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+        f,
+        "{{ x: {}, y: {}, timestamp_ns: {}, polarity: {} }}",
+        e.x, e.y, e.timestamp_ns, e.polarity
+    )
+}
+
+fn write_event_seq_full(f: &mut fmt::Formatter<'_>, events: &[DvsEventSerDe]) -> fmt::Result {
+    writeln!(f, "[")?;
+    for e in events {
+        write!(f, "  ")?;
+        write_event(e, f)?;
+        writeln!(f, ",")?;
+    }
+    write!(f, "]")
+}
+
+fn write_event_seq_preview(
+    f: &mut fmt::Formatter<'_>,
+    events: &[DvsEventSerDe],
+    max_show: usize,
+) -> fmt::Result {
+    let shown = max_show.min(events.len());
+    writeln!(f, "[")?;
+    for e in &events[..shown] {
+        write!(f, "  ")?;
+        write_event(e, f)?;
+        writeln!(f, ",")?;
+    }
+    if events.len() > shown {
+        writeln!(f, "  … ({} more)", events.len() - shown)?;
+    }
+    write!(f, "]")
+}
+
+impl fmt::Debug for DvsEventArraySerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ds = f.debug_struct("DvsEventArraySerDe");
+        ds.field("width", &self.width)
+            .field("height", &self.height)
+            .field("fov_angle", &self.fov_angle)
+            .field("len", &self.len())
+            .field("annotations", &self.annotations);
+        ds.finish_non_exhaustive()?; // header
+
+        write!(f, "\nevents ")?;
+        if f.alternate() {
+            write!(f, "(full, {} total) = ", self.len())?;
+            write_event_seq_full(f, &self.events)
+        } else {
+            write!(
+                f,
+                "(preview showing {} of {}) = ",
+                PREVIEW_EVENTS.min(self.len()),
+                self.len()
+            )?;
+            write_event_seq_preview(f, &self.events, PREVIEW_EVENTS)
+        }
+    }
+}
+
+/// `DVS 640x480, 1520 events (+812/-708)`
+impl fmt::Display for DvsEventArraySerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let positive = self.events.iter().filter(|e| e.polarity).count();
+        write!(
+            f,
+            "DVS {}x{}, {} events (+{positive}/-{})",
+            self.width,
+            self.height,
+            self.len(),
+            self.len() - positive
+        )
+    }
+}
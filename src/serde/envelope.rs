@@ -0,0 +1,160 @@
+use crate::{
+    CollisionEventSerDe, DepthImageSerDe, DvsEventArraySerDe, GnssMeasurementSerDe,
+    ImageEventSerBorrowed, ImageEventSerDe, ImuMeasurementSerDe, InstanceSegmentationImageSerDe,
+    LaneInvasionEventSerDe, LidarMeasurementSerBorrowed, LidarMeasurementSerDe,
+    ObstacleDetectionEventSerDe, OpticalFlowImageSerBorrowed, OpticalFlowImageSerDe,
+    RadarMeasurementSerBorrowed, RadarMeasurementSerDe, RawSensorDataSerDe, RecordingHeaderSerDe,
+    SemanticLidarMeasurementSerBorrowed, SemanticLidarMeasurementSerDe,
+    SemanticSegmentationImageSerDe, SensorDataSerDe, SensorDescriptionSerDe, V2xEventSerDe,
+};
+use serde::{Deserialize, Serialize};
+use std::{error, fmt};
+
+/// Version of the wire format of the `*SerDe` types, bumped on breaking
+/// changes:
+///
+/// - 2: actor attributes are lists of [`ActorAttributeSerDe`], bit-exact
+///   recordings start with a `FloatEncoding` line, every transport sends
+///   frames in an [`Envelope`], recording headers keep the world settings
+///   as `episode` only and traffic spawn poses are [`TransformSerDe`]s.
+///
+/// [`ActorAttributeSerDe`]: crate::ActorAttributeSerDe
+/// [`TransformSerDe`]: crate::TransformSerDe
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Types that can travel in an [`Envelope`].
+pub trait EnvelopePayload {
+    /// Stable name on the wire, the type name without the `SerDe` suffix.
+    const TYPE_NAME: &'static str;
+}
+
+impl<T: EnvelopePayload + ?Sized> EnvelopePayload for &T {
+    const TYPE_NAME: &'static str = T::TYPE_NAME;
+}
+
+macro_rules! envelope_payload {
+    ($($ty:ty => $name:literal),* $(,)?) => {
+        $(impl EnvelopePayload for $ty {
+            const TYPE_NAME: &'static str = $name;
+        })*
+    };
+}
+
+envelope_payload! {
+    SensorDataSerDe => "SensorData",
+    ImageEventSerDe => "ImageEvent",
+    ImageEventSerBorrowed<'_> => "ImageEvent",
+    OpticalFlowImageSerDe => "OpticalFlowImage",
+    OpticalFlowImageSerBorrowed<'_> => "OpticalFlowImage",
+    SemanticSegmentationImageSerDe => "SemanticSegmentationImage",
+    InstanceSegmentationImageSerDe => "InstanceSegmentationImage",
+    DepthImageSerDe => "DepthImage",
+    LidarMeasurementSerDe => "LidarMeasurement",
+    LidarMeasurementSerBorrowed<'_> => "LidarMeasurement",
+    RadarMeasurementSerDe => "RadarMeasurement",
+    RadarMeasurementSerBorrowed<'_> => "RadarMeasurement",
+    SemanticLidarMeasurementSerDe => "SemanticLidarMeasurement",
+    SemanticLidarMeasurementSerBorrowed<'_> => "SemanticLidarMeasurement",
+    ImuMeasurementSerDe => "ImuMeasurement",
+    GnssMeasurementSerDe => "GnssMeasurement",
+    DvsEventArraySerDe => "DvsEventArray",
+    CollisionEventSerDe => "CollisionEvent",
+    LaneInvasionEventSerDe => "LaneInvasionEvent",
+    ObstacleDetectionEventSerDe => "ObstacleDetectionEvent",
+    V2xEventSerDe => "V2xEvent",
+    RawSensorDataSerDe => "RawSensorData",
+    RecordingHeaderSerDe => "RecordingHeader",
+    SensorDescriptionSerDe => "SensorDescription",
+}
+
+/// A payload with what a receiver needs to decode it without prior
+/// agreement on the message type.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub schema_version: u32,
+    /// Encoding of the message, `json` for everything this crate writes.
+    pub codec: String,
+    /// Simulation time in seconds, when the sender knows it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<f64>,
+    pub payload: T,
+}
+
+impl<T: EnvelopePayload> Envelope<T> {
+    pub fn new(payload: T) -> Self {
+        Self {
+            type_name: T::TYPE_NAME.into(),
+            schema_version: SCHEMA_VERSION,
+            codec: "json".into(),
+            timestamp: None,
+            payload,
+        }
+    }
+
+    pub fn with_timestamp(mut self, timestamp: f64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// The payload, if the envelope says it is a `T` of a schema version
+    /// this build can read.
+    pub fn open(self) -> Result<T, EnvelopeError> {
+        if self.type_name != T::TYPE_NAME {
+            return Err(EnvelopeError::WrongType {
+                expected: T::TYPE_NAME,
+                found: self.type_name,
+            });
+        }
+        if self.schema_version > SCHEMA_VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(self.schema_version));
+        }
+        Ok(self.payload)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EnvelopeError {
+    WrongType {
+        expected: &'static str,
+        found: String,
+    },
+    /// Written by a newer version of this crate.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongType { expected, found } => {
+                write!(f, "expected payload type {expected}, found {found}")
+            }
+            Self::UnsupportedVersion(v) => write!(
+                f,
+                "schema version {v} is newer than the supported {SCHEMA_VERSION}"
+            ),
+        }
+    }
+}
+
+impl error::Error for EnvelopeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_newer_schemas() {
+        let header = RecordingHeaderSerDe::default();
+        let mut envelope = Envelope::new(&header);
+        assert_eq!(envelope.type_name, "RecordingHeader");
+        envelope.schema_version = SCHEMA_VERSION + 1;
+        let json = serde_json::to_vec(&envelope).unwrap();
+        let envelope: Envelope<RecordingHeaderSerDe> = serde_json::from_slice(&json).unwrap();
+        assert_eq!(
+            envelope.open(),
+            Err(EnvelopeError::UnsupportedVersion(SCHEMA_VERSION + 1))
+        );
+    }
+}
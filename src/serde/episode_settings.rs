@@ -0,0 +1,137 @@
+use crate::SimulationSettingsSerDe;
+use carla::client::World;
+use carla::rpc::EpisodeSettings;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// All world settings of a run, CARLA's `EpisodeSettings`.
+/// [`SimulationSettingsSerDe`] is the part that decides the timing.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EpisodeSettingsSerDe {
+    /// The server waits for a client tick before each step.
+    pub synchronous_mode: bool,
+    /// Cameras produce no images while set.
+    pub no_rendering_mode: bool,
+    /// Simulation seconds per step; `None` for variable steps.
+    pub fixed_delta_seconds: Option<f64>,
+    pub substepping: bool,
+    pub max_substep_delta_time: f64,
+    pub max_substeps: u64,
+    /// Meters beyond which meshes are not rendered; 0 for no limit.
+    pub max_culling_distance: f32,
+    pub deterministic_ragdolls: bool,
+    /// Meters around the hero vehicle in which tiles of a large map load.
+    pub tile_stream_distance: f32,
+    /// Meters around the hero vehicle in which actors are simulated.
+    pub actor_active_distance: f32,
+}
+
+impl Default for EpisodeSettingsSerDe {
+    fn default() -> Self {
+        // CARLA's defaults, without asking the server
+        Self {
+            synchronous_mode: false,
+            no_rendering_mode: false,
+            fixed_delta_seconds: None,
+            substepping: true,
+            max_substep_delta_time: 0.01,
+            max_substeps: 10,
+            max_culling_distance: 0.0,
+            deterministic_ragdolls: true,
+            tile_stream_distance: 3000.0,
+            actor_active_distance: 2000.0,
+        }
+    }
+}
+
+impl EpisodeSettingsSerDe {
+    /// The settings of `world` right now.
+    pub fn capture(world: &World) -> Self {
+        world.settings().into()
+    }
+
+    /// Give `world` these settings, e.g. to restore a recorded run; returns
+    /// the frame they take effect at.
+    pub fn apply_to(&self, world: &mut World, timeout: Duration) -> u64 {
+        world.apply_settings(&self.into(), timeout)
+    }
+
+    /// The timing part of the settings.
+    pub fn simulation(&self) -> SimulationSettingsSerDe {
+        SimulationSettingsSerDe {
+            synchronous_mode: self.synchronous_mode,
+            fixed_delta_seconds: self.fixed_delta_seconds,
+            substepping: self.substepping,
+            max_substep_delta_time: self.max_substep_delta_time,
+            max_substeps: self.max_substeps,
+            no_rendering_mode: self.no_rendering_mode,
+        }
+    }
+}
+
+impl From<&EpisodeSettings> for EpisodeSettingsSerDe {
+    fn from(s: &EpisodeSettings) -> Self {
+        Self {
+            synchronous_mode: s.synchronous_mode,
+            no_rendering_mode: s.no_rendering_mode,
+            fixed_delta_seconds: s.fixed_delta_seconds,
+            substepping: s.substepping,
+            max_substep_delta_time: s.max_substep_delta_time,
+            max_substeps: s.max_substeps,
+            max_culling_distance: s.max_culling_distance,
+            deterministic_ragdolls: s.deterministic_ragdolls,
+            tile_stream_distance: s.tile_stream_distance,
+            actor_active_distance: s.actor_active_distance,
+        }
+    }
+}
+
+impl From<EpisodeSettings> for EpisodeSettingsSerDe {
+    fn from(s: EpisodeSettings) -> Self {
+        Self::from(&s)
+    }
+}
+
+impl From<&EpisodeSettingsSerDe> for EpisodeSettings {
+    fn from(s: &EpisodeSettingsSerDe) -> Self {
+        Self {
+            synchronous_mode: s.synchronous_mode,
+            no_rendering_mode: s.no_rendering_mode,
+            fixed_delta_seconds: s.fixed_delta_seconds,
+            substepping: s.substepping,
+            max_substep_delta_time: s.max_substep_delta_time,
+            max_substeps: s.max_substeps,
+            max_culling_distance: s.max_culling_distance,
+            deterministic_ragdolls: s.deterministic_ragdolls,
+            tile_stream_distance: s.tile_stream_distance,
+            actor_active_distance: s.actor_active_distance,
+        }
+    }
+}
+
+impl From<EpisodeSettingsSerDe> for EpisodeSettings {
+    fn from(s: EpisodeSettingsSerDe) -> Self {
+        Self::from(&s)
+    }
+}
+
+/// `synchronous, 0.050 s steps, rendering`
+impl fmt::Display for EpisodeSettingsSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.synchronous_mode {
+            "synchronous"
+        } else {
+            "asynchronous"
+        })?;
+        match self.fixed_delta_seconds {
+            Some(dt) => write!(f, ", {dt:.3} s steps")?,
+            None => f.write_str(", variable steps")?,
+        }
+        f.write_str(if self.no_rendering_mode {
+            ", no rendering"
+        } else {
+            ", rendering"
+        })
+    }
+}
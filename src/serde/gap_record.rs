@@ -0,0 +1,66 @@
+use crate::SensorKind;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Why frames are missing from a stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum GapReason {
+    /// Left out on purpose, e.g. by `Recording::decimate` or a sensor's
+    /// decimation setting.
+    Decimation,
+    /// Shed by a full queue, see [`crate::bus::OverloadPolicy`].
+    Overload,
+    /// Withheld by a filter, e.g. [`crate::access::FilteredSink`].
+    Filtered,
+}
+
+/// Frames of one sensor kind that were dropped at this point of a stream,
+/// so readers can tell "nothing happened" from "data was dropped".
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct GapRecordSerDe {
+    pub sensor: SensorKind,
+    pub count: u64,
+    pub reason: GapReason,
+    /// The sensor's role name, where several sensors share a kind.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role_name: Option<String>,
+    /// The vehicle the sensor is mounted on, in fleet sessions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vehicle: Option<String>,
+}
+
+impl GapRecordSerDe {
+    pub fn new(sensor: SensorKind, count: u64, reason: GapReason) -> Self {
+        Self {
+            sensor,
+            count,
+            reason,
+            role_name: None,
+            vehicle: None,
+        }
+    }
+
+    pub fn with_role_name(mut self, role_name: impl Into<String>) -> Self {
+        self.role_name = Some(role_name.into());
+        self
+    }
+
+    pub fn with_vehicle(mut self, vehicle: impl Into<String>) -> Self {
+        self.vehicle = Some(vehicle.into());
+        self
+    }
+}
+
+/// `Gap: 3 Lidar frames (Overload)`
+impl fmt::Display for GapRecordSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Gap: {} {:?} frames", self.count, self.sensor)?;
+        if let Some(role) = &self.role_name {
+            write!(f, " of {role}")?;
+        }
+        if let Some(vehicle) = &self.vehicle {
+            write!(f, " on {vehicle}")?;
+        }
+        write!(f, " ({:?})", self.reason)
+    }
+}
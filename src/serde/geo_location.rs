@@ -0,0 +1,122 @@
+use crate::LocationSerDe;
+use carla::client::Map;
+use carla::geom::GeoLocation;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::fmt;
+
+// LibCarla's projection constants, see `carla/geom/GeoLocation.cpp`
+const EARTH_RADIUS: f64 = 6_378_137.0;
+
+/// A point on the globe, degrees and meters above the map's reference
+/// altitude.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeoLocationSerDe {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+}
+
+impl GeoLocationSerDe {
+    /// The geo location of the world origin of `map`, from the `lat_0` and
+    /// `lon_0` of its OpenDRIVE geo reference; `None` if it has none.
+    pub fn map_origin(map: &Map) -> Option<Self> {
+        Self::from_open_drive(&map.to_open_drive())
+    }
+
+    /// [`map_origin`](Self::map_origin) from the OpenDRIVE document itself.
+    pub fn from_open_drive(xodr: &str) -> Option<Self> {
+        let start = xodr.find("<geoReference>")? + "<geoReference>".len();
+        let proj = &xodr[start..start + xodr[start..].find("</geoReference>")?];
+        let param = |name: &str| {
+            proj.split_whitespace()
+                .find_map(|p| p.strip_prefix(name)?.parse::<f64>().ok())
+        };
+        Some(Self {
+            latitude: param("+lat_0=")?,
+            longitude: param("+lon_0=")?,
+            altitude: 0.0,
+        })
+    }
+
+    /// Where `location` in the world frame of a map with this origin is on
+    /// the globe, the way CARLA's GNSS sensor projects it.
+    pub fn transform(&self, location: &LocationSerDe) -> Self {
+        let scale = lat_to_scale(self.latitude);
+        let (mx, my) = to_mercator(self.latitude, self.longitude, scale);
+        // CARLA's y axis points south
+        let (latitude, longitude) =
+            from_mercator(mx + location.x as f64, my - location.y as f64, scale);
+        Self {
+            latitude,
+            longitude,
+            altitude: self.altitude + location.z as f64,
+        }
+    }
+
+    /// The inverse of [`transform`](Self::transform): where `geo` is in the
+    /// world frame of a map with this origin.
+    pub fn to_location(&self, geo: &GeoLocationSerDe) -> LocationSerDe {
+        let scale = lat_to_scale(self.latitude);
+        let (x0, y0) = to_mercator(self.latitude, self.longitude, scale);
+        let (x, y) = to_mercator(geo.latitude, geo.longitude, scale);
+        LocationSerDe {
+            x: (x - x0) as f32,
+            y: (y0 - y) as f32,
+            z: (geo.altitude - self.altitude) as f32,
+        }
+    }
+}
+
+fn lat_to_scale(latitude: f64) -> f64 {
+    (latitude * PI / 180.0).cos()
+}
+
+fn to_mercator(latitude: f64, longitude: f64, scale: f64) -> (f64, f64) {
+    let x = scale * longitude * PI * EARTH_RADIUS / 180.0;
+    let y = scale * EARTH_RADIUS * ((90.0 + latitude) * PI / 360.0).tan().ln();
+    (x, y)
+}
+
+fn from_mercator(x: f64, y: f64, scale: f64) -> (f64, f64) {
+    let longitude = x * 180.0 / (PI * EARTH_RADIUS * scale);
+    let latitude = 360.0 * (y / (EARTH_RADIUS * scale)).exp().atan() / PI - 90.0;
+    (latitude, longitude)
+}
+
+impl From<GeoLocation> for GeoLocationSerDe {
+    fn from(g: GeoLocation) -> Self {
+        Self::from(&g)
+    }
+}
+
+impl From<&GeoLocation> for GeoLocationSerDe {
+    fn from(g: &GeoLocation) -> Self {
+        Self {
+            latitude: g.latitude,
+            longitude: g.longitude,
+            altitude: g.altitude,
+        }
+    }
+}
+
+impl From<GeoLocationSerDe> for GeoLocation {
+    fn from(g: GeoLocationSerDe) -> Self {
+        GeoLocation {
+            latitude: g.latitude,
+            longitude: g.longitude,
+            altitude: g.altitude,
+        }
+    }
+}
+
+/// `48.137154, 11.576124, 519.0 m`
+impl fmt::Display for GeoLocationSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.6}, {:.6}, {:.1} m",
+            self.latitude, self.longitude, self.altitude
+        )
+    }
+}
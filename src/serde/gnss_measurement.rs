@@ -0,0 +1,60 @@
+use crate::{AnnotationsSerDe, GeoLocationSerDe, LocationSerDe};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A GNSS fix in the map's geo reference, degrees and meters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GnssMeasurementSerDe {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Meters above the map's reference altitude.
+    pub altitude: f64,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+impl GnssMeasurementSerDe {
+    pub fn geo_location(&self) -> GeoLocationSerDe {
+        GeoLocationSerDe {
+            latitude: self.latitude,
+            longitude: self.longitude,
+            altitude: self.altitude,
+        }
+    }
+
+    /// The fix in the world frame of a map with geo reference `origin`,
+    /// e.g. the header's [`crate::RecordingHeaderSerDe::geo_reference`].
+    pub fn location(&self, origin: &GeoLocationSerDe) -> LocationSerDe {
+        origin.to_location(&self.geo_location())
+    }
+}
+
+impl From<GeoLocationSerDe> for GnssMeasurementSerDe {
+    fn from(g: GeoLocationSerDe) -> Self {
+        Self {
+            latitude: g.latitude,
+            longitude: g.longitude,
+            altitude: g.altitude,
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+}
+
+impl From<carla::sensor::data::GnssMeasurement> for GnssMeasurementSerDe {
+    fn from(m: carla::sensor::data::GnssMeasurement) -> Self {
+        (&m).into()
+    }
+}
+
+impl From<&carla::sensor::data::GnssMeasurement> for GnssMeasurementSerDe {
+    fn from(m: &carla::sensor::data::GnssMeasurement) -> Self {
+        GeoLocationSerDe::from(m.geo_location()).into()
+    }
+}
+
+/// `GNSS 48.137154, 11.576124, 519.0 m`
+impl fmt::Display for GnssMeasurementSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GNSS {}", self.geo_location())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::{AnnotationsSerDe, ImageAugmentationSerDe, ImageHashSerDe};
 use carla::sensor::data::{Color, Image as ImageEvent};
 use ndarray::{Array2, ArrayView1, ArrayView2};
 use serde::{Deserialize, Serialize};
@@ -16,6 +17,13 @@ struct ColorRemote {
     a: u8,
 }
 
+// `Image::as_array` is `width x height`, the buffer itself is row-major
+// `height x width`
+fn rows(image: &ImageEvent) -> ArrayView2<'_, Color> {
+    ArrayView2::from_shape((image.height(), image.width()), image.as_slice())
+        .expect("CARLA image buffer holds height * width pixels")
+}
+
 // ------------------------ Borrowed serializer ------------------------
 
 mod arrayview2_color_remote {
@@ -52,15 +60,19 @@ mod arrayview2_color_remote {
 }
 
 /// Borrowed, zero-copy serializer for Image
-#[derive(Serialize)]
+#[derive(Clone, Copy, Serialize)]
 pub struct ImageEventSerBorrowed<'a> {
     pub height: usize,
     pub width: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub len: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub is_empty: bool,
     pub fov_angle: f32,
     #[serde(with = "self::arrayview2_color_remote")]
     pub array: ArrayView2<'a, Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<ImageHashSerDe>,
 }
 
 impl<'a> From<&'a ImageEvent> for ImageEventSerBorrowed<'a> {
@@ -71,14 +83,23 @@ impl<'a> From<&'a ImageEvent> for ImageEventSerBorrowed<'a> {
             len: value.len(),
             is_empty: value.is_empty(),
             fov_angle: value.fov_angle(),
-            array: value.as_array(), // borrow, zero-copy
+            array: rows(value), // borrow, zero-copy
+            hash: None,
         }
     }
 }
 
+impl ImageEventSerBorrowed<'_> {
+    /// Compute the perceptual hashes of the frame.
+    pub fn with_hash(mut self) -> Self {
+        self.hash = Some(ImageHashSerDe::from_array(self.array));
+        self
+    }
+}
+
 // ------------------------ Owned, round-trip ------------------------
 
-mod array2_color_remote {
+pub(crate) mod array2_color_remote {
     use super::*;
     use serde::de::{self, SeqAccess, Visitor};
     use serde::ser::SerializeSeq;
@@ -132,7 +153,7 @@ mod array2_color_remote {
                     rows.push(inner.into_iter().map(|x| x.0).collect());
                 }
                 let h = rows.len();
-                let w = rows.get(0).map_or(0, |r| r.len());
+                let w = rows.first().map_or(0, |r| r.len());
                 if w == 0 && h == 0 {
                     return Ok(Array2::from_shape_vec((0, 0), vec![]).unwrap());
                 }
@@ -150,21 +171,97 @@ mod array2_color_remote {
 }
 
 /// Owned, round-trip serializer for Image
-#[derive(Serialize, Deserialize)]
+///
+/// Deserializing checks the array against `height`, `width` and `len`, see
+/// [`ImageEventSerDe::validate`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "ImageEventRaw")]
 pub struct ImageEventSerDe {
     pub height: usize,
     pub width: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub len: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub is_empty: bool,
     pub fov_angle: f32,
     #[serde(with = "self::array2_color_remote")]
     pub array: Array2<Color>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub augmentations: Vec<ImageAugmentationSerDe>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<ImageHashSerDe>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+// wire form of ImageEventSerDe, before the shape check
+#[derive(Deserialize)]
+struct ImageEventRaw {
+    height: usize,
+    width: usize,
+    // absent in compact payloads
+    #[serde(default)]
+    len: Option<usize>,
+    #[serde(default)]
+    is_empty: Option<bool>,
+    fov_angle: f32,
+    #[serde(with = "self::array2_color_remote")]
+    array: Array2<Color>,
+    #[serde(default)]
+    augmentations: Vec<ImageAugmentationSerDe>,
+    #[serde(default)]
+    hash: Option<ImageHashSerDe>,
+    #[serde(default)]
+    annotations: AnnotationsSerDe,
 }
 
+impl TryFrom<ImageEventRaw> for ImageEventSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(v: ImageEventRaw) -> Result<Self, Self::Error> {
+        let len = v.len.unwrap_or(v.array.len());
+        let image = Self {
+            height: v.height,
+            width: v.width,
+            len,
+            is_empty: v.is_empty.unwrap_or(len == 0),
+            fov_angle: v.fov_angle,
+            array: v.array,
+            augmentations: v.augmentations,
+            hash: v.hash,
+            annotations: v.annotations,
+        };
+        image.validate()?;
+        Ok(image)
+    }
+}
+
+/// The size fields of an image disagree with its pixel array.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageShapeError {
+    pub height: usize,
+    pub width: usize,
+    pub len: usize,
+    pub is_empty: bool,
+    /// `array.dim()`
+    pub shape: (usize, usize),
+}
+
+impl fmt::Display for ImageShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "image is {}x{} with len {} (is_empty {}), but the array is {}x{}",
+            self.width, self.height, self.len, self.is_empty, self.shape.1, self.shape.0
+        )
+    }
+}
+
+impl std::error::Error for ImageShapeError {}
+
 impl From<ImageEvent> for ImageEventSerDe {
     fn from(value: ImageEvent) -> Self {
-        let view = value.as_array();
-        let array: Array2<Color> = view.map(|c| Color {
+        let array: Array2<Color> = rows(&value).map(|c| Color {
             b: c.b,
             g: c.g,
             r: c.r,
@@ -178,15 +275,87 @@ impl From<ImageEvent> for ImageEventSerDe {
             is_empty: value.is_empty(),
             fov_angle: value.fov_angle(),
             array,
+            augmentations: Vec::new(),
+            hash: None,
+            annotations: AnnotationsSerDe::default(),
         }
     }
 }
 
+// Color has no PartialEq
+impl PartialEq for ImageEventSerDe {
+    fn eq(&self, other: &Self) -> bool {
+        let same_px = |a: &Color, b: &Color| (a.b, a.g, a.r, a.a) == (b.b, b.g, b.r, b.a);
+        self.height == other.height
+            && self.width == other.width
+            && self.len == other.len
+            && self.is_empty == other.is_empty
+            && self.fov_angle == other.fov_angle
+            && self.array.dim() == other.array.dim()
+            && self
+                .array
+                .iter()
+                .zip(&other.array)
+                .all(|(a, b)| same_px(a, b))
+            && self.augmentations == other.augmentations
+            && self.hash == other.hash
+            && self.annotations == other.annotations
+    }
+}
+
+impl ImageEventSerDe {
+    /// Number of pixels, from the array rather than the `len` field.
+    pub fn len(&self) -> usize {
+        self.array.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.array.is_empty()
+    }
+
+    /// Check that the array has `height * width == len` pixels in a
+    /// `height x width` shape.
+    pub fn validate(&self) -> Result<(), ImageShapeError> {
+        let shape = self.array.dim();
+        if shape == (self.height, self.width)
+            && self.len == self.array.len()
+            && self.is_empty == (self.len == 0)
+        {
+            Ok(())
+        } else {
+            Err(ImageShapeError {
+                height: self.height,
+                width: self.width,
+                len: self.len,
+                is_empty: self.is_empty,
+                shape,
+            })
+        }
+    }
+
+    /// Overwrite the size fields with what the array holds, e.g. after
+    /// replacing the array or to repair a payload [`validate`] rejects.
+    ///
+    /// [`validate`]: Self::validate
+    pub fn reconcile_shape(&mut self) {
+        (self.height, self.width) = self.array.dim();
+        self.len = self.array.len();
+        self.is_empty = self.len == 0;
+    }
+
+    /// Compute the perceptual hashes of the frame as it is now, i.e. after
+    /// any augmentation already applied.
+    pub fn with_hash(mut self) -> Self {
+        self.hash = Some(ImageHashSerDe::from_array(self.array.view()));
+        self
+    }
+}
+
 // ---------------------------------------------------------------------
 // helpers: write full / preview matrices to the formatter (no allocs)
 // ---------------------------------------------------------------------
 
-fn write_full_matrix<'a, A: 'a>(
+pub(crate) fn write_full_matrix<'a, A: 'a>(
     f: &mut fmt::Formatter<'_>,
     rows: impl IntoIterator<Item = ArrayView1<'a, A>>,
     mut write_px: impl FnMut(&A, &mut fmt::Formatter<'_>) -> fmt::Result,
@@ -205,7 +374,7 @@ fn write_full_matrix<'a, A: 'a>(
     write!(f, "]")
 }
 
-fn write_preview_matrix<'a, A: 'a>(
+pub(crate) fn write_preview_matrix<'a, A: 'a>(
     f: &mut fmt::Formatter<'_>,
     rows: impl IntoIterator<Item = ArrayView1<'a, A>>,
     total_rows: usize,
@@ -247,7 +416,7 @@ fn write_preview_matrix<'a, A: 'a>(
 
 // pretty RGBA printer (RGB order first for humans)
 #[inline]
-fn write_rgba(px: &Color, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+pub(crate) fn write_rgba(px: &Color, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     write!(f, "({}, {}, {}, {})", px.r, px.g, px.b, px.a)
 }
 
@@ -262,7 +431,8 @@ impl<'a> fmt::Debug for ImageEventSerBorrowed<'a> {
             .field("width", &self.width)
             .field("len", &self.len)
             .field("is_empty", &self.is_empty)
-            .field("fov_angle", &self.fov_angle);
+            .field("fov_angle", &self.fov_angle)
+            .field("hash", &self.hash);
         ds.finish_non_exhaustive()?;
 
         write!(f, "\narray ")?;
@@ -300,7 +470,10 @@ impl fmt::Debug for ImageEventSerDe {
             .field("width", &self.width)
             .field("len", &self.len)
             .field("is_empty", &self.is_empty)
-            .field("fov_angle", &self.fov_angle);
+            .field("fov_angle", &self.fov_angle)
+            .field("augmentations", &self.augmentations)
+            .field("hash", &self.hash)
+            .field("annotations", &self.annotations);
         ds.finish_non_exhaustive()?;
 
         write!(f, "\narray ")?;
@@ -328,3 +501,88 @@ impl fmt::Debug for ImageEventSerDe {
         }
     }
 }
+
+// ------------------------ Display impls ------------------------
+
+/// `Image 1920x1080 fov=90°`
+impl fmt::Display for ImageEventSerBorrowed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Image {}x{} fov={}°",
+            self.width, self.height, self.fov_angle
+        )
+    }
+}
+
+impl fmt::Display for ImageEventSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Image {}x{} fov={}°",
+            self.width, self.height, self.fov_angle
+        )?;
+        if !self.augmentations.is_empty() {
+            write!(f, " (augmented)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(height: usize, width: usize) -> ImageEventSerDe {
+        let black = Color {
+            b: 0,
+            g: 0,
+            r: 0,
+            a: 255,
+        };
+        ImageEventSerDe {
+            height,
+            width,
+            len: height * width,
+            is_empty: height * width == 0,
+            fov_angle: 90.0,
+            array: Array2::from_elem((height, width), black),
+            augmentations: Vec::new(),
+            hash: None,
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+
+    #[test]
+    fn validate_wants_rows_by_columns() {
+        let mut image = image(2, 3);
+        assert_eq!(image.validate(), Ok(()));
+        image.array = image.array.reversed_axes();
+        let err = image.validate().unwrap_err();
+        assert_eq!((err.height, err.width, err.shape), (2, 3, (3, 2)));
+        assert_eq!(
+            err.to_string(),
+            "image is 3x2 with len 6 (is_empty false), but the array is 2x3"
+        );
+    }
+
+    #[test]
+    fn reconcile_shape_follows_a_transposed_array() {
+        let mut image = image(2, 3);
+        image.array = image.array.reversed_axes();
+        image.reconcile_shape();
+        assert_eq!((image.height, image.width, image.len), (3, 2, 6));
+        assert_eq!(image.validate(), Ok(()));
+        let info = crate::CameraInfoSerDe::from(&image);
+        assert_eq!((info.width, info.height), (2, 3));
+    }
+
+    #[test]
+    fn deserializing_rejects_a_transposed_array() {
+        let mut json = serde_json::to_value(image(2, 3)).unwrap();
+        assert!(serde_json::from_value::<ImageEventSerDe>(json.clone()).is_ok());
+        json["height"] = 3.into();
+        json["width"] = 2.into();
+        assert!(serde_json::from_value::<ImageEventSerDe>(json).is_err());
+    }
+}
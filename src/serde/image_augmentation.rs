@@ -0,0 +1,90 @@
+use crate::AnnotationValueSerDe;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// One image processing step: a corruption, see
+/// [`crate::augment::ImageAugmenter`], or a normalization, see
+/// [`crate::augment::ImageNormalizer`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ImageAugmentationStageSerDe {
+    /// Additive per-channel gaussian noise, stddev in 0–255 units.
+    GaussianNoise { stddev: f32 },
+    /// Linear motion blur over `length` pixels along `angle` degrees
+    /// (0 = horizontal, counter-clockwise).
+    MotionBlur { length: usize, angle: f32 },
+    /// Radial darkening, `strength` 0 (none) to 1 (black corners).
+    Vignette { strength: f32 },
+    /// `count` lens-like drops with radii up to `max_radius` pixels.
+    Raindrops { count: usize, max_radius: f32 },
+    /// Brightness gain on all channels, e.g. an auto exposure correction.
+    Exposure { gain: f32 },
+    /// Per-channel gains, e.g. a white balance correction.
+    WhiteBalance { r: f32, g: f32, b: f32 },
+    /// `255 * (v / 255)^(1 / gamma)` on every channel; above 1 brightens.
+    Gamma { gamma: f32 },
+    /// Non-decreasing tone curve on every channel, e.g. from histogram
+    /// equalization: the outputs at inputs 0, 16, …, 256, linear in between.
+    ToneCurve { knots: [f32; 17] },
+}
+
+impl ImageAugmentationStageSerDe {
+    /// Whether [`crate::augment::invert_image_augmentation`] can undo the
+    /// stage, up to rounding and where it did not clip.
+    pub fn is_invertible(&self) -> bool {
+        match *self {
+            Self::Exposure { gain } => gain > 0.0,
+            Self::WhiteBalance { r, g, b } => r > 0.0 && g > 0.0 && b > 0.0,
+            Self::Gamma { gamma } => gamma > 0.0,
+            Self::ToneCurve { knots } => knots.windows(2).all(|k| k[0] < k[1]),
+            _ => false,
+        }
+    }
+
+    /// The variant name and the fields of the stage, for provenance.
+    pub(crate) fn params(&self) -> (&'static str, Vec<(&'static str, AnnotationValueSerDe)>) {
+        match *self {
+            Self::GaussianNoise { stddev } => ("GaussianNoise", vec![("stddev", stddev.into())]),
+            Self::MotionBlur { length, angle } => (
+                "MotionBlur",
+                vec![("length", (length as i64).into()), ("angle", angle.into())],
+            ),
+            Self::Vignette { strength } => ("Vignette", vec![("strength", strength.into())]),
+            Self::Raindrops { count, max_radius } => (
+                "Raindrops",
+                vec![
+                    ("count", (count as i64).into()),
+                    ("max_radius", max_radius.into()),
+                ],
+            ),
+            Self::Exposure { gain } => ("Exposure", vec![("gain", gain.into())]),
+            Self::WhiteBalance { r, g, b } => (
+                "WhiteBalance",
+                vec![("r", r.into()), ("g", g.into()), ("b", b.into())],
+            ),
+            Self::Gamma { gamma } => ("Gamma", vec![("gamma", gamma.into())]),
+            Self::ToneCurve { knots } => ("ToneCurve", vec![("knots", knots[..].into())]),
+        }
+    }
+}
+
+/// Record of the augmentation applied to one frame.
+///
+/// `seed` is the per-frame seed for the random stages, so replaying the
+/// stages with it on the original frame reproduces the output exactly.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImageAugmentationSerDe {
+    pub seed: u64,
+    pub stages: Vec<ImageAugmentationStageSerDe>,
+}
+
+/// `seed 7: GaussianNoise, MotionBlur, Vignette`
+impl fmt::Display for ImageAugmentationSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "seed {}", self.seed)?;
+        for (i, stage) in self.stages.iter().enumerate() {
+            let sep = if i == 0 { ": " } else { ", " };
+            write!(f, "{sep}{}", stage.params().0)?;
+        }
+        Ok(())
+    }
+}
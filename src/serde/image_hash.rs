@@ -0,0 +1,106 @@
+use carla::sensor::data::Color;
+use ndarray::{Array2, ArrayView2};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::fmt;
+
+/// Perceptual hashes of a camera frame, for similarity search and
+/// duplicate-scene detection.
+///
+/// Both hashes are 64 bits; compare them with the `*_distance` methods.
+/// Unrelated frames land around 32 differing bits, near-duplicates well
+/// below that.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ImageHashSerDe {
+    /// Difference hash: sign of the horizontal gradient on a 9×8 thumbnail.
+    pub dhash: u64,
+    /// DCT hash: low 8×8 frequencies of a 32×32 thumbnail against their
+    /// median.
+    pub phash: u64,
+}
+
+impl ImageHashSerDe {
+    pub fn from_array(array: ArrayView2<'_, Color>) -> Self {
+        Self {
+            dhash: dhash(array),
+            phash: phash(array),
+        }
+    }
+
+    pub fn dhash_distance(&self, other: &Self) -> u32 {
+        (self.dhash ^ other.dhash).count_ones()
+    }
+
+    pub fn phash_distance(&self, other: &Self) -> u32 {
+        (self.phash ^ other.phash).count_ones()
+    }
+}
+
+#[inline]
+fn luma(c: &Color) -> f32 {
+    0.299 * c.r as f32 + 0.587 * c.g as f32 + 0.114 * c.b as f32
+}
+
+// Box-filtered grayscale thumbnail; every output cell covers at least one
+// source pixel, so tiny frames are upsampled by repetition.
+fn thumbnail(array: ArrayView2<'_, Color>, rows: usize, cols: usize) -> Array2<f32> {
+    let (h, w) = array.dim();
+    if h == 0 || w == 0 {
+        return Array2::zeros((rows, cols));
+    }
+    let span = |i: usize, n: usize, src: usize| {
+        let start = i * src / n;
+        let end = ((i + 1) * src / n).max(start + 1);
+        start..end
+    };
+    Array2::from_shape_fn((rows, cols), |(r, c)| {
+        let (ys, xs) = (span(r, rows, h), span(c, cols, w));
+        let n = (ys.len() * xs.len()) as f32;
+        let mut sum = 0.0;
+        for y in ys {
+            for x in xs.clone() {
+                sum += luma(&array[(y, x)]);
+            }
+        }
+        sum / n
+    })
+}
+
+fn dhash(array: ArrayView2<'_, Color>) -> u64 {
+    let t = thumbnail(array, 8, 9);
+    let mut bits = 0u64;
+    for r in 0..8 {
+        for c in 0..8 {
+            bits = (bits << 1) | (t[(r, c)] < t[(r, c + 1)]) as u64;
+        }
+    }
+    bits
+}
+
+fn phash(array: ArrayView2<'_, Color>) -> u64 {
+    const N: usize = 32;
+    const K: usize = 8;
+    let t = thumbnail(array, N, N);
+
+    // only the K lowest DCT-II frequencies per axis are needed
+    let basis = Array2::from_shape_fn((K, N), |(k, n)| {
+        (PI / N as f32 * (n as f32 + 0.5) * k as f32).cos()
+    });
+    let rows = basis.dot(&t);
+    let coeffs = rows.dot(&basis.t());
+
+    // the DC term only encodes overall brightness
+    let mut ac: Vec<f32> = coeffs.iter().skip(1).copied().collect();
+    ac.sort_by(f32::total_cmp);
+    let median = ac[ac.len() / 2];
+
+    coeffs
+        .iter()
+        .fold(0u64, |bits, &v| (bits << 1) | (v > median) as u64)
+}
+
+impl fmt::Display for ImageHashSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "dhash={:016x} phash={:016x}", self.dhash, self.phash)
+    }
+}
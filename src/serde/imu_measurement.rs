@@ -1,30 +1,248 @@
-use serde::{Deserialize, Serialize};
-use crate::Vector3DSerDe;
+use crate::{AnnotationsSerDe, ImuNoiseModelSerDe, Vector3DSerDe, Vector3Remote};
+use carla::sensor::data::ImuMeasurement as ImuMeasurementEvent;
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+/// Remote schema for the carla type, serialize-only: it reads the
+/// measurement through its accessors.
+#[derive(Serialize)]
+#[serde(remote = "ImuMeasurementEvent")]
+pub struct ImuMeasurementRemote {
+    #[serde(getter = "ImuMeasurementEvent::accelerometer", with = "Vector3Remote")]
+    pub accelerometer: Vector3<f32>,
+    #[serde(getter = "ImuMeasurementEvent::gyroscope", with = "Vector3Remote")]
+    pub gyroscope: Vector3<f32>,
+    #[serde(getter = "ImuMeasurementEvent::compass")]
+    pub compass: f32,
+}
+
+/// Borrowed, zero-copy serializer
+///
+/// Serializes like [`ImuMeasurementSerDe`] without a noise model.
+#[derive(Clone, Copy, Serialize)]
+#[serde(transparent)]
+pub struct ImuMeasurementSerBorrowed<'a> {
+    #[serde(with = "ImuMeasurementRemote")]
+    pub measurement: &'a ImuMeasurementEvent,
+}
+
+impl<'a> From<&'a ImuMeasurementEvent> for ImuMeasurementSerBorrowed<'a> {
+    fn from(measurement: &'a ImuMeasurementEvent) -> Self {
+        Self { measurement }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ImuMeasurementSerDe {
     pub accelerometer: Vector3DSerDe,
     pub gyroscope: Vector3DSerDe,
     pub compass: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noise_model: Option<ImuNoiseModelSerDe>,
+}
+
+/// An IMU sample as a [`SensorDataSerDe`](crate::SensorDataSerDe) carries
+/// it, with its annotations; kept apart from the measurement so that stays
+/// `Copy`. Serialized as the measurement with an `annotations` field, and
+/// derefs to the measurement.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(from = "ImuFrameOwned")]
+pub struct ImuFrameSerDe {
+    pub measurement: ImuMeasurementSerDe,
+    pub annotations: AnnotationsSerDe,
+}
+
+// the wire form of `ImuFrameSerDe`, to serialize and to deserialize
+#[derive(Serialize)]
+#[serde(rename = "ImuMeasurementSerDe")]
+struct ImuFrameFields<'a> {
+    accelerometer: Vector3DSerDe,
+    gyroscope: Vector3DSerDe,
+    compass: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    noise_model: Option<ImuNoiseModelSerDe>,
+    #[serde(skip_serializing_if = "no_annotations")]
+    annotations: &'a AnnotationsSerDe,
+}
+
+fn no_annotations(annotations: &&AnnotationsSerDe) -> bool {
+    annotations.is_empty()
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "ImuMeasurementSerDe")]
+struct ImuFrameOwned {
+    accelerometer: Vector3DSerDe,
+    gyroscope: Vector3DSerDe,
+    compass: f32,
+    #[serde(default)]
+    noise_model: Option<ImuNoiseModelSerDe>,
+    #[serde(default)]
+    annotations: AnnotationsSerDe,
 }
 
-impl From<carla::sensor::data::ImuMeasurement> for ImuMeasurementSerDe {
-    fn from(m: carla::sensor::data::ImuMeasurement) -> Self {
+impl From<ImuFrameOwned> for ImuFrameSerDe {
+    fn from(f: ImuFrameOwned) -> Self {
         Self {
-            accelerometer: m.accelerometer().into(),
-            gyroscope: m.gyroscope().into(),
-            compass: m.compass(),
+            measurement: ImuMeasurementSerDe {
+                accelerometer: f.accelerometer,
+                gyroscope: f.gyroscope,
+                compass: f.compass,
+                noise_model: f.noise_model,
+            },
+            annotations: f.annotations,
+        }
+    }
+}
+
+impl Serialize for ImuFrameSerDe {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let m = self.measurement;
+        ImuFrameFields {
+            accelerometer: m.accelerometer,
+            gyroscope: m.gyroscope,
+            compass: m.compass,
+            noise_model: m.noise_model,
+            annotations: &self.annotations,
         }
+        .serialize(serializer)
     }
 }
 
-// (optional but handy) allow converting from a reference too
-impl From<&carla::sensor::data::ImuMeasurement> for ImuMeasurementSerDe {
-    fn from(m: &carla::sensor::data::ImuMeasurement) -> Self {
+impl From<ImuMeasurementSerDe> for ImuFrameSerDe {
+    fn from(measurement: ImuMeasurementSerDe) -> Self {
+        Self {
+            measurement,
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+}
+
+impl Deref for ImuFrameSerDe {
+    type Target = ImuMeasurementSerDe;
+
+    fn deref(&self) -> &ImuMeasurementSerDe {
+        &self.measurement
+    }
+}
+
+impl DerefMut for ImuFrameSerDe {
+    fn deref_mut(&mut self) -> &mut ImuMeasurementSerDe {
+        &mut self.measurement
+    }
+}
+
+impl From<ImuMeasurementEvent> for ImuMeasurementSerDe {
+    fn from(m: ImuMeasurementEvent) -> Self {
+        Self::from(&m)
+    }
+}
+
+impl From<&ImuMeasurementEvent> for ImuMeasurementSerDe {
+    fn from(m: &ImuMeasurementEvent) -> Self {
         Self {
             accelerometer: m.accelerometer().into(),
             gyroscope: m.gyroscope().into(),
             compass: m.compass(),
+            noise_model: None,
         }
     }
 }
+
+impl From<&ImuMeasurementSerBorrowed<'_>> for ImuMeasurementSerDe {
+    fn from(value: &ImuMeasurementSerBorrowed<'_>) -> Self {
+        Self::from(value.measurement)
+    }
+}
+
+impl ImuMeasurementSerDe {
+    /// Attach the noise model the measurement was generated or augmented with.
+    pub fn with_noise_model(mut self, model: ImuNoiseModelSerDe) -> Self {
+        self.noise_model = Some(model);
+        self
+    }
+}
+
+impl fmt::Debug for ImuMeasurementSerBorrowed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImuMeasurementSerBorrowed")
+            .field(
+                "accelerometer",
+                &Vector3DSerDe::from(self.measurement.accelerometer()),
+            )
+            .field(
+                "gyroscope",
+                &Vector3DSerDe::from(self.measurement.gyroscope()),
+            )
+            .field("compass", &self.measurement.compass())
+            .finish()
+    }
+}
+
+fn write_imu_summary(
+    f: &mut fmt::Formatter<'_>,
+    accelerometer: Vector3DSerDe,
+    gyroscope: Vector3DSerDe,
+    compass: f32,
+) -> fmt::Result {
+    write!(
+        f,
+        "IMU accel {} m/s², gyro {} rad/s, compass {:.1}°",
+        accelerometer,
+        gyroscope,
+        compass.to_degrees()
+    )
+}
+
+/// `IMU accel (0.00, 0.00, 9.81) m/s², gyro (0.00, 0.00, 0.01) rad/s, compass 90.0°`
+impl fmt::Display for ImuMeasurementSerBorrowed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_imu_summary(
+            f,
+            self.measurement.accelerometer().into(),
+            self.measurement.gyroscope().into(),
+            self.measurement.compass(),
+        )
+    }
+}
+
+impl fmt::Display for ImuMeasurementSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_imu_summary(f, self.accelerometer, self.gyroscope, self.compass)
+    }
+}
+
+impl fmt::Display for ImuFrameSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.measurement.fmt(f)
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use crate::SensorDataSerDe;
+
+    #[test]
+    fn frames_keep_annotations_next_to_a_copy_measurement() {
+        let mut frame = crate::fixtures::imu_frame();
+        let measurement = *frame;
+        frame.annotations.insert("label", "turning");
+        let data = SensorDataSerDe::Imu(frame.clone());
+        let json = serde_json::to_string(&data).unwrap();
+        assert!(json.contains(r#""compass":1.57,"annotations":{"#), "{json}");
+        #[cfg(feature = "json-writer")]
+        assert_eq!(
+            crate::json_writer::ImuJsonWriter::new().write_frame(&frame),
+            json
+        );
+
+        let back: SensorDataSerDe = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, data);
+        let SensorDataSerDe::Imu(back) = back else {
+            unreachable!()
+        };
+        assert_eq!(back.measurement, measurement);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::Vector3DSerDe;
+use carla::client::{ActorAttributeValueKind, ActorBase};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Bias and white-noise parameters of an IMU.
+///
+/// The stddev/bias fields mirror the `noise_*` attributes of
+/// `sensor.other.imu`; `accelerometer_bias` and `compass_stddev` have no
+/// CARLA counterpart and are only used by [`crate::augment::ImuAugmenter`].
+/// All zero by default, which is also CARLA's default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImuNoiseModelSerDe {
+    pub accelerometer_stddev: Vector3DSerDe,
+    pub accelerometer_bias: Vector3DSerDe,
+    pub gyroscope_stddev: Vector3DSerDe,
+    pub gyroscope_bias: Vector3DSerDe,
+    pub compass_stddev: f32,
+    pub seed: u64,
+}
+
+/// Reads the attributes from a spawned IMU sensor; missing ones stay zero.
+impl<A: ActorBase> From<&A> for ImuNoiseModelSerDe {
+    fn from(actor: &A) -> Self {
+        let mut model = Self::default();
+        for attr in actor.attributes().iter() {
+            let value = match attr.value() {
+                Some(ActorAttributeValueKind::F32(v)) => v,
+                Some(ActorAttributeValueKind::Int(v)) if attr.id() == "noise_seed" => {
+                    model.seed = v as u64;
+                    continue;
+                }
+                _ => continue,
+            };
+            match attr.id().as_str() {
+                "noise_accel_stddev_x" => model.accelerometer_stddev.x = value,
+                "noise_accel_stddev_y" => model.accelerometer_stddev.y = value,
+                "noise_accel_stddev_z" => model.accelerometer_stddev.z = value,
+                "noise_gyro_stddev_x" => model.gyroscope_stddev.x = value,
+                "noise_gyro_stddev_y" => model.gyroscope_stddev.y = value,
+                "noise_gyro_stddev_z" => model.gyroscope_stddev.z = value,
+                "noise_gyro_bias_x" => model.gyroscope_bias.x = value,
+                "noise_gyro_bias_y" => model.gyroscope_bias.y = value,
+                "noise_gyro_bias_z" => model.gyroscope_bias.z = value,
+                _ => {}
+            }
+        }
+        model
+    }
+}
+
+/// `accel σ (0.01, 0.01, 0.01) bias (0.00, 0.00, 0.00), gyro σ (0.00, 0.00, 0.00) bias (0.00, 0.00, 0.00), compass σ 0.00, seed 0`
+impl fmt::Display for ImuNoiseModelSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "accel σ {} bias {}, gyro σ {} bias {}, compass σ {:.2}, seed {}",
+            self.accelerometer_stddev,
+            self.accelerometer_bias,
+            self.gyroscope_stddev,
+            self.gyroscope_bias,
+            self.compass_stddev,
+            self.seed
+        )
+    }
+}
@@ -0,0 +1,304 @@
+use super::image::{write_full_matrix, write_preview_matrix};
+use crate::{AnnotationsSerDe, ImageEventSerDe, ImageShapeError, SemanticSegmentationImageSerDe};
+use carla::sensor::data::{Color, Image as ImageEvent};
+use ndarray::{Array2, ArrayView1};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+const PREVIEW_W: usize = 4;
+const PREVIEW_H: usize = 3;
+
+/// Class and object of one pixel.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct InstancePixelSerDe {
+    /// Semantic tag, as in semantic segmentation.
+    pub class: u8,
+    /// Object id, unique per object within a frame.
+    pub instance: u16,
+}
+
+impl From<&Color> for InstancePixelSerDe {
+    /// CARLA's encoding: the tag in red, the object id in green (low byte)
+    /// and blue (high byte).
+    fn from(px: &Color) -> Self {
+        Self {
+            class: px.r,
+            instance: u16::from_le_bytes([px.g, px.b]),
+        }
+    }
+}
+
+/// A `sensor.camera.instance_segmentation` frame as classes and object ids.
+///
+/// The pixels are decoded when the frame is converted, `height x width`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "InstanceSegmentationImageRaw")]
+pub struct InstanceSegmentationImageSerDe {
+    pub height: usize,
+    pub width: usize,
+    pub fov_angle: f32,
+    #[serde(with = "super::array_rows")]
+    pub array: Array2<InstancePixelSerDe>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+// wire form of InstanceSegmentationImageSerDe, before the shape check
+#[derive(Deserialize)]
+struct InstanceSegmentationImageRaw {
+    height: usize,
+    width: usize,
+    fov_angle: f32,
+    #[serde(with = "super::array_rows")]
+    array: Array2<InstancePixelSerDe>,
+    #[serde(default)]
+    annotations: AnnotationsSerDe,
+}
+
+impl TryFrom<InstanceSegmentationImageRaw> for InstanceSegmentationImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(v: InstanceSegmentationImageRaw) -> Result<Self, Self::Error> {
+        let image = Self {
+            height: v.height,
+            width: v.width,
+            fov_angle: v.fov_angle,
+            array: v.array,
+            annotations: v.annotations,
+        };
+        image.validate()?;
+        Ok(image)
+    }
+}
+
+/// Fails if the array does not hold `height x width` pixels.
+impl TryFrom<&ImageEventSerDe> for InstanceSegmentationImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(image: &ImageEventSerDe) -> Result<Self, Self::Error> {
+        // the array is in buffer order whichever shape it has
+        let pixels = image.array.iter().map(InstancePixelSerDe::from).collect();
+        Ok(Self {
+            height: image.height,
+            width: image.width,
+            fov_angle: image.fov_angle,
+            array: pixels_array(image.height, image.width, pixels, image.array.dim())?,
+            annotations: image.annotations.clone(),
+        })
+    }
+}
+
+impl TryFrom<&ImageEvent> for InstanceSegmentationImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(image: &ImageEvent) -> Result<Self, Self::Error> {
+        let pixels = image
+            .as_slice()
+            .iter()
+            .map(InstancePixelSerDe::from)
+            .collect();
+        let shape = image.as_array().dim();
+        Ok(Self {
+            height: image.height(),
+            width: image.width(),
+            fov_angle: image.fov_angle(),
+            array: pixels_array(image.height(), image.width(), pixels, shape)?,
+            annotations: AnnotationsSerDe::default(),
+        })
+    }
+}
+
+impl TryFrom<ImageEvent> for InstanceSegmentationImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(image: ImageEvent) -> Result<Self, Self::Error> {
+        Self::try_from(&image)
+    }
+}
+
+fn pixels_array(
+    height: usize,
+    width: usize,
+    pixels: Vec<InstancePixelSerDe>,
+    shape: (usize, usize),
+) -> Result<Array2<InstancePixelSerDe>, ImageShapeError> {
+    Array2::from_shape_vec((height, width), pixels).map_err(|_| ImageShapeError {
+        height,
+        width,
+        len: height.saturating_mul(width),
+        is_empty: height == 0 || width == 0,
+        shape,
+    })
+}
+
+/// The class labels alone.
+impl From<&InstanceSegmentationImageSerDe> for SemanticSegmentationImageSerDe {
+    fn from(image: &InstanceSegmentationImageSerDe) -> Self {
+        Self {
+            height: image.height,
+            width: image.width,
+            fov_angle: image.fov_angle,
+            labels: image.array.map(|px| px.class),
+            raw: None,
+            annotations: image.annotations.clone(),
+        }
+    }
+}
+
+impl InstanceSegmentationImageSerDe {
+    pub fn len(&self) -> usize {
+        self.array.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.array.is_empty()
+    }
+
+    /// The pixel at column `x`, row `y`.
+    pub fn pixel_at(&self, x: usize, y: usize) -> Option<InstancePixelSerDe> {
+        self.array.get((y, x)).copied()
+    }
+
+    /// Pixel count per object, with its class.
+    pub fn instances(&self) -> BTreeMap<u16, (u8, usize)> {
+        let mut instances = BTreeMap::new();
+        for px in &self.array {
+            instances.entry(px.instance).or_insert((px.class, 0)).1 += 1;
+        }
+        instances
+    }
+
+    /// Which pixels belong to `instance`.
+    pub fn mask(&self, instance: u16) -> Array2<bool> {
+        self.array.map(|px| px.instance == instance)
+    }
+
+    /// Check that the array is `height x width`.
+    pub fn validate(&self) -> Result<(), ImageShapeError> {
+        let shape = self.array.dim();
+        if shape == (self.height, self.width) {
+            Ok(())
+        } else {
+            Err(ImageShapeError {
+                height: self.height,
+                width: self.width,
+                len: self.height.saturating_mul(self.width),
+                is_empty: self.height == 0 || self.width == 0,
+                shape,
+            })
+        }
+    }
+
+    /// Overwrite the size fields with what the array holds.
+    pub fn reconcile_shape(&mut self) {
+        (self.height, self.width) = self.array.dim();
+    }
+}
+
+// ------------------------ Custom Debug impls ------------------------
+
+#[inline]
+fn write_instance(px: &InstancePixelSerDe, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}:{}", px.class, px.instance)
+}
+
+impl fmt::Debug for InstanceSegmentationImageSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (h, w) = self.array.dim();
+        let mut ds = f.debug_struct("InstanceSegmentationImageSerDe");
+        ds.field("height", &self.height)
+            .field("width", &self.width)
+            .field("fov_angle", &self.fov_angle)
+            .field("annotations", &self.annotations);
+        ds.finish_non_exhaustive()?;
+
+        write!(f, "\narray (class:instance) ")?;
+        if f.alternate() {
+            write!(f, "(full {}x{}) = ", h, w)?;
+            write_full_matrix(f, self.array.rows(), write_instance)
+        } else {
+            write!(
+                f,
+                "(preview {}x{}, showing {}x{}) = ",
+                h,
+                w,
+                PREVIEW_H.min(h),
+                PREVIEW_W.min(w)
+            )?;
+            write_preview_matrix(
+                f,
+                self.array.rows(),
+                h,
+                PREVIEW_H.min(h),
+                PREVIEW_W.min(w),
+                write_instance,
+                |row: &ArrayView1<'_, InstancePixelSerDe>| row.len(),
+            )
+        }
+    }
+}
+
+/// `Instances 800x600 fov=90°, 37 objects`
+impl fmt::Display for InstanceSegmentationImageSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Instances {}x{} fov={}°, {} objects",
+            self.width,
+            self.height,
+            self.fov_angle,
+            self.instances().len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(height: usize, width: usize, array: Array2<Color>) -> ImageEventSerDe {
+        ImageEventSerDe {
+            height,
+            width,
+            len: array.len(),
+            is_empty: array.is_empty(),
+            fov_angle: 90.0,
+            array,
+            augmentations: Vec::new(),
+            hash: None,
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+
+    fn pixel(r: u8, g: u8, b: u8) -> Color {
+        Color { b, g, r, a: 255 }
+    }
+
+    #[test]
+    fn decodes_class_and_instance() {
+        let array = Array2::from_elem((2, 3), pixel(10, 0x02, 0x01));
+        let decoded = InstanceSegmentationImageSerDe::try_from(&image(2, 3, array)).unwrap();
+        assert_eq!(
+            decoded.pixel_at(2, 1),
+            Some(InstancePixelSerDe {
+                class: 10,
+                instance: 0x0102,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_untrusted_dimensions() {
+        let array = Array2::from_elem((2, 2), pixel(0, 0, 0));
+        let err =
+            InstanceSegmentationImageSerDe::try_from(&image(usize::MAX, 3, array)).unwrap_err();
+        assert_eq!(err.len, usize::MAX);
+        assert_eq!(err.shape, (2, 2));
+
+        let json = r#"{"height":18446744073709551615,"width":3,"fov_angle":90.0,"array":[]}"#;
+        assert!(serde_json::from_str::<InstanceSegmentationImageSerDe>(json).is_err());
+    }
+}
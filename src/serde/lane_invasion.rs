@@ -1,8 +1,10 @@
+use crate::AnnotationsSerDe;
+use carla::road::element::LaneMarking;
 use carla::sensor::data::LaneInvasionEvent;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(remote = "carla::road::element::LaneMarking_Type")]
 pub enum LaneMarkingTypeSerDe {
     Other = 0,
@@ -18,7 +20,7 @@ pub enum LaneMarkingTypeSerDe {
     None = 10,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(remote = "carla::road::element::LaneMarking_Color")]
 pub enum LaneMarkingColorSerDe {
     Standard = 0,
@@ -29,7 +31,7 @@ pub enum LaneMarkingColorSerDe {
     Other = 5,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(remote = "carla::road::element::LaneMarking_LaneChange")]
 pub enum LaneMarkingLaneChangeSerDe {
     None = 0,
@@ -38,7 +40,7 @@ pub enum LaneMarkingLaneChangeSerDe {
     Both = 3,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct LaneMarkingSerDe {
     #[serde(with = "LaneMarkingTypeSerDe")]
     pub marking_type: carla::road::element::LaneMarking_Type,
@@ -52,26 +54,80 @@ pub struct LaneMarkingSerDe {
     pub width: f64,
 }
 
-#[derive(Serialize, Deserialize)]
+impl From<&LaneMarking> for LaneMarkingSerDe {
+    fn from(m: &LaneMarking) -> Self {
+        Self {
+            marking_type: m.type_(),
+            marking_color: m.color(),
+            lane_change: m.lane_change(),
+            width: m.width(),
+        }
+    }
+}
+
+// -------------------- &[LaneMarking] (serialize-only) --------------------
+mod slice_lane_marking {
+    use super::*;
+    use serde::ser::{SerializeSeq, Serializer};
+
+    pub fn serialize<S: Serializer>(slice: &[LaneMarking], s: S) -> Result<S::Ok, S::Error> {
+        let mut seq = s.serialize_seq(Some(slice.len()))?;
+        for m in slice {
+            seq.serialize_element(&LaneMarkingSerDe::from(m))?;
+        }
+        seq.end()
+    }
+}
+
+/// Borrowed, zero-copy serializer
+///
+/// Serializes like [`LaneInvasionEventSerDe`] without collecting the
+/// markings. The carla crate hands them out as a `Vec`, so keep that and
+/// borrow it: `LaneInvasionEventSerBorrowed::new(&event.crossed_lane_markings())`.
+#[derive(Clone, Copy, Serialize)]
+pub struct LaneInvasionEventSerBorrowed<'a> {
+    #[serde(serialize_with = "self::slice_lane_marking::serialize")]
+    pub crossed_lane_markings: &'a [LaneMarking],
+}
+
+impl<'a> LaneInvasionEventSerBorrowed<'a> {
+    pub fn new(crossed_lane_markings: &'a [LaneMarking]) -> Self {
+        Self {
+            crossed_lane_markings,
+        }
+    }
+}
+
+impl<'a> From<&'a [LaneMarking]> for LaneInvasionEventSerBorrowed<'a> {
+    fn from(crossed_lane_markings: &'a [LaneMarking]) -> Self {
+        Self::new(crossed_lane_markings)
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct LaneInvasionEventSerDe {
     pub crossed_lane_markings: Vec<LaneMarkingSerDe>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
 }
 
 impl From<LaneInvasionEvent> for LaneInvasionEventSerDe {
     fn from(value: LaneInvasionEvent) -> Self {
-        let mut crossed_lane_markings: Vec<LaneMarkingSerDe> = Vec::new();
-        for clm in value.crossed_lane_markings() {
-            let lane_marking_serde = LaneMarkingSerDe {
-                marking_type: clm.type_(),
-                marking_color: clm.color(),
-                lane_change: clm.lane_change(),
-                width: clm.width(),
-            };
-            crossed_lane_markings.push(lane_marking_serde);
-        }
+        LaneInvasionEventSerDe::from(&LaneInvasionEventSerBorrowed::new(
+            &value.crossed_lane_markings(),
+        ))
+    }
+}
 
+impl From<&LaneInvasionEventSerBorrowed<'_>> for LaneInvasionEventSerDe {
+    fn from(value: &LaneInvasionEventSerBorrowed<'_>) -> Self {
         LaneInvasionEventSerDe {
-            crossed_lane_markings,
+            crossed_lane_markings: value
+                .crossed_lane_markings
+                .iter()
+                .map(LaneMarkingSerDe::from)
+                .collect(),
+            annotations: AnnotationsSerDe::default(),
         }
     }
 }
@@ -138,6 +194,32 @@ impl fmt::Debug for LaneMarkingSerDe {
     }
 }
 
+// the carla enums only implement Clone; compare through the local mirrors
+impl PartialEq for LaneMarkingSerDe {
+    fn eq(&self, other: &Self) -> bool {
+        LaneMarkingTypeSerDe::from(self.marking_type.clone())
+            == LaneMarkingTypeSerDe::from(other.marking_type.clone())
+            && LaneMarkingColorSerDe::from(self.marking_color.clone())
+                == LaneMarkingColorSerDe::from(other.marking_color.clone())
+            && LaneMarkingLaneChangeSerDe::from(self.lane_change.clone())
+                == LaneMarkingLaneChangeSerDe::from(other.lane_change.clone())
+            && self.width == other.width
+    }
+}
+
+impl fmt::Debug for LaneInvasionEventSerBorrowed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let markings: Vec<_> = self
+            .crossed_lane_markings
+            .iter()
+            .map(LaneMarkingSerDe::from)
+            .collect();
+        f.debug_struct("LaneInvasionEventSerBorrowed")
+            .field("crossed_lane_markings", &markings)
+            .finish()
+    }
+}
+
 impl fmt::Debug for LaneInvasionEventSerDe {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("LaneInvasionEventSerDe")
@@ -145,3 +227,63 @@ impl fmt::Debug for LaneInvasionEventSerDe {
             .finish()
     }
 }
+
+/// `Standard Broken`, i.e. color and type
+impl fmt::Display for LaneMarkingSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let color = LaneMarkingColorSerDe::from(self.marking_color.clone());
+        let kind = LaneMarkingTypeSerDe::from(self.marking_type.clone());
+        write!(f, "{color:?} {kind:?}")
+    }
+}
+
+fn write_lane_invasion_summary(
+    f: &mut fmt::Formatter<'_>,
+    markings: impl IntoIterator<Item = LaneMarkingSerDe>,
+) -> fmt::Result {
+    write!(f, "Lane invasion:")?;
+    for (i, m) in markings.into_iter().enumerate() {
+        let sep = if i == 0 { " " } else { ", " };
+        write!(f, "{sep}{m}")?;
+    }
+    Ok(())
+}
+
+/// `Lane invasion: Standard Broken, Yellow Solid`
+impl fmt::Display for LaneInvasionEventSerBorrowed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_lane_invasion_summary(
+            f,
+            self.crossed_lane_markings
+                .iter()
+                .map(LaneMarkingSerDe::from),
+        )
+    }
+}
+
+impl fmt::Display for LaneInvasionEventSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_lane_invasion_summary(f, self.crossed_lane_markings.iter().cloned())
+    }
+}
+
+/// The variant name, e.g. `Broken`.
+impl fmt::Display for LaneMarkingTypeSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// The variant name, e.g. `Yellow`.
+impl fmt::Display for LaneMarkingColorSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// The variant name, e.g. `Both`.
+impl fmt::Display for LaneMarkingLaneChangeSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
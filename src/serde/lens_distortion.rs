@@ -0,0 +1,162 @@
+use crate::AnnotationValueSerDe;
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Annotation holding the [`LensDistortionSerDe::coefficients`] of an
+/// image that was distorted, e.g. by [`ImageDistortion`].
+///
+/// [`ImageDistortion`]: crate::distortion::ImageDistortion
+pub const DISTORTION_KEY: &str = "distortion";
+
+// Iterations of the numeric inverses; both converge well before.
+const UNDISTORT_ITERATIONS: usize = 20;
+
+/// Lens distortion of a camera, from the normalized coordinates of an ideal
+/// pinhole (`x / z`, `y / z` in the optical frame, x right, y down, z
+/// forward) to those of the lens.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LensDistortionSerDe {
+    /// Radial and tangential distortion, OpenCV's default model and ROS's
+    /// `plumb_bob`.
+    BrownConrady {
+        k1: f32,
+        k2: f32,
+        p1: f32,
+        p2: f32,
+        k3: f32,
+    },
+    /// Equidistant fisheye, OpenCV's `cv::fisheye` model: a ray at angle
+    /// `θ` to the optical axis lands at radius
+    /// `θ (1 + k1 θ² + k2 θ⁴ + k3 θ⁶ + k4 θ⁸)`. Sees up to 180° and beyond.
+    KannalaBrandt { k1: f32, k2: f32, k3: f32, k4: f32 },
+}
+
+impl LensDistortionSerDe {
+    /// From OpenCV's coefficient order: `[k1, k2, p1, p2, k3]` for
+    /// Brown-Conrady, `[k1, k2, k3, k4]` of `cv::fisheye` for Kannala-Brandt.
+    pub fn from_coefficients(c: &[f32]) -> Option<Self> {
+        match *c {
+            [k1, k2, p1, p2, k3] => Some(Self::BrownConrady { k1, k2, p1, p2, k3 }),
+            [k1, k2, k3, k4] => Some(Self::KannalaBrandt { k1, k2, k3, k4 }),
+            _ => None,
+        }
+    }
+
+    /// The coefficients in OpenCV's order, see
+    /// [`from_coefficients`](Self::from_coefficients).
+    pub fn coefficients(&self) -> Vec<f32> {
+        match *self {
+            Self::BrownConrady { k1, k2, p1, p2, k3 } => vec![k1, k2, p1, p2, k3],
+            Self::KannalaBrandt { k1, k2, k3, k4 } => vec![k1, k2, k3, k4],
+        }
+    }
+
+    /// The coefficients, as kept under [`DISTORTION_KEY`].
+    pub fn to_annotation(&self) -> AnnotationValueSerDe {
+        AnnotationValueSerDe::Vector(self.coefficients())
+    }
+
+    /// Distorted normalized coordinates of a ray in the optical frame;
+    /// `None` for rays the lens does not image.
+    pub fn distort(&self, ray: &Vector3<f32>) -> Option<(f32, f32)> {
+        match *self {
+            Self::BrownConrady { k1, k2, p1, p2, k3 } => {
+                if ray.z <= 0.0 {
+                    return None;
+                }
+                let (x, y) = (ray.x / ray.z, ray.y / ray.z);
+                let r2 = x * x + y * y;
+                let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+                Some((
+                    x * radial + 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x),
+                    y * radial + p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y,
+                ))
+            }
+            Self::KannalaBrandt { .. } => {
+                let r = ray.x.hypot(ray.y);
+                if r <= f32::EPSILON * ray.z.abs() {
+                    return (ray.z > 0.0).then_some((0.0, 0.0));
+                }
+                let theta_d = self.theta_d(r.atan2(ray.z))?;
+                Some((theta_d * ray.x / r, theta_d * ray.y / r))
+            }
+        }
+    }
+
+    /// The unit ray the lens images at distorted normalized coordinates
+    /// `x`, `y`; `None` outside the region where the model inverts.
+    pub fn undistort(&self, x: f32, y: f32) -> Option<Vector3<f32>> {
+        match *self {
+            Self::BrownConrady { k1, k2, p1, p2, k3 } => {
+                // Newton's method; OpenCV's fixed-point iteration diverges
+                // towards the corners of strong barrel distortion
+                let (mut u, mut v) = (x, y);
+                for _ in 0..UNDISTORT_ITERATIONS {
+                    let r2 = u * u + v * v;
+                    let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+                    let slope = k1 + r2 * (2.0 * k2 + r2 * 3.0 * k3);
+                    let ex = u * radial + 2.0 * p1 * u * v + p2 * (r2 + 2.0 * u * u) - x;
+                    let ey = v * radial + p1 * (r2 + 2.0 * v * v) + 2.0 * p2 * u * v - y;
+                    let xu = radial + 2.0 * u * u * slope + 2.0 * p1 * v + 6.0 * p2 * u;
+                    let xv = 2.0 * u * v * slope + 2.0 * p1 * u + 2.0 * p2 * v;
+                    let yv = radial + 2.0 * v * v * slope + 6.0 * p1 * v + 2.0 * p2 * u;
+                    let det = xu * yv - xv * xv;
+                    if det.abs() <= f32::EPSILON {
+                        return None;
+                    }
+                    u -= (yv * ex - xv * ey) / det;
+                    v -= (xu * ey - xv * ex) / det;
+                }
+                let ray = Vector3::new(u, v, 1.0);
+                let (xd, yd) = self.distort(&ray)?;
+                ((xd - x).hypot(yd - y) < 1e-4 * (1.0 + x.hypot(y))).then(|| ray.normalize())
+            }
+            Self::KannalaBrandt { k1, k2, k3, k4 } => {
+                let theta_d = x.hypot(y);
+                if theta_d <= f32::EPSILON {
+                    return Some(Vector3::z());
+                }
+                let mut theta = theta_d;
+                for _ in 0..UNDISTORT_ITERATIONS {
+                    let t2 = theta * theta;
+                    let slope =
+                        1.0 + t2 * (3.0 * k1 + t2 * (5.0 * k2 + t2 * (7.0 * k3 + t2 * 9.0 * k4)));
+                    if slope <= 0.0 {
+                        return None;
+                    }
+                    theta -= (self.theta_d(theta)? - theta_d) / slope;
+                }
+                if !(0.0..=std::f32::consts::PI).contains(&theta)
+                    || (self.theta_d(theta)? - theta_d).abs() > 1e-4 * (1.0 + theta_d)
+                {
+                    return None;
+                }
+                let (sin, cos) = theta.sin_cos();
+                Some(Vector3::new(sin * x / theta_d, sin * y / theta_d, cos))
+            }
+        }
+    }
+
+    // distorted radius of a ray at `theta` to the axis, Kannala-Brandt only
+    fn theta_d(&self, theta: f32) -> Option<f32> {
+        match *self {
+            Self::KannalaBrandt { k1, k2, k3, k4 } => {
+                let t2 = theta * theta;
+                Some(theta * (1.0 + t2 * (k1 + t2 * (k2 + t2 * (k3 + t2 * k4)))))
+            }
+            Self::BrownConrady { .. } => None,
+        }
+    }
+}
+
+/// `kannala-brandt [0.1, -0.02, 0, 0]`
+impl fmt::Display for LensDistortionSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::BrownConrady { .. } => "brown-conrady",
+            Self::KannalaBrandt { .. } => "kannala-brandt",
+        };
+        write!(f, "{name} {:?}", self.coefficients())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::{AnnotationsSerDe, LidarNoiseModelSerDe};
 use carla::geom::Location as CarlaLocation;
 use carla::sensor::data::{
     LidarDetection as CarlaLidarDetection, LidarMeasurement as LidarMeasurementEvent,
@@ -18,7 +19,7 @@ pub struct LocationRemote {
 }
 
 /// `with`-module for (de)serializing `CarlaLocation` by delegating to `LocationRemote`.
-mod location_with {
+pub(crate) mod location_with {
     use super::*;
     use serde::{Deserializer, Serializer};
 
@@ -66,14 +67,18 @@ mod slice_lidar_detection_remote {
 }
 
 /// Borrowed, zero-copy serializer
-#[derive(Serialize)]
+#[derive(Clone, Copy, Serialize)]
 pub struct LidarMeasurementSerBorrowed<'a> {
     pub horizontal_angle: f32,
     pub channel_count: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub len: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub is_empty: bool,
     #[serde(with = "self::slice_lidar_detection_remote")]
     pub detections: &'a [CarlaLidarDetection],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub noise_model: Option<LidarNoiseModelSerDe>,
 }
 
 impl<'a> From<&'a LidarMeasurementEvent> for LidarMeasurementSerBorrowed<'a> {
@@ -84,10 +89,19 @@ impl<'a> From<&'a LidarMeasurementEvent> for LidarMeasurementSerBorrowed<'a> {
             len: m.len(),
             is_empty: m.is_empty(),
             detections: m.as_slice(), // borrow – zero alloc/copy
+            noise_model: None,
         }
     }
 }
 
+impl<'a> LidarMeasurementSerBorrowed<'a> {
+    /// Attach the sensor's noise/drop-off attributes to the frame.
+    pub fn with_noise_model(mut self, model: LidarNoiseModelSerDe) -> Self {
+        self.noise_model = Some(model);
+        self
+    }
+}
+
 // -------------------- Vec<LidarDetection> (round-trip) --------------------
 mod vec_lidar_detection_remote {
     use super::*;
@@ -140,31 +154,116 @@ mod vec_lidar_detection_remote {
     }
 }
 
+/// Deserializing checks `len` and `is_empty` against the detections, see
+/// [`LidarMeasurementSerDe::validate`].
 #[derive(Serialize, Deserialize)]
+#[serde(try_from = "LidarMeasurementRaw")]
 pub struct LidarMeasurementSerDe {
     pub horizontal_angle: f32,
     pub channel_count: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub len: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub is_empty: bool,
     #[serde(with = "self::vec_lidar_detection_remote")]
     pub detections: Vec<CarlaLidarDetection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noise_model: Option<LidarNoiseModelSerDe>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+// wire form of LidarMeasurementSerDe; `len` and `is_empty` are absent in
+// compact payloads
+#[derive(Deserialize)]
+struct LidarMeasurementRaw {
+    horizontal_angle: f32,
+    channel_count: usize,
+    #[serde(default)]
+    len: Option<usize>,
+    #[serde(default)]
+    is_empty: Option<bool>,
+    #[serde(with = "self::vec_lidar_detection_remote")]
+    detections: Vec<CarlaLidarDetection>,
+    #[serde(default)]
+    noise_model: Option<LidarNoiseModelSerDe>,
+    #[serde(default)]
+    annotations: AnnotationsSerDe,
+}
+
+impl TryFrom<LidarMeasurementRaw> for LidarMeasurementSerDe {
+    type Error = LidarCountError;
+
+    fn try_from(v: LidarMeasurementRaw) -> Result<Self, Self::Error> {
+        let n = v.detections.len();
+        let m = Self {
+            horizontal_angle: v.horizontal_angle,
+            channel_count: v.channel_count,
+            len: v.len.unwrap_or(n),
+            is_empty: v.is_empty.unwrap_or(n == 0),
+            detections: v.detections,
+            noise_model: v.noise_model,
+            annotations: v.annotations,
+        };
+        m.validate()?;
+        Ok(m)
+    }
+}
+
+/// The count fields of a lidar or semantic lidar measurement disagree with
+/// its points.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LidarCountError {
+    pub len: usize,
+    pub is_empty: bool,
+    /// `detections.len()`
+    pub actual: usize,
+}
+
+impl LidarCountError {
+    // `Ok` if `len` and `is_empty` describe `actual` points
+    pub(crate) fn check(len: usize, is_empty: bool, actual: usize) -> Result<(), Self> {
+        if len == actual && is_empty == (actual == 0) {
+            Ok(())
+        } else {
+            Err(Self {
+                len,
+                is_empty,
+                actual,
+            })
+        }
+    }
+}
+
+impl fmt::Display for LidarCountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "lidar measurement claims {} points (is_empty {}), but has {}",
+            self.len, self.is_empty, self.actual
+        )
+    }
+}
+
+impl std::error::Error for LidarCountError {}
+
+// CarlaLidarDetection isn't Clone, rebuild from public fields (no FFI trait bounds)
+#[inline]
+fn copy_detection(d: &CarlaLidarDetection) -> CarlaLidarDetection {
+    CarlaLidarDetection {
+        point: CarlaLocation {
+            x: d.point.x,
+            y: d.point.y,
+            z: d.point.z,
+        },
+        intensity: d.intensity,
+    }
 }
 
 impl From<LidarMeasurementEvent> for LidarMeasurementSerDe {
     fn from(m: LidarMeasurementEvent) -> Self {
-        // If CarlaLidarDetection isn't Clone, rebuild from public fields (no FFI trait bounds):
-        let detections: Vec<CarlaLidarDetection> = m
-            .as_slice()
-            .iter()
-            .map(|d| CarlaLidarDetection {
-                point: CarlaLocation {
-                    x: d.point.x,
-                    y: d.point.y,
-                    z: d.point.z,
-                },
-                intensity: d.intensity,
-            })
-            .collect();
+        let detections: Vec<CarlaLidarDetection> =
+            m.as_slice().iter().map(copy_detection).collect();
 
         Self {
             horizontal_angle: m.horizontal_angle(),
@@ -172,10 +271,76 @@ impl From<LidarMeasurementEvent> for LidarMeasurementSerDe {
             len: m.len(),
             is_empty: m.is_empty(),
             detections,
+            noise_model: None,
+            annotations: AnnotationsSerDe::default(),
         }
     }
 }
 
+impl Clone for LidarMeasurementSerDe {
+    fn clone(&self) -> Self {
+        Self {
+            horizontal_angle: self.horizontal_angle,
+            channel_count: self.channel_count,
+            len: self.len,
+            is_empty: self.is_empty,
+            detections: self.detections.iter().map(copy_detection).collect(),
+            noise_model: self.noise_model,
+            annotations: self.annotations.clone(),
+        }
+    }
+}
+
+impl PartialEq for LidarMeasurementSerDe {
+    fn eq(&self, other: &Self) -> bool {
+        let same = |a: &CarlaLidarDetection, b: &CarlaLidarDetection| {
+            (a.point.x, a.point.y, a.point.z, a.intensity)
+                == (b.point.x, b.point.y, b.point.z, b.intensity)
+        };
+        self.horizontal_angle == other.horizontal_angle
+            && self.channel_count == other.channel_count
+            && self.len == other.len
+            && self.is_empty == other.is_empty
+            && self.detections.len() == other.detections.len()
+            && self
+                .detections
+                .iter()
+                .zip(&other.detections)
+                .all(|(a, b)| same(a, b))
+            && self.noise_model == other.noise_model
+            && self.annotations == other.annotations
+    }
+}
+
+impl LidarMeasurementSerDe {
+    /// Number of points, from the vector rather than the `len` field.
+    pub fn len(&self) -> usize {
+        self.detections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.detections.is_empty()
+    }
+
+    /// Check that `len` equals the number of points and `is_empty` agrees.
+    pub fn validate(&self) -> Result<(), LidarCountError> {
+        LidarCountError::check(self.len, self.is_empty, self.detections.len())
+    }
+
+    /// Overwrite `len` and `is_empty` with the number of points, e.g. after
+    /// filtering them.
+    pub fn reconcile_counts(&mut self) {
+        self.len = self.detections.len();
+        self.is_empty = self.detections.is_empty();
+    }
+
+    /// Attach the sensor's noise/drop-off attributes to the frame.
+    pub fn with_noise_model(mut self, model: LidarNoiseModelSerDe) -> Self {
+        self.noise_model = Some(model);
+        self
+    }
+}
+
 // ------------------------ Debug helpers (no allocations/copies) ------------------------
 
 #[inline]
@@ -237,7 +402,8 @@ impl<'a> fmt::Debug for LidarMeasurementSerBorrowed<'a> {
         ds.field("horizontal_angle", &self.horizontal_angle)
             .field("channel_count", &self.channel_count)
             .field("len", &self.len)
-            .field("is_empty", &self.is_empty);
+            .field("is_empty", &self.is_empty)
+            .field("noise_model", &self.noise_model);
         ds.finish_non_exhaustive()?; // header
 
         write!(f, "\ndetections ")?;
@@ -262,7 +428,9 @@ impl fmt::Debug for LidarMeasurementSerDe {
         ds.field("horizontal_angle", &self.horizontal_angle)
             .field("channel_count", &self.channel_count)
             .field("len", &self.len)
-            .field("is_empty", &self.is_empty);
+            .field("is_empty", &self.is_empty)
+            .field("noise_model", &self.noise_model)
+            .field("annotations", &self.annotations);
         ds.finish_non_exhaustive()?; // header
 
         write!(f, "\ndetections ")?;
@@ -280,3 +448,77 @@ impl fmt::Debug for LidarMeasurementSerDe {
         }
     }
 }
+
+// ------------------------ Display impls ------------------------
+
+fn write_lidar_summary<'a>(
+    f: &mut fmt::Formatter<'_>,
+    channel_count: usize,
+    detections: impl IntoIterator<Item = &'a CarlaLidarDetection>,
+) -> fmt::Result {
+    let (mut n, mut min, mut max) = (0usize, f32::INFINITY, f32::NEG_INFINITY);
+    for d in detections {
+        let p = &d.point;
+        let range = (p.x * p.x + p.y * p.y + p.z * p.z).sqrt();
+        n += 1;
+        min = min.min(range);
+        max = max.max(range);
+    }
+    write!(f, "Lidar {n} points, {channel_count} channels")?;
+    if n > 0 {
+        write!(f, ", range {min:.1}–{max:.1} m")?;
+    }
+    Ok(())
+}
+
+/// `Lidar 120000 points, 32 channels, range 0.8–99.6 m`
+impl fmt::Display for LidarMeasurementSerBorrowed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_lidar_summary(f, self.channel_count, self.detections)
+    }
+}
+
+impl fmt::Display for LidarMeasurementSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_lidar_summary(f, self.channel_count, &self.detections)
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use crate::SensorDataSerDe;
+
+    #[test]
+    fn rejects_counts_that_disagree_with_the_points() {
+        let mut lidar = crate::fixtures::lidar_frame();
+        let n = lidar.detections.len();
+        assert_eq!(lidar.validate(), Ok(()));
+
+        lidar.len = n - 1;
+        assert_eq!(
+            lidar.validate(),
+            Err(LidarCountError {
+                len: n - 1,
+                is_empty: false,
+                actual: n
+            })
+        );
+        let json = serde_json::to_string(&SensorDataSerDe::Lidar(lidar.clone())).unwrap();
+        assert!(serde_json::from_str::<SensorDataSerDe>(&json).is_err());
+
+        lidar.len = n;
+        lidar.is_empty = true;
+        assert!(lidar.validate().is_err());
+    }
+
+    #[test]
+    fn reconciles_counts_after_filtering() {
+        let mut lidar = crate::fixtures::lidar_frame();
+        lidar.detections.retain(|d| d.intensity > 0.5);
+        assert!(lidar.validate().is_err());
+        lidar.reconcile_counts();
+        assert_eq!(lidar.validate(), Ok(()));
+        assert_eq!(lidar.len, lidar.detections.len());
+    }
+}
@@ -0,0 +1,63 @@
+use carla::client::ActorBase;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Noise and drop-off attributes of a `sensor.lidar.ray_cast` blueprint.
+///
+/// Defaults match the values CARLA uses when the attribute is not set.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LidarNoiseModelSerDe {
+    pub atmosphere_attenuation_rate: f32,
+    pub dropoff_general_rate: f32,
+    pub dropoff_intensity_limit: f32,
+    pub dropoff_zero_intensity: f32,
+    pub noise_stddev: f32,
+}
+
+impl Default for LidarNoiseModelSerDe {
+    fn default() -> Self {
+        Self {
+            atmosphere_attenuation_rate: 0.004,
+            dropoff_general_rate: 0.45,
+            dropoff_intensity_limit: 0.8,
+            dropoff_zero_intensity: 0.4,
+            noise_stddev: 0.0,
+        }
+    }
+}
+
+/// Reads the attributes from a spawned lidar sensor; missing ones keep their default.
+impl<A: ActorBase> From<&A> for LidarNoiseModelSerDe {
+    fn from(actor: &A) -> Self {
+        let mut model = Self::default();
+        for attr in actor.attributes().iter() {
+            let Some(value) = attr.value().and_then(|v| v.try_into_f32().ok()) else {
+                continue;
+            };
+            match attr.id().as_str() {
+                "atmosphere_attenuation_rate" => model.atmosphere_attenuation_rate = value,
+                "dropoff_general_rate" => model.dropoff_general_rate = value,
+                "dropoff_intensity_limit" => model.dropoff_intensity_limit = value,
+                "dropoff_zero_intensity" => model.dropoff_zero_intensity = value,
+                "noise_stddev" => model.noise_stddev = value,
+                _ => {}
+            }
+        }
+        model
+    }
+}
+
+/// `noise σ 0.000 m, attenuation 0.004, drop-off 45% (40% at zero intensity, none above 0.80)`
+impl fmt::Display for LidarNoiseModelSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "noise σ {:.3} m, attenuation {:.3}, drop-off {:.0}% ({:.0}% at zero intensity, none above {:.2})",
+            self.noise_stddev,
+            self.atmosphere_attenuation_rate,
+            self.dropoff_general_rate * 100.0,
+            self.dropoff_zero_intensity * 100.0,
+            self.dropoff_intensity_limit
+        )
+    }
+}
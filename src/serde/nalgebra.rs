@@ -1,7 +1,8 @@
-use nalgebra::Vector3;
+use nalgebra::{Vector2, Vector3};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Vector3DSerDe {
     pub x: f32,
     pub y: f32,
@@ -53,3 +54,82 @@ impl From<Vector3DSerDe> for carla::geom::Vector3D {
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vector2DSerDe {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl From<Vector2<f32>> for Vector2DSerDe {
+    fn from(v: Vector2<f32>) -> Self {
+        Self { x: v.x, y: v.y }
+    }
+}
+
+impl From<&Vector2<f32>> for Vector2DSerDe {
+    fn from(v: &Vector2<f32>) -> Self {
+        Self { x: v.x, y: v.y }
+    }
+}
+
+impl From<Vector2DSerDe> for Vector2<f32> {
+    fn from(v: Vector2DSerDe) -> Self {
+        Vector2::new(v.x, v.y)
+    }
+}
+
+impl From<&carla::geom::Vector2D> for Vector2DSerDe {
+    fn from(v: &carla::geom::Vector2D) -> Self {
+        Self { x: v.x, y: v.y }
+    }
+}
+
+impl From<Vector2DSerDe> for carla::geom::Vector2D {
+    fn from(v: Vector2DSerDe) -> Self {
+        carla::geom::Vector2D { x: v.x, y: v.y }
+    }
+}
+
+/// Remote schema for nalgebra's `Vector3<f32>`, as `{x, y, z}` like
+/// [`Vector3DSerDe`]
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Vector3<f32>")]
+pub struct Vector3Remote {
+    #[serde(getter = "vector3_x")]
+    pub x: f32,
+    #[serde(getter = "vector3_y")]
+    pub y: f32,
+    #[serde(getter = "vector3_z")]
+    pub z: f32,
+}
+
+fn vector3_x(v: &Vector3<f32>) -> f32 {
+    v.x
+}
+
+fn vector3_y(v: &Vector3<f32>) -> f32 {
+    v.y
+}
+
+fn vector3_z(v: &Vector3<f32>) -> f32 {
+    v.z
+}
+
+impl From<Vector3Remote> for Vector3<f32> {
+    fn from(v: Vector3Remote) -> Self {
+        Vector3::new(v.x, v.y, v.z)
+    }
+}
+
+impl fmt::Display for Vector3DSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:.2}, {:.2}, {:.2})", self.x, self.y, self.z)
+    }
+}
+
+impl fmt::Display for Vector2DSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:.2}, {:.2})", self.x, self.y)
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{
+    ActorSnapshotSerDe, BoundingBoxSerDe, CameraInfoSerDe, DepthImageSerDe, TransformSerDe,
+};
+use nalgebra::{Isometry3, Point3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A ground-truth object of a frame: an actor or level object, its box in
+/// the world and, once computed, how much of it one camera sees.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObjectLabelSerDe {
+    pub id: carla::rpc::ActorId,
+    /// The blueprint or semantic class, e.g. `vehicle.tesla.model3`.
+    pub label: String,
+    /// In the world frame, see [`ObjectLabelSerDe::from_actor`].
+    pub bounding_box: BoundingBoxSerDe,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<VisibilitySerDe>,
+}
+
+impl ObjectLabelSerDe {
+    pub fn new(
+        id: carla::rpc::ActorId,
+        label: impl Into<String>,
+        bounding_box: BoundingBoxSerDe,
+    ) -> Self {
+        Self {
+            id,
+            label: label.into(),
+            bounding_box,
+            visibility: None,
+        }
+    }
+
+    /// The label of an actor whose box `bounding_box` is relative to it,
+    /// as CARLA reports actor boxes.
+    pub fn from_actor(
+        actor: &ActorSnapshotSerDe,
+        label: impl Into<String>,
+        bounding_box: &BoundingBoxSerDe,
+    ) -> Self {
+        let pose = actor.transform * Isometry3::from(bounding_box.transform());
+        let world = TransformSerDe::from(&pose);
+        let bounding_box = BoundingBoxSerDe {
+            extent: bounding_box.extent,
+            location: world.location,
+            rotation: world.rotation,
+        };
+        Self::new(actor.id, label, bounding_box)
+    }
+
+    /// Whether at least `fraction` of the object is visible; `false` until
+    /// the visibility is computed.
+    pub fn is_visible(&self, fraction: f32) -> bool {
+        self.visibility
+            .as_ref()
+            .is_some_and(|v| v.pixels > 0 && v.fraction >= fraction)
+    }
+}
+
+/// How much of an object one camera sees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VisibilitySerDe {
+    /// Pixels of the image the box covers, counted at the estimator's
+    /// stride.
+    pub pixels: u32,
+    /// Of those, the pixels where nothing is in front of the box.
+    pub visible: u32,
+    /// `visible / pixels`, 0 for objects out of view.
+    pub fraction: f32,
+    /// `[x_min, y_min, x_max, y_max]` of the visible pixels, exclusive
+    /// maxima; `None` if none are.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub box_2d: Option<[u32; 4]>,
+}
+
+/// Visibility of ground-truth boxes in a depth frame: for every pixel the
+/// box covers, whether the depth camera sees the box or something in front
+/// of it.
+///
+/// `camera` is the world pose of the depth sensor, e.g. the vehicle's
+/// transform times the sensor mount, at the tick of the frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VisibilityEstimator {
+    camera: Isometry3<f32>,
+    tolerance: f32,
+    stride: usize,
+}
+
+impl VisibilityEstimator {
+    pub fn new(camera: Isometry3<f32>) -> Self {
+        Self {
+            camera,
+            tolerance: 0.25,
+            stride: 1,
+        }
+    }
+
+    /// Meters a depth pixel may lie in front of the box and still count as
+    /// the box, which absorbs the depth encoding error and surfaces the
+    /// box does not fit tightly; 0.25 by default.
+    pub fn with_tolerance(mut self, meters: f32) -> Self {
+        self.tolerance = meters;
+        self
+    }
+
+    /// Test only every `stride`-th pixel of every `stride`-th row, 1 by
+    /// default.
+    pub fn with_stride(mut self, stride: usize) -> Self {
+        self.stride = stride.max(1);
+        self
+    }
+
+    /// Visibility of a box in the world frame in `depth`.
+    pub fn compute(
+        &self,
+        depth: &DepthImageSerDe,
+        bounding_box: &BoundingBoxSerDe,
+    ) -> VisibilitySerDe {
+        let camera = CameraInfoSerDe::from_fov(depth.width, depth.height, depth.fov_angle);
+        let pose = Isometry3::from(bounding_box.transform());
+        let box_from_camera = pose.inverse() * self.camera;
+        let e = &bounding_box.extent;
+        let extent = Vector3::new(e.x, e.y, e.z);
+
+        let Some((x0, y0, x1, y1)) = self.footprint(&camera, &box_from_camera.inverse(), &extent)
+        else {
+            return VisibilitySerDe::default();
+        };
+        let origin = box_from_camera * Point3::origin();
+        let mut v = VisibilitySerDe::default();
+        let mut bounds = [u32::MAX, u32::MAX, 0, 0];
+        for y in (y0..y1).step_by(self.stride) {
+            for x in (x0..x1).step_by(self.stride) {
+                let Some(ray) = camera.ray(x as f32 + 0.5, y as f32 + 0.5) else {
+                    continue;
+                };
+                // optical (x right, y down, z forward) -> CARLA camera axes
+                let ray = Vector3::new(ray.z, ray.x, -ray.y);
+                let Some(t) = slab_entry(&origin, &(box_from_camera * ray), &extent) else {
+                    continue;
+                };
+                v.pixels += 1;
+                // CARLA's depth is the distance along the camera's x axis
+                let hit = t * ray.x;
+                if depth
+                    .depth_at(x, y)
+                    .is_some_and(|d| d >= hit - self.tolerance)
+                {
+                    v.visible += 1;
+                    let (x, y) = (x as u32, y as u32);
+                    bounds = [
+                        bounds[0].min(x),
+                        bounds[1].min(y),
+                        bounds[2].max(x + 1),
+                        bounds[3].max(y + 1),
+                    ];
+                }
+            }
+        }
+        if v.pixels > 0 {
+            v.fraction = v.visible as f32 / v.pixels as f32;
+        }
+        if v.visible > 0 {
+            v.box_2d = Some(bounds);
+        }
+        v
+    }
+
+    /// Compute the visibility of every object.
+    pub fn label(&self, depth: &DepthImageSerDe, objects: &mut [ObjectLabelSerDe]) {
+        for object in objects {
+            object.visibility = Some(self.compute(depth, &object.bounding_box));
+        }
+    }
+
+    // pixel range the projected corners span, the whole image if a corner
+    // is behind the camera; `None` if the box is out of view
+    fn footprint(
+        &self,
+        camera: &CameraInfoSerDe,
+        camera_from_box: &Isometry3<f32>,
+        extent: &Vector3<f32>,
+    ) -> Option<(usize, usize, usize, usize)> {
+        let (w, h) = (camera.width as f32, camera.height as f32);
+        let (mut lo, mut hi) = ((f32::MAX, f32::MAX), (f32::MIN, f32::MIN));
+        let mut behind = 0;
+        for i in 0..8 {
+            let sign = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+            let corner = Point3::new(sign(1) * extent.x, sign(2) * extent.y, sign(4) * extent.z);
+            let p = camera_from_box * corner;
+            match camera.project(&Vector3::new(p.y, -p.z, p.x)) {
+                Some((u, v)) => {
+                    lo = (lo.0.min(u), lo.1.min(v));
+                    hi = (hi.0.max(u), hi.1.max(v));
+                }
+                None => behind += 1,
+            }
+        }
+        if behind == 8 {
+            return None;
+        }
+        if behind > 0 {
+            return Some((0, 0, camera.width, camera.height));
+        }
+        if hi.0 < 0.0 || hi.1 < 0.0 || lo.0 >= w || lo.1 >= h {
+            return None;
+        }
+        let clamp = |v: f32, max: f32| v.clamp(0.0, max) as usize;
+        Some((
+            clamp(lo.0.floor(), w),
+            clamp(lo.1.floor(), h),
+            clamp(hi.0.ceil(), w),
+            clamp(hi.1.ceil(), h),
+        ))
+    }
+}
+
+// distance along `dir` at which the ray from `origin` enters the box of
+// half sizes `extent` around the origin, 0 if it starts inside
+fn slab_entry(origin: &Point3<f32>, dir: &Vector3<f32>, extent: &Vector3<f32>) -> Option<f32> {
+    let (mut near, mut far) = (0.0f32, f32::MAX);
+    for axis in 0..3 {
+        if dir[axis].abs() < 1e-9 {
+            if origin[axis].abs() > extent[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t0 = (-extent[axis] - origin[axis]) / dir[axis];
+        let t1 = (extent[axis] - origin[axis]) / dir[axis];
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+    }
+    (near <= far).then_some(near)
+}
+
+/// `vehicle.tesla.model3 #24, 63% visible`
+impl fmt::Display for ObjectLabelSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} #{}", self.label, self.id)?;
+        match &self.visibility {
+            Some(v) => write!(f, ", {:.0}% visible", v.fraction * 100.0),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `63% visible, 120 of 190 px`
+impl fmt::Display for VisibilitySerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.0}% visible, {} of {} px",
+            self.fraction * 100.0,
+            self.visible,
+            self.pixels
+        )
+    }
+}
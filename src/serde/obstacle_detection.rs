@@ -1,12 +1,15 @@
-use crate::ActorSerDe;
+use crate::{ActorSerDe, AnnotationsSerDe};
 use carla::sensor::data::ObstacleDetectionEvent;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ObstacleDetectionEventSerDe {
     pub actor: ActorSerDe,
     pub other_actor: ActorSerDe,
     pub distance: f32,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
 }
 
 impl From<ObstacleDetectionEvent> for ObstacleDetectionEventSerDe {
@@ -14,7 +17,19 @@ impl From<ObstacleDetectionEvent> for ObstacleDetectionEventSerDe {
         ObstacleDetectionEventSerDe {
             actor: value.actor().into(),
             other_actor: value.other_actor().into(),
-            distance: value.distance().into(),
+            distance: value.distance(),
+            annotations: AnnotationsSerDe::default(),
         }
     }
 }
+
+/// `Obstacle walker.pedestrian.0001 at 7.5 m`
+impl fmt::Display for ObstacleDetectionEventSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Obstacle {} at {:.1} m",
+            self.other_actor.type_id, self.distance
+        )
+    }
+}
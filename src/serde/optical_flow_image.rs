@@ -0,0 +1,366 @@
+use super::image::{write_full_matrix, write_preview_matrix};
+use crate::{AnnotationsSerDe, ImageShapeError};
+use ndarray::{Array2, ArrayView1, ArrayView2};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const PREVIEW_W: usize = 3;
+const PREVIEW_H: usize = 3;
+
+// x: f32, y: f32, packed
+const RAW_PIXEL_SIZE: usize = 8;
+
+/// Apparent motion of one pixel since the previous frame, in CARLA's
+/// normalized units: a fraction of the image width and height.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpticalFlowPixelSerDe {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl OpticalFlowPixelSerDe {
+    pub fn magnitude(&self) -> f32 {
+        self.x.hypot(self.y)
+    }
+}
+
+// ------------------------ Borrowed serializer ------------------------
+
+/// Borrowed, zero-copy serializer for an optical flow image
+#[derive(Clone, Copy, Serialize)]
+pub struct OpticalFlowImageSerBorrowed<'a> {
+    pub height: usize,
+    pub width: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
+    pub len: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
+    pub is_empty: bool,
+    pub fov_angle: f32,
+    #[serde(serialize_with = "super::array_rows::serialize")]
+    pub array: ArrayView2<'a, OpticalFlowPixelSerDe>,
+}
+
+impl<'a> OpticalFlowImageSerBorrowed<'a> {
+    /// `array` is `height x width`.
+    pub fn new(fov_angle: f32, array: ArrayView2<'a, OpticalFlowPixelSerDe>) -> Self {
+        let (height, width) = array.dim();
+        Self {
+            height,
+            width,
+            len: array.len(),
+            is_empty: array.is_empty(),
+            fov_angle,
+            array,
+        }
+    }
+}
+
+impl<'a> From<&'a OpticalFlowImageSerDe> for OpticalFlowImageSerBorrowed<'a> {
+    fn from(value: &'a OpticalFlowImageSerDe) -> Self {
+        Self {
+            height: value.height,
+            width: value.width,
+            len: value.len,
+            is_empty: value.is_empty,
+            fov_angle: value.fov_angle,
+            array: value.array.view(),
+        }
+    }
+}
+
+// ------------------------ Owned, round-trip ------------------------
+
+/// Owned, round-trip serializer for an optical flow image
+///
+/// The carla crate has no binding for `OpticalFlowImage` yet; build it from
+/// the sensor's raw data with [`OpticalFlowImageSerDe::from_raw`].
+/// Deserializing checks the array against `height`, `width` and `len`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "OpticalFlowImageRaw")]
+pub struct OpticalFlowImageSerDe {
+    pub height: usize,
+    pub width: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
+    pub len: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
+    pub is_empty: bool,
+    pub fov_angle: f32,
+    #[serde(with = "super::array_rows")]
+    pub array: Array2<OpticalFlowPixelSerDe>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+// wire form of OpticalFlowImageSerDe, before the shape check
+#[derive(Deserialize)]
+struct OpticalFlowImageRaw {
+    height: usize,
+    width: usize,
+    // absent in compact payloads
+    #[serde(default)]
+    len: Option<usize>,
+    #[serde(default)]
+    is_empty: Option<bool>,
+    fov_angle: f32,
+    #[serde(with = "super::array_rows")]
+    array: Array2<OpticalFlowPixelSerDe>,
+    #[serde(default)]
+    annotations: AnnotationsSerDe,
+}
+
+impl TryFrom<OpticalFlowImageRaw> for OpticalFlowImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(v: OpticalFlowImageRaw) -> Result<Self, Self::Error> {
+        let len = v.len.unwrap_or(v.array.len());
+        let image = Self {
+            height: v.height,
+            width: v.width,
+            len,
+            is_empty: v.is_empty.unwrap_or(len == 0),
+            fov_angle: v.fov_angle,
+            array: v.array,
+            annotations: v.annotations,
+        };
+        image.validate()?;
+        Ok(image)
+    }
+}
+
+/// Raw optical flow data whose length does not match the image size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpticalFlowRawLengthError {
+    pub width: usize,
+    pub height: usize,
+    pub len: usize,
+}
+
+impl fmt::Display for OpticalFlowRawLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes of optical flow data for a {}x{} image, ",
+            self.len, self.width, self.height
+        )?;
+        match raw_len(self.width, self.height) {
+            Some(expected) => write!(f, "expected {expected}"),
+            None => f.write_str("which is too large to address"),
+        }
+    }
+}
+
+// bytes of raw data of a `width x height` image, `None` past `usize`
+fn raw_len(width: usize, height: usize) -> Option<usize> {
+    width.checked_mul(height)?.checked_mul(RAW_PIXEL_SIZE)
+}
+
+impl std::error::Error for OpticalFlowRawLengthError {}
+
+impl From<&OpticalFlowImageSerBorrowed<'_>> for OpticalFlowImageSerDe {
+    fn from(value: &OpticalFlowImageSerBorrowed<'_>) -> Self {
+        Self {
+            height: value.height,
+            width: value.width,
+            len: value.len,
+            is_empty: value.is_empty,
+            fov_angle: value.fov_angle,
+            array: value.array.to_owned(),
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+}
+
+impl OpticalFlowImageSerDe {
+    /// Decode CARLA's `raw_data` of an optical flow frame: row-major
+    /// little-endian pixels of `x: f32, y: f32`.
+    pub fn from_raw(
+        width: usize,
+        height: usize,
+        fov_angle: f32,
+        raw: &[u8],
+    ) -> Result<Self, OpticalFlowRawLengthError> {
+        if raw_len(width, height) != Some(raw.len()) {
+            return Err(OpticalFlowRawLengthError {
+                width,
+                height,
+                len: raw.len(),
+            });
+        }
+        let f32_at = |b: &[u8]| f32::from_le_bytes(b.try_into().expect("4 bytes"));
+        let pixels = raw
+            .chunks_exact(RAW_PIXEL_SIZE)
+            .map(|px| OpticalFlowPixelSerDe {
+                x: f32_at(&px[0..4]),
+                y: f32_at(&px[4..8]),
+            })
+            .collect();
+        let array = Array2::from_shape_vec((height, width), pixels).expect("length checked");
+        Ok(Self {
+            height,
+            width,
+            len: array.len(),
+            is_empty: array.is_empty(),
+            fov_angle,
+            array,
+            annotations: AnnotationsSerDe::default(),
+        })
+    }
+
+    /// Number of pixels, from the array rather than the `len` field.
+    pub fn len(&self) -> usize {
+        self.array.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.array.is_empty()
+    }
+
+    /// Check that the array is `height x width` with `len` pixels.
+    pub fn validate(&self) -> Result<(), ImageShapeError> {
+        let shape = self.array.dim();
+        if shape == (self.height, self.width)
+            && self.len == self.array.len()
+            && self.is_empty == (self.len == 0)
+        {
+            Ok(())
+        } else {
+            Err(ImageShapeError {
+                height: self.height,
+                width: self.width,
+                len: self.len,
+                is_empty: self.is_empty,
+                shape,
+            })
+        }
+    }
+
+    /// Overwrite the size fields with what the array holds.
+    pub fn reconcile_shape(&mut self) {
+        (self.height, self.width) = self.array.dim();
+        self.len = self.array.len();
+        self.is_empty = self.len == 0;
+    }
+
+    /// The largest flow in the frame; NaN pixels are skipped.
+    pub fn max_magnitude(&self) -> f32 {
+        self.array
+            .iter()
+            .map(OpticalFlowPixelSerDe::magnitude)
+            .filter(|m| !m.is_nan())
+            .fold(0.0, f32::max)
+    }
+}
+
+// ------------------------ Custom Debug impls ------------------------
+
+#[inline]
+fn write_flow(px: &OpticalFlowPixelSerDe, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "({}, {})", px.x, px.y)
+}
+
+fn write_array(
+    f: &mut fmt::Formatter<'_>,
+    array: ArrayView2<'_, OpticalFlowPixelSerDe>,
+) -> fmt::Result {
+    let (h, w) = array.dim();
+    write!(f, "\narray ")?;
+    if f.alternate() {
+        write!(f, "(full {}x{}) = ", h, w)?;
+        write_full_matrix(f, array.rows(), write_flow)
+    } else {
+        write!(
+            f,
+            "(preview {}x{}, showing {}x{}) = ",
+            h,
+            w,
+            PREVIEW_H.min(h),
+            PREVIEW_W.min(w)
+        )?;
+        write_preview_matrix(
+            f,
+            array.rows(),
+            h,
+            PREVIEW_H.min(h),
+            PREVIEW_W.min(w),
+            write_flow,
+            |row: &ArrayView1<'_, OpticalFlowPixelSerDe>| row.len(),
+        )
+    }
+}
+
+impl fmt::Debug for OpticalFlowImageSerBorrowed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ds = f.debug_struct("OpticalFlowImageSerBorrowed");
+        ds.field("height", &self.height)
+            .field("width", &self.width)
+            .field("len", &self.len)
+            .field("is_empty", &self.is_empty)
+            .field("fov_angle", &self.fov_angle);
+        ds.finish_non_exhaustive()?;
+        write_array(f, self.array)
+    }
+}
+
+impl fmt::Debug for OpticalFlowImageSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ds = f.debug_struct("OpticalFlowImageSerDe");
+        ds.field("height", &self.height)
+            .field("width", &self.width)
+            .field("len", &self.len)
+            .field("is_empty", &self.is_empty)
+            .field("fov_angle", &self.fov_angle)
+            .field("annotations", &self.annotations);
+        ds.finish_non_exhaustive()?;
+        write_array(f, self.array.view())
+    }
+}
+
+// ------------------------ Display impls ------------------------
+
+/// `Optical flow 800x600 fov=90°`
+impl fmt::Display for OpticalFlowImageSerBorrowed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Optical flow {}x{} fov={}°",
+            self.width, self.height, self.fov_angle
+        )
+    }
+}
+
+impl fmt::Display for OpticalFlowImageSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Optical flow {}x{} fov={}°",
+            self.width, self.height, self.fov_angle
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_raw_pixels() {
+        let raw: Vec<u8> = [0.5f32, -0.25, 1.0, 0.0]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let image = OpticalFlowImageSerDe::from_raw(2, 1, 90.0, &raw).unwrap();
+        assert_eq!(image.array.dim(), (1, 2));
+        assert_eq!(
+            image.array[(0, 0)],
+            OpticalFlowPixelSerDe { x: 0.5, y: -0.25 }
+        );
+        image.validate().unwrap();
+    }
+
+    #[test]
+    fn rejects_dimensions_past_usize() {
+        let err = OpticalFlowImageSerDe::from_raw(usize::MAX, 3, 90.0, &[0; 8]).unwrap_err();
+        assert_eq!(err.len, 8);
+        assert!(err.to_string().ends_with("too large to address"), "{err}");
+    }
+}
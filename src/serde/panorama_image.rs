@@ -0,0 +1,388 @@
+use super::image::{write_full_matrix, write_preview_matrix, write_rgba};
+use crate::{
+    AnnotationsSerDe, CameraInfoSerDe, ImageEventSerDe, ImageShapeError, RotationSerDe,
+    SensorDescriptionSerDe, TransformSerDe, resize::bilinear,
+};
+use carla::sensor::data::Color;
+use nalgebra::Vector3;
+use ndarray::{Array2, ArrayView1};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::fmt;
+
+const PREVIEW_W: usize = 3;
+const PREVIEW_H: usize = 3;
+
+/// An equirectangular panorama around a rig, `height x width`.
+///
+/// Columns span the longitude from -180° at the left edge to 180° at the
+/// right, 0° being the rig's forward axis and positive to its right; rows
+/// span the latitude from 90° (up) to -90°. Pixels no camera saw have an
+/// alpha of 0.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "PanoramaImageRaw")]
+pub struct PanoramaImageSerDe {
+    pub height: usize,
+    pub width: usize,
+    #[serde(with = "super::image::array2_color_remote")]
+    pub array: Array2<Color>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+// wire form of PanoramaImageSerDe, before the shape check
+#[derive(Deserialize)]
+struct PanoramaImageRaw {
+    height: usize,
+    width: usize,
+    #[serde(with = "super::image::array2_color_remote")]
+    array: Array2<Color>,
+    #[serde(default)]
+    annotations: AnnotationsSerDe,
+}
+
+impl TryFrom<PanoramaImageRaw> for PanoramaImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(v: PanoramaImageRaw) -> Result<Self, Self::Error> {
+        let image = Self {
+            height: v.height,
+            width: v.width,
+            array: v.array,
+            annotations: v.annotations,
+        };
+        image.validate()?;
+        Ok(image)
+    }
+}
+
+impl PanoramaImageSerDe {
+    /// Rig-frame unit direction through the point `x`, `y` of the image,
+    /// in pixels from its top left corner; pixel centers are at `.5`.
+    pub fn direction(&self, x: f32, y: f32) -> Vector3<f32> {
+        let longitude = (x / self.width as f32 - 0.5) * 2.0 * PI;
+        let latitude = (0.5 - y / self.height as f32) * PI;
+        let (sin_lat, cos_lat) = latitude.sin_cos();
+        let (sin_lon, cos_lon) = longitude.sin_cos();
+        Vector3::new(cos_lat * cos_lon, cos_lat * sin_lon, sin_lat)
+    }
+
+    /// Where `direction`, in the rig frame, lands in the image; the inverse
+    /// of [`direction`](Self::direction).
+    pub fn pixel(&self, direction: &Vector3<f32>) -> Option<(f32, f32)> {
+        let d = direction.try_normalize(f32::EPSILON)?;
+        let longitude = d.y.atan2(d.x);
+        let latitude = d.z.clamp(-1.0, 1.0).asin();
+        Some((
+            (longitude / (2.0 * PI) + 0.5) * self.width as f32,
+            (0.5 - latitude / PI) * self.height as f32,
+        ))
+    }
+
+    /// Share of pixels some camera saw.
+    pub fn coverage(&self) -> f32 {
+        if self.array.is_empty() {
+            return 0.0;
+        }
+        let seen = self.array.iter().filter(|px| px.a > 0).count();
+        seen as f32 / self.array.len() as f32
+    }
+
+    /// Check that the array is `height x width`.
+    pub fn validate(&self) -> Result<(), ImageShapeError> {
+        let shape = self.array.dim();
+        if shape == (self.height, self.width) {
+            Ok(())
+        } else {
+            Err(ImageShapeError {
+                height: self.height,
+                width: self.width,
+                len: self.height * self.width,
+                is_empty: self.height * self.width == 0,
+                shape,
+            })
+        }
+    }
+}
+
+impl PartialEq for PanoramaImageSerDe {
+    fn eq(&self, other: &Self) -> bool {
+        let same_px = |a: &Color, b: &Color| (a.b, a.g, a.r, a.a) == (b.b, b.g, b.r, b.a);
+        self.height == other.height
+            && self.width == other.width
+            && self.array.dim() == other.array.dim()
+            && self
+                .array
+                .iter()
+                .zip(&other.array)
+                .all(|(a, b)| same_px(a, b))
+            && self.annotations == other.annotations
+    }
+}
+
+impl fmt::Debug for PanoramaImageSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (h, w) = self.array.dim();
+        let mut ds = f.debug_struct("PanoramaImageSerDe");
+        ds.field("height", &self.height)
+            .field("width", &self.width)
+            .field("annotations", &self.annotations);
+        ds.finish_non_exhaustive()?;
+
+        write!(f, "\narray ")?;
+        if f.alternate() {
+            write!(f, "(full {}x{}) = ", h, w)?;
+            write_full_matrix(f, self.array.rows(), write_rgba)
+        } else {
+            write!(
+                f,
+                "(preview {}x{}, showing {}x{}) = ",
+                h,
+                w,
+                PREVIEW_H.min(h),
+                PREVIEW_W.min(w)
+            )?;
+            write_preview_matrix(
+                f,
+                self.array.rows(),
+                h,
+                PREVIEW_H.min(h),
+                PREVIEW_W.min(w),
+                write_rgba,
+                |row: &ArrayView1<'_, Color>| row.len(),
+            )
+        }
+    }
+}
+
+/// `Panorama 2048x1024, 100% covered`
+impl fmt::Display for PanoramaImageSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Panorama {}x{}, {:.0}% covered",
+            self.width,
+            self.height,
+            100.0 * self.coverage()
+        )
+    }
+}
+
+/// The six 90° cameras of a cube rig.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum CubeFace {
+    Front,
+    Right,
+    Back,
+    Left,
+    Up,
+    Down,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::Front,
+        CubeFace::Right,
+        CubeFace::Back,
+        CubeFace::Left,
+        CubeFace::Up,
+        CubeFace::Down,
+    ];
+
+    /// Mount rotation of the face's camera relative to the rig.
+    pub fn rotation(&self) -> RotationSerDe {
+        let (pitch, yaw) = match self {
+            CubeFace::Front => (0.0, 0.0),
+            CubeFace::Right => (0.0, 90.0),
+            CubeFace::Back => (0.0, 180.0),
+            CubeFace::Left => (0.0, -90.0),
+            CubeFace::Up => (90.0, 0.0),
+            CubeFace::Down => (-90.0, 0.0),
+        };
+        RotationSerDe {
+            pitch,
+            yaw,
+            roll: 0.0,
+        }
+    }
+}
+
+/// Stitches the images of cameras sharing one mount point, e.g. a
+/// [`CubeFace`] rig, into a [`PanoramaImageSerDe`].
+///
+/// Every pixel is sampled bilinearly from the camera that sees its
+/// direction closest to the camera's own axis. The cameras are taken to
+/// sit at the same point, so nearby objects seen by two cameras of a rig
+/// with separate mounts may show seams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PanoramaStitcher {
+    width: usize,
+    height: usize,
+}
+
+impl PanoramaStitcher {
+    /// A `width x width / 2` panorama, which keeps pixels square.
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            height: width / 2,
+        }
+    }
+
+    pub fn with_height(mut self, height: usize) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn stitch_cube(&self, faces: &[(CubeFace, &ImageEventSerDe)]) -> PanoramaImageSerDe {
+        let cameras: Vec<_> = faces
+            .iter()
+            .map(|(face, image)| (face.rotation(), *image))
+            .collect();
+        self.stitch(&cameras)
+    }
+
+    /// Stitch the images of rig cameras, oriented by their mounts.
+    pub fn stitch_rig(
+        &self,
+        cameras: &[(&SensorDescriptionSerDe, &ImageEventSerDe)],
+    ) -> PanoramaImageSerDe {
+        let cameras: Vec<_> = cameras
+            .iter()
+            .map(|(sensor, image)| (TransformSerDe::from(&sensor.mount).rotation, *image))
+            .collect();
+        self.stitch(&cameras)
+    }
+
+    /// Stitch images of cameras with the given rotations relative to the
+    /// rig, in CARLA's convention.
+    pub fn stitch(&self, cameras: &[(RotationSerDe, &ImageEventSerDe)]) -> PanoramaImageSerDe {
+        let cameras: Vec<_> = cameras
+            .iter()
+            .filter(|(_, image)| !image.array.is_empty())
+            .map(|(rotation, image)| {
+                (
+                    camera_axes(rotation),
+                    CameraInfoSerDe::from(*image),
+                    &image.array,
+                )
+            })
+            .collect();
+        let mut panorama = PanoramaImageSerDe {
+            height: self.height,
+            width: self.width,
+            array: Array2::from_elem(
+                (self.height, self.width),
+                Color {
+                    b: 0,
+                    g: 0,
+                    r: 0,
+                    a: 0,
+                },
+            ),
+            annotations: AnnotationsSerDe::default(),
+        };
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let d = panorama.direction(x as f32 + 0.5, y as f32 + 0.5);
+                let mut best: Option<(f32, f32, f32, &Array2<Color>)> = None;
+                for ([forward, right, up], info, array) in &cameras {
+                    let depth = d.dot(forward);
+                    if depth <= 0.0 || best.is_some_and(|(b, ..)| b >= depth) {
+                        continue;
+                    }
+                    let u = info.cx + info.fx * d.dot(right) / depth;
+                    let v = info.cy - info.fy * d.dot(up) / depth;
+                    let (h, w) = array.dim();
+                    // a pixel of slack, so rounding in the mounts leaves no
+                    // gaps at the seams
+                    if (-1.0..w as f32 + 1.0).contains(&u) && (-1.0..h as f32 + 1.0).contains(&v) {
+                        best = Some((depth, u, v, array));
+                    }
+                }
+                if let Some((_, u, v, array)) = best {
+                    let (h, w) = array.dim();
+                    let su = (u - 0.5).clamp(0.0, (w - 1) as f32);
+                    let sv = (v - 0.5).clamp(0.0, (h - 1) as f32);
+                    let mut px = bilinear(array, su, sv);
+                    px.a = 255;
+                    panorama.array[(y, x)] = px;
+                }
+            }
+        }
+        panorama
+    }
+}
+
+// Forward, right and up axes of a camera with `rotation`, in the rig frame,
+// as Unreal computes them from pitch, yaw and roll.
+fn camera_axes(rotation: &RotationSerDe) -> [Vector3<f32>; 3] {
+    let (sp, cp) = rotation.pitch.to_radians().sin_cos();
+    let (sy, cy) = rotation.yaw.to_radians().sin_cos();
+    let (sr, cr) = rotation.roll.to_radians().sin_cos();
+    [
+        Vector3::new(cp * cy, cp * sy, sp),
+        Vector3::new(sr * sp * cy - cr * sy, sr * sp * sy + cr * cy, -sr * cp),
+        Vector3::new(-(cr * sp * cy + sr * sy), cy * sr - cr * sp * sy, cr * cp),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 90° camera `height` rows by `width` columns
+    fn camera(height: usize, width: usize) -> ImageEventSerDe {
+        ImageEventSerDe {
+            height,
+            width,
+            len: height * width,
+            is_empty: false,
+            fov_angle: 90.0,
+            array: Array2::from_elem(
+                (height, width),
+                Color {
+                    b: 0,
+                    g: 0,
+                    r: 255,
+                    a: 255,
+                },
+            ),
+            augmentations: Vec::new(),
+            hash: None,
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+
+    #[test]
+    fn a_landscape_camera_covers_more_longitude_than_latitude() {
+        let front = camera(6, 10);
+        let panorama = PanoramaStitcher::new(72).stitch_cube(&[(CubeFace::Front, &front)]);
+        assert_eq!(panorama.array.dim(), (36, 72));
+        assert_eq!(panorama.validate(), Ok(()));
+        let seen = |longitude: f32, latitude: f32| {
+            let (lon, lat) = (longitude.to_radians(), latitude.to_radians());
+            let d = Vector3::new(lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin());
+            let (x, y) = panorama.pixel(&d).unwrap();
+            panorama.array[(y as usize, x as usize)].a > 0
+        };
+        // pixels are 5° wide, this is the center of the one right below
+        // and right of the forward axis
+        assert!(seen(2.5, -2.5));
+        // the camera sees ±45° across and ±31° up and down
+        assert!(seen(42.5, -2.5));
+        assert!(seen(-42.5, -2.5));
+        assert!(!seen(2.5, 42.5));
+        assert!(!seen(2.5, -42.5));
+        assert!(!seen(180.0 - 2.5, -2.5));
+    }
+
+    #[test]
+    fn pixel_inverts_direction() {
+        let panorama = PanoramaStitcher::new(64).with_height(16).stitch(&[]);
+        assert_eq!((panorama.height, panorama.width), (16, 64));
+        let (x, y) = panorama.pixel(&panorama.direction(40.5, 3.5)).unwrap();
+        assert!((x - 40.5).abs() < 1e-3 && (y - 3.5).abs() < 1e-3);
+        assert_eq!(panorama.coverage(), 0.0);
+    }
+}
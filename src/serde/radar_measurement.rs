@@ -1,3 +1,4 @@
+use crate::AnnotationsSerDe;
 use carla::sensor::data::{
     RadarDetection as CarlaRadarDetection, RadarMeasurement as RadarMeasurementEvent,
 };
@@ -42,12 +43,15 @@ mod slice_radar_detection_remote {
 }
 
 /// Borrowed, zero-copy serializer
-#[derive(Serialize)]
+#[derive(Clone, Copy, Serialize)]
 pub struct RadarMeasurementSerBorrowed<'a> {
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub detection_amount: usize,
     #[serde(with = "self::slice_radar_detection_remote")]
     pub detections: &'a [CarlaRadarDetection],
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub len: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub is_empty: bool,
 }
 
@@ -113,13 +117,165 @@ mod vec_radar_detection_remote {
     }
 }
 
+/// Deserializing checks `detection_amount`, `len` and `is_empty` against
+/// the detections, see [`RadarMeasurementSerDe::validate`].
 #[derive(Serialize, Deserialize)]
+#[serde(try_from = "RadarMeasurementRaw")]
 pub struct RadarMeasurementSerDe {
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub detection_amount: usize,
     #[serde(with = "self::vec_radar_detection_remote")]
     pub detections: Vec<CarlaRadarDetection>,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub len: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
     pub is_empty: bool,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+// wire form of RadarMeasurementSerDe, before the count check
+#[derive(Deserialize)]
+struct RadarMeasurementRaw {
+    // counts are absent in compact payloads
+    #[serde(default)]
+    detection_amount: Option<usize>,
+    #[serde(with = "self::vec_radar_detection_remote")]
+    detections: Vec<CarlaRadarDetection>,
+    #[serde(default)]
+    len: Option<usize>,
+    #[serde(default)]
+    is_empty: Option<bool>,
+    #[serde(default)]
+    annotations: AnnotationsSerDe,
+}
+
+impl TryFrom<RadarMeasurementRaw> for RadarMeasurementSerDe {
+    type Error = RadarCountError;
+
+    fn try_from(v: RadarMeasurementRaw) -> Result<Self, Self::Error> {
+        let n = v.detections.len();
+        let m = Self {
+            detection_amount: v.detection_amount.unwrap_or(n),
+            detections: v.detections,
+            len: v.len.unwrap_or(n),
+            is_empty: v.is_empty.unwrap_or(n == 0),
+            annotations: v.annotations,
+        };
+        m.validate()?;
+        Ok(m)
+    }
+}
+
+/// The count fields of a radar measurement disagree with its detections.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RadarCountError {
+    pub detection_amount: usize,
+    pub len: usize,
+    pub is_empty: bool,
+    /// `detections.len()`
+    pub actual: usize,
+}
+
+impl fmt::Display for RadarCountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "radar measurement claims {} detections (len {}, is_empty {}), but has {}",
+            self.detection_amount, self.len, self.is_empty, self.actual
+        )
+    }
+}
+
+impl std::error::Error for RadarCountError {}
+
+impl RadarMeasurementSerDe {
+    /// Number of detections, from the vector rather than the count fields.
+    pub fn len(&self) -> usize {
+        self.detections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.detections.is_empty()
+    }
+
+    /// Check that `detection_amount` and `len` equal the number of
+    /// detections and `is_empty` agrees.
+    pub fn validate(&self) -> Result<(), RadarCountError> {
+        let actual = self.detections.len();
+        if self.detection_amount == actual && self.len == actual && self.is_empty == (actual == 0) {
+            Ok(())
+        } else {
+            Err(RadarCountError {
+                detection_amount: self.detection_amount,
+                len: self.len,
+                is_empty: self.is_empty,
+                actual,
+            })
+        }
+    }
+
+    /// Overwrite the count fields with the number of detections, e.g. after
+    /// filtering them.
+    pub fn reconcile_counts(&mut self) {
+        self.detection_amount = self.detections.len();
+        self.len = self.detections.len();
+        self.is_empty = self.detections.is_empty();
+    }
+}
+
+// CarlaRadarDetection isn't Clone, rebuild from public fields
+#[inline]
+fn copy_detection(d: &CarlaRadarDetection) -> CarlaRadarDetection {
+    CarlaRadarDetection {
+        velocity: d.velocity,
+        azimuth: d.azimuth,
+        altitude: d.altitude,
+        depth: d.depth,
+    }
+}
+
+impl From<RadarMeasurementEvent> for RadarMeasurementSerDe {
+    fn from(m: RadarMeasurementEvent) -> Self {
+        Self {
+            detection_amount: m.detection_amount(),
+            detections: m.as_slice().iter().map(copy_detection).collect(),
+            len: m.len(),
+            is_empty: m.is_empty(),
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+}
+
+impl Clone for RadarMeasurementSerDe {
+    fn clone(&self) -> Self {
+        Self {
+            detection_amount: self.detection_amount,
+            detections: self.detections.iter().map(copy_detection).collect(),
+            len: self.len,
+            is_empty: self.is_empty,
+            annotations: self.annotations.clone(),
+        }
+    }
+}
+
+impl PartialEq for RadarMeasurementSerDe {
+    fn eq(&self, other: &Self) -> bool {
+        let same = |a: &CarlaRadarDetection, b: &CarlaRadarDetection| {
+            (a.velocity, a.azimuth, a.altitude, a.depth)
+                == (b.velocity, b.azimuth, b.altitude, b.depth)
+        };
+        self.detection_amount == other.detection_amount
+            && self.len == other.len
+            && self.is_empty == other.is_empty
+            && self.detections.len() == other.detections.len()
+            && self
+                .detections
+                .iter()
+                .zip(&other.detections)
+                .all(|(a, b)| same(a, b))
+            && self.annotations == other.annotations
+    }
 }
 
 // ======================= Debug helpers (no allocations) =======================
@@ -201,7 +357,8 @@ impl fmt::Debug for RadarMeasurementSerDe {
         let mut ds = f.debug_struct("RadarMeasurementSerDe");
         ds.field("detection_amount", &self.detection_amount)
             .field("len", &self.len)
-            .field("is_empty", &self.is_empty);
+            .field("is_empty", &self.is_empty)
+            .field("annotations", &self.annotations);
         ds.finish_non_exhaustive()?; // header
 
         write!(f, "\ndetections ")?;
@@ -219,3 +376,74 @@ impl fmt::Debug for RadarMeasurementSerDe {
         }
     }
 }
+
+// ======================= Display impls =======================
+
+fn write_radar_summary<'a>(
+    f: &mut fmt::Formatter<'_>,
+    detections: impl IntoIterator<Item = &'a CarlaRadarDetection>,
+) -> fmt::Result {
+    let (mut n, mut min, mut max) = (0usize, f32::INFINITY, f32::NEG_INFINITY);
+    for d in detections {
+        n += 1;
+        min = min.min(d.depth);
+        max = max.max(d.depth);
+    }
+    write!(f, "Radar {n} detections")?;
+    if n > 0 {
+        write!(f, ", depth {min:.1}–{max:.1} m")?;
+    }
+    Ok(())
+}
+
+/// `Radar 214 detections, depth 1.2–84.3 m`
+impl fmt::Display for RadarMeasurementSerBorrowed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_radar_summary(f, self.detections)
+    }
+}
+
+impl fmt::Display for RadarMeasurementSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_radar_summary(f, &self.detections)
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use crate::SensorDataSerDe;
+
+    #[test]
+    fn rejects_counts_that_disagree_with_the_detections() {
+        let mut radar = crate::fixtures::radar_frame();
+        let n = radar.detections.len();
+        assert!(n > 0);
+        assert_eq!(radar.validate(), Ok(()));
+
+        radar.detection_amount = n + 1;
+        let error = radar.validate().unwrap_err();
+        assert_eq!((error.detection_amount, error.actual), (n + 1, n));
+        let json = serde_json::to_string(&SensorDataSerDe::Radar(radar.clone())).unwrap();
+        let e = serde_json::from_str::<SensorDataSerDe>(&json).unwrap_err();
+        assert!(e.to_string().contains("claims"), "{e}");
+
+        radar.detection_amount = n;
+        radar.is_empty = true;
+        assert!(radar.validate().is_err());
+    }
+
+    #[test]
+    fn reconciles_counts_after_filtering() {
+        let mut radar = crate::fixtures::radar_frame();
+        radar.detections.truncate(1);
+        assert!(radar.validate().is_err());
+        radar.reconcile_counts();
+        assert_eq!(radar.validate(), Ok(()));
+        assert_eq!((radar.detection_amount, radar.len), (1, 1));
+
+        radar.detections.clear();
+        radar.reconcile_counts();
+        assert!(radar.is_empty);
+        assert_eq!(radar.validate(), Ok(()));
+    }
+}
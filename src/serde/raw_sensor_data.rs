@@ -0,0 +1,100 @@
+use crate::AnnotationsSerDe;
+use carla::sensor::SensorDataBase;
+use nalgebra::Isometry3;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// How many bytes to show in non-alternate ({:?}) mode
+const PREVIEW_BYTES: usize = 16;
+
+/// Sensor data of a type this crate has no wrapper for, kept as CARLA's
+/// raw buffer so it can be recorded now and decoded later.
+///
+/// The carla crate gives no access to the buffer of a plain `SensorData`;
+/// pass the bytes in from wherever the sensor's `raw_data` is available,
+/// e.g. a C++ or Python bridge.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawSensorDataSerDe {
+    /// Blueprint id of the sensor, e.g. `sensor.camera.dvs`.
+    pub type_id: String,
+    pub frame: u64,
+    /// Simulation time in seconds.
+    pub timestamp: f64,
+    pub sensor_transform: Isometry3<f32>,
+    pub data: Vec<u8>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+impl RawSensorDataSerDe {
+    /// `data` of a `type_id` sensor, without frame information.
+    pub fn new(type_id: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            type_id: type_id.into(),
+            frame: 0,
+            timestamp: 0.0,
+            sensor_transform: Isometry3::identity(),
+            data: data.into(),
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+
+    /// `data` of a `type_id` sensor, with the frame, time and sensor pose
+    /// of `sensor_data`.
+    pub fn from_sensor_data(
+        type_id: impl Into<String>,
+        sensor_data: &impl SensorDataBase,
+        data: impl Into<Vec<u8>>,
+    ) -> Self {
+        Self {
+            frame: sensor_data.frame() as u64,
+            timestamp: sensor_data.timestamp(),
+            sensor_transform: sensor_data.sensor_transform(),
+            ..Self::new(type_id, data)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+impl fmt::Debug for RawSensorDataSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shown = if f.alternate() {
+            self.data.len()
+        } else {
+            PREVIEW_BYTES.min(self.data.len())
+        };
+        let mut ds = f.debug_struct("RawSensorDataSerDe");
+        ds.field("type_id", &self.type_id)
+            .field("frame", &self.frame)
+            .field("timestamp", &self.timestamp)
+            .field("sensor_transform", &self.sensor_transform)
+            .field("len", &self.data.len())
+            .field("data", &&self.data[..shown])
+            .field("annotations", &self.annotations);
+        if shown < self.data.len() {
+            ds.finish_non_exhaustive()
+        } else {
+            ds.finish()
+        }
+    }
+}
+
+/// `Raw sensor.camera.dvs frame 1234, 65000 bytes`
+impl fmt::Display for RawSensorDataSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Raw {} frame {}, {} bytes",
+            self.type_id,
+            self.frame,
+            self.data.len()
+        )
+    }
+}
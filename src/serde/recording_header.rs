@@ -0,0 +1,248 @@
+use crate::{
+    ActorBlueprintSerDe, AnnotationValueSerDe, AnnotationsSerDe, ClockSyncSerDe,
+    EpisodeSettingsSerDe, ExternalClockSerDe, GeoLocationSerDe, SensorDescriptionSerDe,
+    SimulationSettingsSerDe, SpawnedActorSerDe, TrafficSeedSerDe, VehiclePhysicsControlSerDe,
+    WeatherParametersSerDe,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Metadata that precedes the frames of a recording.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordingHeaderSerDe {
+    pub provenance: ProvenanceSerDe,
+    /// World settings during capture; see [`simulation`](Self::simulation)
+    /// for their timing part.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub episode: Option<EpisodeSettingsSerDe>,
+    /// Weather during capture, to reproduce the run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weather: Option<WeatherParametersSerDe>,
+    /// The sensor rig the frames were captured with.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sensors: Vec<SensorDescriptionSerDe>,
+    /// One entry per ego vehicle in a fleet session; their frames are told
+    /// apart by [`crate::fleet::VEHICLE_KEY`]. Empty for single-vehicle
+    /// recordings, which only use `sensors`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vehicles: Vec<VehicleManifestSerDe>,
+    /// Free-form run metadata, e.g. `town` or `scenario`.
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub metadata: AnnotationsSerDe,
+    /// How the simulation time of the frames maps to external clocks, for
+    /// aligning logs of co-simulated tools.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clocks: Vec<ClockSyncSerDe>,
+    /// Background traffic spawned for the scenario.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traffic: Option<TrafficSeedSerDe>,
+    /// The geo location of the map's world origin, to place GNSS fixes in
+    /// the world frame; see [`GeoLocationSerDe::map_origin`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geo_reference: Option<GeoLocationSerDe>,
+    /// The actors the session spawned, with the blueprint and attribute
+    /// values of each, in spawn order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spawned: Vec<SpawnedActorSerDe>,
+}
+
+/// One instrumented vehicle of a fleet session.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VehicleManifestSerDe {
+    /// Namespace of the vehicle within the session, e.g. `ego_0`.
+    pub id: String,
+    /// Blueprint id, e.g. `vehicle.tesla.model3`.
+    #[serde(default)]
+    pub type_id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sensors: Vec<SensorDescriptionSerDe>,
+    /// Dynamics the vehicle was driven with, to restore the run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub physics: Option<VehiclePhysicsControlSerDe>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub metadata: AnnotationsSerDe,
+}
+
+impl RecordingHeaderSerDe {
+    /// The timing part of the world settings; timestamps are only evenly
+    /// spaced if [`SimulationSettingsSerDe::is_deterministic`].
+    pub fn simulation(&self) -> Option<SimulationSettingsSerDe> {
+        self.episode.as_ref().map(EpisodeSettingsSerDe::simulation)
+    }
+
+    /// The blueprint the actor `id` was spawned from.
+    pub fn blueprint(&self, id: carla::rpc::ActorId) -> Option<&ActorBlueprintSerDe> {
+        self.spawned
+            .iter()
+            .find(|a| a.id == id)
+            .map(|a| &a.blueprint)
+    }
+
+    pub fn vehicle(&self, id: &str) -> Option<&VehicleManifestSerDe> {
+        self.vehicles.iter().find(|v| v.id == id)
+    }
+
+    pub fn clock(&self, clock: &ExternalClockSerDe) -> Option<&ClockSyncSerDe> {
+        self.clocks.iter().find(|c| c.clock == *clock)
+    }
+
+    /// Add the mapping, replacing an earlier one for the same clock.
+    pub fn set_clock(&mut self, sync: ClockSyncSerDe) {
+        self.clocks.retain(|c| c.clock != sync.clock);
+        self.clocks.push(sync);
+    }
+
+    /// `sim` seconds of simulation time on the external clock.
+    pub fn external_time(&self, clock: &ExternalClockSerDe, sim: f64) -> Option<f64> {
+        self.clock(clock).map(|c| c.to_external(sim))
+    }
+}
+
+/// Who made a recording, with what, under which terms, and how it has been
+/// processed since.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProvenanceSerDe {
+    pub creator: String,
+    #[serde(default)]
+    pub tools: Vec<ToolVersionSerDe>,
+    /// SPDX license identifier, e.g. `CC-BY-4.0`.
+    #[serde(default)]
+    pub license: Option<String>,
+    /// Processing steps, oldest first.
+    #[serde(default)]
+    pub history: Vec<ProcessingStepSerDe>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ToolVersionSerDe {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProcessingStepSerDe {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub params: AnnotationsSerDe,
+    /// Seconds since the Unix epoch.
+    pub timestamp: f64,
+    /// Digest of the frames the step read, e.g. `fnv1a64:0123abcd...`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_hash: Option<String>,
+}
+
+impl ProvenanceSerDe {
+    /// Provenance listing this crate as the first tool.
+    pub fn new(creator: impl Into<String>) -> Self {
+        Self {
+            creator: creator.into(),
+            tools: vec![ToolVersionSerDe {
+                name: env!("CARGO_PKG_NAME").into(),
+                version: env!("CARGO_PKG_VERSION").into(),
+            }],
+            license: None,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn with_tool(mut self, name: impl Into<String>, version: impl Into<String>) -> Self {
+        self.tools.push(ToolVersionSerDe {
+            name: name.into(),
+            version: version.into(),
+        });
+        self
+    }
+
+    pub fn with_license(mut self, spdx: impl Into<String>) -> Self {
+        self.license = Some(spdx.into());
+        self
+    }
+
+    pub fn record(&mut self, step: ProcessingStepSerDe) {
+        self.history.push(step);
+    }
+}
+
+impl ProcessingStepSerDe {
+    /// A step stamped with the current time.
+    pub fn new(name: impl Into<String>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |d| d.as_secs_f64());
+        Self {
+            name: name.into(),
+            params: AnnotationsSerDe::default(),
+            timestamp,
+            input_hash: None,
+        }
+    }
+
+    pub fn with_param(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<AnnotationValueSerDe>,
+    ) -> Self {
+        self.params.insert(key, value);
+        self
+    }
+}
+
+/// `recording by carla-data-serde: 4 sensors, 2 vehicles, 12 spawned actors`
+impl fmt::Display for RecordingHeaderSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "recording by {}: {} sensors, {} vehicles, {} spawned actors",
+            self.provenance.creator,
+            self.sensors.len(),
+            self.vehicles.len(),
+            self.spawned.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulation_is_the_timing_part_of_the_episode() {
+        let mut header = RecordingHeaderSerDe::default();
+        assert_eq!(header.simulation(), None);
+        let episode = EpisodeSettingsSerDe {
+            synchronous_mode: true,
+            fixed_delta_seconds: Some(0.05),
+            ..Default::default()
+        };
+        header.episode = Some(episode);
+        let json = serde_json::to_string(&header).unwrap();
+        assert!(!json.contains("\"simulation\""), "{json}");
+        let back: RecordingHeaderSerDe = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.simulation(), Some(episode.simulation()));
+        assert!(back.simulation().unwrap().is_deterministic());
+    }
+
+    #[test]
+    fn displays_a_one_line_summary() {
+        let mut header = RecordingHeaderSerDe {
+            provenance: ProvenanceSerDe::new("rig"),
+            ..Default::default()
+        };
+        assert_eq!(
+            header.to_string(),
+            "recording by rig: 0 sensors, 0 vehicles, 0 spawned actors"
+        );
+        header.vehicles.push(VehicleManifestSerDe::default());
+        assert!(header.to_string().contains(" 1 vehicles"));
+
+        let settings = SimulationSettingsSerDe {
+            synchronous_mode: true,
+            fixed_delta_seconds: Some(0.05),
+            ..Default::default()
+        };
+        assert_eq!(settings.to_string(), "synchronous, 0.050 s steps");
+        header.metadata.insert("town", "Town10HD");
+        assert_eq!(header.metadata.to_string(), "1 annotation: town");
+    }
+}
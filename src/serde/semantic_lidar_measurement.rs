@@ -0,0 +1,442 @@
+use crate::{AnnotationsSerDe, LidarCountError};
+use carla::geom::Location as CarlaLocation;
+use carla::sensor::data::{
+    SemanticLidarDetection as CarlaSemanticLidarDetection,
+    SemanticLidarMeasurement as SemanticLidarMeasurementEvent,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+// How many detections to show in non-alternate ({:?}) mode
+const PREVIEW_DETECTIONS: usize = 5;
+
+/// Remote schema for the foreign element type `SemanticLidarDetection`
+/// (nested `point` uses the lidar module's `location_with`)
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(remote = "carla::sensor::data::SemanticLidarDetection")]
+pub struct SemanticLidarDetectionRemote {
+    #[serde(with = "super::lidar_measurement::location_with")]
+    pub point: CarlaLocation,
+    /// Cosine of the angle between the ray and the hit surface's normal.
+    pub cos_inc_angle: f32,
+    /// Id of the actor that was hit, 0 for the static world.
+    pub object_idx: u32,
+    /// CARLA semantic tag of the hit object.
+    pub object_tag: u32,
+}
+
+// -------------------- &[SemanticLidarDetection] (serialize-only) --------------------
+mod slice_semantic_detection_remote {
+    use super::*;
+    use serde::ser::{SerializeSeq, Serializer};
+
+    struct AsRemote<'a>(&'a CarlaSemanticLidarDetection);
+    impl<'a> Serialize for AsRemote<'a> {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            super::SemanticLidarDetectionRemote::serialize(self.0, s)
+        }
+    }
+
+    pub fn serialize<S: Serializer>(
+        slice: &[CarlaSemanticLidarDetection],
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = s.serialize_seq(Some(slice.len()))?;
+        for d in slice {
+            seq.serialize_element(&AsRemote(d))?;
+        }
+        seq.end()
+    }
+}
+
+/// Borrowed, zero-copy serializer
+#[derive(Clone, Copy, Serialize)]
+pub struct SemanticLidarMeasurementSerBorrowed<'a> {
+    pub horizontal_angle: f32,
+    pub channel_count: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
+    pub len: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
+    pub is_empty: bool,
+    #[serde(with = "self::slice_semantic_detection_remote")]
+    pub detections: &'a [CarlaSemanticLidarDetection],
+}
+
+impl<'a> From<&'a SemanticLidarMeasurementEvent> for SemanticLidarMeasurementSerBorrowed<'a> {
+    fn from(m: &'a SemanticLidarMeasurementEvent) -> Self {
+        Self {
+            horizontal_angle: m.horizontal_angle(),
+            channel_count: m.channel_count(),
+            len: m.len(),
+            is_empty: m.is_empty(),
+            detections: m.as_slice(),
+        }
+    }
+}
+
+// -------------------- Vec<SemanticLidarDetection> (round-trip) --------------------
+mod vec_semantic_detection_remote {
+    use super::*;
+    use serde::de::{SeqAccess, Visitor};
+    use serde::ser::SerializeSeq;
+    use serde::{Deserializer, Serializer};
+    use std::fmt;
+
+    struct AsRemote<'a>(&'a CarlaSemanticLidarDetection);
+    impl<'a> Serialize for AsRemote<'a> {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            super::SemanticLidarDetectionRemote::serialize(self.0, s)
+        }
+    }
+
+    struct FromRemote(CarlaSemanticLidarDetection);
+    impl<'de> Deserialize<'de> for FromRemote {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            super::SemanticLidarDetectionRemote::deserialize(d).map(FromRemote)
+        }
+    }
+
+    pub fn serialize<S: Serializer>(
+        v: &Vec<CarlaSemanticLidarDetection>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = s.serialize_seq(Some(v.len()))?;
+        for d in v {
+            seq.serialize_element(&AsRemote(d))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Vec<CarlaSemanticLidarDetection>, D::Error> {
+        struct V;
+        impl<'de> Visitor<'de> for V {
+            type Value = Vec<CarlaSemanticLidarDetection>;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "Vec<SemanticLidarDetection>")
+            }
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut out = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(FromRemote(x)) = seq.next_element::<FromRemote>()? {
+                    out.push(x);
+                }
+                Ok(out)
+            }
+        }
+        d.deserialize_seq(V)
+    }
+}
+
+/// Deserializing checks `len` and `is_empty` against the detections, see
+/// [`SemanticLidarMeasurementSerDe::validate`].
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "SemanticLidarMeasurementRaw")]
+pub struct SemanticLidarMeasurementSerDe {
+    pub horizontal_angle: f32,
+    pub channel_count: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
+    pub len: usize,
+    #[serde(skip_serializing_if = "crate::compact::skip_derived")]
+    pub is_empty: bool,
+    #[serde(with = "self::vec_semantic_detection_remote")]
+    pub detections: Vec<CarlaSemanticLidarDetection>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+// wire form of SemanticLidarMeasurementSerDe; `len` and `is_empty` are
+// absent in compact payloads
+#[derive(Deserialize)]
+struct SemanticLidarMeasurementRaw {
+    horizontal_angle: f32,
+    channel_count: usize,
+    #[serde(default)]
+    len: Option<usize>,
+    #[serde(default)]
+    is_empty: Option<bool>,
+    #[serde(with = "self::vec_semantic_detection_remote")]
+    detections: Vec<CarlaSemanticLidarDetection>,
+    #[serde(default)]
+    annotations: AnnotationsSerDe,
+}
+
+impl TryFrom<SemanticLidarMeasurementRaw> for SemanticLidarMeasurementSerDe {
+    type Error = LidarCountError;
+
+    fn try_from(v: SemanticLidarMeasurementRaw) -> Result<Self, Self::Error> {
+        let n = v.detections.len();
+        let m = Self {
+            horizontal_angle: v.horizontal_angle,
+            channel_count: v.channel_count,
+            len: v.len.unwrap_or(n),
+            is_empty: v.is_empty.unwrap_or(n == 0),
+            detections: v.detections,
+            annotations: v.annotations,
+        };
+        m.validate()?;
+        Ok(m)
+    }
+}
+
+// CarlaSemanticLidarDetection isn't Clone, rebuild from public fields
+#[inline]
+fn copy_detection(d: &CarlaSemanticLidarDetection) -> CarlaSemanticLidarDetection {
+    CarlaSemanticLidarDetection {
+        point: CarlaLocation {
+            x: d.point.x,
+            y: d.point.y,
+            z: d.point.z,
+        },
+        cos_inc_angle: d.cos_inc_angle,
+        object_idx: d.object_idx,
+        object_tag: d.object_tag,
+    }
+}
+
+impl From<SemanticLidarMeasurementEvent> for SemanticLidarMeasurementSerDe {
+    fn from(m: SemanticLidarMeasurementEvent) -> Self {
+        Self {
+            horizontal_angle: m.horizontal_angle(),
+            channel_count: m.channel_count(),
+            len: m.len(),
+            is_empty: m.is_empty(),
+            detections: m.as_slice().iter().map(copy_detection).collect(),
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+}
+
+impl Clone for SemanticLidarMeasurementSerDe {
+    fn clone(&self) -> Self {
+        Self {
+            horizontal_angle: self.horizontal_angle,
+            channel_count: self.channel_count,
+            len: self.len,
+            is_empty: self.is_empty,
+            detections: self.detections.iter().map(copy_detection).collect(),
+            annotations: self.annotations.clone(),
+        }
+    }
+}
+
+impl PartialEq for SemanticLidarMeasurementSerDe {
+    fn eq(&self, other: &Self) -> bool {
+        let same = |a: &CarlaSemanticLidarDetection, b: &CarlaSemanticLidarDetection| {
+            (a.point.x, a.point.y, a.point.z, a.cos_inc_angle)
+                == (b.point.x, b.point.y, b.point.z, b.cos_inc_angle)
+                && (a.object_idx, a.object_tag) == (b.object_idx, b.object_tag)
+        };
+        self.horizontal_angle == other.horizontal_angle
+            && self.channel_count == other.channel_count
+            && self.len == other.len
+            && self.is_empty == other.is_empty
+            && self.detections.len() == other.detections.len()
+            && self
+                .detections
+                .iter()
+                .zip(&other.detections)
+                .all(|(a, b)| same(a, b))
+            && self.annotations == other.annotations
+    }
+}
+
+impl SemanticLidarMeasurementSerDe {
+    /// Number of points, from the vector rather than the `len` field.
+    pub fn len(&self) -> usize {
+        self.detections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.detections.is_empty()
+    }
+
+    /// Check that `len` equals the number of points and `is_empty` agrees.
+    pub fn validate(&self) -> Result<(), LidarCountError> {
+        LidarCountError::check(self.len, self.is_empty, self.detections.len())
+    }
+
+    /// Overwrite `len` and `is_empty` with the number of points, e.g. after
+    /// filtering them.
+    pub fn reconcile_counts(&mut self) {
+        self.len = self.detections.len();
+        self.is_empty = self.detections.is_empty();
+    }
+
+    /// The points that hit an object with this semantic tag.
+    pub fn with_tag(&self, tag: u32) -> impl Iterator<Item = &CarlaSemanticLidarDetection> {
+        self.detections.iter().filter(move |d| d.object_tag == tag)
+    }
+}
+
+// ======================= Debug helpers (no allocations) =======================
+
+#[inline]
+fn write_semantic_detection(
+    d: &CarlaSemanticLidarDetection,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    write!(
+        f,
+        "{{ point: ({}, {}, {}), cos_inc_angle: {}, object_idx: {}, object_tag: {} }}",
+        d.point.x, d.point.y, d.point.z, d.cos_inc_angle, d.object_idx, d.object_tag
+    )
+}
+
+fn write_semantic_seq_full<'a>(
+    f: &mut fmt::Formatter<'_>,
+    detections: impl IntoIterator<Item = &'a CarlaSemanticLidarDetection>,
+) -> fmt::Result {
+    writeln!(f, "[")?;
+    for d in detections {
+        write!(f, "  ")?;
+        write_semantic_detection(d, f)?;
+        writeln!(f, ",")?;
+    }
+    write!(f, "]")
+}
+
+fn write_semantic_seq_preview<'a>(
+    f: &mut fmt::Formatter<'_>,
+    detections: impl IntoIterator<Item = &'a CarlaSemanticLidarDetection>,
+    total: usize,
+    max_show: usize,
+) -> fmt::Result {
+    let max_show = max_show.min(total);
+    writeln!(f, "[")?;
+
+    let mut shown = 0usize;
+    for d in detections.into_iter().take(max_show) {
+        write!(f, "  ")?;
+        write_semantic_detection(d, f)?;
+        writeln!(f, ",")?;
+        shown += 1;
+    }
+
+    if total > shown {
+        writeln!(f, "  … ({} more)", total - shown)?;
+    }
+
+    write!(f, "]")
+}
+
+// ======================= Custom Debug impls =======================
+
+impl<'a> fmt::Debug for SemanticLidarMeasurementSerBorrowed<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ds = f.debug_struct("SemanticLidarMeasurementSerBorrowed");
+        ds.field("horizontal_angle", &self.horizontal_angle)
+            .field("channel_count", &self.channel_count)
+            .field("len", &self.len)
+            .field("is_empty", &self.is_empty);
+        ds.finish_non_exhaustive()?; // header
+
+        write!(f, "\ndetections ")?;
+        if f.alternate() {
+            write!(f, "(full, {} total) = ", self.len)?;
+            write_semantic_seq_full(f, self.detections.iter())
+        } else {
+            write!(
+                f,
+                "(preview showing {} of {}) = ",
+                PREVIEW_DETECTIONS.min(self.len),
+                self.len
+            )?;
+            write_semantic_seq_preview(f, self.detections.iter(), self.len, PREVIEW_DETECTIONS)
+        }
+    }
+}
+
+impl fmt::Debug for SemanticLidarMeasurementSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ds = f.debug_struct("SemanticLidarMeasurementSerDe");
+        ds.field("horizontal_angle", &self.horizontal_angle)
+            .field("channel_count", &self.channel_count)
+            .field("len", &self.len)
+            .field("is_empty", &self.is_empty)
+            .field("annotations", &self.annotations);
+        ds.finish_non_exhaustive()?; // header
+
+        write!(f, "\ndetections ")?;
+        if f.alternate() {
+            write!(f, "(full, {} total) = ", self.len)?;
+            write_semantic_seq_full(f, self.detections.iter())
+        } else {
+            write!(
+                f,
+                "(preview showing {} of {}) = ",
+                PREVIEW_DETECTIONS.min(self.len),
+                self.len
+            )?;
+            write_semantic_seq_preview(f, self.detections.iter(), self.len, PREVIEW_DETECTIONS)
+        }
+    }
+}
+
+// ======================= Display impls =======================
+
+fn write_semantic_summary<'a>(
+    f: &mut fmt::Formatter<'_>,
+    channel_count: usize,
+    detections: impl IntoIterator<Item = &'a CarlaSemanticLidarDetection>,
+) -> fmt::Result {
+    let mut n = 0usize;
+    let mut tags = BTreeSet::new();
+    for d in detections {
+        n += 1;
+        tags.insert(d.object_tag);
+    }
+    write!(f, "Semantic lidar {n} points, {channel_count} channels")?;
+    if n > 0 {
+        write!(f, ", {} tags", tags.len())?;
+    }
+    Ok(())
+}
+
+/// `Semantic lidar 56000 points, 32 channels, 9 tags`
+impl fmt::Display for SemanticLidarMeasurementSerBorrowed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_semantic_summary(f, self.channel_count, self.detections)
+    }
+}
+
+impl fmt::Display for SemanticLidarMeasurementSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_semantic_summary(f, self.channel_count, &self.detections)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(
+        len: usize,
+        is_empty: bool,
+    ) -> serde_json::Result<SemanticLidarMeasurementSerDe> {
+        let point = r#"{"point":{"x":1.0,"y":2.0,"z":0.5},"cos_inc_angle":0.9,"object_idx":7,"object_tag":14}"#;
+        serde_json::from_str(&format!(
+            r#"{{"horizontal_angle":0.0,"channel_count":1,"len":{len},"is_empty":{is_empty},"detections":[{point},{point}]}}"#
+        ))
+    }
+
+    #[test]
+    fn rejects_counts_that_disagree_with_the_points() {
+        let m = measurement(2, false).unwrap();
+        assert_eq!(m.validate(), Ok(()));
+        let e = measurement(3, false).unwrap_err();
+        assert!(e.to_string().contains("claims 3 points"), "{e}");
+        assert!(measurement(2, true).is_err());
+    }
+
+    #[test]
+    fn reconciles_counts_after_filtering() {
+        let mut m = measurement(2, false).unwrap();
+        m.detections.retain(|d| d.object_tag != 14);
+        assert!(m.validate().is_err());
+        m.reconcile_counts();
+        assert_eq!(m.validate(), Ok(()));
+        assert!(m.is_empty);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::{AnnotationsSerDe, ImageEventSerDe, ImageShapeError};
+use carla::sensor::data::Image as ImageEvent;
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+const PREVIEW_W: usize = 8;
+const PREVIEW_H: usize = 3;
+
+/// A `sensor.camera.semantic_segmentation` frame as class labels.
+///
+/// CARLA encodes the semantic tag of each pixel in its red channel; `labels`
+/// holds that tag, `height x width`, so readers need not know the encoding.
+/// The BGRA frame is kept in `raw` only when asked for, see
+/// [`SemanticSegmentationImageSerDe::with_raw`].
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SemanticSegmentationImageRaw")]
+pub struct SemanticSegmentationImageSerDe {
+    pub height: usize,
+    pub width: usize,
+    pub fov_angle: f32,
+    #[serde(with = "super::array_rows")]
+    pub labels: Array2<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<ImageEventSerDe>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+// wire form of SemanticSegmentationImageSerDe, before the shape check
+#[derive(Deserialize)]
+struct SemanticSegmentationImageRaw {
+    height: usize,
+    width: usize,
+    fov_angle: f32,
+    #[serde(with = "super::array_rows")]
+    labels: Array2<u8>,
+    #[serde(default)]
+    raw: Option<ImageEventSerDe>,
+    #[serde(default)]
+    annotations: AnnotationsSerDe,
+}
+
+impl TryFrom<SemanticSegmentationImageRaw> for SemanticSegmentationImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(v: SemanticSegmentationImageRaw) -> Result<Self, Self::Error> {
+        let image = Self {
+            height: v.height,
+            width: v.width,
+            fov_angle: v.fov_angle,
+            labels: v.labels,
+            raw: v.raw,
+            annotations: v.annotations,
+        };
+        image.validate()?;
+        Ok(image)
+    }
+}
+
+/// Fails if the array does not hold `height x width` pixels.
+impl TryFrom<&ImageEventSerDe> for SemanticSegmentationImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(image: &ImageEventSerDe) -> Result<Self, Self::Error> {
+        // the array is in buffer order whichever shape it has
+        let tags = image.array.iter().map(|c| c.r).collect();
+        Ok(Self {
+            height: image.height,
+            width: image.width,
+            fov_angle: image.fov_angle,
+            labels: labels(image.height, image.width, tags, image.array.dim())?,
+            raw: None,
+            annotations: image.annotations.clone(),
+        })
+    }
+}
+
+impl TryFrom<&ImageEvent> for SemanticSegmentationImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(image: &ImageEvent) -> Result<Self, Self::Error> {
+        let tags = image.as_slice().iter().map(|c| c.r).collect();
+        let shape = image.as_array().dim();
+        Ok(Self {
+            height: image.height(),
+            width: image.width(),
+            fov_angle: image.fov_angle(),
+            labels: labels(image.height(), image.width(), tags, shape)?,
+            raw: None,
+            annotations: AnnotationsSerDe::default(),
+        })
+    }
+}
+
+impl TryFrom<ImageEvent> for SemanticSegmentationImageSerDe {
+    type Error = ImageShapeError;
+
+    fn try_from(image: ImageEvent) -> Result<Self, Self::Error> {
+        Self::try_from(&image)
+    }
+}
+
+fn labels(
+    height: usize,
+    width: usize,
+    tags: Vec<u8>,
+    shape: (usize, usize),
+) -> Result<Array2<u8>, ImageShapeError> {
+    Array2::from_shape_vec((height, width), tags).map_err(|_| ImageShapeError {
+        height,
+        width,
+        len: height.saturating_mul(width),
+        is_empty: height == 0 || width == 0,
+        shape,
+    })
+}
+
+impl SemanticSegmentationImageSerDe {
+    /// Also keep the BGRA frame the labels were decoded from.
+    pub fn with_raw(mut self, image: ImageEventSerDe) -> Self {
+        self.raw = Some(image);
+        self
+    }
+
+    /// Decode `image`, keeping it in `raw` if `keep_raw` is set; fails if
+    /// its array does not hold `height x width` pixels.
+    pub fn decode(image: ImageEventSerDe, keep_raw: bool) -> Result<Self, ImageShapeError> {
+        let decoded = Self::try_from(&image)?;
+        Ok(if keep_raw {
+            decoded.with_raw(image)
+        } else {
+            decoded
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// The tag at column `x`, row `y`.
+    pub fn label_at(&self, x: usize, y: usize) -> Option<u8> {
+        self.labels.get((y, x)).copied()
+    }
+
+    /// Pixel count per tag.
+    pub fn class_counts(&self) -> BTreeMap<u8, usize> {
+        let mut counts = BTreeMap::new();
+        for tag in &self.labels {
+            *counts.entry(*tag).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Check that `labels` is `height x width`.
+    pub fn validate(&self) -> Result<(), ImageShapeError> {
+        let shape = self.labels.dim();
+        if shape == (self.height, self.width) {
+            Ok(())
+        } else {
+            Err(ImageShapeError {
+                height: self.height,
+                width: self.width,
+                len: self.height * self.width,
+                is_empty: self.height * self.width == 0,
+                shape,
+            })
+        }
+    }
+
+    /// Overwrite the size fields with what `labels` holds.
+    pub fn reconcile_shape(&mut self) {
+        (self.height, self.width) = self.labels.dim();
+    }
+}
+
+impl fmt::Debug for SemanticSegmentationImageSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (h, w) = self.labels.dim();
+        let mut ds = f.debug_struct("SemanticSegmentationImageSerDe");
+        ds.field("height", &self.height)
+            .field("width", &self.width)
+            .field("fov_angle", &self.fov_angle)
+            .field("raw", &self.raw.as_ref().map(|_| "…"))
+            .field("annotations", &self.annotations);
+        ds.finish_non_exhaustive()?;
+
+        let (show_h, show_w) = if f.alternate() {
+            write!(f, "\nlabels (full {h}x{w}) = [")?;
+            (h, w)
+        } else {
+            write!(
+                f,
+                "\nlabels (preview {h}x{w}, showing {}x{}) = [",
+                PREVIEW_H.min(h),
+                PREVIEW_W.min(w)
+            )?;
+            (PREVIEW_H.min(h), PREVIEW_W.min(w))
+        };
+        for row in self.labels.rows().into_iter().take(show_h) {
+            write!(f, "\n  {:?}", &row.to_vec()[..show_w])?;
+            if w > show_w {
+                write!(f, " …")?;
+            }
+        }
+        if h > show_h {
+            write!(f, "\n  …")?;
+        }
+        write!(f, "\n]")
+    }
+}
+
+/// `Segmentation 800x600 fov=90°, 12 classes`
+impl fmt::Display for SemanticSegmentationImageSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Segmentation {}x{} fov={}°, {} classes",
+            self.width,
+            self.height,
+            self.fov_angle,
+            self.class_counts().len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use carla::sensor::data::Color;
+
+    fn image(height: usize, width: usize, array: Array2<Color>) -> ImageEventSerDe {
+        ImageEventSerDe {
+            height,
+            width,
+            len: height * width,
+            is_empty: height * width == 0,
+            fov_angle: 90.0,
+            array,
+            augmentations: Vec::new(),
+            hash: None,
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+
+    fn pixel(r: u8) -> Color {
+        Color {
+            b: 0,
+            g: 0,
+            r,
+            a: 255,
+        }
+    }
+
+    #[test]
+    fn decodes_matching_shape() {
+        let array = Array2::from_shape_fn((2, 3), |(y, x)| pixel((y * 3 + x) as u8));
+        let decoded = SemanticSegmentationImageSerDe::decode(image(2, 3, array), true).unwrap();
+        assert_eq!(decoded.labels.dim(), (2, 3));
+        assert!(decoded.raw.is_some());
+    }
+
+    #[test]
+    fn rejects_size_mismatch() {
+        let array = Array2::from_elem((2, 2), pixel(1));
+        let err = SemanticSegmentationImageSerDe::try_from(&image(2, 3, array)).unwrap_err();
+        assert_eq!(err.len, 6);
+        assert_eq!(err.shape, (2, 2));
+    }
+}
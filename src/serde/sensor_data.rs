@@ -0,0 +1,286 @@
+use crate::{
+    AnnotationValueSerDe, AnnotationsSerDe, CollisionEventSerDe, DepthImageSerDe,
+    DvsEventArraySerDe, GnssMeasurementSerDe, ImageEventSerDe, ImuFrameSerDe, ImuMeasurementSerDe,
+    InstanceSegmentationImageSerDe, LaneInvasionEventSerDe, LidarMeasurementSerDe,
+    ObstacleDetectionEventSerDe, OpticalFlowImageSerDe, RadarMeasurementSerDe, RawSensorDataSerDe,
+    SemanticLidarMeasurementSerDe, SemanticSegmentationImageSerDe, V2xEventSerDe,
+};
+use carla::sensor::data::{
+    CollisionEvent, GnssMeasurement, Image, ImuMeasurement, LaneInvasionEvent, LidarMeasurement,
+    ObstacleDetectionEvent, RadarMeasurement, SemanticLidarMeasurement,
+};
+use carla::sensor::{SensorData, SensorDataBase};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Annotation holding the CARLA frame number a frame was captured at, see
+/// [`SensorDataSerDe::capture`].
+pub const FRAME_NUMBER_KEY: &str = "frame";
+
+/// Annotation holding the simulation time, in seconds, a frame was
+/// captured at.
+pub const TIMESTAMP_KEY: &str = "timestamp";
+
+/// Any of the owned sensor payloads, tagged by sensor kind.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SensorDataSerDe {
+    Image(ImageEventSerDe),
+    Lidar(LidarMeasurementSerDe),
+    Radar(RadarMeasurementSerDe),
+    Imu(ImuFrameSerDe),
+    Collision(CollisionEventSerDe),
+    LaneInvasion(LaneInvasionEventSerDe),
+    ObstacleDetection(ObstacleDetectionEventSerDe),
+    SemanticLidar(SemanticLidarMeasurementSerDe),
+    Gnss(GnssMeasurementSerDe),
+    Dvs(DvsEventArraySerDe),
+    OpticalFlow(OpticalFlowImageSerDe),
+    SemanticSegmentation(SemanticSegmentationImageSerDe),
+    Depth(DepthImageSerDe),
+    InstanceSegmentation(InstanceSegmentationImageSerDe),
+    V2x(V2xEventSerDe),
+    /// Data of a sensor type without a wrapper, as raw bytes.
+    Raw(RawSensorDataSerDe),
+}
+
+/// Payload type of a [`SensorDataSerDe`], without the data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SensorKind {
+    Image,
+    Lidar,
+    Radar,
+    Imu,
+    Collision,
+    LaneInvasion,
+    ObstacleDetection,
+    SemanticLidar,
+    Gnss,
+    Dvs,
+    OpticalFlow,
+    SemanticSegmentation,
+    Depth,
+    InstanceSegmentation,
+    V2x,
+    Raw,
+}
+
+impl SensorDataSerDe {
+    pub fn kind(&self) -> SensorKind {
+        match self {
+            Self::Image(_) => SensorKind::Image,
+            Self::Lidar(_) => SensorKind::Lidar,
+            Self::Radar(_) => SensorKind::Radar,
+            Self::Imu(_) => SensorKind::Imu,
+            Self::Collision(_) => SensorKind::Collision,
+            Self::LaneInvasion(_) => SensorKind::LaneInvasion,
+            Self::ObstacleDetection(_) => SensorKind::ObstacleDetection,
+            Self::SemanticLidar(_) => SensorKind::SemanticLidar,
+            Self::Gnss(_) => SensorKind::Gnss,
+            Self::Dvs(_) => SensorKind::Dvs,
+            Self::OpticalFlow(_) => SensorKind::OpticalFlow,
+            Self::SemanticSegmentation(_) => SensorKind::SemanticSegmentation,
+            Self::Depth(_) => SensorKind::Depth,
+            Self::InstanceSegmentation(_) => SensorKind::InstanceSegmentation,
+            Self::V2x(_) => SensorKind::V2x,
+            Self::Raw(_) => SensorKind::Raw,
+        }
+    }
+
+    pub fn annotations(&self) -> &AnnotationsSerDe {
+        match self {
+            Self::Image(v) => &v.annotations,
+            Self::Lidar(v) => &v.annotations,
+            Self::Radar(v) => &v.annotations,
+            Self::Imu(v) => &v.annotations,
+            Self::Collision(v) => &v.annotations,
+            Self::LaneInvasion(v) => &v.annotations,
+            Self::ObstacleDetection(v) => &v.annotations,
+            Self::SemanticLidar(v) => &v.annotations,
+            Self::Gnss(v) => &v.annotations,
+            Self::Dvs(v) => &v.annotations,
+            Self::OpticalFlow(v) => &v.annotations,
+            Self::SemanticSegmentation(v) => &v.annotations,
+            Self::Depth(v) => &v.annotations,
+            Self::InstanceSegmentation(v) => &v.annotations,
+            Self::V2x(v) => &v.annotations,
+            Self::Raw(v) => &v.annotations,
+        }
+    }
+
+    /// Side-data of the frame, whatever its payload type.
+    pub fn annotations_mut(&mut self) -> &mut AnnotationsSerDe {
+        match self {
+            Self::Image(v) => &mut v.annotations,
+            Self::Lidar(v) => &mut v.annotations,
+            Self::Radar(v) => &mut v.annotations,
+            Self::Imu(v) => &mut v.annotations,
+            Self::Collision(v) => &mut v.annotations,
+            Self::LaneInvasion(v) => &mut v.annotations,
+            Self::ObstacleDetection(v) => &mut v.annotations,
+            Self::SemanticLidar(v) => &mut v.annotations,
+            Self::Gnss(v) => &mut v.annotations,
+            Self::Dvs(v) => &mut v.annotations,
+            Self::OpticalFlow(v) => &mut v.annotations,
+            Self::SemanticSegmentation(v) => &mut v.annotations,
+            Self::Depth(v) => &mut v.annotations,
+            Self::InstanceSegmentation(v) => &mut v.annotations,
+            Self::V2x(v) => &mut v.annotations,
+            Self::Raw(v) => &mut v.annotations,
+        }
+    }
+
+    /// Like `try_from`, and keeps CARLA's frame number and timestamp of the
+    /// data as annotations, e.g. to merge recordings of several clients
+    /// later.
+    pub fn capture(data: SensorData) -> Result<Self, SensorData> {
+        let (frame, timestamp) = (data.frame(), data.timestamp());
+        let mut converted = Self::try_from(data)?;
+        converted.set_frame_number(frame as u64);
+        converted.set_timestamp(timestamp);
+        Ok(converted)
+    }
+
+    /// Keep `data` of a `type_id` sensor that `try_from` hands back as raw
+    /// bytes, annotated like [`capture`](Self::capture) does.
+    pub fn capture_raw(
+        type_id: impl Into<String>,
+        sensor_data: &impl SensorDataBase,
+        data: impl Into<Vec<u8>>,
+    ) -> Self {
+        let mut captured: Self =
+            RawSensorDataSerDe::from_sensor_data(type_id, sensor_data, data).into();
+        captured.set_frame_number(sensor_data.frame() as u64);
+        captured.set_timestamp(sensor_data.timestamp());
+        captured
+    }
+
+    pub fn frame_number(&self) -> Option<u64> {
+        match self.annotations().get(FRAME_NUMBER_KEY)? {
+            AnnotationValueSerDe::Int(n) => u64::try_from(*n).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn set_frame_number(&mut self, frame: u64) {
+        self.annotations_mut()
+            .insert(FRAME_NUMBER_KEY, AnnotationValueSerDe::Int(frame as i64));
+    }
+
+    pub fn timestamp(&self) -> Option<f64> {
+        match self.annotations().get(TIMESTAMP_KEY)? {
+            AnnotationValueSerDe::Float(t) => Some(*t),
+            _ => None,
+        }
+    }
+
+    pub fn set_timestamp(&mut self, timestamp: f64) {
+        self.annotations_mut()
+            .insert(TIMESTAMP_KEY, AnnotationValueSerDe::Float(timestamp));
+    }
+}
+
+macro_rules! impl_from_payload {
+    ($($variant:ident($ty:ty)),* $(,)?) => {
+        $(
+            impl From<$ty> for SensorDataSerDe {
+                fn from(v: $ty) -> Self {
+                    Self::$variant(v)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_payload!(
+    Image(ImageEventSerDe),
+    Lidar(LidarMeasurementSerDe),
+    Radar(RadarMeasurementSerDe),
+    Imu(ImuFrameSerDe),
+    Collision(CollisionEventSerDe),
+    LaneInvasion(LaneInvasionEventSerDe),
+    ObstacleDetection(ObstacleDetectionEventSerDe),
+    SemanticLidar(SemanticLidarMeasurementSerDe),
+    Gnss(GnssMeasurementSerDe),
+    Dvs(DvsEventArraySerDe),
+    OpticalFlow(OpticalFlowImageSerDe),
+    SemanticSegmentation(SemanticSegmentationImageSerDe),
+    Depth(DepthImageSerDe),
+    InstanceSegmentation(InstanceSegmentationImageSerDe),
+    V2x(V2xEventSerDe),
+    Raw(RawSensorDataSerDe),
+);
+
+impl From<ImuMeasurementSerDe> for SensorDataSerDe {
+    fn from(v: ImuMeasurementSerDe) -> Self {
+        Self::Imu(v.into())
+    }
+}
+
+/// Converts whatever a `Sensor::listen` callback received; hands the data
+/// back if its type has no wrapper in this crate.
+impl TryFrom<SensorData> for SensorDataSerDe {
+    type Error = SensorData;
+
+    fn try_from(data: SensorData) -> Result<Self, Self::Error> {
+        let data = match Image::try_from(data) {
+            Ok(v) => return Ok(ImageEventSerDe::from(v).into()),
+            Err(data) => data,
+        };
+        let data = match LidarMeasurement::try_from(data) {
+            Ok(v) => return Ok(LidarMeasurementSerDe::from(v).into()),
+            Err(data) => data,
+        };
+        let data = match SemanticLidarMeasurement::try_from(data) {
+            Ok(v) => return Ok(SemanticLidarMeasurementSerDe::from(v).into()),
+            Err(data) => data,
+        };
+        let data = match RadarMeasurement::try_from(data) {
+            Ok(v) => return Ok(RadarMeasurementSerDe::from(v).into()),
+            Err(data) => data,
+        };
+        let data = match ImuMeasurement::try_from(data) {
+            Ok(v) => return Ok(ImuMeasurementSerDe::from(v).into()),
+            Err(data) => data,
+        };
+        let data = match GnssMeasurement::try_from(data) {
+            Ok(v) => return Ok(GnssMeasurementSerDe::from(v).into()),
+            Err(data) => data,
+        };
+        let data = match CollisionEvent::try_from(data) {
+            Ok(v) => return Ok(CollisionEventSerDe::from(v).into()),
+            Err(data) => data,
+        };
+        let data = match LaneInvasionEvent::try_from(data) {
+            Ok(v) => return Ok(LaneInvasionEventSerDe::from(v).into()),
+            Err(data) => data,
+        };
+        match ObstacleDetectionEvent::try_from(data) {
+            Ok(v) => Ok(ObstacleDetectionEventSerDe::from(v).into()),
+            Err(data) => Err(data),
+        }
+    }
+}
+
+/// The payload's one-line summary.
+impl fmt::Display for SensorDataSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Image(v) => v.fmt(f),
+            Self::Lidar(v) => v.fmt(f),
+            Self::Radar(v) => v.fmt(f),
+            Self::Imu(v) => v.fmt(f),
+            Self::Collision(v) => v.fmt(f),
+            Self::LaneInvasion(v) => v.fmt(f),
+            Self::ObstacleDetection(v) => v.fmt(f),
+            Self::SemanticLidar(v) => v.fmt(f),
+            Self::Gnss(v) => v.fmt(f),
+            Self::Dvs(v) => v.fmt(f),
+            Self::OpticalFlow(v) => v.fmt(f),
+            Self::SemanticSegmentation(v) => v.fmt(f),
+            Self::Depth(v) => v.fmt(f),
+            Self::InstanceSegmentation(v) => v.fmt(f),
+            Self::V2x(v) => v.fmt(f),
+            Self::Raw(v) => v.fmt(f),
+        }
+    }
+}
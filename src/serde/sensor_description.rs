@@ -0,0 +1,86 @@
+use crate::CameraInfoSerDe;
+use carla::client::{ActorAttributeValueKind, ActorBase};
+use nalgebra::Isometry3;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Static description of a sensor mounted on a rig.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SensorDescriptionSerDe {
+    pub id: carla::rpc::ActorId,
+    pub type_id: String,
+    pub role_name: String,
+    /// Pose relative to the parent actor (usually the ego vehicle), in
+    /// CARLA's coordinate frame. World pose if the sensor is not attached.
+    pub mount: Isometry3<f32>,
+    /// Intrinsics, for `sensor.camera.*` types only.
+    pub camera: Option<CameraInfoSerDe>,
+}
+
+impl SensorDescriptionSerDe {
+    /// `role_name` if set, otherwise the actor id.
+    pub fn name(&self) -> String {
+        if self.role_name.is_empty() {
+            format!("sensor_{}", self.id)
+        } else {
+            self.role_name.clone()
+        }
+    }
+
+    pub fn is_camera(&self) -> bool {
+        self.type_id.starts_with("sensor.camera.")
+    }
+
+    /// A `sensor.camera.rgb`, the only camera whose frames are recorded
+    /// as RGB images which the image transforms act on.
+    pub fn is_rgb_camera(&self) -> bool {
+        self.type_id == "sensor.camera.rgb"
+    }
+}
+
+impl<A: ActorBase> From<&A> for SensorDescriptionSerDe {
+    fn from(actor: &A) -> Self {
+        let mut role_name = String::new();
+        let (mut width, mut height, mut fov) = (None, None, None);
+        for attr in actor.attributes().iter() {
+            match (attr.id().as_str(), attr.value()) {
+                ("role_name", Some(ActorAttributeValueKind::String(v))) => role_name = v,
+                ("image_size_x", Some(ActorAttributeValueKind::Int(v))) => width = Some(v),
+                ("image_size_y", Some(ActorAttributeValueKind::Int(v))) => height = Some(v),
+                ("fov", Some(ActorAttributeValueKind::F32(v))) => fov = Some(v),
+                _ => {}
+            }
+        }
+
+        let type_id = actor.type_id();
+        let camera = match (width, height, fov) {
+            (Some(w), Some(h), Some(fov)) if type_id.starts_with("sensor.camera.") => {
+                Some(CameraInfoSerDe::from_fov(w as usize, h as usize, fov))
+            }
+            _ => None,
+        };
+        let mount = match actor.parent() {
+            Some(parent) => parent.transform().inverse() * actor.transform(),
+            None => actor.transform(),
+        };
+
+        Self {
+            id: actor.id(),
+            type_id,
+            role_name,
+            mount,
+            camera,
+        }
+    }
+}
+
+/// `front sensor.camera.rgb 800x600 fov=90° f=400.0`
+impl fmt::Display for SensorDescriptionSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name(), self.type_id)?;
+        if let Some(camera) = &self.camera {
+            write!(f, " {camera}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use carla::rpc::EpisodeSettings;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The world settings that decide how the timestamps of a recording relate
+/// to each other, the timing part of CARLA's `EpisodeSettings`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SimulationSettingsSerDe {
+    /// The server waits for a client tick before each step.
+    pub synchronous_mode: bool,
+    /// Simulation seconds per step; `None` for variable steps that follow
+    /// the server's wall clock.
+    pub fixed_delta_seconds: Option<f64>,
+    /// Physics runs in up to `max_substeps` substeps of at most
+    /// `max_substep_delta_time` seconds per step.
+    pub substepping: bool,
+    pub max_substep_delta_time: f64,
+    pub max_substeps: u64,
+    /// Cameras produce no images while set.
+    #[serde(default)]
+    pub no_rendering_mode: bool,
+}
+
+impl SimulationSettingsSerDe {
+    /// Whether consecutive frames are exactly `fixed_delta_seconds` apart
+    /// and no step was skipped waiting for a client.
+    pub fn is_deterministic(&self) -> bool {
+        self.synchronous_mode && self.fixed_delta_seconds.is_some()
+    }
+
+    /// Simulation seconds between `from` and `to`, for fixed steps.
+    pub fn elapsed(&self, from: u64, to: u64) -> Option<f64> {
+        self.fixed_delta_seconds
+            .map(|dt| (to as f64 - from as f64) * dt)
+    }
+
+    /// Whether physics can keep up with the step: substeps of at most
+    /// `max_substep_delta_time` must cover `fixed_delta_seconds`.
+    pub fn substeps_suffice(&self) -> bool {
+        match self.fixed_delta_seconds {
+            Some(dt) if self.substepping => {
+                dt <= self.max_substep_delta_time * self.max_substeps as f64
+            }
+            _ => true,
+        }
+    }
+
+    /// Write these settings into `settings`, leaving the unrelated ones.
+    pub fn apply_to(&self, settings: &mut EpisodeSettings) {
+        settings.synchronous_mode = self.synchronous_mode;
+        settings.fixed_delta_seconds = self.fixed_delta_seconds;
+        settings.substepping = self.substepping;
+        settings.max_substep_delta_time = self.max_substep_delta_time;
+        settings.max_substeps = self.max_substeps;
+        settings.no_rendering_mode = self.no_rendering_mode;
+    }
+}
+
+impl From<&EpisodeSettings> for SimulationSettingsSerDe {
+    fn from(settings: &EpisodeSettings) -> Self {
+        Self {
+            synchronous_mode: settings.synchronous_mode,
+            fixed_delta_seconds: settings.fixed_delta_seconds,
+            substepping: settings.substepping,
+            max_substep_delta_time: settings.max_substep_delta_time,
+            max_substeps: settings.max_substeps,
+            no_rendering_mode: settings.no_rendering_mode,
+        }
+    }
+}
+
+impl From<EpisodeSettings> for SimulationSettingsSerDe {
+    fn from(settings: EpisodeSettings) -> Self {
+        (&settings).into()
+    }
+}
+
+/// `synchronous, 0.050 s steps, up to 10 substeps of 0.010 s`, plus
+/// `, no rendering` when set; `asynchronous, variable steps` otherwise.
+impl fmt::Display for SimulationSettingsSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.synchronous_mode {
+            "synchronous"
+        } else {
+            "asynchronous"
+        })?;
+        match self.fixed_delta_seconds {
+            Some(dt) => write!(f, ", {dt:.3} s steps")?,
+            None => f.write_str(", variable steps")?,
+        }
+        if self.substepping {
+            write!(
+                f,
+                ", up to {} substeps of {:.3} s",
+                self.max_substeps, self.max_substep_delta_time
+            )?;
+        }
+        if self.no_rendering_mode {
+            f.write_str(", no rendering")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+use crate::{
+    AnnotationsSerDe, CameraInfoSerDe, DEPTH_FAR_PLANE, DepthImageSerDe, ImageEventSerDe,
+    SensorDescriptionSerDe,
+};
+use carla::sensor::data::Color;
+use nalgebra::{Isometry3, Translation3};
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A rectified stereo pair: two cameras with the same intrinsics and
+/// orientation, the right one `baseline` meters to the right of the left.
+///
+/// CARLA cameras are ideal pinholes, so two cameras mounted side by side
+/// with equal rotation and attributes are rectified as captured.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StereoFrameSerDe {
+    pub left: ImageEventSerDe,
+    pub right: ImageEventSerDe,
+    /// Meters between the optical centers.
+    pub baseline: f32,
+    /// Pose of the right camera relative to the left, in CARLA's
+    /// coordinate frame.
+    pub extrinsics: Isometry3<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disparity: Option<DisparityMapSerDe>,
+}
+
+impl StereoFrameSerDe {
+    /// A pair whose right camera sits `baseline` meters right of the left.
+    pub fn new(left: ImageEventSerDe, right: ImageEventSerDe, baseline: f32) -> Self {
+        Self {
+            left,
+            right,
+            baseline,
+            extrinsics: Translation3::new(0.0, baseline, 0.0).into(),
+            disparity: None,
+        }
+    }
+
+    /// A pair with the extrinsics and baseline of two cameras of a rig.
+    pub fn from_rig(
+        left: ImageEventSerDe,
+        right: ImageEventSerDe,
+        left_sensor: &SensorDescriptionSerDe,
+        right_sensor: &SensorDescriptionSerDe,
+    ) -> Self {
+        let extrinsics = left_sensor.mount.inverse() * right_sensor.mount;
+        Self {
+            left,
+            right,
+            baseline: extrinsics.translation.vector.norm(),
+            extrinsics,
+            disparity: None,
+        }
+    }
+
+    /// Compute the disparity of the left image with `matcher` and keep it.
+    /// Left unset if the images differ in size.
+    pub fn with_disparity(mut self, matcher: &BlockMatcher) -> Self {
+        self.disparity = matcher.compute(&self.left, &self.right);
+        self
+    }
+
+    /// Metric depth of the left image from the disparity,
+    /// `fx * baseline / disparity`. Pixels without a match, or farther,
+    /// are at [`DEPTH_FAR_PLANE`] like in CARLA's depth camera.
+    pub fn depth(&self) -> Option<DepthImageSerDe> {
+        let disparity = self.disparity.as_ref()?;
+        let fx = CameraInfoSerDe::from(&self.left).fx;
+        let depth = disparity.disparity.mapv(|d| {
+            if d > 0.0 {
+                (fx * self.baseline / d).min(DEPTH_FAR_PLANE)
+            } else {
+                DEPTH_FAR_PLANE
+            }
+        });
+        let (height, width) = depth.dim();
+        Some(DepthImageSerDe {
+            height,
+            width,
+            fov_angle: self.left.fov_angle,
+            depth,
+            raw: None,
+            annotations: AnnotationsSerDe::default(),
+        })
+    }
+}
+
+/// `800x600 pair, baseline 0.54 m, with disparity`
+impl fmt::Display for StereoFrameSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{} pair, baseline {:.2} m",
+            self.left.width, self.left.height, self.baseline
+        )?;
+        if self.disparity.is_some() {
+            f.write_str(", with disparity")?;
+        }
+        Ok(())
+    }
+}
+
+/// Disparity of the left image of a [`StereoFrameSerDe`], in pixels,
+/// `height x width`. 0 where no match was found.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DisparityMapSerDe {
+    #[serde(with = "super::array_rows")]
+    pub disparity: Array2<f32>,
+}
+
+impl DisparityMapSerDe {
+    pub fn height(&self) -> usize {
+        self.disparity.nrows()
+    }
+
+    pub fn width(&self) -> usize {
+        self.disparity.ncols()
+    }
+
+    /// Pixels at column `x`, row `y`.
+    pub fn disparity_at(&self, x: usize, y: usize) -> Option<f32> {
+        self.disparity.get((y, x)).copied()
+    }
+
+    /// Share of pixels with a match.
+    pub fn coverage(&self) -> f32 {
+        if self.disparity.is_empty() {
+            return 0.0;
+        }
+        let matched = self.disparity.iter().filter(|&&d| d > 0.0).count();
+        matched as f32 / self.disparity.len() as f32
+    }
+}
+
+/// Block-matching stereo: for every pixel of the left image, the shift
+/// along the row whose square block in the right image has the smallest
+/// sum of absolute gray-level differences, refined to sub-pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockMatcher {
+    block_size: usize,
+    max_disparity: usize,
+}
+
+impl Default for BlockMatcher {
+    fn default() -> Self {
+        Self {
+            block_size: 7,
+            max_disparity: 64,
+        }
+    }
+}
+
+impl BlockMatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Side of the compared blocks in pixels, 7 by default; even sizes
+    /// are rounded up.
+    pub fn with_block_size(mut self, size: usize) -> Self {
+        self.block_size = size | 1;
+        self
+    }
+
+    /// Largest shift searched, in pixels; 64 by default.
+    pub fn with_max_disparity(mut self, pixels: usize) -> Self {
+        self.max_disparity = pixels;
+        self
+    }
+
+    /// The disparity of `left`, if both images have the same size. Pixels
+    /// whose block does not fit into both images get no match.
+    pub fn compute(
+        &self,
+        left: &ImageEventSerDe,
+        right: &ImageEventSerDe,
+    ) -> Option<DisparityMapSerDe> {
+        if left.array.dim() != right.array.dim() {
+            return None;
+        }
+        let (h, w) = left.array.dim();
+        let (l, r) = (left.array.map(gray), right.array.map(gray));
+        let half = self.block_size / 2;
+        let mut best = Array2::from_elem((h, w), (f64::INFINITY, 0usize));
+        // costs right before and after the best shift, for the refinement
+        let mut around = Array2::from_elem((h, w), (f64::NAN, f64::NAN));
+        let mut previous = Array2::from_elem((h, w), f64::NAN);
+
+        for d in 0..=self.max_disparity.min(w.saturating_sub(1)) {
+            let cost = block_costs(&l, &r, d, half);
+            for ((y, x), &c) in cost.indexed_iter() {
+                let (best_cost, best_d) = &mut best[(y, x)];
+                if c < *best_cost {
+                    (*best_cost, *best_d) = (c, d);
+                    around[(y, x)] = (previous[(y, x)], f64::NAN);
+                } else if *best_d + 1 == d && best_cost.is_finite() {
+                    around[(y, x)].1 = c;
+                }
+            }
+            previous = cost;
+        }
+
+        let disparity = Array2::from_shape_fn((h, w), |(y, x)| {
+            let (c, d) = best[(y, x)];
+            if !c.is_finite() {
+                return 0.0;
+            }
+            let (before, after) = around[(y, x)];
+            let curvature = before - 2.0 * c + after;
+            let refine = if curvature > 0.0 {
+                0.5 * (before - after) / curvature
+            } else {
+                0.0
+            };
+            (d as f64 + refine).max(0.0) as f32
+        });
+        Some(DisparityMapSerDe { disparity })
+    }
+}
+
+fn gray(px: &Color) -> f32 {
+    0.299 * px.r as f32 + 0.587 * px.g as f32 + 0.114 * px.b as f32
+}
+
+// Sum of absolute differences of the block around every left pixel and
+// the block `d` pixels to its left in the right image; infinite where a
+// block does not fit.
+fn block_costs(l: &Array2<f32>, r: &Array2<f32>, d: usize, half: usize) -> Array2<f64> {
+    let (h, w) = l.dim();
+    // integral image of the differences
+    let mut sums = Array2::<f64>::zeros((h + 1, w + 1));
+    for y in 0..h {
+        for x in 0..w {
+            let diff = if x >= d {
+                (l[(y, x)] - r[(y, x - d)]).abs() as f64
+            } else {
+                0.0
+            };
+            sums[(y + 1, x + 1)] = diff + sums[(y, x + 1)] + sums[(y + 1, x)] - sums[(y, x)];
+        }
+    }
+    Array2::from_shape_fn((h, w), |(y, x)| {
+        if y < half || y + half >= h || x < d + half || x + half >= w {
+            return f64::INFINITY;
+        }
+        let (y0, y1, x0, x1) = (y - half, y + half + 1, x - half, x + half + 1);
+        sums[(y1, x1)] - sums[(y0, x1)] - sums[(y1, x0)] + sums[(y0, x0)]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a textured `height x width` frame, shifted left by `shift` pixels
+    fn image(height: usize, width: usize, shift: usize) -> ImageEventSerDe {
+        let array = Array2::from_shape_fn((height, width), |(y, x)| {
+            let v = ((x + shift) * 91 + y * 39) % 97 * 2;
+            Color {
+                b: v as u8,
+                g: v as u8,
+                r: v as u8,
+                a: 255,
+            }
+        });
+        ImageEventSerDe {
+            height,
+            width,
+            len: height * width,
+            is_empty: false,
+            fov_angle: 90.0,
+            array,
+            augmentations: Vec::new(),
+            hash: None,
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+
+    #[test]
+    fn matches_a_shifted_landscape_pair() {
+        let matcher = BlockMatcher::new().with_block_size(3).with_max_disparity(4);
+        let pair =
+            StereoFrameSerDe::new(image(5, 12, 0), image(5, 12, 2), 0.5).with_disparity(&matcher);
+        let disparity = pair.disparity.as_ref().unwrap();
+        assert_eq!((disparity.height(), disparity.width()), (5, 12));
+        for x in 4..11 {
+            let d = disparity.disparity_at(x, 2).unwrap();
+            assert!((d - 2.0).abs() < 0.5, "disparity {d} at column {x}");
+        }
+        assert_eq!(disparity.disparity_at(1, 2), Some(0.0));
+
+        let depth = pair.depth().unwrap();
+        assert_eq!((depth.height, depth.width), (5, 12));
+        assert_eq!(depth.depth.dim(), (5, 12));
+        // fx of a 12 pixel wide 90° camera is 6
+        assert!((depth.depth[(2, 6)] - 6.0 * 0.5 / 2.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn pairs_of_different_sizes_have_no_disparity() {
+        let pair = StereoFrameSerDe::new(image(5, 12, 0), image(12, 5, 0), 0.5)
+            .with_disparity(&BlockMatcher::new());
+        assert!(pair.disparity.is_none());
+    }
+}
@@ -0,0 +1,114 @@
+use carla::client::{ActorBase, TrafficLight};
+use carla::rpc::TrafficLightState;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(remote = "carla::rpc::TrafficLightState")]
+pub enum TrafficLightStateSerDe {
+    Red = 0,
+    Yellow = 1,
+    Green = 2,
+    Off = 3,
+    Unknown = 4,
+    /// Count of the states above, never reported by a light.
+    SIZE = 5,
+}
+
+/// The state of one traffic light at one frame, ground truth for the
+/// signalized intersections of a run.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrafficLightSnapshotSerDe {
+    pub id: carla::rpc::ActorId,
+    #[serde(with = "TrafficLightStateSerDe")]
+    pub state: TrafficLightState,
+    /// Seconds since the light entered `state`.
+    pub elapsed_time: f32,
+    /// Index of the light's pole in its group.
+    pub pole_index: u32,
+}
+
+impl TrafficLightSnapshotSerDe {
+    pub fn is_red(&self) -> bool {
+        self.state == TrafficLightState::Red
+    }
+}
+
+impl From<&TrafficLight> for TrafficLightSnapshotSerDe {
+    fn from(light: &TrafficLight) -> Self {
+        Self {
+            id: light.id(),
+            state: light.state(),
+            elapsed_time: light.elapsed_time(),
+            pole_index: light.pole_index(),
+        }
+    }
+}
+
+impl From<TrafficLight> for TrafficLightSnapshotSerDe {
+    fn from(light: TrafficLight) -> Self {
+        Self::from(&light)
+    }
+}
+
+// ---------- enum conversions ----------
+impl From<TrafficLightState> for TrafficLightStateSerDe {
+    fn from(v: TrafficLightState) -> Self {
+        use TrafficLightState as F;
+        match v {
+            F::Red => Self::Red,
+            F::Yellow => Self::Yellow,
+            F::Green => Self::Green,
+            F::Off => Self::Off,
+            F::Unknown => Self::Unknown,
+            F::SIZE => Self::SIZE,
+        }
+    }
+}
+
+impl From<TrafficLightStateSerDe> for TrafficLightState {
+    fn from(v: TrafficLightStateSerDe) -> Self {
+        use TrafficLightStateSerDe as L;
+        match v {
+            L::Red => Self::Red,
+            L::Yellow => Self::Yellow,
+            L::Green => Self::Green,
+            L::Off => Self::Off,
+            L::Unknown => Self::Unknown,
+            L::SIZE => Self::SIZE,
+        }
+    }
+}
+
+// the carla enum does not implement Debug; show it through the local mirror
+impl fmt::Debug for TrafficLightSnapshotSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrafficLightSnapshotSerDe")
+            .field("id", &self.id)
+            .field("state", &TrafficLightStateSerDe::from(self.state.clone()))
+            .field("elapsed_time", &self.elapsed_time)
+            .field("pole_index", &self.pole_index)
+            .finish()
+    }
+}
+
+/// `traffic light #42 Red for 3.20 s, pole 1`
+impl fmt::Display for TrafficLightSnapshotSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "traffic light #{} {:?} for {:.2} s, pole {}",
+            self.id,
+            TrafficLightStateSerDe::from(self.state.clone()),
+            self.elapsed_time,
+            self.pole_index
+        )
+    }
+}
+
+/// The variant name, e.g. `Red`.
+impl fmt::Display for TrafficLightStateSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
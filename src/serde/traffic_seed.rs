@@ -0,0 +1,141 @@
+use super::actor_blueprint::actor_attributes;
+use crate::{ActorAttributeSerDe, TransformSerDe};
+use carla::client::ActorBase;
+use serde::{Deserialize, Serialize};
+
+/// The vehicle and walker attributes a blueprint lets a client set; CARLA
+/// throws on setting any other, e.g. `number_of_wheels` or `gender`.
+const MODIFIABLE_ATTRIBUTES: &[&str] = &[
+    "color",
+    "driver_id",
+    "is_invincible",
+    "role_name",
+    "speed",
+    "sticky_control",
+    "terramechanics",
+];
+
+/// How the background traffic of a scenario was spawned, enough to spawn
+/// similar traffic again. The Traffic Manager is not deterministic across
+/// server versions and machines, so a replay only approximates the
+/// original run.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrafficSeedSerDe {
+    /// Seed passed to the Traffic Manager's `set_random_device_seed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tm_seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tm_port: Option<u16>,
+    /// In spawn order.
+    pub entities: Vec<TrafficEntitySerDe>,
+}
+
+/// One spawned vehicle or walker.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrafficEntitySerDe {
+    /// Blueprint id, e.g. `vehicle.audi.tt` or `walker.pedestrian.0001`.
+    pub type_id: String,
+    /// World pose at spawn time.
+    pub spawn: TransformSerDe,
+    /// Handed to the Traffic Manager after spawning.
+    #[serde(default)]
+    pub autopilot: bool,
+    /// Blueprint attributes of the spawned actor, e.g. `color` or
+    /// `role_name`, read-only ones included.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<ActorAttributeSerDe>,
+}
+
+impl TrafficSeedSerDe {
+    pub fn push(&mut self, entity: TrafficEntitySerDe) {
+        self.entities.push(entity);
+    }
+
+    pub fn vehicles(&self) -> impl Iterator<Item = &TrafficEntitySerDe> {
+        self.entities.iter().filter(|e| e.is_vehicle())
+    }
+
+    pub fn walkers(&self) -> impl Iterator<Item = &TrafficEntitySerDe> {
+        self.entities.iter().filter(|e| e.is_walker())
+    }
+}
+
+impl TrafficEntitySerDe {
+    pub fn new(type_id: impl Into<String>, spawn: TransformSerDe) -> Self {
+        Self {
+            type_id: type_id.into(),
+            spawn,
+            autopilot: false,
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn with_autopilot(mut self, autopilot: bool) -> Self {
+        self.autopilot = autopilot;
+        self
+    }
+
+    pub fn is_vehicle(&self) -> bool {
+        self.type_id.starts_with("vehicle.")
+    }
+
+    pub fn is_walker(&self) -> bool {
+        self.type_id.starts_with("walker.")
+    }
+
+    /// The attributes to set on the blueprint to spawn the entity again,
+    /// as `set_attribute` takes them, colors as `r,g,b`; read-only ones
+    /// are skipped.
+    pub fn attribute_strings(&self) -> impl Iterator<Item = (&str, String)> {
+        self.attributes
+            .iter()
+            .filter(|attr| MODIFIABLE_ATTRIBUTES.contains(&attr.id.as_str()))
+            .map(|attr| (attr.id.as_str(), attr.value.to_attribute_string()))
+    }
+}
+
+/// Reads the type, pose and attributes of a just-spawned actor; whether it
+/// drives on autopilot is not visible on the actor, see
+/// [`TrafficEntitySerDe::with_autopilot`].
+impl<A: ActorBase> From<&A> for TrafficEntitySerDe {
+    fn from(actor: &A) -> Self {
+        let mut entity = Self::new(actor.type_id(), (&actor.transform()).into());
+        entity.attributes = actor_attributes(actor);
+        entity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ActorAttributeValueSerDe, LocationSerDe};
+
+    #[test]
+    fn replay_skips_read_only_attributes() {
+        let spawn = TransformSerDe {
+            location: LocationSerDe {
+                x: 1.0,
+                y: 2.0,
+                z: 0.5,
+            },
+            ..Default::default()
+        };
+        let mut entity = TrafficEntitySerDe::new("vehicle.audi.tt", spawn);
+        entity.attributes = vec![
+            ActorAttributeSerDe {
+                id: "color".into(),
+                value: ActorAttributeValueSerDe::RgbColor([255, 0, 0]),
+            },
+            ActorAttributeSerDe {
+                id: "number_of_wheels".into(),
+                value: ActorAttributeValueSerDe::Int(4),
+            },
+        ];
+        let strings: Vec<_> = entity.attribute_strings().collect();
+        assert_eq!(strings, vec![("color", "255,0,0".to_string())]);
+
+        let json = serde_json::to_value(&entity).unwrap();
+        assert_eq!(json["spawn"]["location"]["x"], 1.0);
+        assert_eq!(json["spawn"]["rotation"]["yaw"], 0.0);
+    }
+}
@@ -0,0 +1,142 @@
+use carla::geom::{Location, Rotation, Transform, TransformExt};
+use nalgebra::Isometry3;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A point in CARLA's left-handed world frame, in meters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocationSerDe {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl From<Location> for LocationSerDe {
+    fn from(l: Location) -> Self {
+        Self::from(&l)
+    }
+}
+
+impl From<&Location> for LocationSerDe {
+    fn from(l: &Location) -> Self {
+        Self {
+            x: l.x,
+            y: l.y,
+            z: l.z,
+        }
+    }
+}
+
+impl From<LocationSerDe> for Location {
+    fn from(l: LocationSerDe) -> Self {
+        Location {
+            x: l.x,
+            y: l.y,
+            z: l.z,
+        }
+    }
+}
+
+/// An orientation as CARLA's Euler angles, in degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RotationSerDe {
+    pub pitch: f32,
+    pub yaw: f32,
+    pub roll: f32,
+}
+
+impl From<Rotation> for RotationSerDe {
+    fn from(r: Rotation) -> Self {
+        Self::from(&r)
+    }
+}
+
+impl From<&Rotation> for RotationSerDe {
+    fn from(r: &Rotation) -> Self {
+        Self {
+            pitch: r.pitch,
+            yaw: r.yaw,
+            roll: r.roll,
+        }
+    }
+}
+
+impl From<RotationSerDe> for Rotation {
+    fn from(r: RotationSerDe) -> Self {
+        Rotation {
+            pitch: r.pitch,
+            yaw: r.yaw,
+            roll: r.roll,
+        }
+    }
+}
+
+/// A pose as CARLA's `Transform`, e.g. a sensor's mount or an actor's
+/// place in the world.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransformSerDe {
+    pub location: LocationSerDe,
+    pub rotation: RotationSerDe,
+}
+
+impl From<Transform> for TransformSerDe {
+    fn from(t: Transform) -> Self {
+        Self::from(&t)
+    }
+}
+
+impl From<&Transform> for TransformSerDe {
+    fn from(t: &Transform) -> Self {
+        Self {
+            location: (&t.location).into(),
+            rotation: (&t.rotation).into(),
+        }
+    }
+}
+
+impl From<TransformSerDe> for Transform {
+    fn from(t: TransformSerDe) -> Self {
+        Transform {
+            location: t.location.into(),
+            rotation: t.rotation.into(),
+        }
+    }
+}
+
+/// The pose of an `Isometry3`, as the sensor transforms of the carla crate
+/// have it.
+impl From<&Isometry3<f32>> for TransformSerDe {
+    fn from(pose: &Isometry3<f32>) -> Self {
+        Transform::from_na(pose).into()
+    }
+}
+
+impl From<TransformSerDe> for Isometry3<f32> {
+    fn from(t: TransformSerDe) -> Self {
+        Transform::from(t).to_na()
+    }
+}
+
+impl fmt::Display for LocationSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:.2}, {:.2}, {:.2}) m", self.x, self.y, self.z)
+    }
+}
+
+/// `pitch 0.0°, yaw 90.0°, roll 0.0°`
+impl fmt::Display for RotationSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pitch {:.1}°, yaw {:.1}°, roll {:.1}°",
+            self.pitch, self.yaw, self.roll
+        )
+    }
+}
+
+/// `(1.50, 0.00, 2.40) m, pitch 0.0°, yaw 90.0°, roll 0.0°`
+impl fmt::Display for TransformSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {}", self.location, self.rotation)
+    }
+}
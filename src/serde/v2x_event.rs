@@ -0,0 +1,161 @@
+use crate::AnnotationsSerDe;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// The ITS PDU header every V2X message starts with.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct ItsPduHeaderSerDe {
+    pub protocol_version: u8,
+    /// 2 for a CAM.
+    pub message_id: u8,
+    /// The sender, unique per actor.
+    pub station_id: u32,
+}
+
+/// A sender's position, degrees and meters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CamReferencePositionSerDe {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum DriveDirectionSerDe {
+    Forward,
+    Backward,
+    #[default]
+    Unavailable,
+}
+
+/// The dynamic state of a sending vehicle, in SI units: degrees for the
+/// heading, m/s, meters, m/s², 1/m and degrees per second.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CamHighFrequencySerDe {
+    pub heading: f32,
+    pub speed: f32,
+    pub drive_direction: DriveDirectionSerDe,
+    pub vehicle_length: f32,
+    pub vehicle_width: f32,
+    pub longitudinal_acceleration: f32,
+    pub curvature: f32,
+    pub yaw_rate: f32,
+}
+
+/// The slowly changing part of a CAM, sent every few messages.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct CamLowFrequencySerDe {
+    /// ETSI vehicle role, 0 for none.
+    pub vehicle_role: u8,
+    /// ETSI exterior lights bit string, bit 0 low beam.
+    pub exterior_lights: u8,
+}
+
+/// A cooperative awareness message, as sent by `sensor.other.v2x`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CamMessageSerDe {
+    pub header: ItsPduHeaderSerDe,
+    /// Milliseconds of the generation time, modulo 65536.
+    pub generation_delta_time: u16,
+    /// ETSI station type, e.g. 5 for a passenger car.
+    pub station_type: u8,
+    pub reference_position: CamReferencePositionSerDe,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high_frequency: Option<CamHighFrequencySerDe>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_frequency: Option<CamLowFrequencySerDe>,
+}
+
+/// An application message, as sent by `sensor.other.v2x_custom`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CustomV2xMessageSerDe {
+    pub header: ItsPduHeaderSerDe,
+    pub data: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum V2xPayloadSerDe {
+    Cam(CamMessageSerDe),
+    Custom(CustomV2xMessageSerDe),
+}
+
+impl V2xPayloadSerDe {
+    pub fn header(&self) -> &ItsPduHeaderSerDe {
+        match self {
+            Self::Cam(cam) => &cam.header,
+            Self::Custom(custom) => &custom.header,
+        }
+    }
+}
+
+/// One received message.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct V2xMessageSerDe {
+    /// Received power, in dBm.
+    pub power: f32,
+    pub payload: V2xPayloadSerDe,
+}
+
+/// The messages a V2X receiver got in one tick.
+///
+/// The carla crate has no binding for `CAMEvent` or `CustomV2XEvent` yet;
+/// build the messages from what the sensor reports.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct V2xEventSerDe {
+    pub messages: Vec<V2xMessageSerDe>,
+    #[serde(default, skip_serializing_if = "AnnotationsSerDe::is_empty")]
+    pub annotations: AnnotationsSerDe,
+}
+
+impl V2xEventSerDe {
+    pub fn new(messages: Vec<V2xMessageSerDe>) -> Self {
+        Self {
+            messages,
+            annotations: AnnotationsSerDe::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// The CAMs among the messages.
+    pub fn cams(&self) -> impl Iterator<Item = &CamMessageSerDe> {
+        self.messages.iter().filter_map(|m| match &m.payload {
+            V2xPayloadSerDe::Cam(cam) => Some(cam),
+            V2xPayloadSerDe::Custom(_) => None,
+        })
+    }
+
+    /// The senders heard from.
+    pub fn station_ids(&self) -> BTreeSet<u32> {
+        self.messages
+            .iter()
+            .map(|m| m.payload.header().station_id)
+            .collect()
+    }
+}
+
+/// `V2X 3 messages (2 CAM), 2 stations`
+impl fmt::Display for V2xEventSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "V2X {} messages ({} CAM), {} stations",
+            self.len(),
+            self.cams().count(),
+            self.station_ids().len()
+        )
+    }
+}
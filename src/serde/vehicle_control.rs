@@ -0,0 +1,99 @@
+use carla::client::Vehicle;
+use carla::rpc::VehicleControl;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Driver inputs of a vehicle, CARLA's `VehicleControl`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VehicleControlSerDe {
+    /// 0 to 1.
+    pub throttle: f32,
+    /// -1 (full left) to 1 (full right).
+    pub steer: f32,
+    /// 0 to 1.
+    pub brake: f32,
+    pub hand_brake: bool,
+    pub reverse: bool,
+    pub manual_gear_shift: bool,
+    pub gear: i32,
+}
+
+/// The control a vehicle received at one frame of a drive.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ControlSampleSerDe {
+    /// CARLA frame number the control was applied at.
+    pub frame: u64,
+    /// Role name of the vehicle, or its fleet id.
+    pub vehicle: String,
+    pub control: VehicleControlSerDe,
+}
+
+impl ControlSampleSerDe {
+    /// The control `vehicle` is driving with right now.
+    pub fn capture(frame: u64, name: impl Into<String>, vehicle: &Vehicle) -> Self {
+        Self {
+            frame,
+            vehicle: name.into(),
+            control: vehicle.control().into(),
+        }
+    }
+}
+
+impl From<VehicleControl> for VehicleControlSerDe {
+    fn from(c: VehicleControl) -> Self {
+        Self::from(&c)
+    }
+}
+
+impl From<&VehicleControl> for VehicleControlSerDe {
+    fn from(c: &VehicleControl) -> Self {
+        Self {
+            throttle: c.throttle,
+            steer: c.steer,
+            brake: c.brake,
+            hand_brake: c.hand_brake,
+            reverse: c.reverse,
+            manual_gear_shift: c.manual_gear_shift,
+            gear: c.gear,
+        }
+    }
+}
+
+impl From<&VehicleControlSerDe> for VehicleControl {
+    fn from(c: &VehicleControlSerDe) -> Self {
+        Self {
+            throttle: c.throttle,
+            steer: c.steer,
+            brake: c.brake,
+            hand_brake: c.hand_brake,
+            reverse: c.reverse,
+            manual_gear_shift: c.manual_gear_shift,
+            gear: c.gear,
+        }
+    }
+}
+
+impl From<VehicleControlSerDe> for VehicleControl {
+    fn from(c: VehicleControlSerDe) -> Self {
+        Self::from(&c)
+    }
+}
+
+/// `throttle 0.60 steer -0.12 brake 0.00`, plus `hand brake` and `reverse`
+/// when set.
+impl fmt::Display for VehicleControlSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "throttle {:.2} steer {:.2} brake {:.2}",
+            self.throttle, self.steer, self.brake
+        )?;
+        if self.hand_brake {
+            f.write_str(" hand brake")?;
+        }
+        if self.reverse {
+            f.write_str(" reverse")?;
+        }
+        Ok(())
+    }
+}
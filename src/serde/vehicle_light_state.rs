@@ -0,0 +1,177 @@
+use carla::client::Vehicle;
+use carla::rpc::VehicleLightState;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const POSITION: u32 = 1;
+const LOW_BEAM: u32 = 2;
+const HIGH_BEAM: u32 = 4;
+const BRAKE: u32 = 8;
+const RIGHT_BLINKER: u32 = 16;
+const LEFT_BLINKER: u32 = 32;
+const REVERSE: u32 = 64;
+const FOG: u32 = 128;
+const INTERIOR: u32 = 256;
+const SPECIAL1: u32 = 512;
+const SPECIAL2: u32 = 1024;
+
+/// The lights of a vehicle, CARLA's `VehicleLightState` bitmask as named
+/// flags. Flags missing from a document are off.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(default)]
+pub struct VehicleLightStateSerDe {
+    pub position: bool,
+    pub low_beam: bool,
+    pub high_beam: bool,
+    pub brake: bool,
+    pub right_blinker: bool,
+    pub left_blinker: bool,
+    pub reverse: bool,
+    pub fog: bool,
+    pub interior: bool,
+    /// Sirens and beacons, e.g. of emergency vehicles.
+    pub special1: bool,
+    pub special2: bool,
+}
+
+impl VehicleLightStateSerDe {
+    /// Bits outside CARLA's flags are dropped.
+    pub fn from_bits(bits: u32) -> Self {
+        Self {
+            position: bits & POSITION != 0,
+            low_beam: bits & LOW_BEAM != 0,
+            high_beam: bits & HIGH_BEAM != 0,
+            brake: bits & BRAKE != 0,
+            right_blinker: bits & RIGHT_BLINKER != 0,
+            left_blinker: bits & LEFT_BLINKER != 0,
+            reverse: bits & REVERSE != 0,
+            fog: bits & FOG != 0,
+            interior: bits & INTERIOR != 0,
+            special1: bits & SPECIAL1 != 0,
+            special2: bits & SPECIAL2 != 0,
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.flags()
+            .iter()
+            .filter(|(_, on, _)| *on)
+            .fold(0, |bits, (bit, ..)| bits | bit)
+    }
+
+    /// The lights of `vehicle` right now.
+    pub fn capture(vehicle: &Vehicle) -> Self {
+        Self::from(&vehicle.light_state())
+    }
+
+    /// The carla crate passes the bitmask as its enum, which can only hold
+    /// no light, a single light or all of them; `None` for other states.
+    pub fn to_carla(&self) -> Option<VehicleLightState> {
+        use VehicleLightState as L;
+        Some(match self.bits() {
+            0 => L::None,
+            POSITION => L::Position,
+            LOW_BEAM => L::LowBeam,
+            HIGH_BEAM => L::HighBeam,
+            BRAKE => L::Brake,
+            RIGHT_BLINKER => L::RightBlinker,
+            LEFT_BLINKER => L::LeftBlinker,
+            REVERSE => L::Reverse,
+            FOG => L::Fog,
+            INTERIOR => L::Interior,
+            SPECIAL1 => L::Special1,
+            SPECIAL2 => L::Special2,
+            0x7ff => L::All,
+            _ => return None,
+        })
+    }
+
+    fn flags(&self) -> [(u32, bool, &'static str); 11] {
+        [
+            (POSITION, self.position, "position"),
+            (LOW_BEAM, self.low_beam, "low_beam"),
+            (HIGH_BEAM, self.high_beam, "high_beam"),
+            (BRAKE, self.brake, "brake"),
+            (RIGHT_BLINKER, self.right_blinker, "right_blinker"),
+            (LEFT_BLINKER, self.left_blinker, "left_blinker"),
+            (REVERSE, self.reverse, "reverse"),
+            (FOG, self.fog, "fog"),
+            (INTERIOR, self.interior, "interior"),
+            (SPECIAL1, self.special1, "special1"),
+            (SPECIAL2, self.special2, "special2"),
+        ]
+    }
+}
+
+impl From<u32> for VehicleLightStateSerDe {
+    fn from(bits: u32) -> Self {
+        Self::from_bits(bits)
+    }
+}
+
+impl From<VehicleLightStateSerDe> for u32 {
+    fn from(state: VehicleLightStateSerDe) -> Self {
+        state.bits()
+    }
+}
+
+/// Like [`VehicleLightStateSerDe::to_carla`], the enum only holds no light,
+/// a single light or all of them.
+impl From<&VehicleLightState> for VehicleLightStateSerDe {
+    fn from(state: &VehicleLightState) -> Self {
+        use VehicleLightState as L;
+        Self::from_bits(match state {
+            L::None => 0,
+            L::Position => POSITION,
+            L::LowBeam => LOW_BEAM,
+            L::HighBeam => HIGH_BEAM,
+            L::Brake => BRAKE,
+            L::RightBlinker => RIGHT_BLINKER,
+            L::LeftBlinker => LEFT_BLINKER,
+            L::Reverse => REVERSE,
+            L::Fog => FOG,
+            L::Interior => INTERIOR,
+            L::Special1 => SPECIAL1,
+            L::Special2 => SPECIAL2,
+            L::All => u32::MAX,
+        })
+    }
+}
+
+impl From<VehicleLightState> for VehicleLightStateSerDe {
+    fn from(state: VehicleLightState) -> Self {
+        Self::from(&state)
+    }
+}
+
+/// `low_beam brake left_blinker`, or `off`
+impl fmt::Display for VehicleLightStateSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut on = self.flags().into_iter().filter(|(_, on, _)| *on);
+        match on.next() {
+            None => f.write_str("off"),
+            Some((_, _, first)) => {
+                f.write_str(first)?;
+                on.try_for_each(|(_, _, name)| write!(f, " {name}"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carla_states_round_trip() {
+        for bits in (0..11).map(|i| 1 << i).chain([0, 0x7ff]) {
+            let state = VehicleLightStateSerDe::from_bits(bits);
+            let carla = state.to_carla().unwrap();
+            assert_eq!(VehicleLightStateSerDe::from(&carla), state);
+        }
+        let all = VehicleLightStateSerDe::from(VehicleLightState::All);
+        assert_eq!(all.bits(), 0x7ff);
+    }
+}
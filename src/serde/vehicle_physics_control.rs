@@ -0,0 +1,208 @@
+use crate::{Vector2DSerDe, Vector3DSerDe};
+use carla::client::Vehicle;
+use carla::rpc::{GearPhysicsControl, VehiclePhysicsControl, WheelPhysicsControl};
+use nalgebra::{Translation3, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// One forward gear of a vehicle, CARLA's `GearPhysicsControl`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GearPhysicsControlSerDe {
+    pub ratio: f32,
+    /// Fraction of the max rpm below which the gearbox shifts down.
+    pub down_ratio: f32,
+    /// Fraction of the max rpm above which the gearbox shifts up.
+    pub up_ratio: f32,
+}
+
+/// One wheel of a vehicle, CARLA's `WheelPhysicsControl`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WheelPhysicsControlSerDe {
+    pub tire_friction: f32,
+    pub damping_rate: f32,
+    /// Degrees.
+    pub max_steer_angle: f32,
+    /// Centimeters.
+    pub radius: f32,
+    /// Nm.
+    pub max_brake_torque: f32,
+    /// Nm.
+    pub max_handbrake_torque: f32,
+    pub lat_stiff_max_load: f32,
+    pub lat_stiff_value: f32,
+    pub long_stiff_value: f32,
+    /// World position of the wheel, in centimeters as CARLA reports it.
+    pub position: Vector3DSerDe,
+}
+
+/// The dynamics of a vehicle, CARLA's `VehiclePhysicsControl`, so a run
+/// can be restored with the same engine, gearbox, mass and wheels.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VehiclePhysicsControlSerDe {
+    /// (rpm, torque in Nm) points.
+    pub torque_curve: Vec<Vector2DSerDe>,
+    pub max_rpm: f32,
+    /// Moment of inertia of the engine, kg m².
+    pub moi: f32,
+    pub damping_rate_full_throttle: f32,
+    pub damping_rate_zero_throttle_clutch_engaged: f32,
+    pub damping_rate_zero_throttle_clutch_disengaged: f32,
+    pub use_gear_autobox: bool,
+    /// Seconds.
+    pub gear_switch_time: f32,
+    pub clutch_strength: f32,
+    pub final_ratio: f32,
+    pub forward_gears: Vec<GearPhysicsControlSerDe>,
+    /// Kilograms.
+    pub mass: f32,
+    pub drag_coefficient: f32,
+    /// Meters, relative to the vehicle's origin.
+    pub center_of_mass: Vector3DSerDe,
+    /// (speed in km/h, steering factor) points.
+    pub steering_curve: Vec<Vector2DSerDe>,
+    pub wheels: Vec<WheelPhysicsControlSerDe>,
+    pub use_sweep_wheel_collision: bool,
+}
+
+impl VehiclePhysicsControlSerDe {
+    /// The physics `vehicle` is simulated with right now.
+    pub fn capture(vehicle: &Vehicle) -> Self {
+        vehicle.physics_control().into()
+    }
+
+    /// Give `vehicle` these physics, e.g. to restore a recorded run.
+    pub fn apply_to(&self, vehicle: &Vehicle) {
+        vehicle.apply_physics_control(&self.into());
+    }
+}
+
+impl From<&GearPhysicsControl> for GearPhysicsControlSerDe {
+    fn from(g: &GearPhysicsControl) -> Self {
+        Self {
+            ratio: g.ratio,
+            down_ratio: g.down_ratio,
+            up_ratio: g.up_ratio,
+        }
+    }
+}
+
+impl From<&GearPhysicsControlSerDe> for GearPhysicsControl {
+    fn from(g: &GearPhysicsControlSerDe) -> Self {
+        Self {
+            ratio: g.ratio,
+            down_ratio: g.down_ratio,
+            up_ratio: g.up_ratio,
+        }
+    }
+}
+
+impl From<&WheelPhysicsControl> for WheelPhysicsControlSerDe {
+    fn from(w: &WheelPhysicsControl) -> Self {
+        Self {
+            tire_friction: w.tire_friction,
+            damping_rate: w.damping_rate,
+            max_steer_angle: w.max_steer_angle,
+            radius: w.radius,
+            max_brake_torque: w.max_brake_torque,
+            max_handbrake_torque: w.max_handbrake_torque,
+            lat_stiff_max_load: w.lat_stiff_max_load,
+            lat_stiff_value: w.lat_stiff_value,
+            long_stiff_value: w.long_stiff_value,
+            position: (&w.position).into(),
+        }
+    }
+}
+
+impl From<&WheelPhysicsControlSerDe> for WheelPhysicsControl {
+    fn from(w: &WheelPhysicsControlSerDe) -> Self {
+        Self {
+            tire_friction: w.tire_friction,
+            damping_rate: w.damping_rate,
+            max_steer_angle: w.max_steer_angle,
+            radius: w.radius,
+            max_brake_torque: w.max_brake_torque,
+            max_handbrake_torque: w.max_handbrake_torque,
+            lat_stiff_max_load: w.lat_stiff_max_load,
+            lat_stiff_value: w.lat_stiff_value,
+            long_stiff_value: w.long_stiff_value,
+            position: w.position.into(),
+        }
+    }
+}
+
+impl From<VehiclePhysicsControl> for VehiclePhysicsControlSerDe {
+    fn from(c: VehiclePhysicsControl) -> Self {
+        Self::from(&c)
+    }
+}
+
+impl From<&VehiclePhysicsControl> for VehiclePhysicsControlSerDe {
+    fn from(c: &VehiclePhysicsControl) -> Self {
+        Self {
+            torque_curve: c.torque_curve.iter().map(Vector2DSerDe::from).collect(),
+            max_rpm: c.max_rpm,
+            moi: c.moi,
+            damping_rate_full_throttle: c.damping_rate_full_throttle,
+            damping_rate_zero_throttle_clutch_engaged: c.damping_rate_zero_throttle_clutch_engaged,
+            damping_rate_zero_throttle_clutch_disengaged: c
+                .damping_rate_zero_throttle_clutch_disengaged,
+            use_gear_autobox: c.use_gear_autobox,
+            gear_switch_time: c.gear_switch_time,
+            clutch_strength: c.clutch_strength,
+            final_ratio: c.final_ratio,
+            forward_gears: c.forward_gears.iter().map(Into::into).collect(),
+            mass: c.mass,
+            drag_coefficient: c.drag_coefficient,
+            center_of_mass: (&c.center_of_mass.vector).into(),
+            steering_curve: c.steering_curve.iter().map(Vector2DSerDe::from).collect(),
+            wheels: c.wheels.iter().map(Into::into).collect(),
+            use_sweep_wheel_collision: c.use_sweep_wheel_collision,
+        }
+    }
+}
+
+impl From<&VehiclePhysicsControlSerDe> for VehiclePhysicsControl {
+    fn from(c: &VehiclePhysicsControlSerDe) -> Self {
+        Self {
+            torque_curve: c.torque_curve.iter().map(|&p| Vector2::from(p)).collect(),
+            max_rpm: c.max_rpm,
+            moi: c.moi,
+            damping_rate_full_throttle: c.damping_rate_full_throttle,
+            damping_rate_zero_throttle_clutch_engaged: c.damping_rate_zero_throttle_clutch_engaged,
+            damping_rate_zero_throttle_clutch_disengaged: c
+                .damping_rate_zero_throttle_clutch_disengaged,
+            use_gear_autobox: c.use_gear_autobox,
+            gear_switch_time: c.gear_switch_time,
+            clutch_strength: c.clutch_strength,
+            final_ratio: c.final_ratio,
+            forward_gears: c.forward_gears.iter().map(Into::into).collect(),
+            mass: c.mass,
+            drag_coefficient: c.drag_coefficient,
+            center_of_mass: Translation3::from(Vector3::from(c.center_of_mass)),
+            steering_curve: c.steering_curve.iter().map(|&p| Vector2::from(p)).collect(),
+            wheels: c.wheels.iter().map(Into::into).collect(),
+            use_sweep_wheel_collision: c.use_sweep_wheel_collision,
+        }
+    }
+}
+
+impl From<VehiclePhysicsControlSerDe> for VehiclePhysicsControl {
+    fn from(c: VehiclePhysicsControlSerDe) -> Self {
+        Self::from(&c)
+    }
+}
+
+/// `1845 kg, max 5000 rpm, 6 gears (auto), 4 wheels`
+impl fmt::Display for VehiclePhysicsControlSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.0} kg, max {:.0} rpm, {} gears{}, {} wheels",
+            self.mass,
+            self.max_rpm,
+            self.forward_gears.len(),
+            if self.use_gear_autobox { " (auto)" } else { "" },
+            self.wheels.len()
+        )
+    }
+}
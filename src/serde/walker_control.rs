@@ -0,0 +1,96 @@
+use crate::{TransformSerDe, Vector3DSerDe};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Movement of a pedestrian, CARLA's `WalkerControl`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WalkerControlSerDe {
+    /// World direction of travel.
+    pub direction: Vector3DSerDe,
+    /// m/s.
+    pub speed: f32,
+    pub jump: bool,
+}
+
+impl WalkerControlSerDe {
+    /// `direction` scaled to `speed`, in m/s.
+    pub fn velocity(&self) -> Vector3<f32> {
+        Vector3::from(self.direction)
+            .try_normalize(f32::EPSILON)
+            .map_or_else(Vector3::zeros, |d| d * self.speed)
+    }
+}
+
+/// `speed 1.40 m/s towards (1.00, 0.00, 0.00)`, plus `jump` when set.
+impl fmt::Display for WalkerControlSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "speed {:.2} m/s towards {}", self.speed, self.direction)?;
+        if self.jump {
+            f.write_str(" jump")?;
+        }
+        Ok(())
+    }
+}
+
+/// The pose given to one bone of a pedestrian's skeleton.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BoneTransformSerDe {
+    /// Bone name, e.g. `crl_hand__R`.
+    pub bone_name: String,
+    /// Relative to the bone's parent.
+    pub transform: TransformSerDe,
+}
+
+/// Poses of single bones of a pedestrian, CARLA's `WalkerBoneControlIn`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WalkerBoneControlSerDe {
+    pub bone_transforms: Vec<BoneTransformSerDe>,
+}
+
+impl WalkerBoneControlSerDe {
+    pub fn bone(&self, name: &str) -> Option<&TransformSerDe> {
+        self.bone_transforms
+            .iter()
+            .find(|b| b.bone_name == name)
+            .map(|b| &b.transform)
+    }
+}
+
+/// The control a pedestrian received at one frame of a run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WalkerControlSampleSerDe {
+    /// CARLA frame number the control was applied at.
+    pub frame: u64,
+    /// Role name of the pedestrian.
+    pub walker: String,
+    pub control: WalkerControlSerDe,
+    /// Bone poses applied on top of the walk animation, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bones: Option<WalkerBoneControlSerDe>,
+}
+
+/// `2 bones: crl_hand__R, crl_hand__L`
+impl fmt::Display for WalkerBoneControlSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.bone_transforms.len();
+        write!(f, "{count} {}", if count == 1 { "bone" } else { "bones" })?;
+        for (i, bone) in self.bone_transforms.iter().enumerate() {
+            let sep = if i == 0 { ": " } else { ", " };
+            write!(f, "{sep}{}", bone.bone_name)?;
+        }
+        Ok(())
+    }
+}
+
+/// `frame 120 ped_1: speed 1.40 m/s towards (1.00, 0.00, 0.00)`, plus
+/// `, 2 bones: …` when bones were posed.
+impl fmt::Display for WalkerControlSampleSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "frame {} {}: {}", self.frame, self.walker, self.control)?;
+        match &self.bones {
+            Some(bones) => write!(f, ", {bones}"),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{LaneMarkingLaneChangeSerDe, LaneMarkingSerDe};
+use carla::client::Waypoint;
+use carla::road::element::LaneMarking_LaneChange;
+use carla::road::{JuncId, LaneId, LaneType, RoadId, SectionId};
+use nalgebra::Isometry3;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(remote = "carla::road::LaneType")]
+pub enum LaneTypeSerDe {
+    None = 1,
+    Driving = 2,
+    Stop = 4,
+    Shoulder = 8,
+    Biking = 16,
+    Sidewalk = 32,
+    Border = 64,
+    Restricted = 128,
+    Parking = 256,
+    Bidirectional = 512,
+    Median = 1024,
+    Special1 = 2048,
+    Special2 = 4096,
+    Special3 = 8192,
+    RoadWorks = 16384,
+    Tram = 32768,
+    Rail = 65536,
+    Entry = 131072,
+    Exit = 262144,
+    OffRamp = 524288,
+    OnRamp = 1048576,
+    Any = -2,
+}
+
+/// A point on the center of a lane, CARLA's `Waypoint`, for planner traces
+/// and route exports.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WaypointSerDe {
+    pub id: u64,
+    pub road_id: RoadId,
+    pub section_id: SectionId,
+    /// Positive for lanes left of the road's reference line, negative for
+    /// lanes right of it.
+    pub lane_id: LaneId,
+    /// Meters along the road from its start.
+    pub s: f64,
+    /// Set inside a junction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub junction_id: Option<JuncId>,
+    /// World pose, facing along the lane.
+    pub transform: Isometry3<f32>,
+    /// Meters.
+    pub lane_width: f64,
+    #[serde(with = "LaneTypeSerDe")]
+    pub lane_type: LaneType,
+    /// Lane changes the markings allow.
+    #[serde(with = "LaneMarkingLaneChangeSerDe")]
+    pub lane_change: LaneMarking_LaneChange,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub left_lane_marking: Option<LaneMarkingSerDe>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub right_lane_marking: Option<LaneMarkingSerDe>,
+}
+
+impl From<&Waypoint> for WaypointSerDe {
+    fn from(w: &Waypoint) -> Self {
+        Self {
+            id: w.id(),
+            road_id: w.road_id(),
+            section_id: w.section_id(),
+            lane_id: w.lane_id(),
+            s: w.distance(),
+            junction_id: w.is_junction().then(|| w.junction_id()),
+            transform: w.transform(),
+            lane_width: w.lane_width(),
+            lane_type: w.type_(),
+            lane_change: w.lane_change(),
+            left_lane_marking: w.left_lane_marking().as_ref().map(LaneMarkingSerDe::from),
+            right_lane_marking: w.right_lane_marking().as_ref().map(LaneMarkingSerDe::from),
+        }
+    }
+}
+
+impl From<Waypoint> for WaypointSerDe {
+    fn from(w: Waypoint) -> Self {
+        Self::from(&w)
+    }
+}
+
+// ---------- enum conversions ----------
+impl From<LaneType> for LaneTypeSerDe {
+    fn from(v: LaneType) -> Self {
+        use LaneType as F;
+        match v {
+            F::None => Self::None,
+            F::Driving => Self::Driving,
+            F::Stop => Self::Stop,
+            F::Shoulder => Self::Shoulder,
+            F::Biking => Self::Biking,
+            F::Sidewalk => Self::Sidewalk,
+            F::Border => Self::Border,
+            F::Restricted => Self::Restricted,
+            F::Parking => Self::Parking,
+            F::Bidirectional => Self::Bidirectional,
+            F::Median => Self::Median,
+            F::Special1 => Self::Special1,
+            F::Special2 => Self::Special2,
+            F::Special3 => Self::Special3,
+            F::RoadWorks => Self::RoadWorks,
+            F::Tram => Self::Tram,
+            F::Rail => Self::Rail,
+            F::Entry => Self::Entry,
+            F::Exit => Self::Exit,
+            F::OffRamp => Self::OffRamp,
+            F::OnRamp => Self::OnRamp,
+            F::Any => Self::Any,
+        }
+    }
+}
+
+// the carla enums only implement Clone; show and compare them through the
+// local mirrors
+impl fmt::Debug for WaypointSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaypointSerDe")
+            .field("id", &self.id)
+            .field("road_id", &self.road_id)
+            .field("section_id", &self.section_id)
+            .field("lane_id", &self.lane_id)
+            .field("s", &self.s)
+            .field("junction_id", &self.junction_id)
+            .field("transform", &self.transform)
+            .field("lane_width", &self.lane_width)
+            .field("lane_type", &LaneTypeSerDe::from(self.lane_type.clone()))
+            .field(
+                "lane_change",
+                &LaneMarkingLaneChangeSerDe::from(self.lane_change.clone()),
+            )
+            .field("left_lane_marking", &self.left_lane_marking)
+            .field("right_lane_marking", &self.right_lane_marking)
+            .finish()
+    }
+}
+
+impl PartialEq for WaypointSerDe {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.road_id == other.road_id
+            && self.section_id == other.section_id
+            && self.lane_id == other.lane_id
+            && self.s == other.s
+            && self.junction_id == other.junction_id
+            && self.transform == other.transform
+            && self.lane_width == other.lane_width
+            && self.lane_type == other.lane_type
+            && LaneMarkingLaneChangeSerDe::from(self.lane_change.clone())
+                == LaneMarkingLaneChangeSerDe::from(other.lane_change.clone())
+            && self.left_lane_marking == other.left_lane_marking
+            && self.right_lane_marking == other.right_lane_marking
+    }
+}
+
+/// `road 12 section 0 lane -1 at 35.20 m, Driving 3.50 m wide`
+impl fmt::Display for WaypointSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "road {} section {} lane {} at {:.2} m, {:?} {:.2} m wide",
+            self.road_id,
+            self.section_id,
+            self.lane_id,
+            self.s,
+            LaneTypeSerDe::from(self.lane_type.clone()),
+            self.lane_width
+        )
+    }
+}
+
+/// The variant name, e.g. `Driving`.
+impl fmt::Display for LaneTypeSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
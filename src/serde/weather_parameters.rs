@@ -0,0 +1,116 @@
+use carla::client::World;
+use carla::rpc::WeatherParameters;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The weather of a world, CARLA's `WeatherParameters`. Amounts are 0 to
+/// 100 unless noted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WeatherParametersSerDe {
+    pub cloudiness: f32,
+    pub precipitation: f32,
+    /// Puddles on the road.
+    pub precipitation_deposits: f32,
+    pub wind_intensity: f32,
+    /// Degrees, 0 to 360.
+    pub sun_azimuth_angle: f32,
+    /// Degrees, -90 (midnight) to 90 (midday).
+    pub sun_altitude_angle: f32,
+    pub fog_density: f32,
+    /// Meters to where the fog starts.
+    pub fog_distance: f32,
+    /// Density falloff of the fog with height; 0 or more.
+    pub fog_falloff: f32,
+    pub wetness: f32,
+    pub scattering_intensity: f32,
+    pub mie_scattering_scale: f32,
+    pub rayleigh_scattering_scale: f32,
+    pub dust_storm: f32,
+}
+
+impl WeatherParametersSerDe {
+    /// The weather of `world` right now.
+    pub fn capture(world: &World) -> Self {
+        world.weather().into()
+    }
+
+    /// Give `world` this weather, e.g. to restore a recorded run.
+    pub fn apply_to(&self, world: &mut World) {
+        world.set_weather(&self.into());
+    }
+
+    /// The sun is below the horizon.
+    pub fn is_night(&self) -> bool {
+        self.sun_altitude_angle < 0.0
+    }
+}
+
+impl From<&WeatherParameters> for WeatherParametersSerDe {
+    fn from(w: &WeatherParameters) -> Self {
+        Self {
+            cloudiness: w.cloudiness,
+            precipitation: w.precipitation,
+            precipitation_deposits: w.precipitation_deposits,
+            wind_intensity: w.wind_intensity,
+            sun_azimuth_angle: w.sun_azimuth_angle,
+            sun_altitude_angle: w.sun_altitude_angle,
+            fog_density: w.fog_density,
+            fog_distance: w.fog_distance,
+            fog_falloff: w.fog_falloff,
+            wetness: w.wetness,
+            scattering_intensity: w.scattering_intensity,
+            mie_scattering_scale: w.mie_scattering_scale,
+            rayleigh_scattering_scale: w.rayleigh_scattering_scale,
+            dust_storm: w.dust_storm,
+        }
+    }
+}
+
+impl From<WeatherParameters> for WeatherParametersSerDe {
+    fn from(w: WeatherParameters) -> Self {
+        Self::from(&w)
+    }
+}
+
+impl From<&WeatherParametersSerDe> for WeatherParameters {
+    fn from(w: &WeatherParametersSerDe) -> Self {
+        Self {
+            cloudiness: w.cloudiness,
+            precipitation: w.precipitation,
+            precipitation_deposits: w.precipitation_deposits,
+            wind_intensity: w.wind_intensity,
+            sun_azimuth_angle: w.sun_azimuth_angle,
+            sun_altitude_angle: w.sun_altitude_angle,
+            fog_density: w.fog_density,
+            fog_distance: w.fog_distance,
+            fog_falloff: w.fog_falloff,
+            wetness: w.wetness,
+            scattering_intensity: w.scattering_intensity,
+            mie_scattering_scale: w.mie_scattering_scale,
+            rayleigh_scattering_scale: w.rayleigh_scattering_scale,
+            dust_storm: w.dust_storm,
+        }
+    }
+}
+
+impl From<WeatherParametersSerDe> for WeatherParameters {
+    fn from(w: WeatherParametersSerDe) -> Self {
+        Self::from(&w)
+    }
+}
+
+/// `clouds 30 rain 0 fog 0 wet 0, sun 45° at 170°`
+impl fmt::Display for WeatherParametersSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "clouds {:.0} rain {:.0} fog {:.0} wet {:.0}, sun {:.0}° at {:.0}°",
+            self.cloudiness,
+            self.precipitation,
+            self.fog_density,
+            self.wetness,
+            self.sun_altitude_angle,
+            self.sun_azimuth_angle
+        )
+    }
+}
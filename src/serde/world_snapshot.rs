@@ -0,0 +1,91 @@
+use crate::Vector3DSerDe;
+use carla::client::{ActorBase, World};
+use nalgebra::Isometry3;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The kinematic state of one actor at one tick, CARLA's `ActorSnapshot`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActorSnapshotSerDe {
+    pub id: carla::rpc::ActorId,
+    /// World pose.
+    pub transform: Isometry3<f32>,
+    /// m/s.
+    pub velocity: Vector3DSerDe,
+    /// Degrees per second, as CARLA reports it.
+    pub angular_velocity: Vector3DSerDe,
+    /// m/s².
+    pub acceleration: Vector3DSerDe,
+}
+
+impl<A: ActorBase> From<&A> for ActorSnapshotSerDe {
+    fn from(actor: &A) -> Self {
+        Self {
+            id: actor.id(),
+            transform: actor.transform(),
+            velocity: actor.velocity().into(),
+            angular_velocity: actor.angular_velocity().into(),
+            acceleration: actor.acceleration().into(),
+        }
+    }
+}
+
+/// The state of the whole world at one tick, CARLA's `WorldSnapshot`, as
+/// one document.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorldSnapshotSerDe {
+    /// Id of the snapshot, the same for all ticks of an episode.
+    pub id: u64,
+    pub frame: u64,
+    /// Simulation seconds since the episode started.
+    pub elapsed_seconds: f64,
+    /// Simulation seconds since the previous tick.
+    pub delta_seconds: f64,
+    /// Wall clock seconds of the tick, as given by the server's OS.
+    pub platform_timestamp: f64,
+    /// Sorted by id when captured.
+    pub actors: Vec<ActorSnapshotSerDe>,
+}
+
+impl WorldSnapshotSerDe {
+    /// The world as of its latest tick. The carla crate does not hand out
+    /// the actor states of a snapshot, so they are read right after it; call
+    /// this between ticks, e.g. after `World::tick` in synchronous mode.
+    pub fn capture(world: &World) -> Self {
+        let snapshot = world.snapshot();
+        let timestamp = snapshot.timestamp();
+        let mut actors: Vec<_> = world
+            .actors()
+            .iter()
+            .filter(|actor| snapshot.contains(actor.id()))
+            .map(|actor| ActorSnapshotSerDe::from(&actor))
+            .collect();
+        actors.sort_by_key(|actor| actor.id);
+        Self {
+            id: snapshot.id(),
+            frame: snapshot.frame() as u64,
+            elapsed_seconds: timestamp.elapsed_seconds,
+            delta_seconds: timestamp.delta_seconds,
+            platform_timestamp: timestamp.platform_timestamp,
+            actors,
+        }
+    }
+
+    pub fn actor(&self, id: carla::rpc::ActorId) -> Option<&ActorSnapshotSerDe> {
+        self.actors.iter().find(|actor| actor.id == id)
+    }
+}
+
+/// `frame 1234 at 61.700 s (+0.050 s), 42 actors`
+impl fmt::Display for WorldSnapshotSerDe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "frame {} at {:.3} s ({:+.3} s), {} actors",
+            self.frame,
+            self.elapsed_seconds,
+            self.delta_seconds,
+            self.actors.len()
+        )
+    }
+}
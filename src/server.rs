@@ -0,0 +1,17 @@
+//! HTTP endpoints for a recorder running as a long-lived service.
+//!
+//! The server is deliberately minimal: HTTP/1.1 over [`std::net`], one
+//! request per connection, answered by a fixed pool of [`WORKERS`]
+//! threads. Run [`serve`] on a thread of its own next to the recording
+//! loop.
+//!
+//! [`Health`] backs `/healthz` and `/readyz` for orchestrators, and
+//! [`RecordingServer`] serves the JSON-lines recordings in a directory.
+
+mod health;
+mod http;
+mod recordings;
+
+pub use health::*;
+pub use http::*;
+pub use recordings::*;
@@ -0,0 +1,199 @@
+use super::{Request, Response};
+use crate::SensorKind;
+use crate::pipeline::{SharedFrame, Sink};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+struct State {
+    started: Instant,
+    ready: bool,
+    stale_after: Duration,
+    min_disk_free: u64,
+    disk_path: Option<PathBuf>,
+    sinks: BTreeMap<String, Option<String>>,
+    last_frame: BTreeMap<SensorKind, Instant>,
+}
+
+/// Shared service state behind the health endpoints.
+///
+/// Clones are handles to the same state: hand one to the recorder, which
+/// reports sink status and readiness, subscribe one to the bus as a
+/// [`Sink`] to track frame arrivals, and give one to [`serve`](super::serve)
+/// through [`Health::handle`].
+#[derive(Clone, Debug)]
+pub struct Health {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Body of `/healthz` and `/readyz`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub ready: bool,
+    pub uptime_s: f64,
+    /// Sink name to its last error; `null` for healthy sinks.
+    pub sinks: BTreeMap<String, Option<String>>,
+    /// Milliseconds since the last frame of each sensor kind seen.
+    pub last_frame_ms: BTreeMap<SensorKind, u64>,
+    /// Free space on the recording volume, where it can be determined.
+    pub disk_free_bytes: Option<u64>,
+    /// Why the service is not healthy or not ready.
+    pub problems: Vec<String>,
+}
+
+impl Health {
+    /// Not ready until [`set_ready`](Self::set_ready); sensors go stale
+    /// after 2 s without frames.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                started: Instant::now(),
+                ready: false,
+                stale_after: Duration::from_secs(2),
+                min_disk_free: 0,
+                disk_path: None,
+                sinks: BTreeMap::new(),
+                last_frame: BTreeMap::new(),
+            })),
+        }
+    }
+
+    pub fn with_stale_after(self, stale_after: Duration) -> Self {
+        self.lock().stale_after = stale_after;
+        self
+    }
+
+    /// Report free space of the volume holding `path`, and fail readiness
+    /// when it drops below `min_free` bytes.
+    pub fn with_disk(self, path: impl Into<PathBuf>, min_free: u64) -> Self {
+        {
+            let mut state = self.lock();
+            state.disk_path = Some(path.into());
+            state.min_disk_free = min_free;
+        }
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Set once the CARLA session is up and sensors are spawned.
+    pub fn set_ready(&self, ready: bool) {
+        self.lock().ready = ready;
+    }
+
+    pub fn sink_ok(&self, name: impl Into<String>) {
+        self.lock().sinks.insert(name.into(), None);
+    }
+
+    /// Mark a sink as failing; the service is unhealthy until it reports
+    /// [`sink_ok`](Self::sink_ok) again.
+    pub fn sink_failed(&self, name: impl Into<String>, error: impl Into<String>) {
+        self.lock().sinks.insert(name.into(), Some(error.into()));
+    }
+
+    pub fn frame_seen(&self, kind: SensorKind) {
+        self.lock().last_frame.insert(kind, Instant::now());
+    }
+
+    pub fn report(&self) -> HealthReport {
+        let state = self.lock();
+        let mut problems = Vec::new();
+
+        let mut healthy = true;
+        for (name, error) in &state.sinks {
+            if let Some(error) = error {
+                healthy = false;
+                problems.push(format!("sink {name}: {error}"));
+            }
+        }
+
+        let mut ready = state.ready && healthy;
+        if !state.ready {
+            problems.push("not ready".into());
+        }
+        let last_frame_ms = state
+            .last_frame
+            .iter()
+            .map(|(kind, t)| {
+                let age = t.elapsed();
+                if age > state.stale_after {
+                    ready = false;
+                    problems.push(format!("no {kind:?} frame for {} ms", age.as_millis()));
+                }
+                (*kind, age.as_millis() as u64)
+            })
+            .collect();
+
+        let disk_free_bytes = state.disk_path.as_deref().and_then(disk_free);
+        if let Some(free) = disk_free_bytes
+            && free < state.min_disk_free
+        {
+            ready = false;
+            problems.push(format!("only {free} bytes free on the recording volume"));
+        }
+
+        HealthReport {
+            healthy,
+            ready,
+            uptime_s: state.started.elapsed().as_secs_f64(),
+            sinks: state.sinks.clone(),
+            last_frame_ms,
+            disk_free_bytes,
+            problems,
+        }
+    }
+
+    /// Answers `/healthz` (liveness) and `/readyz` (readiness) with 200 or
+    /// 503 and a [`HealthReport`]; `None` for other paths, so it can be
+    /// chained with other handlers.
+    pub fn handle(&self, request: &Request) -> Option<Response> {
+        let report = self.report();
+        let ok = match request.path.as_str() {
+            "/healthz" => report.healthy,
+            "/readyz" => report.ready,
+            _ => return None,
+        };
+        Some(Response::json(if ok { 200 } else { 503 }, &report))
+    }
+}
+
+impl Sink for Health {
+    fn consume(&mut self, frame: SharedFrame) {
+        self.frame_seen(frame.kind());
+    }
+}
+
+#[cfg(unix)]
+fn disk_free(path: &std::path::Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stat` is only read after
+    // statvfs reported success.
+    let stat = unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stat.assume_init()
+    };
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn disk_free(_path: &std::path::Path) -> Option<u64> {
+    None
+}
@@ -0,0 +1,400 @@
+use serde::Serialize;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Bytes a streamed body is sent in at most, per chunk.
+const CHUNK: usize = 64 << 10;
+
+/// How long [`serve`] waits on a stalled client before dropping it.
+pub const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+// most bytes of request line and headers read
+const MAX_HEAD: u64 = 16 << 10;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// Path without the query string, e.g. `/readyz`.
+    pub path: String,
+    /// Decoded query parameters, in order.
+    pub query: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Parse a request line such as `GET /frames?from=10 HTTP/1.1`.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let method = parts.next()?.to_string();
+        let target = parts.next()?;
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query = query
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let (k, v) = p.split_once('=').unwrap_or((p, ""));
+                (form_decode(k), form_decode(v))
+            })
+            .collect();
+        Some(Self {
+            method,
+            path: percent_decode(path),
+            query,
+        })
+    }
+
+    /// The first value of query parameter `name`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    /// Empty for a streamed body, see [`Response::stream`].
+    pub body: Vec<u8>,
+    stream: Option<StreamedBody>,
+}
+
+type BodyWriter = dyn Fn(&mut dyn Write) -> io::Result<()> + Send + Sync;
+
+// Writes a body as it is sent; compared by identity.
+#[derive(Clone)]
+struct StreamedBody(Arc<BodyWriter>);
+
+impl PartialEq for StreamedBody {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for StreamedBody {}
+
+impl fmt::Debug for StreamedBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StreamedBody")
+    }
+}
+
+impl Response {
+    pub fn json(status: u16, body: &impl Serialize) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec(body).expect("response serializes to JSON"),
+            stream: None,
+        }
+    }
+
+    pub fn bytes(content_type: &'static str, body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            body,
+            stream: None,
+        }
+    }
+
+    /// A body `write` produces while it is sent, in chunks, so it need not
+    /// fit in memory. An error from `write` cuts the response off.
+    pub fn stream(
+        content_type: &'static str,
+        write: impl Fn(&mut dyn Write) -> io::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            body: Vec::new(),
+            stream: Some(StreamedBody(Arc::new(write))),
+        }
+    }
+
+    pub fn is_streamed(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// A JSON `{"error": message}` body.
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        #[derive(Serialize)]
+        struct Error {
+            error: String,
+        }
+        Self::json(
+            status,
+            &Error {
+                error: message.into(),
+            },
+        )
+    }
+
+    pub fn not_found() -> Self {
+        Self::error(404, "not found")
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            500 => "Internal Server Error",
+            503 => "Service Unavailable",
+            _ => "",
+        }
+    }
+}
+
+/// Connections [`serve`] answers at once; further clients wait in the
+/// listen backlog until a worker is free.
+pub const WORKERS: usize = 8;
+
+/// Answer requests on `listener` until it fails, on [`WORKERS`] threads,
+/// so a slow client does not hold up the others and a flood of clients
+/// does not spawn a thread each. Clients that stall for [`IO_TIMEOUT`] are
+/// dropped, and so are errors on a single connection, with the connection.
+pub fn serve(
+    listener: TcpListener,
+    handler: impl Fn(&Request) -> Response + Send + Sync + 'static,
+) {
+    let handler = Arc::new(handler);
+    for _ in 1..WORKERS {
+        let Ok(listener) = listener.try_clone() else {
+            break;
+        };
+        let handler = Arc::clone(&handler);
+        std::thread::spawn(move || accept(&listener, &*handler));
+    }
+    accept(&listener, &*handler);
+}
+
+// one worker of `serve`, answering a connection at a time
+fn accept(listener: &TcpListener, handler: &impl Fn(&Request) -> Response) {
+    for stream in listener.incoming().flatten() {
+        let timeouts = stream
+            .set_read_timeout(Some(IO_TIMEOUT))
+            .and_then(|()| stream.set_write_timeout(Some(IO_TIMEOUT)));
+        if timeouts.is_ok() {
+            let _ = handle_connection(stream, handler);
+        }
+    }
+}
+
+/// Read one request from `stream`, answer it and close the connection.
+/// A `HEAD` request gets the headers of the `GET` response, its
+/// `Content-Length` included.
+pub fn handle_connection(
+    stream: TcpStream,
+    handler: &impl Fn(&Request) -> Response,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut head_lines = (&mut reader).take(MAX_HEAD);
+    let mut line = String::new();
+    head_lines.read_line(&mut line)?;
+    // no endpoint takes a body
+    skip_headers(&mut head_lines)?;
+
+    let mut head = false;
+    let response = match Request::parse(&line) {
+        Some(request) if request.method == "GET" || request.method == "HEAD" => {
+            head = request.method == "HEAD";
+            handler(&request)
+        }
+        Some(_) => Response::error(405, "only GET and HEAD are supported"),
+        None => Response::error(400, "malformed request line"),
+    };
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+    )?;
+    match &response.stream {
+        None => {
+            write!(
+                stream,
+                "Content-Length: {}\r\nConnection: close\r\n\r\n",
+                response.body.len()
+            )?;
+            if !head {
+                stream.write_all(&response.body)?;
+            }
+        }
+        Some(StreamedBody(write)) => {
+            write!(
+                stream,
+                "Transfer-Encoding: chunked\r\nConnection: close\r\n\r\n"
+            )?;
+            if !head {
+                let mut chunked = Chunked::new(&mut stream);
+                write(&mut chunked)?;
+                chunked.finish()?;
+            }
+        }
+    }
+    stream.flush()
+}
+
+// Read up to and including the blank line that ends the headers.
+fn skip_headers(reader: &mut impl BufRead) -> io::Result<()> {
+    let mut header = String::new();
+    loop {
+        header.clear();
+        if reader.read_line(&mut header)? == 0 || header == "\r\n" || header == "\n" {
+            return Ok(());
+        }
+    }
+}
+
+// Chunked transfer encoding, buffering up to `CHUNK` bytes per chunk.
+struct Chunked<W: Write> {
+    inner: W,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> Chunked<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buf: Vec::with_capacity(CHUNK),
+        }
+    }
+
+    fn send(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            write!(self.inner, "{:x}\r\n", self.buf.len())?;
+            self.inner.write_all(&self.buf)?;
+            self.inner.write_all(b"\r\n")?;
+            self.buf.clear();
+        }
+        Ok(())
+    }
+
+    // the last chunk, which ends the body
+    fn finish(mut self) -> io::Result<()> {
+        self.send()?;
+        self.inner.write_all(b"0\r\n\r\n")
+    }
+}
+
+impl<W: Write> Write for Chunked<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(CHUNK - self.buf.len());
+        self.buf.extend_from_slice(&buf[..n]);
+        if self.buf.len() == CHUNK {
+            self.send()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()?;
+        self.inner.flush()
+    }
+}
+
+fn form_decode(s: &str) -> String {
+    percent_decode(&s.replace('+', " "))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn exchange(request: &str, response: Response) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(request.as_bytes()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, &|_| response.clone()).unwrap();
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+        raw
+    }
+
+    #[test]
+    fn streams_in_chunks() {
+        let response = Response::stream("text/plain", |out| out.write_all(b"hello"));
+        let raw = exchange("GET / HTTP/1.1\r\n\r\n", response);
+        assert!(raw.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(raw.ends_with("\r\n\r\n5\r\nhello\r\n0\r\n\r\n"));
+    }
+
+    #[test]
+    fn head_reports_the_get_length() {
+        let response = Response::bytes("text/plain", b"hello".to_vec());
+        let raw = exchange("HEAD / HTTP/1.1\r\n\r\n", response);
+        assert!(raw.contains("Content-Length: 5\r\n"));
+        assert!(raw.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn serves_connections_concurrently() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            serve(listener, |_| Response::bytes("text/plain", b"ok".to_vec()))
+        });
+        // an idle connection does not hold up the next one
+        let _idle = TcpStream::connect(addr).unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+        assert!(raw.ends_with("\r\n\r\nok"));
+    }
+
+    #[test]
+    fn skips_headers_up_to_the_blank_line() {
+        let mut reader = &b"Host: a\r\nx\n\r\n\nbody"[..];
+        skip_headers(&mut reader).unwrap();
+        assert_eq!(reader, b"\nbody");
+        let mut reader = &b"a\nb: c\n\nbody"[..];
+        skip_headers(&mut reader).unwrap();
+        assert_eq!(reader, b"body");
+    }
+
+    #[test]
+    fn chunks_are_bounded() {
+        let mut out = Vec::new();
+        let mut chunked = Chunked::new(&mut out);
+        chunked.write_all(&vec![b'x'; CHUNK + 1]).unwrap();
+        chunked.finish().unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with(&format!("{CHUNK:x}\r\n")));
+        assert!(text.ends_with("\r\n1\r\nx\r\n0\r\n\r\n"));
+    }
+}
@@ -0,0 +1,282 @@
+use super::{Request, Response};
+use crate::captions::event_summary;
+use crate::recording::{LineDecoder, is_footer_line, recording_name, recording_path};
+use crate::report::encode_bmp;
+use crate::{Envelope, RecordingHeaderSerDe, SensorDataSerDe, SensorKind};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::ops::Range;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RecordingInfo {
+    /// File stem, used in URLs.
+    pub name: String,
+    pub bytes: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FrameInfo {
+    /// Position in the recording, counting every frame.
+    pub index: usize,
+    pub kind: SensorKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+impl FrameInfo {
+    fn new(index: usize, frame: &SensorDataSerDe) -> Self {
+        let (width, height) = match frame {
+            SensorDataSerDe::Image(image) => (Some(image.width), Some(image.height)),
+            _ => (None, None),
+        };
+        Self {
+            index,
+            kind: frame.kind(),
+            width,
+            height,
+            summary: event_summary(frame),
+        }
+    }
+}
+
+/// Read-only HTTP access to the JSON-lines recordings (`*.jsonl`, one
+/// [`SensorDataSerDe`] per line) in a directory:
+///
+/// - `GET /recordings` lists them as [`RecordingInfo`].
+/// - `GET /recordings/<name>/header` returns its [`RecordingHeaderSerDe`].
+/// - `GET /recordings/<name>/frames` lists [`FrameInfo`]s.
+/// - `GET /recordings/<name>/frames/<index>` returns one frame as JSON, or
+///   as a BMP with `format=bmp` for camera frames.
+/// - `GET /recordings/<name>/stream` streams the frames as JSON lines,
+///   each wrapped in an [`Envelope`] with `envelope=true`.
+///
+/// The listing and the stream take `sensor=<kind>` (e.g. `Lidar`) and a
+/// `from`/`to` index range, `to` exclusive. Files are re-read on every
+/// request, so recordings still being written show up as they grow.
+#[derive(Clone, Debug)]
+pub struct RecordingServer {
+    root: PathBuf,
+}
+
+impl RecordingServer {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn recordings(&self) -> io::Result<Vec<RecordingInfo>> {
+        let mut recordings = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let path = entry?.path();
+            if let Some(name) = recording_name(&path) {
+                recordings.push(RecordingInfo {
+                    name: name.into(),
+                    bytes: fs::metadata(&path)?.len(),
+                });
+            }
+        }
+        recordings.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(recordings)
+    }
+
+    /// Frames of recording `name` with an index in `range` and, if given,
+    /// of kind `sensor`, with their indices.
+    pub fn frames(
+        &self,
+        name: &str,
+        sensor: Option<SensorKind>,
+        range: Range<usize>,
+    ) -> io::Result<Vec<(usize, SensorDataSerDe)>> {
+        let mut frames = Vec::new();
+        self.for_each_frame(name, sensor, range, |index, frame| {
+            frames.push((index, frame));
+            Ok(())
+        })?;
+        Ok(frames)
+    }
+
+    /// [`frames`](Self::frames) one at a time, without holding them all.
+    pub fn for_each_frame(
+        &self,
+        name: &str,
+        sensor: Option<SensorKind>,
+        range: Range<usize>,
+        mut f: impl FnMut(usize, SensorDataSerDe) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let path = self.path(name)?;
+        let mut decoder = LineDecoder::default();
+        let mut index = 0;
+        for line in BufReader::new(File::open(path)?).lines() {
+            if index >= range.end {
+                break;
+            }
+            let line = line?;
+            if line.trim().is_empty()
+                || decoder.encoding_line(&line)
+                || decoder.header(&line).is_some()
+                || decoder.gap(&line).is_some()
+                || is_footer_line(&line)
+            {
+                continue;
+            }
+            index += 1;
+            if index <= range.start {
+                continue;
+            }
+            let frame = decoder.frame(&line)?;
+            if sensor.is_none_or(|kind| frame.kind() == kind) {
+                f(index - 1, frame)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Header of recording `name`, `None` if it has none.
+    pub fn header(&self, name: &str) -> io::Result<Option<RecordingHeaderSerDe>> {
+        let path = self.path(name)?;
+        let mut decoder = LineDecoder::default();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if !line.trim().is_empty() && !decoder.encoding_line(&line) {
+                return Ok(decoder.header(&line));
+            }
+        }
+        Ok(None)
+    }
+
+    fn path(&self, name: &str) -> io::Result<PathBuf> {
+        recording_path(&self.root, name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such recording"))
+    }
+
+    /// Answers the endpoints above; `None` for paths outside
+    /// `/recordings`, so it can be chained with other handlers.
+    pub fn handle(&self, request: &Request) -> Option<Response> {
+        let rest = request.path.strip_prefix("/recordings")?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+        let segments: Vec<&str> = rest.split('/').filter(|s| !s.is_empty()).collect();
+        Some(self.route(request, &segments).unwrap_or_else(|e| e))
+    }
+
+    fn route(&self, request: &Request, segments: &[&str]) -> Result<Response, Response> {
+        match segments {
+            [] => Ok(Response::json(200, &self.recordings().map_err(io_error)?)),
+            [name, "header"] => match self.header(name).map_err(io_error)? {
+                Some(header) => Ok(Response::json(200, &header)),
+                None => Err(Response::error(404, "recording has no header")),
+            },
+            [name, "frames"] => {
+                let (sensor, range) = filter(request)?;
+                let frames = self.frames(name, sensor, range).map_err(io_error)?;
+                let infos: Vec<_> = frames.iter().map(|(i, f)| FrameInfo::new(*i, f)).collect();
+                Ok(Response::json(200, &infos))
+            }
+            [name, "frames", index] => {
+                let invalid = || Response::error(400, "invalid frame index");
+                let index: usize = index.parse().map_err(|_| invalid())?;
+                let end = index.checked_add(1).ok_or_else(invalid)?;
+                let (_, frame) = self
+                    .frames(name, None, index..end)
+                    .map_err(io_error)?
+                    .pop()
+                    .ok_or_else(Response::not_found)?;
+                match (request.param("format"), &frame) {
+                    (None | Some("json"), _) => Ok(Response::json(200, &frame)),
+                    (Some("bmp"), SensorDataSerDe::Image(image)) => {
+                        Ok(Response::bytes("image/bmp", encode_bmp(&image.array)))
+                    }
+                    (Some("bmp"), _) => Err(Response::error(400, "not a camera frame")),
+                    (Some(_), _) => Err(Response::error(400, "format must be json or bmp")),
+                }
+            }
+            [name, "stream"] => {
+                let (sensor, range) = filter(request)?;
+                let envelope = match request.param("envelope") {
+                    None | Some("false") => false,
+                    Some("true") => true,
+                    Some(_) => return Err(Response::error(400, "envelope must be true or false")),
+                };
+                // fail with a status while one can still be sent
+                File::open(self.path(name).map_err(io_error)?).map_err(io_error)?;
+                let (server, name) = (self.clone(), name.to_string());
+                Ok(Response::stream("application/x-ndjson", move |out| {
+                    server.for_each_frame(&name, sensor, range.clone(), |_, frame| {
+                        if envelope {
+                            serde_json::to_writer(&mut *out, &Envelope::new(frame))?;
+                        } else {
+                            serde_json::to_writer(&mut *out, &frame)?;
+                        }
+                        out.write_all(b"\n")
+                    })
+                }))
+            }
+            _ => Err(Response::not_found()),
+        }
+    }
+}
+
+fn filter(request: &Request) -> Result<(Option<SensorKind>, Range<usize>), Response> {
+    let sensor = request
+        .param("sensor")
+        .map(|s| {
+            serde_json::from_value(serde_json::Value::String(s.into()))
+                .map_err(|_| Response::error(400, format!("unknown sensor kind `{s}`")))
+        })
+        .transpose()?;
+    let index = |name: &str, default: usize| {
+        request.param(name).map_or(Ok(default), |v| {
+            v.parse()
+                .map_err(|_| Response::error(400, format!("invalid `{name}`")))
+        })
+    };
+    Ok((sensor, index("from", 0)?..index("to", usize::MAX)?))
+}
+
+fn io_error(e: io::Error) -> Response {
+    match e.kind() {
+        io::ErrorKind::NotFound => Response::error(404, e.to_string()),
+        io::ErrorKind::InvalidData => Response::error(500, format!("corrupt recording: {e}")),
+        _ => Response::error(500, e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(name: &str) -> RecordingServer {
+        let root = std::env::temp_dir().join(format!("recording-server-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join(format!("{name}.jsonl")), "").unwrap();
+        RecordingServer::new(root)
+    }
+
+    fn get(server: &RecordingServer, target: &str) -> Response {
+        let request = Request::parse(&format!("GET {target} HTTP/1.1")).unwrap();
+        server.handle(&request).unwrap()
+    }
+
+    #[test]
+    fn rejects_the_last_frame_index() {
+        let server = server("overflow");
+        let response = get(
+            &server,
+            &format!("/recordings/overflow/frames/{}", usize::MAX),
+        );
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn streams_existing_recordings_only() {
+        let server = server("streamed");
+        assert!(get(&server, "/recordings/streamed/stream").is_streamed());
+        assert_eq!(get(&server, "/recordings/missing/stream").status, 404);
+    }
+}
@@ -0,0 +1,344 @@
+//! Headless replay of recorded frames, for CI and downstream tests.
+//!
+//! Recordings are JSON lines, one [`SensorDataSerDe`] per line. The
+//! [`ReplayHarness`] decodes them, runs every frame through the configured
+//! transforms and hands it to each sink, all on the calling thread and in
+//! recorded order, so a run is fully deterministic and needs no simulator.
+//!
+//! [`assert_round_trip`] checks that a serde type survives every encoding
+//! the crate writes, see [`Format::ALL`].
+
+use crate::SensorDataSerDe;
+use crate::compact::compact;
+use crate::float_bits::{self, FloatEncoding};
+use crate::naming::FieldNaming;
+use crate::pipeline::{FrameTransform, SharedFrame, Sink};
+use crate::recording::{LineDecoder, is_footer_line};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+/// Decode a JSON-lines recording. Blank lines, the header, gap records and
+/// footers are skipped; bit-exact recordings are decoded by the encoding
+/// they name.
+pub fn read_jsonl(reader: impl BufRead) -> serde_json::Result<Vec<SensorDataSerDe>> {
+    let mut frames = Vec::new();
+    let mut decoder = LineDecoder::default();
+    for line in reader.lines() {
+        let line = line.map_err(serde_json::Error::io)?;
+        if line.trim().is_empty()
+            || decoder.encoding_line(&line)
+            || (frames.is_empty() && decoder.header(&line).is_some())
+            || decoder.gap(&line).is_some()
+            || is_footer_line(&line)
+        {
+            continue;
+        }
+        frames.push(decoder.frame(&line)?);
+    }
+    Ok(frames)
+}
+
+/// Encode frames as a JSON-lines recording, e.g. to create new fixtures.
+pub fn write_jsonl<'a>(
+    mut writer: impl Write,
+    frames: impl IntoIterator<Item = &'a SensorDataSerDe>,
+) -> serde_json::Result<()> {
+    for frame in frames {
+        serde_json::to_writer(&mut writer, frame)?;
+        writer.write_all(b"\n").map_err(serde_json::Error::io)?;
+    }
+    Ok(())
+}
+
+/// Sink that keeps everything it receives, for assertions.
+#[derive(Debug, Default)]
+pub struct CollectingSink {
+    pub frames: Vec<SharedFrame>,
+    pub flushed: bool,
+}
+
+impl Sink for CollectingSink {
+    fn consume(&mut self, frame: SharedFrame) {
+        self.frames.push(frame);
+    }
+
+    fn flush(&mut self) {
+        self.flushed = true;
+    }
+}
+
+/// Drives decode → transform → sinks over an in-memory recording.
+#[derive(Default)]
+pub struct ReplayHarness {
+    frames: Vec<SharedFrame>,
+    transforms: Vec<Box<dyn FrameTransform>>,
+}
+
+impl ReplayHarness {
+    pub fn from_frames(frames: impl IntoIterator<Item = SensorDataSerDe>) -> Self {
+        Self {
+            frames: frames.into_iter().map(Arc::new).collect(),
+            transforms: Vec::new(),
+        }
+    }
+
+    pub fn from_jsonl(reader: impl BufRead) -> serde_json::Result<Self> {
+        read_jsonl(reader).map(Self::from_frames)
+    }
+
+    /// Append a transform; transforms run in the order they were added.
+    pub fn with_transform(mut self, transform: impl FrameTransform + 'static) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    pub fn frames(&self) -> &[SharedFrame] {
+        &self.frames
+    }
+
+    /// Replay every frame into every sink, then flush the sinks.
+    ///
+    /// The recorded frames are left untouched (transforms copy on write), so
+    /// the harness can be run again with the same result as long as the
+    /// transforms themselves are deterministic.
+    pub fn run(&mut self, sinks: &mut [&mut dyn Sink]) -> usize {
+        for frame in &self.frames {
+            let mut frame = Arc::clone(frame);
+            for transform in &mut self.transforms {
+                transform.apply_shared(&mut frame);
+            }
+            for sink in sinks.iter_mut() {
+                sink.consume(Arc::clone(&frame));
+            }
+        }
+        for sink in sinks.iter_mut() {
+            sink.flush();
+        }
+        self.frames.len()
+    }
+}
+
+/// Encodings [`check_round_trip`] exercises.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Compact JSON text, as in recordings.
+    Json,
+    /// Pretty-printed JSON text, as in configs and reports.
+    JsonPretty,
+    /// An in-memory `serde_json::Value`, which takes serde's
+    /// self-describing path without a text parser.
+    JsonValue,
+    /// JSON text without derived count fields, see [`crate::compact`].
+    Compact,
+    /// JSON text with bit-exact floats, read back with
+    /// [`float_bits::deserialize`].
+    BitExact(FloatEncoding),
+    /// JSON text with the field names of a naming profile. Readers expect
+    /// snake_case, so the names are mapped back before decoding.
+    Named(FieldNaming),
+    /// TOML text, as in `.toml` configs; the value must encode as a table.
+    #[cfg(feature = "toml")]
+    Toml,
+    /// YAML text, as in `.yaml` configs.
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl Format {
+    /// Every encoding enabled in this build.
+    pub const ALL: &'static [Format] = &[
+        Format::Json,
+        Format::JsonPretty,
+        Format::JsonValue,
+        Format::Compact,
+        Format::BitExact(FloatEncoding::Bits),
+        Format::BitExact(FloatEncoding::HexFloat),
+        Format::Named(FieldNaming::CamelCase),
+        Format::Named(FieldNaming::ScreamingSnakeCase),
+        #[cfg(feature = "toml")]
+        Format::Toml,
+        #[cfg(feature = "yaml")]
+        Format::Yaml,
+    ];
+
+    fn round_trip<T: Serialize + DeserializeOwned>(
+        self,
+        value: &T,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        Ok(match self {
+            Format::Json => serde_json::from_str(&serde_json::to_string(value)?)?,
+            Format::JsonPretty => serde_json::from_str(&serde_json::to_string_pretty(value)?)?,
+            Format::JsonValue => serde_json::from_value(serde_json::to_value(value)?)?,
+            Format::Compact => serde_json::from_str(&serde_json::to_string(&compact(value))?)?,
+            Format::BitExact(encoding) => {
+                let text = serde_json::to_string(&encoding.wrap(value))?;
+                float_bits::deserialize(&mut serde_json::Deserializer::from_str(&text))?
+            }
+            Format::Named(naming) => {
+                let mut names = HashMap::new();
+                rename_keys(&serde_json::to_value(value)?, naming, &mut names);
+                let named = serde_json::to_value(naming.wrap(value))?;
+                serde_json::from_value(restore_keys(named, &names))?
+            }
+            #[cfg(feature = "toml")]
+            Format::Toml => toml::from_str(&toml::to_string(value)?)?,
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::from_str(&serde_yaml::to_string(value)?)?,
+        })
+    }
+}
+
+// the snake_case key of every key of `value` as `naming` writes it
+fn rename_keys(value: &Value, naming: FieldNaming, names: &mut HashMap<String, String>) {
+    match value {
+        Value::Array(values) => values.iter().for_each(|v| rename_keys(v, naming, names)),
+        Value::Object(map) => {
+            for (key, v) in map {
+                names.insert(naming.rename(key), key.clone());
+                rename_keys(v, naming, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn restore_keys(value: Value, names: &HashMap<String, String>) -> Value {
+    match value {
+        Value::Array(values) => values.into_iter().map(|v| restore_keys(v, names)).collect(),
+        Value::Object(map) => map
+            .into_iter()
+            .map(|(key, v)| {
+                let key = names.get(&key).cloned().unwrap_or(key);
+                (key, restore_keys(v, names))
+            })
+            .collect(),
+        value => value,
+    }
+}
+
+/// Why a value did not survive [`check_round_trip`].
+#[derive(Debug)]
+pub struct RoundTripError {
+    pub format: Format,
+    /// Dotted path of the first field that differs, empty if the value
+    /// failed to encode or decode.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for RoundTripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} at `{}`: {}", self.format, self.path, self.message)
+    }
+}
+
+impl std::error::Error for RoundTripError {}
+
+/// Encode `value` in every [`Format`], decode it again and compare the
+/// result to the original, numbers within relative tolerance `tolerance`.
+///
+/// Values are compared through their serialized form, so `T` needs no
+/// `PartialEq`; this is meant for custom payload types, e.g. annotations
+/// or new `*SerDe` wrappers, to check their serde impls.
+pub fn check_round_trip<T: Serialize + DeserializeOwned>(
+    value: &T,
+    tolerance: f64,
+) -> Result<(), RoundTripError> {
+    let error = |format, path: String, message: String| RoundTripError {
+        format,
+        path,
+        message,
+    };
+    let expected = serde_json::to_value(value)
+        .map_err(|e| error(Format::JsonValue, String::new(), e.to_string()))?;
+    for &format in Format::ALL {
+        let decoded = format
+            .round_trip(value)
+            .map_err(|e| error(format, String::new(), e.to_string()))?;
+        let actual = serde_json::to_value(&decoded)
+            .map_err(|e| error(format, String::new(), e.to_string()))?;
+        if let Some((path, message)) = first_difference("", &expected, &actual, tolerance) {
+            return Err(error(format, path, message));
+        }
+    }
+    Ok(())
+}
+
+/// [`check_round_trip`], panicking with the first difference.
+#[track_caller]
+pub fn assert_round_trip<T: Serialize + DeserializeOwned>(value: &T, tolerance: f64) {
+    if let Err(e) = check_round_trip(value, tolerance) {
+        panic!("round trip failed: {e}");
+    }
+}
+
+fn first_difference(
+    path: &str,
+    expected: &Value,
+    actual: &Value,
+    tolerance: f64,
+) -> Option<(String, String)> {
+    let child = |key: &dyn fmt::Display| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match (expected, actual) {
+        (Value::Number(e), Value::Number(a)) => {
+            let (e, a) = (e.as_f64()?, a.as_f64()?);
+            let ok = (e - a).abs() <= tolerance * e.abs().max(a.abs()) || e == a;
+            (!ok).then(|| (path.into(), format!("{e} became {a}")))
+        }
+        (Value::Array(e), Value::Array(a)) if e.len() == a.len() => e
+            .iter()
+            .zip(a)
+            .enumerate()
+            .find_map(|(i, (e, a))| first_difference(&child(&i), e, a, tolerance)),
+        (Value::Object(e), Value::Object(a)) if e.len() == a.len() => {
+            e.iter().find_map(|(key, e)| match a.get(key) {
+                Some(a) => first_difference(&child(key), e, a, tolerance),
+                None => Some((child(key), "field lost".into())),
+            })
+        }
+        (e, a) if e == a => None,
+        (e, a) => Some((path.into(), format!("{e} became {a}"))),
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixtures_survive_every_format() {
+        for frame in crate::fixtures::all() {
+            assert_round_trip(&frame, 1e-6);
+        }
+    }
+
+    #[test]
+    fn names_the_format_and_field_that_differ() {
+        #[derive(Serialize, serde::Deserialize)]
+        struct Lossy {
+            fov_angle: f32,
+            #[serde(skip_deserializing)]
+            lost: u8,
+        }
+        let e = check_round_trip(
+            &Lossy {
+                fov_angle: 90.0,
+                lost: 1,
+            },
+            1e-6,
+        )
+        .unwrap_err();
+        assert_eq!((e.format, e.path.as_str()), (Format::Json, "lost"));
+    }
+}
@@ -0,0 +1,676 @@
+//! Streaming encoded frames over datagram links.
+//!
+//! [`Fragmenter`] splits an encoded frame into packets no larger than the
+//! link's MTU, each with a small header (message sequence number, fragment
+//! index and count), and [`Reassembler`] puts them back together on the
+//! receiving end, giving up on messages whose fragments stop arriving.
+//! [`ShapedSink`] does both halves of sending: it encodes frames, fragments
+//! them and paces the packets with a [`TokenBucket`] per sensor kind, so a
+//! burst of camera frames cannot starve the link.
+//!
+//! Every transport sends a frame as JSON wrapped in an [`Envelope`], see
+//! [`encode_frame`], so receivers know its type and schema version.
+//! With the `udp` feature, [`UdpJsonSink::udp`] streams JSON frames to a
+//! unicast address or multicast group and [`UdpReceiver`] turns the packets
+//! back into frames. With the `quic` feature, [`QuicSink`] and
+//! [`QuicReceiver`] do the same over QUIC, reliably and with one
+//! prioritized stream per sensor kind. With the `shm` feature on Linux,
+//! [`ShmSink`] writes frames into a shared-memory ring that [`ShmReader`]s
+//! in other processes on the same host read without going through a socket.
+//! With the `iceoryx2` feature, [`IceoryxSink`] publishes frames as iceoryx2
+//! samples, one service per sensor kind, so any iceoryx2 subscriber on the
+//! machine receives them zero-copy; [`IceoryxReceiver`] is one.
+
+#[cfg(feature = "iceoryx2")]
+mod iceoryx;
+#[cfg(any(
+    feature = "udp",
+    feature = "quic",
+    feature = "shm",
+    feature = "iceoryx2"
+))]
+mod json;
+#[cfg(feature = "quic")]
+mod quic;
+#[cfg(all(feature = "shm", target_os = "linux"))]
+mod shm;
+#[cfg(feature = "udp")]
+mod udp;
+
+#[cfg(feature = "iceoryx2")]
+pub use iceoryx::*;
+#[cfg(any(
+    feature = "udp",
+    feature = "quic",
+    feature = "shm",
+    feature = "iceoryx2"
+))]
+pub use json::*;
+#[cfg(feature = "quic")]
+pub use quic::*;
+#[cfg(all(feature = "shm", target_os = "linux"))]
+pub use shm::*;
+#[cfg(feature = "udp")]
+pub use udp::*;
+
+use crate::pipeline::{SharedFrame, Sink};
+use crate::{ProcessingStepSerDe, SensorDataSerDe, SensorKind};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+/// Bytes in front of every fragment: sequence (u32), index (u16) and count
+/// (u16), little-endian.
+pub const FRAGMENT_HEADER: usize = 8;
+
+/// The header of one packet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FragmentHeader {
+    pub sequence: u32,
+    pub index: u16,
+    pub count: u16,
+}
+
+impl FragmentHeader {
+    pub fn parse(packet: &[u8]) -> Result<(Self, &[u8]), FragmentError> {
+        if packet.len() < FRAGMENT_HEADER {
+            return Err(FragmentError::Short { len: packet.len() });
+        }
+        let header = Self {
+            sequence: u32::from_le_bytes(packet[0..4].try_into().expect("4 bytes")),
+            index: u16::from_le_bytes([packet[4], packet[5]]),
+            count: u16::from_le_bytes([packet[6], packet[7]]),
+        };
+        if header.index >= header.count {
+            return Err(FragmentError::Index(header));
+        }
+        Ok((header, &packet[FRAGMENT_HEADER..]))
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend(self.sequence.to_le_bytes());
+        out.extend(self.index.to_le_bytes());
+        out.extend(self.count.to_le_bytes());
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FragmentError {
+    /// A packet shorter than [`FRAGMENT_HEADER`].
+    Short { len: usize },
+    /// A fragment index not below its count.
+    Index(FragmentHeader),
+    /// A fragment count that differs from earlier fragments of the message.
+    Count(FragmentHeader),
+    /// A message that needs more than `u16::MAX` fragments.
+    TooLarge { len: usize },
+    /// A fragment whose length differs from earlier fragments of the
+    /// message; all but the last have the same.
+    Length(FragmentHeader),
+    /// A message larger than the receiver's limit.
+    Limit { header: FragmentHeader, max: usize },
+}
+
+impl fmt::Display for FragmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Short { len } => write!(f, "{len}-byte packet is shorter than its header"),
+            Self::Index(h) => write!(
+                f,
+                "fragment {} of {} in message {}",
+                h.index, h.count, h.sequence
+            ),
+            Self::Count(h) => write!(
+                f,
+                "message {} fragment {} claims {} fragments, earlier ones differ",
+                h.sequence, h.index, h.count
+            ),
+            Self::TooLarge { len } => write!(f, "{len}-byte message needs too many fragments"),
+            Self::Length(h) => write!(
+                f,
+                "message {} fragment {} differs in length from earlier ones",
+                h.sequence, h.index
+            ),
+            Self::Limit { header, max } => write!(
+                f,
+                "message {} of {} fragments exceeds the {max}-byte limit",
+                header.sequence, header.count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FragmentError {}
+
+/// Splits messages into packets of at most `mtu` bytes.
+#[derive(Clone, Debug)]
+pub struct Fragmenter {
+    mtu: usize,
+    sequence: u32,
+}
+
+impl Fragmenter {
+    /// `mtu` is the largest datagram the link carries, e.g. 1472 for UDP
+    /// over Ethernet. Panics if it leaves no room after the header.
+    pub fn new(mtu: usize) -> Self {
+        assert!(mtu > FRAGMENT_HEADER, "MTU of {mtu} leaves no payload");
+        Self { mtu, sequence: 0 }
+    }
+
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    /// The packets of the next message. An empty message still gets one
+    /// packet, so the receiver sees it.
+    pub fn split(&mut self, message: &[u8]) -> Result<Vec<Vec<u8>>, FragmentError> {
+        let chunk = self.mtu - FRAGMENT_HEADER;
+        let count = message.len().div_ceil(chunk).max(1);
+        let count =
+            u16::try_from(count).map_err(|_| FragmentError::TooLarge { len: message.len() })?;
+        let sequence = self.sequence;
+        self.sequence = self.sequence.wrapping_add(1);
+
+        let mut chunks = message.chunks(chunk);
+        Ok((0..count)
+            .map(|index| {
+                let body = chunks.next().unwrap_or_default();
+                let mut packet = Vec::with_capacity(FRAGMENT_HEADER + body.len());
+                FragmentHeader {
+                    sequence,
+                    index,
+                    count,
+                }
+                .write(&mut packet);
+                packet.extend_from_slice(body);
+                packet
+            })
+            .collect())
+    }
+}
+
+/// Largest message a [`Reassembler`] accepts by default, 256 MiB.
+pub const DEFAULT_MAX_MESSAGE: usize = 256 << 20;
+
+// how far behind the oldest open message a new sequence still counts as
+// late rather than as a restarted sender
+const LATE_HORIZON: u32 = 1 << 10;
+
+#[derive(Debug)]
+struct Partial {
+    sequence: u32,
+    fragments: Vec<Option<Vec<u8>>>,
+    missing: usize,
+    // body length of all fragments but the last, once one arrived
+    chunk: Option<usize>,
+}
+
+/// Collects fragments into messages.
+///
+/// Fragments may arrive in any order and more than once. At most `window`
+/// messages are kept open; when another one starts, the oldest open message
+/// is abandoned and counted in [`Reassembler::lost`]. The sequences of the
+/// last few delivered or abandoned messages are remembered, so their
+/// duplicate and late fragments are dropped and counted in
+/// [`Reassembler::late`] instead of delivering a message twice or opening
+/// it again. So are fragments of a new message older than every open one
+/// while the window is full.
+///
+/// The fragment count comes from the peer, so a message whose count times
+/// fragment size exceeds [`Reassembler::with_max_message`] fails with
+/// [`FragmentError::Limit`] and is dropped.
+#[derive(Debug)]
+pub struct Reassembler {
+    window: usize,
+    max_message: usize,
+    open: VecDeque<Partial>,
+    finished: VecDeque<u32>,
+    lost: u64,
+    late: u64,
+}
+
+impl Reassembler {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            max_message: DEFAULT_MAX_MESSAGE,
+            open: VecDeque::new(),
+            finished: VecDeque::new(),
+            lost: 0,
+            late: 0,
+        }
+    }
+
+    /// Reject messages larger than `bytes`, [`DEFAULT_MAX_MESSAGE`] by
+    /// default.
+    pub fn with_max_message(mut self, bytes: usize) -> Self {
+        self.max_message = bytes;
+        self
+    }
+
+    /// Messages abandoned with fragments missing.
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+
+    /// Fragments dropped because their message was already delivered or
+    /// abandoned, or started too late.
+    pub fn late(&self) -> u64 {
+        self.late
+    }
+
+    /// Messages with some but not all fragments received.
+    pub fn open(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Feed one packet; returns the message it completes, if any.
+    pub fn push(&mut self, packet: &[u8]) -> Result<Option<Vec<u8>>, FragmentError> {
+        let (header, body) = FragmentHeader::parse(packet)?;
+        if self.finished.contains(&header.sequence) {
+            self.late += 1;
+            return Ok(None);
+        }
+        let at = match self.open.iter().position(|p| p.sequence == header.sequence) {
+            Some(at) => at,
+            None => {
+                if header.count as usize > self.max_message.max(1) {
+                    return Err(FragmentError::Limit {
+                        header,
+                        max: self.max_message,
+                    });
+                }
+                if self.open.len() == self.window {
+                    if self
+                        .open
+                        .iter()
+                        .all(|p| is_late(header.sequence, p.sequence))
+                    {
+                        self.late += 1;
+                        return Ok(None);
+                    }
+                    let abandoned = self.open.pop_front().expect("window is not empty");
+                    self.finish(abandoned.sequence);
+                    self.lost += 1;
+                }
+                self.open.push_back(Partial {
+                    sequence: header.sequence,
+                    fragments: vec![None; header.count as usize],
+                    missing: header.count as usize,
+                    chunk: None,
+                });
+                self.open.len() - 1
+            }
+        };
+
+        let partial = &mut self.open[at];
+        if partial.fragments.len() != header.count as usize {
+            return Err(FragmentError::Count(header));
+        }
+        let last = header.index + 1 == header.count;
+        let chunk = match partial.chunk {
+            Some(chunk) if body.len() != chunk && !(last && body.len() < chunk) => {
+                return Err(FragmentError::Length(header));
+            }
+            Some(chunk) => Some(chunk),
+            None if last => None,
+            None => Some(body.len()),
+        };
+        // the last fragment is at most a chunk long
+        let size = chunk.map_or(body.len(), |chunk| {
+            chunk.saturating_mul(header.count as usize)
+        });
+        if size > self.max_message {
+            let partial = self.open.remove(at).expect("index in bounds");
+            self.finish(partial.sequence);
+            return Err(FragmentError::Limit {
+                header,
+                max: self.max_message,
+            });
+        }
+        partial.chunk = chunk;
+        let slot = &mut partial.fragments[header.index as usize];
+        if slot.is_none() {
+            *slot = Some(body.to_vec());
+            partial.missing -= 1;
+        }
+        if partial.missing > 0 {
+            return Ok(None);
+        }
+        let partial = self.open.remove(at).expect("index in bounds");
+        self.finish(partial.sequence);
+        Ok(Some(
+            partial.fragments.into_iter().flatten().flatten().collect(),
+        ))
+    }
+
+    // remember a delivered or abandoned message, a few windows' worth
+    fn finish(&mut self, sequence: u32) {
+        if self.finished.len() == 4 * self.window {
+            self.finished.pop_front();
+        }
+        self.finished.push_back(sequence);
+    }
+}
+
+// whether `sequence` comes shortly before `than`, modulo wrapping
+fn is_late(sequence: u32, than: u32) -> bool {
+    let behind = than.wrapping_sub(sequence);
+    behind != 0 && behind <= LATE_HORIZON
+}
+
+/// A token bucket: `rate` bytes per second, at most `burst` at once.
+#[derive(Clone, Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Starts full. Panics unless `bytes_per_second` is positive.
+    pub fn new(bytes_per_second: f64, burst: usize) -> Self {
+        assert!(
+            bytes_per_second > 0.0,
+            "rate of {bytes_per_second} B/s never refills"
+        );
+        Self {
+            rate: bytes_per_second,
+            burst: burst as f64,
+            tokens: burst as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn burst(&self) -> usize {
+        self.burst as usize
+    }
+
+    /// Take `bytes` tokens at `now`, or return how long to wait until there
+    /// are enough. Sizes above the burst are let through once the bucket is
+    /// full, leaving it in debt, so they are slowed but never stuck.
+    pub fn take_at(&mut self, bytes: usize, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.updated = now;
+        let needed = (bytes as f64).min(self.burst);
+        if self.tokens >= needed {
+            self.tokens -= bytes as f64;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((needed - self.tokens) / self.rate))
+    }
+
+    /// Take `bytes` tokens, sleeping until they are there.
+    pub fn take(&mut self, bytes: usize) {
+        while let Err(wait) = self.take_at(bytes, Instant::now()) {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// Where [`ShapedSink`] sends its packets.
+pub trait PacketTransport {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()>;
+}
+
+/// A connected socket, see [`UdpSocket::connect`].
+impl PacketTransport for UdpSocket {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        UdpSocket::send(self, packet).map(|_| ())
+    }
+}
+
+impl PacketTransport for Vec<Vec<u8>> {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        self.push(packet.to_vec());
+        Ok(())
+    }
+}
+
+/// Counters of a [`ShapedSink`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransportStats {
+    pub messages: u64,
+    pub packets: u64,
+    pub bytes: u64,
+    /// Frames not sent because they were too large or a send failed.
+    pub failed: u64,
+}
+
+/// Encodes frames with `encode`, fragments them and sends the packets,
+/// paced per sensor kind.
+pub struct ShapedSink<T, E> {
+    transport: T,
+    encode: E,
+    fragmenter: Fragmenter,
+    buckets: BTreeMap<SensorKind, TokenBucket>,
+    stats: TransportStats,
+    last_error: Option<io::Error>,
+}
+
+impl<T, E> ShapedSink<T, E>
+where
+    T: PacketTransport,
+    E: FnMut(&SensorDataSerDe) -> Vec<u8>,
+{
+    /// Unshaped until [`ShapedSink::with_rate`] is called.
+    pub fn new(transport: T, mtu: usize, encode: E) -> Self {
+        Self {
+            transport,
+            encode,
+            fragmenter: Fragmenter::new(mtu),
+            buckets: BTreeMap::new(),
+            stats: TransportStats::default(),
+            last_error: None,
+        }
+    }
+
+    /// Limit `kind` to `bytes_per_second`, headers included. Panics unless
+    /// the rate is positive.
+    pub fn with_rate(mut self, kind: SensorKind, bytes_per_second: f64, burst: usize) -> Self {
+        self.buckets
+            .insert(kind, TokenBucket::new(bytes_per_second, burst));
+        self
+    }
+
+    pub fn stats(&self) -> &TransportStats {
+        &self.stats
+    }
+
+    /// The error of the last failed send, cleared by reading it.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.last_error.take()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    pub fn describe(&self) -> ProcessingStepSerDe {
+        let mut step = ProcessingStepSerDe::new("shaped_transport")
+            .with_param("mtu", self.fragmenter.mtu() as i64);
+        for (kind, bucket) in &self.buckets {
+            step = step.with_param(
+                format!("{kind:?}"),
+                format!("{} B/s burst {}", bucket.rate(), bucket.burst()),
+            );
+        }
+        step
+    }
+}
+
+impl<T, E> Sink for ShapedSink<T, E>
+where
+    T: PacketTransport,
+    E: FnMut(&SensorDataSerDe) -> Vec<u8>,
+{
+    fn consume(&mut self, frame: SharedFrame) {
+        let message = (self.encode)(&frame);
+        let packets = match self.fragmenter.split(&message) {
+            Ok(packets) => packets,
+            Err(_) => {
+                self.stats.failed += 1;
+                return;
+            }
+        };
+        let mut bucket = self.buckets.get_mut(&frame.kind());
+        for packet in packets {
+            if let Some(bucket) = bucket.as_mut() {
+                bucket.take(packet.len());
+            }
+            if let Err(e) = self.transport.send(&packet) {
+                self.stats.failed += 1;
+                self.last_error = Some(e);
+                return;
+            }
+            self.stats.packets += 1;
+            self.stats.bytes += packet.len() as u64;
+        }
+        self.stats.messages += 1;
+    }
+}
+
+impl<T, E> fmt::Debug for ShapedSink<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShapedSink")
+            .field("mtu", &self.fragmenter.mtu())
+            .field("buckets", &self.buckets)
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn reassembles_reordered_and_repeated_fragments() {
+        let mut fragmenter = Fragmenter::new(FRAGMENT_HEADER + 4);
+        let packets = fragmenter.split(&message(10)).unwrap();
+        assert_eq!(packets.len(), 3);
+        let mut reassembler = Reassembler::new(4);
+        assert_eq!(reassembler.push(&packets[2]).unwrap(), None);
+        assert_eq!(reassembler.push(&packets[0]).unwrap(), None);
+        assert_eq!(reassembler.push(&packets[0]).unwrap(), None);
+        assert_eq!(reassembler.push(&packets[1]).unwrap(), Some(message(10)));
+        assert_eq!(reassembler.open(), 0);
+    }
+
+    #[test]
+    fn delivers_a_message_once() {
+        let mut fragmenter = Fragmenter::new(FRAGMENT_HEADER + 4);
+        let packets = fragmenter.split(&message(3)).unwrap();
+        let mut reassembler = Reassembler::new(4);
+        assert_eq!(reassembler.push(&packets[0]).unwrap(), Some(message(3)));
+        assert_eq!(reassembler.push(&packets[0]).unwrap(), None);
+        assert_eq!(reassembler.open(), 0);
+        assert_eq!(reassembler.late(), 1);
+    }
+
+    #[test]
+    fn late_fragments_do_not_evict_open_messages() {
+        let mut fragmenter = Fragmenter::new(FRAGMENT_HEADER + 4);
+        let old = fragmenter.split(&message(8)).unwrap();
+        let first = fragmenter.split(&message(8)).unwrap();
+        let second = fragmenter.split(&message(8)).unwrap();
+        let mut reassembler = Reassembler::new(1);
+        reassembler.push(&old[0]).unwrap();
+        // abandons `old`
+        reassembler.push(&first[0]).unwrap();
+        assert_eq!(reassembler.lost(), 1);
+        assert_eq!(reassembler.push(&old[1]).unwrap(), None);
+        assert_eq!(reassembler.push(&first[1]).unwrap(), Some(message(8)));
+
+        reassembler.push(&second[0]).unwrap();
+        let mut unseen = Fragmenter::new(FRAGMENT_HEADER + 4);
+        let older = unseen.split(&message(8)).unwrap();
+        assert_eq!(reassembler.push(&older[0]).unwrap(), None);
+        assert_eq!(reassembler.push(&second[1]).unwrap(), Some(message(8)));
+        assert_eq!(reassembler.lost(), 1);
+        assert_eq!(reassembler.late(), 2);
+    }
+
+    #[test]
+    fn rejects_messages_over_the_limit() {
+        let mut fragmenter = Fragmenter::new(FRAGMENT_HEADER + 4);
+        let packets = fragmenter.split(&message(12)).unwrap();
+        let mut reassembler = Reassembler::new(4).with_max_message(8);
+        assert!(matches!(
+            reassembler.push(&packets[0]),
+            Err(FragmentError::Limit { max: 8, .. })
+        ));
+        assert_eq!(reassembler.open(), 0);
+        assert_eq!(reassembler.push(&packets[1]).unwrap(), None);
+
+        let mut huge = Vec::new();
+        FragmentHeader {
+            sequence: 7,
+            index: 0,
+            count: u16::MAX,
+        }
+        .write(&mut huge);
+        assert!(matches!(
+            reassembler.push(&huge),
+            Err(FragmentError::Limit { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_fragments_of_different_lengths() {
+        let mut fragmenter = Fragmenter::new(FRAGMENT_HEADER + 4);
+        let packets = fragmenter.split(&message(12)).unwrap();
+        let mut reassembler = Reassembler::new(4);
+        reassembler.push(&packets[0]).unwrap();
+        let mut short = packets[1].clone();
+        short.pop();
+        assert!(matches!(
+            reassembler.push(&short),
+            Err(FragmentError::Length(_))
+        ));
+    }
+
+    #[cfg(feature = "fixtures")]
+    #[test]
+    fn frames_travel_in_envelopes() {
+        for frame in crate::fixtures::all() {
+            let message = encode_frame(&frame);
+            let envelope: serde_json::Value = serde_json::from_slice(&message).unwrap();
+            assert_eq!(envelope["type"], "SensorData");
+            assert_eq!(decode_frame(&message).unwrap().kind(), frame.kind());
+        }
+        let bare = serde_json::to_vec(&crate::fixtures::all()[0]).unwrap();
+        assert_eq!(
+            decode_frame(&bare).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn bucket_waits_for_tokens() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(100.0, 10);
+        bucket.updated = now;
+        assert_eq!(bucket.take_at(10, now), Ok(()));
+        assert_eq!(bucket.take_at(5, now), Err(Duration::from_millis(50)));
+        assert_eq!(bucket.take_at(5, now + Duration::from_millis(50)), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "never refills")]
+    fn bucket_rejects_zero_rate() {
+        TokenBucket::new(0.0, 10);
+    }
+}
@@ -0,0 +1,232 @@
+use super::{decode_frame, write_frame};
+use crate::pipeline::{SharedFrame, Sink};
+use crate::{SensorDataSerDe, SensorKind};
+use iceoryx2::port::publisher::Publisher;
+use iceoryx2::port::subscriber::Subscriber;
+use iceoryx2::prelude::*;
+use iceoryx2::service::port_factory::publish_subscribe::PortFactory;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+// how long a blocking receive sleeps between polls
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// frames travel as byte slices without a user header
+type FrameService = PortFactory<ipc::Service, [u8], ()>;
+type FramePublisher = Publisher<ipc::Service, [u8], ()>;
+type FrameSubscriber = Subscriber<ipc::Service, [u8], ()>;
+
+/// Name of the iceoryx2 service carrying `kind` under `prefix`, e.g.
+/// `carla/ego/Lidar`.
+pub fn iceoryx_service_name(prefix: &str, kind: SensorKind) -> String {
+    format!("{prefix}/{kind:?}")
+}
+
+fn open_service(
+    node: &Node<ipc::Service>,
+    prefix: &str,
+    kind: SensorKind,
+) -> io::Result<FrameService> {
+    let name = ServiceName::new(&iceoryx_service_name(prefix, kind)).map_err(io::Error::other)?;
+    node.service_builder(&name)
+        .publish_subscribe::<[u8]>()
+        .open_or_create()
+        .map_err(io::Error::other)
+}
+
+/// Publishes enveloped JSON frames as iceoryx2 samples, one publish-subscribe service
+/// of `[u8]` per sensor kind, named by [`iceoryx_service_name`].
+///
+/// Each frame is encoded and copied once into a loaned shared-memory
+/// sample, which subscribers in other processes (Rust, C or C++) read in
+/// place. Services
+/// are opened on the first frame of their kind; a subscriber that falls
+/// behind loses the oldest samples, publishing never blocks.
+pub struct IceoryxSink {
+    node: Node<ipc::Service>,
+    prefix: String,
+    initial_len: usize,
+    publishers: BTreeMap<SensorKind, FramePublisher>,
+    buffer: Vec<u8>,
+    sent: u64,
+    last_error: Option<io::Error>,
+}
+
+impl IceoryxSink {
+    /// Publish under `prefix`, e.g. `carla/ego`.
+    pub fn new(prefix: impl Into<String>) -> io::Result<Self> {
+        let node = NodeBuilder::new()
+            .create::<ipc::Service>()
+            .map_err(io::Error::other)?;
+        Ok(Self {
+            node,
+            prefix: prefix.into(),
+            initial_len: 64 * 1024,
+            publishers: BTreeMap::new(),
+            buffer: Vec::new(),
+            sent: 0,
+            last_error: None,
+        })
+    }
+
+    /// Sample size a publisher starts with, in bytes; it grows for larger
+    /// frames. Takes effect for services opened afterwards.
+    pub fn with_initial_len(mut self, bytes: usize) -> Self {
+        self.initial_len = bytes;
+        self
+    }
+
+    /// Frames published so far.
+    pub fn sent(&self) -> u64 {
+        self.sent
+    }
+
+    /// The first error since the last call, cleared by reading it.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.last_error.take()
+    }
+
+    fn publish(&mut self, kind: SensorKind) -> io::Result<()> {
+        if !self.publishers.contains_key(&kind) {
+            let publisher = open_service(&self.node, &self.prefix, kind)?
+                .publisher_builder()
+                .initial_max_slice_len(self.initial_len)
+                .allocation_strategy(AllocationStrategy::PowerOfTwo)
+                .create()
+                .map_err(io::Error::other)?;
+            self.publishers.insert(kind, publisher);
+        }
+        let publisher = &self.publishers[&kind];
+        let sample = publisher
+            .loan_slice_uninit(self.buffer.len())
+            .map_err(io::Error::other)?;
+        sample
+            .write_from_slice(&self.buffer)
+            .send()
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+impl Sink for IceoryxSink {
+    fn consume(&mut self, frame: SharedFrame) {
+        self.buffer.clear();
+        write_frame(&mut self.buffer, &frame);
+        match self.publish(frame.kind()) {
+            Ok(()) => self.sent += 1,
+            Err(e) => {
+                self.last_error.get_or_insert(e);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for IceoryxSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IceoryxSink")
+            .field("prefix", &self.prefix)
+            .field("services", &self.publishers.keys().collect::<Vec<_>>())
+            .field("sent", &self.sent)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Subscribes to the services of an [`IceoryxSink`].
+///
+/// Frames of one sensor kind arrive in order; kinds are polled in turn.
+pub struct IceoryxReceiver {
+    node: Node<ipc::Service>,
+    subscribers: Vec<(SensorKind, FrameSubscriber)>,
+    next: usize,
+    malformed: u64,
+}
+
+impl IceoryxReceiver {
+    /// Subscribe to `kinds` under `prefix`.
+    pub fn subscribe(
+        prefix: &str,
+        kinds: impl IntoIterator<Item = SensorKind>,
+    ) -> io::Result<Self> {
+        let node = NodeBuilder::new()
+            .create::<ipc::Service>()
+            .map_err(io::Error::other)?;
+        let mut subscribers = Vec::new();
+        for kind in kinds {
+            let subscriber = open_service(&node, prefix, kind)?
+                .subscriber_builder()
+                .create()
+                .map_err(io::Error::other)?;
+            subscribers.push((kind, subscriber));
+        }
+        Ok(Self {
+            node,
+            subscribers,
+            next: 0,
+            malformed: 0,
+        })
+    }
+
+    /// Samples that were no enveloped frame.
+    pub fn malformed(&self) -> u64 {
+        self.malformed
+    }
+
+    /// Hand the next sample to `read` while it is still in shared memory,
+    /// without copying it.
+    pub fn try_recv_with<R>(
+        &mut self,
+        read: impl FnOnce(SensorKind, &[u8]) -> R,
+    ) -> io::Result<Option<R>> {
+        for _ in 0..self.subscribers.len() {
+            let (kind, subscriber) = &self.subscribers[self.next];
+            self.next = (self.next + 1) % self.subscribers.len();
+            if let Some(sample) = subscriber.receive().map_err(io::Error::other)? {
+                return Ok(Some(read(*kind, sample.payload())));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The next frame, if one is there. Samples that are no enveloped frame are
+    /// counted in [`IceoryxReceiver::malformed`] and skipped.
+    pub fn try_recv(&mut self) -> io::Result<Option<SensorDataSerDe>> {
+        loop {
+            let frame = self.try_recv_with(|_, bytes| decode_frame(bytes).ok())?;
+            match frame {
+                None => return Ok(None),
+                Some(Some(frame)) => return Ok(Some(frame)),
+                Some(None) => self.malformed += 1,
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for the next frame. Returns `Ok(None)` on
+    /// timeout, and an error if the process was asked to terminate.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> io::Result<Option<SensorDataSerDe>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(frame) = self.try_recv()? {
+                return Ok(Some(frame));
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(None);
+            }
+            self.node
+                .wait(left.min(POLL_INTERVAL))
+                .map_err(io::Error::other)?;
+        }
+    }
+}
+
+impl fmt::Debug for IceoryxReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kinds: Vec<_> = self.subscribers.iter().map(|(kind, _)| kind).collect();
+        f.debug_struct("IceoryxReceiver")
+            .field("kinds", &kinds)
+            .field("malformed", &self.malformed)
+            .finish_non_exhaustive()
+    }
+}
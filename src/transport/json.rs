@@ -0,0 +1,24 @@
+use crate::{Envelope, SensorDataSerDe};
+use std::io;
+
+/// A frame as the transports send it: JSON in an [`Envelope`].
+pub fn encode_frame(frame: &SensorDataSerDe) -> Vec<u8> {
+    let mut message = Vec::new();
+    write_frame(&mut message, frame);
+    message
+}
+
+// `encode_frame` into a reused buffer
+pub(crate) fn write_frame(out: &mut Vec<u8>, frame: &SensorDataSerDe) {
+    serde_json::to_writer(out, &Envelope::new(frame)).expect("frames serialize to JSON");
+}
+
+/// The frame of a message [`encode_frame`] wrote. Anything else fails with
+/// [`io::ErrorKind::InvalidData`], and so does a frame of a newer schema
+/// version.
+pub fn decode_frame(message: &[u8]) -> io::Result<SensorDataSerDe> {
+    let envelope: Envelope<SensorDataSerDe> = serde_json::from_slice(message)?;
+    envelope
+        .open()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,328 @@
+use super::{decode_frame, encode_frame};
+use crate::bus::{OverloadPolicy, Priority};
+use crate::pipeline::{SharedFrame, Sink};
+use crate::{SensorDataSerDe, SensorKind};
+use quinn::{ClientConfig, Connection, Endpoint, ReadExactError, RecvStream, ServerConfig, VarInt};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc::{self, Sender};
+use tokio::task::JoinHandle;
+
+/// Frames larger than this end the stream they arrive on, as
+/// [`super::Reassembler`] drops them by default.
+const MAX_QUIC_FRAME: usize = super::DEFAULT_MAX_MESSAGE;
+
+/// Frames a stream of [`QuicSink`] and a [`QuicReceiver`] queue before
+/// `consume` blocks, respectively the peer's streams stall.
+const QUEUE_FRAMES: usize = 64;
+
+// bytes a stream reads at a time, so a frame's buffer grows with the data
+// that arrived rather than with the length its sender claims
+const READ_CHUNK: usize = 64 << 10;
+
+fn runtime() -> io::Result<Runtime> {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+}
+
+// QUIC stream priority, higher goes first
+fn stream_priority(priority: Priority) -> i32 {
+    match priority {
+        Priority::Low => 0,
+        Priority::Normal => 1,
+        Priority::High => 2,
+        Priority::Critical => 3,
+    }
+}
+
+/// Sends enveloped JSON frames over one QUIC connection, one unidirectional stream
+/// per sensor kind.
+///
+/// A lost packet only holds up the stream it belonged to, and when the link
+/// is saturated streams of a higher [`Priority`] are sent first; priorities
+/// default to those of [`OverloadPolicy`]. Each frame goes out as its
+/// length (u32, little-endian) followed by its JSON. `consume` only queues
+/// the frame, blocking while its stream has a full queue of frames not sent
+/// yet, so it must not be called from async code; [`Sink::flush`] waits
+/// until the peer has everything and closes the connection.
+pub struct QuicSink {
+    runtime: Runtime,
+    endpoint: Endpoint,
+    connection: Connection,
+    priorities: BTreeMap<SensorKind, Priority>,
+    streams: BTreeMap<SensorKind, Sender<Vec<u8>>>,
+    writers: Vec<JoinHandle<io::Result<()>>>,
+    sent: u64,
+    last_error: Option<io::Error>,
+}
+
+impl QuicSink {
+    /// Connect to a [`QuicReceiver`] at `addr`, whose certificate must be
+    /// valid for `server_name` under `config`.
+    pub fn connect(addr: SocketAddr, server_name: &str, config: ClientConfig) -> io::Result<Self> {
+        let runtime = runtime()?;
+        let local: IpAddr = match addr {
+            SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+            SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+        };
+        let (endpoint, connection) = runtime.block_on(async {
+            let endpoint = Endpoint::client((local, 0).into())?;
+            let connection = endpoint
+                .connect_with(config, addr, server_name)
+                .map_err(io::Error::other)?
+                .await
+                .map_err(io::Error::other)?;
+            io::Result::Ok((endpoint, connection))
+        })?;
+        Ok(Self {
+            runtime,
+            endpoint,
+            connection,
+            priorities: BTreeMap::new(),
+            streams: BTreeMap::new(),
+            writers: Vec::new(),
+            sent: 0,
+            last_error: None,
+        })
+    }
+
+    /// Priority of `kind`'s stream. Takes effect for streams opened
+    /// afterwards, i.e. set it before the first frame of that kind.
+    pub fn with_priority(mut self, kind: SensorKind, priority: Priority) -> Self {
+        self.priorities.insert(kind, priority);
+        self
+    }
+
+    pub fn priority(&self, kind: SensorKind) -> Priority {
+        self.priorities
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| OverloadPolicy::default().priority(kind))
+    }
+
+    /// Frames queued so far.
+    pub fn sent(&self) -> u64 {
+        self.sent
+    }
+
+    /// The first error of a stream that failed, cleared by reading it.
+    /// Streams report errors when flushed.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.last_error.take()
+    }
+
+    fn open_stream(&mut self, kind: SensorKind) -> Sender<Vec<u8>> {
+        let (tx, mut rx) = mpsc::channel::<Vec<u8>>(QUEUE_FRAMES);
+        let connection = self.connection.clone();
+        let priority = stream_priority(self.priority(kind));
+        self.writers.push(self.runtime.spawn(async move {
+            let mut stream = connection.open_uni().await.map_err(io::Error::other)?;
+            stream.set_priority(priority).map_err(io::Error::other)?;
+            while let Some(message) = rx.recv().await {
+                let len = u32::try_from(message.len()).map_err(io::Error::other)?;
+                stream
+                    .write_all(&len.to_le_bytes())
+                    .await
+                    .map_err(io::Error::other)?;
+                stream.write_all(&message).await.map_err(io::Error::other)?;
+            }
+            stream.finish().map_err(io::Error::other)?;
+            stream.stopped().await.map_err(io::Error::other)?;
+            Ok(())
+        }));
+        tx
+    }
+}
+
+impl Sink for QuicSink {
+    fn consume(&mut self, frame: SharedFrame) {
+        let message = encode_frame(&frame);
+        let kind = frame.kind();
+        let stream = match self.streams.get(&kind) {
+            Some(stream) => stream.clone(),
+            None => {
+                let stream = self.open_stream(kind);
+                self.streams.insert(kind, stream.clone());
+                stream
+            }
+        };
+        // a closed channel means the writer failed; flush reports why
+        if stream.blocking_send(message).is_ok() {
+            self.sent += 1;
+        }
+    }
+
+    fn flush(&mut self) {
+        self.streams.clear();
+        for writer in std::mem::take(&mut self.writers) {
+            let result = self
+                .runtime
+                .block_on(writer)
+                .unwrap_or_else(|e| Err(io::Error::other(e)));
+            if let Err(e) = result {
+                self.last_error.get_or_insert(e);
+            }
+        }
+        self.connection.close(VarInt::from_u32(0), b"done");
+        self.runtime.block_on(self.endpoint.wait_idle());
+    }
+}
+
+impl fmt::Debug for QuicSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuicSink")
+            .field("remote", &self.connection.remote_address())
+            .field("priorities", &self.priorities)
+            .field("streams", &self.streams.keys().collect::<Vec<_>>())
+            .field("sent", &self.sent)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Receives what [`QuicSink`]s send, from any number of connections.
+///
+/// Frames of one sensor kind arrive in order; across kinds, in the order
+/// they were delivered. While the receiver holds a full queue of frames not
+/// read yet, the senders' streams stall, which QUIC flow control passes
+/// back to them.
+pub struct QuicReceiver {
+    endpoint: Endpoint,
+    frames: Mutex<mpsc::Receiver<SensorDataSerDe>>,
+    malformed: Arc<AtomicU64>,
+    // runs the accept and read tasks; dropped after the endpoint
+    runtime: Runtime,
+}
+
+impl QuicReceiver {
+    pub fn bind(addr: SocketAddr, config: ServerConfig) -> io::Result<Self> {
+        let runtime = runtime()?;
+        let endpoint = {
+            let _guard = runtime.enter();
+            Endpoint::server(config, addr)?
+        };
+        let (tx, frames) = mpsc::channel(QUEUE_FRAMES);
+        let malformed = Arc::new(AtomicU64::new(0));
+        let accepting = endpoint.clone();
+        let counter = malformed.clone();
+        runtime.spawn(async move {
+            while let Some(incoming) = accepting.accept().await {
+                let (tx, counter) = (tx.clone(), counter.clone());
+                tokio::spawn(async move {
+                    let Ok(connection) = incoming.await else {
+                        return;
+                    };
+                    while let Ok(stream) = connection.accept_uni().await {
+                        tokio::spawn(read_frames(stream, tx.clone(), counter.clone()));
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            endpoint,
+            frames: Mutex::new(frames),
+            malformed,
+            runtime,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.endpoint.local_addr()
+    }
+
+    /// Messages that were no enveloped frame.
+    pub fn malformed(&self) -> u64 {
+        self.malformed.load(Ordering::Relaxed)
+    }
+
+    /// Block until the next frame.
+    pub fn recv(&self) -> Option<SensorDataSerDe> {
+        self.frames().blocking_recv()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<SensorDataSerDe> {
+        let mut frames = self.frames();
+        self.runtime
+            .block_on(tokio::time::timeout(timeout, frames.recv()))
+            .ok()
+            .flatten()
+    }
+
+    pub fn try_recv(&self) -> Option<SensorDataSerDe> {
+        self.frames().try_recv().ok()
+    }
+
+    fn frames(&self) -> std::sync::MutexGuard<'_, mpsc::Receiver<SensorDataSerDe>> {
+        self.frames.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for QuicReceiver {
+    fn drop(&mut self) {
+        self.endpoint.close(VarInt::from_u32(0), b"closed");
+    }
+}
+
+impl fmt::Debug for QuicReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuicReceiver")
+            .field("local", &self.endpoint.local_addr().ok())
+            .field("malformed", &self.malformed())
+            .finish_non_exhaustive()
+    }
+}
+
+async fn read_frames(
+    mut stream: RecvStream,
+    frames: Sender<SensorDataSerDe>,
+    malformed: Arc<AtomicU64>,
+) {
+    let mut len = [0; 4];
+    loop {
+        match stream.read_exact(&mut len).await {
+            Ok(()) => {}
+            Err(ReadExactError::FinishedEarly(0)) => return,
+            Err(_) => {
+                malformed.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_QUIC_FRAME {
+            malformed.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let Some(message) = read_message(&mut stream, len).await else {
+            malformed.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+        match decode_frame(&message) {
+            Ok(frame) => {
+                if frames.send(frame).await.is_err() {
+                    return;
+                }
+            }
+            Err(_) => {
+                malformed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+// the next `len` bytes of the stream, `None` if it ends or fails first
+async fn read_message(stream: &mut RecvStream, len: usize) -> Option<Vec<u8>> {
+    let mut message = Vec::with_capacity(len.min(READ_CHUNK));
+    while message.len() < len {
+        let want = (len - message.len()).min(READ_CHUNK);
+        let chunk = stream.read_chunk(want, true).await.ok()??;
+        message.extend_from_slice(&chunk.bytes);
+    }
+    Some(message)
+}
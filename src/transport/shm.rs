@@ -0,0 +1,675 @@
+use super::{decode_frame, encode_frame};
+use crate::SensorDataSerDe;
+use crate::pipeline::{SharedFrame, Sink};
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering, fence};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// "CDSRING1"
+const RING_MAGIC: u64 = 0x3147_4e49_5253_4443;
+
+// the header is padded to a cache line, the data follows it
+const HEADER_SIZE: usize = 64;
+
+// per message: u32 length, u32 padding
+const RECORD_PREFIX: usize = 8;
+
+#[repr(C)]
+struct RingHeader {
+    magic: u64,
+    capacity: u64,
+    // end of the message being written; data before `reserved - capacity`
+    // may be overwritten
+    reserved: AtomicU64,
+    // end of the last complete message
+    committed: AtomicU64,
+    // start of the last complete message, where lapped readers resume
+    last: AtomicU64,
+    // messages committed so far
+    count: AtomicU64,
+}
+
+fn record_size(len: usize) -> usize {
+    RECORD_PREFIX + len.next_multiple_of(8)
+}
+
+fn os_error<T>(ret: T, failed: T) -> io::Result<T>
+where
+    T: PartialEq,
+{
+    if ret == failed {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+// A shared mapping of the whole ring file.
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+// SAFETY: the mapping is plain memory; concurrent access goes through the
+// header's atomics and the validation in `ShmReader::try_recv_message`.
+unsafe impl Send for Mapping {}
+
+impl Mapping {
+    fn new(fd: RawFd, len: usize, writable: bool) -> io::Result<Self> {
+        let prot = if writable {
+            libc::PROT_READ | libc::PROT_WRITE
+        } else {
+            libc::PROT_READ
+        };
+        // SAFETY: a fresh mapping of `len` bytes of `fd`; it is unmapped in
+        // Drop and never handed out beyond `self`'s lifetime.
+        let ptr = unsafe { libc::mmap(ptr::null_mut(), len, prot, libc::MAP_SHARED, fd, 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: ptr.cast(),
+            len,
+        })
+    }
+
+    fn header(&self) -> &RingHeader {
+        // SAFETY: the mapping is page-aligned and at least HEADER_SIZE long
+        unsafe { &*self.ptr.cast::<RingHeader>() }
+    }
+
+    fn capacity(&self) -> usize {
+        self.len - HEADER_SIZE
+    }
+
+    // copy `out.len()` bytes starting at ring position `pos`, wrapping
+    fn read(&self, pos: u64, out: &mut [u8]) {
+        let capacity = self.capacity();
+        let start = (pos % capacity as u64) as usize;
+        let first = out.len().min(capacity - start);
+        // SAFETY: both ranges lie inside the data area; the bytes may be
+        // overwritten meanwhile, which the caller detects and discards
+        unsafe {
+            let data = self.ptr.add(HEADER_SIZE);
+            ptr::copy_nonoverlapping(data.add(start), out.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(data, out.as_mut_ptr().add(first), out.len() - first);
+        }
+    }
+
+    // only called on the writer's read-write mapping
+    fn write(&mut self, pos: u64, bytes: &[u8]) {
+        let capacity = self.capacity();
+        let start = (pos % capacity as u64) as usize;
+        let first = bytes.len().min(capacity - start);
+        // SAFETY: as in `read`, inside the data area of a writable mapping
+        unsafe {
+            let data = self.ptr.add(HEADER_SIZE);
+            ptr::copy_nonoverlapping(bytes.as_ptr(), data.add(start), first);
+            ptr::copy_nonoverlapping(bytes.as_ptr().add(first), data, bytes.len() - first);
+        }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` and `len` are the mapping made in `new`
+        unsafe {
+            libc::munmap(self.ptr.cast(), self.len);
+        }
+    }
+}
+
+// A reader's connection and the eventfd the writer signals it through.
+struct Subscriber {
+    stream: UnixStream,
+    event: OwnedFd,
+}
+
+impl Subscriber {
+    fn notify(&self) {
+        let one = 1u64;
+        // SAFETY: writes 8 bytes from a live u64; a full counter (EAGAIN)
+        // means the reader has a wakeup pending anyway
+        unsafe {
+            libc::write(self.event.as_raw_fd(), (&one as *const u64).cast(), 8);
+        }
+    }
+
+    // the reader hung up
+    fn is_closed(&self) -> bool {
+        let mut poll = libc::pollfd {
+            fd: self.stream.as_raw_fd(),
+            events: libc::POLLIN | libc::POLLRDHUP,
+            revents: 0,
+        };
+        // SAFETY: polls one valid descriptor without waiting
+        let ready = unsafe { libc::poll(&mut poll, 1, 0) };
+        ready > 0 && poll.revents & (libc::POLLRDHUP | libc::POLLHUP | libc::POLLERR) != 0
+    }
+}
+
+/// The producing end of a shared-memory ring for readers on the same host.
+///
+/// The ring lives in a memfd of `capacity` bytes. Readers connect through
+/// a Unix socket at the given path and receive the memfd and an eventfd of
+/// their own, which the writer signals after every message. The writer
+/// never waits for readers: it overwrites the oldest messages, and a reader
+/// that falls a whole ring behind skips ahead, see [`ShmReader::lost`].
+pub struct ShmWriter {
+    map: Mapping,
+    memfd: Arc<OwnedFd>,
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl ShmWriter {
+    /// Create a ring of `capacity` bytes, rounded up to a multiple of 8,
+    /// and listen for readers at `path`, which must not exist yet.
+    pub fn create(path: impl AsRef<Path>, capacity: usize) -> io::Result<Self> {
+        let capacity = capacity.max(RECORD_PREFIX).next_multiple_of(8);
+        let len = HEADER_SIZE + capacity;
+        // SAFETY: the name is NUL-terminated; the returned descriptor is
+        // owned from here on
+        let memfd = unsafe {
+            let fd = os_error(
+                libc::memfd_create(c"carla-data-serde-ring".as_ptr(), libc::MFD_CLOEXEC),
+                -1,
+            )?;
+            OwnedFd::from_raw_fd(fd)
+        };
+        // SAFETY: resizes a descriptor we own
+        os_error(
+            unsafe { libc::ftruncate(memfd.as_raw_fd(), len as libc::off_t) },
+            -1,
+        )?;
+        let map = Mapping::new(memfd.as_raw_fd(), len, true)?;
+        // SAFETY: the mapping is fresh and zeroed, nobody else sees it yet
+        unsafe {
+            let header = map.ptr.cast::<RingHeader>();
+            (*header).capacity = capacity as u64;
+            (*header).magic = RING_MAGIC;
+        }
+
+        let path = path.as_ref().to_path_buf();
+        let listener = UnixListener::bind(&path)?;
+        let memfd = Arc::new(memfd);
+        let subscribers = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let acceptor = {
+            let (memfd, subscribers, stop) = (memfd.clone(), subscribers.clone(), stop.clone());
+            thread::spawn(move || accept(listener, &memfd, &subscribers, &stop))
+        };
+        Ok(Self {
+            map,
+            memfd,
+            subscribers,
+            path,
+            stop,
+            acceptor: Some(acceptor),
+        })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// Readers connected now.
+    pub fn readers(&self) -> usize {
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        subscribers.retain(|s| !s.is_closed());
+        subscribers.len()
+    }
+
+    /// Append `message` and wake the readers. Fails with
+    /// [`io::ErrorKind::InvalidInput`] if it cannot fit the ring.
+    pub fn push(&mut self, message: &[u8]) -> io::Result<()> {
+        let size = record_size(message.len());
+        if size > self.capacity() || u32::try_from(message.len()).is_err() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{}-byte message does not fit a {}-byte ring",
+                    message.len(),
+                    self.capacity()
+                ),
+            ));
+        }
+        let header = self.map.header();
+        let start = header.committed.load(Ordering::Relaxed);
+        let end = start + size as u64;
+        header.reserved.store(end, Ordering::Relaxed);
+        // readers that see any of the bytes below also see `reserved`
+        fence(Ordering::Release);
+
+        let mut prefix = [0; RECORD_PREFIX];
+        prefix[..4].copy_from_slice(&(message.len() as u32).to_le_bytes());
+        self.map.write(start, &prefix);
+        self.map.write(start + RECORD_PREFIX as u64, message);
+
+        let header = self.map.header();
+        header.last.store(start, Ordering::Release);
+        header.count.fetch_add(1, Ordering::Release);
+        header.committed.store(end, Ordering::Release);
+
+        let subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        for subscriber in subscribers.iter() {
+            subscriber.notify();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ShmWriter {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // wake the acceptor so it sees `stop`
+        let _ = UnixStream::connect(&self.path);
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+        let _ = std::fs::remove_file(&self.path);
+        // the memfd lives on in the readers that still map it
+        let _ = &self.memfd;
+    }
+}
+
+fn accept(
+    listener: UnixListener,
+    memfd: &OwnedFd,
+    subscribers: &Mutex<Vec<Subscriber>>,
+    stop: &AtomicBool,
+) {
+    for stream in listener.incoming() {
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(stream) = stream else {
+            continue;
+        };
+        // SAFETY: the returned descriptor is owned from here on
+        let event = unsafe {
+            match libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) {
+                -1 => continue,
+                fd => OwnedFd::from_raw_fd(fd),
+            }
+        };
+        if send_fds(&stream, [memfd.as_raw_fd(), event.as_raw_fd()]).is_ok() {
+            let mut subscribers = subscribers.lock().unwrap_or_else(PoisonError::into_inner);
+            subscribers.retain(|s| !s.is_closed());
+            subscribers.push(Subscriber { stream, event });
+        }
+    }
+}
+
+fn send_fds(stream: &UnixStream, fds: [RawFd; 2]) -> io::Result<()> {
+    let mut byte = [0u8];
+    let mut iov = libc::iovec {
+        iov_base: byte.as_mut_ptr().cast(),
+        iov_len: 1,
+    };
+    // SAFETY: the control buffer is sized with CMSG_SPACE for two fds and
+    // outlives the sendmsg call; CMSG_FIRSTHDR of it is non-null
+    unsafe {
+        let space = libc::CMSG_SPACE(size_of_val(&fds) as u32) as usize;
+        let mut control = vec![0u8; space];
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = space as _;
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(size_of_val(&fds) as u32) as _;
+        ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(cmsg).cast(), fds.len());
+        os_error(libc::sendmsg(stream.as_raw_fd(), &msg, 0), -1)?;
+    }
+    Ok(())
+}
+
+fn recv_fds(stream: &UnixStream) -> io::Result<[OwnedFd; 2]> {
+    let mut byte = [0u8];
+    let mut iov = libc::iovec {
+        iov_base: byte.as_mut_ptr().cast(),
+        iov_len: 1,
+    };
+    let mut fds: [RawFd; 2] = [-1; 2];
+    // SAFETY: as in send_fds; descriptors are only taken over if the
+    // kernel passed a control message of exactly two
+    unsafe {
+        let space = libc::CMSG_SPACE(size_of_val(&fds) as u32) as usize;
+        let mut control = vec![0u8; space];
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = space as _;
+        os_error(
+            libc::recvmsg(stream.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC),
+            -1,
+        )?;
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        if cmsg.is_null()
+            || (*cmsg).cmsg_type != libc::SCM_RIGHTS
+            || (*cmsg).cmsg_len as usize != libc::CMSG_LEN(size_of_val(&fds) as u32) as usize
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "writer sent no ring descriptors",
+            ));
+        }
+        ptr::copy_nonoverlapping(libc::CMSG_DATA(cmsg).cast(), fds.as_mut_ptr(), fds.len());
+        Ok(fds.map(|fd| OwnedFd::from_raw_fd(fd)))
+    }
+}
+
+/// The consuming end of a [`ShmWriter`]'s ring.
+///
+/// A reader sees the messages pushed after it connected. It maps the ring
+/// read-only and checks every message after copying it out, so a message
+/// the writer overwrote meanwhile is dropped rather than returned torn.
+pub struct ShmReader {
+    map: Mapping,
+    event: OwnedFd,
+    // keeps this reader registered with the writer
+    _stream: UnixStream,
+    pos: u64,
+    index: u64,
+    lost: u64,
+}
+
+impl ShmReader {
+    pub fn connect(path: impl AsRef<Path>) -> io::Result<Self> {
+        let stream = UnixStream::connect(path)?;
+        let [memfd, event] = recv_fds(&stream)?;
+        let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+        // SAFETY: `stat` is only read after fstat reported success
+        let len = unsafe {
+            os_error(libc::fstat(memfd.as_raw_fd(), stat.as_mut_ptr()), -1)?;
+            stat.assume_init().st_size as usize
+        };
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not a frame ring");
+        if len < HEADER_SIZE + RECORD_PREFIX {
+            return Err(invalid());
+        }
+        let map = Mapping::new(memfd.as_raw_fd(), len, false)?;
+        let header = map.header();
+        if header.magic != RING_MAGIC || header.capacity != map.capacity() as u64 {
+            return Err(invalid());
+        }
+        let index = header.count.load(Ordering::Acquire);
+        let pos = header.committed.load(Ordering::Acquire);
+        Ok(Self {
+            map,
+            event,
+            _stream: stream,
+            pos,
+            index,
+            lost: 0,
+        })
+    }
+
+    /// Messages skipped because the writer overwrote them first.
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+
+    /// The next message, if one is there.
+    pub fn try_recv_message(&mut self) -> Option<Vec<u8>> {
+        let capacity = self.map.capacity() as u64;
+        loop {
+            let header = self.map.header();
+            let committed = header.committed.load(Ordering::Acquire);
+            if self.pos == committed {
+                return None;
+            }
+            if committed < self.pos || committed - self.pos > capacity {
+                self.skip_to_last();
+                continue;
+            }
+
+            let mut prefix = [0; RECORD_PREFIX];
+            self.map.read(self.pos, &mut prefix);
+            let len = u32::from_le_bytes(prefix[..4].try_into().expect("4 bytes")) as usize;
+            let size = record_size(len) as u64;
+            let mut message = Vec::new();
+            if size <= committed - self.pos {
+                message.resize(len, 0);
+                self.map.read(self.pos + RECORD_PREFIX as u64, &mut message);
+            }
+            // anything the writer overwrote while we copied shows here
+            fence(Ordering::Acquire);
+            let reserved = self.map.header().reserved.load(Ordering::Relaxed);
+            if size > committed - self.pos || reserved - self.pos > capacity {
+                self.skip_to_last();
+                continue;
+            }
+            self.pos += size;
+            self.index += 1;
+            return Some(message);
+        }
+    }
+
+    /// Wait up to `timeout` for the next message; `None` waits forever.
+    /// Returns `Ok(None)` on timeout.
+    pub fn recv_message_timeout(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> io::Result<Option<Vec<u8>>> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            if let Some(message) = self.try_recv_message() {
+                return Ok(Some(message));
+            }
+            let wait = match deadline {
+                None => -1,
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return Ok(None);
+                    }
+                    left.as_millis().clamp(1, i32::MAX as u128) as i32
+                }
+            };
+            let mut poll = libc::pollfd {
+                fd: self.event.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let mut count = 0u64;
+            // SAFETY: polls and reads our own eventfd into a live u64; the
+            // read is non-blocking and only resets the counter
+            unsafe {
+                if libc::poll(&mut poll, 1, wait) < 0 {
+                    let e = io::Error::last_os_error();
+                    if e.kind() != io::ErrorKind::Interrupted {
+                        return Err(e);
+                    }
+                }
+                libc::read(self.event.as_raw_fd(), (&mut count as *mut u64).cast(), 8);
+            }
+        }
+    }
+
+    pub fn recv_message(&mut self) -> io::Result<Vec<u8>> {
+        Ok(self.recv_message_timeout(None)?.expect("no timeout"))
+    }
+
+    /// Wait for the next frame of a [`ShmSink`]. A message that is no
+    /// enveloped frame fails with [`io::ErrorKind::InvalidData`], see
+    /// [`decode_frame`](super::decode_frame); the reader stays usable.
+    pub fn recv_frame_timeout(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> io::Result<Option<SensorDataSerDe>> {
+        match self.recv_message_timeout(timeout)? {
+            Some(message) => Ok(Some(decode_frame(&message)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn recv_frame(&mut self) -> io::Result<SensorDataSerDe> {
+        Ok(self.recv_frame_timeout(None)?.expect("no timeout"))
+    }
+
+    // resume at the newest message after being lapped
+    fn skip_to_last(&mut self) {
+        let header = self.map.header();
+        let count = header.count.load(Ordering::Acquire);
+        self.pos = header.last.load(Ordering::Acquire);
+        let resumed = count.saturating_sub(1);
+        self.lost += resumed.saturating_sub(self.index);
+        self.index = resumed;
+    }
+}
+
+/// Writes enveloped JSON frames into a [`ShmWriter`]'s ring.
+pub struct ShmSink {
+    writer: ShmWriter,
+    failed: u64,
+}
+
+impl ShmSink {
+    pub fn create(path: impl AsRef<Path>, capacity: usize) -> io::Result<Self> {
+        Ok(Self {
+            writer: ShmWriter::create(path, capacity)?,
+            failed: 0,
+        })
+    }
+
+    pub fn writer(&self) -> &ShmWriter {
+        &self.writer
+    }
+
+    /// Frames too large for the ring.
+    pub fn failed(&self) -> u64 {
+        self.failed
+    }
+}
+
+impl Sink for ShmSink {
+    fn consume(&mut self, frame: SharedFrame) {
+        let message = encode_frame(&frame);
+        if self.writer.push(&message).is_err() {
+            self.failed += 1;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "fixtures"))]
+mod tests {
+    use super::*;
+    use crate::fixtures::imu_frame;
+
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("shm-{}-{name}.sock", std::process::id()))
+    }
+
+    const TIMEOUT: Option<Duration> = Some(Duration::from_secs(5));
+
+    #[test]
+    fn readers_receive_messages_and_frames() {
+        let path = path("round-trip");
+        let mut sink = ShmSink::create(&path, 4096).unwrap();
+        let mut reader = ShmReader::connect(&path).unwrap();
+        for message in [&b"one"[..], b"two", b""] {
+            sink.writer.push(message).unwrap();
+        }
+        for message in [&b"one"[..], b"two", b""] {
+            let got = reader.recv_message_timeout(TIMEOUT).unwrap();
+            assert_eq!(got.as_deref(), Some(message));
+        }
+        let frame = SensorDataSerDe::Imu(imu_frame());
+        sink.consume(SharedFrame::new(frame.clone()));
+        assert_eq!(reader.recv_frame_timeout(TIMEOUT).unwrap(), Some(frame));
+        let none = reader.recv_message_timeout(Some(Duration::from_millis(10)));
+        assert_eq!(none.unwrap(), None);
+        assert_eq!((reader.lost(), sink.failed()), (0, 0));
+    }
+
+    #[test]
+    fn a_message_larger_than_the_ring_is_refused() {
+        let mut writer = ShmWriter::create(path("too-large"), 64).unwrap();
+        let err = writer.push(&[0; 64]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        writer.push(&[0; 56]).unwrap();
+    }
+
+    #[test]
+    fn a_lapped_reader_skips_to_the_newest_message() {
+        let path = path("lapped");
+        // room for 4 records of 8-byte messages
+        let mut writer = ShmWriter::create(&path, 64).unwrap();
+        let mut reader = ShmReader::connect(&path).unwrap();
+        let message = |i: usize| format!("msg-{i:04}").into_bytes();
+        for i in 0..10 {
+            writer.push(&message(i)).unwrap();
+        }
+        assert_eq!(reader.try_recv_message(), Some(message(9)));
+        assert_eq!(reader.lost(), 9);
+        assert_eq!(reader.try_recv_message(), None);
+
+        // back in step, nothing more is lost
+        for i in 10..13 {
+            writer.push(&message(i)).unwrap();
+        }
+        for i in 10..13 {
+            assert_eq!(reader.try_recv_message(), Some(message(i)));
+        }
+        assert_eq!(reader.lost(), 9);
+    }
+
+    #[test]
+    fn a_reader_racing_the_writer_gets_no_torn_message() {
+        let path = path("race");
+        let mut writer = ShmWriter::create(&path, 256).unwrap();
+        let mut reader = ShmReader::connect(&path).unwrap();
+        const MESSAGES: u32 = 20_000;
+        // the index, then a filler whose length and bytes follow from it
+        let message = |i: u32| {
+            let mut m = i.to_le_bytes().to_vec();
+            m.resize(4 + i as usize % 50, (i % 251) as u8);
+            m
+        };
+        let pusher = thread::spawn(move || {
+            for i in 0..MESSAGES {
+                writer.push(&message(i)).unwrap();
+            }
+            writer
+        });
+        let (mut received, mut last) = (0u64, None);
+        let mut check = |got: Vec<u8>| {
+            let i = u32::from_le_bytes(got[..4].try_into().unwrap());
+            assert_eq!(got, message(i), "torn message");
+            assert!(last < Some(i), "message {i} after {last:?}");
+            last = Some(i);
+            received += 1;
+        };
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !pusher.is_finished() && Instant::now() < deadline {
+            if let Some(got) = reader.try_recv_message() {
+                check(got);
+            }
+        }
+        let _writer = pusher.join().unwrap();
+        while let Some(got) = reader.try_recv_message() {
+            check(got);
+        }
+        assert_eq!(last, Some(MESSAGES - 1));
+        assert!(received + reader.lost() <= MESSAGES as u64);
+    }
+}
@@ -0,0 +1,216 @@
+use super::{FragmentError, Reassembler, ShapedSink, decode_frame, encode_frame};
+use crate::SensorDataSerDe;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+/// Largest UDP payload, the receive buffer size.
+const MAX_DATAGRAM: usize = 65_507;
+
+/// Open messages a [`UdpReceiver`] keeps by default, per sender.
+const DEFAULT_WINDOW: usize = 8;
+
+/// Senders a [`UdpReceiver`] reassembles from at once by default.
+const DEFAULT_MAX_PEERS: usize = 64;
+
+/// A [`ShapedSink`] writing enveloped JSON frames to a UDP socket, see
+/// [`encode_frame`].
+pub type UdpJsonSink = ShapedSink<UdpSocket, fn(&SensorDataSerDe) -> Vec<u8>>;
+
+/// A socket sending to `target`, a unicast address or a multicast group.
+/// Multicast goes out with the system's default TTL of 1, i.e. stays on
+/// the local network; raise it with [`UdpSocket::set_multicast_ttl_v4`].
+pub fn connect_udp(target: SocketAddr) -> io::Result<UdpSocket> {
+    let local: IpAddr = match target {
+        SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+    let socket = UdpSocket::bind((local, 0))?;
+    socket.connect(target)?;
+    Ok(socket)
+}
+
+impl UdpJsonSink {
+    /// Stream frames as JSON to `target` in packets of at most `mtu` bytes.
+    pub fn udp(target: SocketAddr, mtu: usize) -> io::Result<Self> {
+        Ok(Self::new(connect_udp(target)?, mtu, encode_frame))
+    }
+}
+
+/// Receives what a [`UdpJsonSink`] sends.
+///
+/// Packets that are no fragments are skipped and counted in
+/// [`UdpReceiver::malformed`]; messages whose fragments do not all arrive
+/// are given up, see [`Reassembler`]. Every sender, e.g. each recorder
+/// streaming to one multicast group, gets a reassembler of its own, so
+/// their sequence numbers do not mix. Past
+/// [`with_max_peers`](Self::with_max_peers) senders, the one heard from
+/// least recently is forgotten.
+#[derive(Debug)]
+pub struct UdpReceiver {
+    socket: UdpSocket,
+    window: usize,
+    max_peers: usize,
+    peers: HashMap<SocketAddr, Peer>,
+    packets: u64,
+    buf: Vec<u8>,
+    malformed: u64,
+    lost: u64,
+}
+
+#[derive(Debug)]
+struct Peer {
+    reassembler: Reassembler,
+    // `packets` of the receiver when this peer last sent one
+    last_packet: u64,
+}
+
+impl UdpReceiver {
+    /// Listen on `addr`. For a multicast group, bind its port on all
+    /// interfaces and join the group on the default one.
+    pub fn bind(addr: SocketAddr) -> io::Result<Self> {
+        let socket = match addr.ip() {
+            IpAddr::V4(group) if group.is_multicast() => {
+                let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, addr.port()))?;
+                socket.join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)?;
+                socket
+            }
+            IpAddr::V6(group) if group.is_multicast() => {
+                let socket = UdpSocket::bind((Ipv6Addr::UNSPECIFIED, addr.port()))?;
+                socket.join_multicast_v6(&group, 0)?;
+                socket
+            }
+            _ => UdpSocket::bind(addr)?,
+        };
+        Ok(Self::from_socket(socket))
+    }
+
+    /// Receive on a socket set up elsewhere, e.g. joined on a specific
+    /// interface.
+    pub fn from_socket(socket: UdpSocket) -> Self {
+        Self {
+            socket,
+            window: DEFAULT_WINDOW,
+            max_peers: DEFAULT_MAX_PEERS,
+            peers: HashMap::new(),
+            packets: 0,
+            buf: vec![0; MAX_DATAGRAM],
+            malformed: 0,
+            lost: 0,
+        }
+    }
+
+    /// Keep up to `window` messages open per sender, for links that
+    /// reorder a lot.
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window;
+        self.peers.clear();
+        self
+    }
+
+    /// Reassemble from up to `peers` senders at once, 64 by default.
+    pub fn with_max_peers(mut self, peers: usize) -> Self {
+        self.max_peers = peers.max(1);
+        self
+    }
+
+    /// The socket, e.g. to set a read timeout.
+    pub fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Messages given up with fragments missing, of all senders.
+    pub fn lost(&self) -> u64 {
+        let peers: u64 = self.peers.values().map(|p| p.reassembler.lost()).sum();
+        self.lost + peers
+    }
+
+    /// Packets skipped because they were no valid fragment.
+    pub fn malformed(&self) -> u64 {
+        self.malformed
+    }
+
+    /// Block until the next complete message.
+    pub fn recv_message(&mut self) -> io::Result<Vec<u8>> {
+        Ok(self.recv_message_from()?.0)
+    }
+
+    /// [`recv_message`](Self::recv_message) with the sender's address.
+    pub fn recv_message_from(&mut self) -> io::Result<(Vec<u8>, SocketAddr)> {
+        loop {
+            let (n, from) = self.socket.recv_from(&mut self.buf)?;
+            match self.push(from, n) {
+                Ok(Some(message)) => return Ok((message, from)),
+                Ok(None) => {}
+                Err(_) => self.malformed += 1,
+            }
+        }
+    }
+
+    // feed the first `len` bytes of `buf`, a packet of `from`
+    fn push(&mut self, from: SocketAddr, len: usize) -> Result<Option<Vec<u8>>, FragmentError> {
+        self.packets += 1;
+        if !self.peers.contains_key(&from) && self.peers.len() == self.max_peers {
+            let quiet = self
+                .peers
+                .iter()
+                .min_by_key(|(_, p)| p.last_packet)
+                .map(|(addr, _)| *addr)
+                .expect("max_peers is positive");
+            let forgotten = self.peers.remove(&quiet).expect("peer exists");
+            self.lost += forgotten.reassembler.lost() + forgotten.reassembler.open() as u64;
+        }
+        let window = self.window;
+        let peer = self.peers.entry(from).or_insert_with(|| Peer {
+            reassembler: Reassembler::new(window),
+            last_packet: 0,
+        });
+        peer.last_packet = self.packets;
+        peer.reassembler.push(&self.buf[..len])
+    }
+
+    /// Block until the next frame. A message that is no enveloped frame
+    /// fails with [`io::ErrorKind::InvalidData`], see [`decode_frame`]; the
+    /// receiver stays usable.
+    pub fn recv_frame(&mut self) -> io::Result<SensorDataSerDe> {
+        decode_frame(&self.recv_message()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{FRAGMENT_HEADER, Fragmenter};
+    use std::time::Duration;
+
+    #[test]
+    fn reassembles_per_sender() {
+        let mut receiver = UdpReceiver::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        receiver
+            .socket()
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let target = receiver.local_addr().unwrap();
+        let (a, b) = (connect_udp(target).unwrap(), connect_udp(target).unwrap());
+        let split = |message: &[u8]| Fragmenter::new(FRAGMENT_HEADER + 4).split(message).unwrap();
+        let (from_a, from_b) = (split(b"aaaaaaaa"), split(b"bbbbbbbb"));
+        for (pa, pb) in from_a.iter().zip(&from_b) {
+            a.send(pa).unwrap();
+            b.send(pb).unwrap();
+        }
+
+        let mut messages = [
+            receiver.recv_message_from().unwrap(),
+            receiver.recv_message_from().unwrap(),
+        ];
+        messages.sort();
+        assert_eq!(messages[0].0, b"aaaaaaaa");
+        assert_eq!(messages[0].1, a.local_addr().unwrap());
+        assert_eq!(messages[1].0, b"bbbbbbbb");
+        assert_eq!(receiver.malformed(), 0);
+    }
+}